@@ -0,0 +1,114 @@
+//! Help for reading the board without seeing it well, or at all: what
+//! screen readers announce for a note and the high-contrast look
+
+use crate::NoteData;
+use crate::expiry::format_local_datetime;
+use crate::priority::Priority;
+use egui::{Color32, FontId, Stroke, TextStyle, Visuals};
+use std::collections::BTreeMap;
+
+/// What a screen reader announces for `note`: where it is, what it is
+/// filed under and its text
+pub fn note_label(note: &NoteData) -> String {
+    let mut label = format!("Note at {:.0}, {:.0}", note.pos.x, note.pos.y);
+    if let Some(lane) = &note.lane {
+        label += &format!(", in {lane}");
+    }
+    if let Some(category) = &note.category {
+        label += &format!(", {category}");
+    }
+    if !note.tags.is_empty() {
+        let tags: Vec<String> = note.tags.iter().map(|t| format!("#{t}")).collect();
+        label += &format!(", tagged {}", tags.join(" "));
+    }
+    if let Some(due) = note.due {
+        label += &format!(", due {}", format_local_datetime(due));
+    }
+    if note.priority != Priority::Normal {
+        label += &format!(", {} priority", note.priority.name().to_lowercase());
+    }
+    if note.locked {
+        label += ", locked";
+    }
+    let text = note.text.trim();
+    if text.is_empty() {
+        label + ", empty"
+    } else {
+        format!("{label}: {text}")
+    }
+}
+
+/// Panels in white and yellow on black, with thick outlines
+pub fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+    visuals.hyperlink_color = Color32::YELLOW;
+    visuals.selection.bg_fill = Color32::from_rgb(0, 90, 200);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+    let widgets = &mut visuals.widgets;
+    for state in [
+        &mut widgets.noninteractive,
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+        &mut widgets.open,
+    ] {
+        state.fg_stroke = Stroke::new(2.0, Color32::WHITE);
+        state.bg_stroke = Stroke::new(1.5, Color32::WHITE);
+    }
+    widgets.inactive.bg_fill = Color32::BLACK;
+    widgets.inactive.weak_bg_fill = Color32::BLACK;
+    widgets.hovered.bg_stroke = Stroke::new(2.0, Color32::YELLOW);
+    widgets.active.bg_stroke = Stroke::new(2.5, Color32::YELLOW);
+    visuals
+}
+
+/// The usual sizes of panel text, none smaller than `min_size` if given
+pub fn readable_text_styles(min_size: Option<f32>) -> BTreeMap<TextStyle, FontId> {
+    let mut styles = egui::Style::default().text_styles;
+    if let Some(min_size) = min_size {
+        for font in styles.values_mut() {
+            font.size = font.size.max(min_size);
+        }
+    }
+    styles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Pos2;
+
+    #[test]
+    fn labels_say_where_a_note_is_and_what_it_says() {
+        let mut note = NoteData {
+            text: "  Call the bank \n".into(),
+            pos: Pos2::new(120.4, 80.0),
+            ..Default::default()
+        };
+        assert_eq!(note_label(&note), "Note at 120, 80: Call the bank");
+        note.text.clear();
+        note.tags = vec!["money".into(), "urgent".into()];
+        note.category = Some("Chore".into());
+        note.locked = true;
+        assert_eq!(
+            note_label(&note),
+            "Note at 120, 80, Chore, tagged #money #urgent, locked, empty"
+        );
+    }
+
+    #[test]
+    fn small_panel_text_grows_to_the_minimum() {
+        let usual = readable_text_styles(None);
+        assert_eq!(usual, egui::Style::default().text_styles);
+        let readable = readable_text_styles(Some(18.0));
+        assert!(readable.values().all(|font| font.size >= 18.0));
+        // Text that is already bigger keeps its size
+        let heading = &usual[&TextStyle::Heading];
+        assert_eq!(readable[&TextStyle::Heading].size, heading.size.max(18.0));
+    }
+}
@@ -0,0 +1,91 @@
+//! Files and web links attached to a note beside its text, opened with the
+//! system's default application
+
+use crate::links::link_path;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A file or web link attached to a note
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Attachment {
+    File(PathBuf),
+    Url(String),
+}
+
+impl Attachment {
+    /// The attachment for what was typed: a web address (`https://…`,
+    /// `www.…`, `mailto:…`), a `file://` link or else a file path
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        if input.starts_with("file://") {
+            return link_path(input).map(Self::File);
+        }
+        if input.contains("://") || input.starts_with("mailto:") {
+            return Some(Self::Url(input.to_owned()));
+        }
+        if input.starts_with("www.") {
+            return Some(Self::Url(format!("https://{input}")));
+        }
+        Some(Self::File(PathBuf::from(input)))
+    }
+
+    /// Short name shown on the note: the file's name or the link's host
+    pub fn label(&self) -> String {
+        match self {
+            Self::File(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            Self::Url(url) => {
+                let rest = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+                let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+                if host.is_empty() { url } else { host }.to_owned()
+            }
+        }
+    }
+
+    /// The full path or address
+    pub fn target(&self) -> String {
+        match self {
+            Self::File(path) => path.display().to_string(),
+            Self::Url(url) => url.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_links_and_paths_become_attachments() {
+        let url = Attachment::parse(" https://example.com/docs/plan?x=1 ").unwrap();
+        assert_eq!(
+            url,
+            Attachment::Url("https://example.com/docs/plan?x=1".into())
+        );
+        assert_eq!(url.label(), "example.com");
+        assert_eq!(
+            Attachment::parse("www.example.com"),
+            Some(Attachment::Url("https://www.example.com".into()))
+        );
+        assert_eq!(
+            Attachment::parse("mailto:team@example.com")
+                .unwrap()
+                .label(),
+            "mailto:team@example.com"
+        );
+
+        let file = Attachment::parse("file:///tmp/My%20Notes.pdf").unwrap();
+        assert_eq!(file, Attachment::File(PathBuf::from("/tmp/My Notes.pdf")));
+        assert_eq!(file.label(), "My Notes.pdf");
+        assert_eq!(
+            Attachment::parse("docs/plan.md"),
+            Some(Attachment::File(PathBuf::from("docs/plan.md")))
+        );
+        assert_eq!(Attachment::parse("   "), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Saving periodically, but only when something changed since the last write
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Seconds between autosaves unless configured otherwise
+pub const DEFAULT_INTERVAL: f64 = 30.0;
+
+/// Intervals offered in the UI, in seconds; `None` turns autosave off
+pub const INTERVAL_CHOICES: [Option<f64>; 5] =
+    [None, Some(15.0), Some(30.0), Some(60.0), Some(300.0)];
+
+/// When the next autosave is due and what was written last
+#[derive(Debug, Clone)]
+pub struct Autosaver {
+    /// Seconds between saves, `None` when autosave is off
+    pub interval: Option<f64>,
+    next_due: f64,
+    last_written: Option<u64>,
+}
+
+impl Default for Autosaver {
+    fn default() -> Self {
+        Self::new(Some(DEFAULT_INTERVAL))
+    }
+}
+
+impl Autosaver {
+    pub fn new(interval: Option<f64>) -> Self {
+        Self {
+            interval,
+            next_due: interval.unwrap_or_default(),
+            last_written: None,
+        }
+    }
+
+    /// Whether a save is due at app time `now`; if so, the next one is
+    /// scheduled
+    pub fn due(&mut self, now: f64) -> bool {
+        let Some(interval) = self.interval else {
+            return false;
+        };
+        if now < self.next_due {
+            return false;
+        }
+        self.next_due = now + interval;
+        true
+    }
+
+    /// Change the interval, counting from app time `now`
+    pub fn set_interval(&mut self, interval: Option<f64>, now: f64) {
+        self.interval = interval;
+        self.next_due = now + interval.unwrap_or_default();
+    }
+
+    /// Whether `contents` differ from what was last saved or loaded
+    pub fn is_dirty(&self, contents: &str) -> bool {
+        self.last_written != Some(fingerprint(contents))
+    }
+
+    /// Remember `contents` as what is on disk now
+    pub fn mark_saved(&mut self, contents: &str) {
+        self.last_written = Some(fingerprint(contents));
+    }
+}
+
+fn fingerprint(contents: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Label for an autosave interval
+pub fn interval_label(interval: Option<f64>) -> String {
+    match interval {
+        None => "Off".into(),
+        Some(secs) if secs >= 60.0 => format!("{} min", secs / 60.0),
+        Some(secs) => format!("{secs} s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_are_due_once_per_interval() {
+        let mut saver = Autosaver::new(Some(30.0));
+        assert!(!saver.due(10.0));
+        assert!(saver.due(30.0));
+        assert!(!saver.due(45.0));
+        assert!(saver.due(61.0));
+        saver.set_interval(None, 70.0);
+        assert!(!saver.due(1000.0));
+    }
+
+    #[test]
+    fn only_changed_contents_are_dirty() {
+        let mut saver = Autosaver::default();
+        assert!(saver.is_dirty("{}"));
+        saver.mark_saved("{}");
+        assert!(!saver.is_dirty("{}"));
+        assert!(saver.is_dirty("{\"notes\": []}"));
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(interval_label(None), "Off");
+        assert_eq!(interval_label(Some(15.0)), "15 s");
+        assert_eq!(interval_label(Some(300.0)), "5 min");
+    }
+}
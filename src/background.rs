@@ -0,0 +1,125 @@
+//! How the board behind the notes is drawn
+
+use egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Pattern drawn over a board's background color
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackgroundStyle {
+    /// Just the color
+    Solid,
+    /// A dot on every grid point
+    Dotted,
+    /// Grid lines
+    #[default]
+    Lined,
+    /// Speckles like a cork board
+    Cork,
+}
+
+impl BackgroundStyle {
+    pub const ALL: [Self; 4] = [Self::Solid, Self::Dotted, Self::Lined, Self::Cork];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Solid => "Solid",
+            Self::Dotted => "Dotted",
+            Self::Lined => "Lined",
+            Self::Cork => "Cork",
+        }
+    }
+
+    /// Whether the style draws the snap grid
+    pub fn shows_grid(self) -> bool {
+        matches!(self, Self::Dotted | Self::Lined)
+    }
+}
+
+/// Side of the squares cork speckles are scattered over, in board units
+pub const CORK_CELL: f32 = 16.0;
+
+/// Most cork squares drawn at once; zoomed further out the texture is left
+/// out, as the speckles would be too small to see anyway
+pub const MAX_CORK_CELLS: usize = 20_000;
+
+/// A dot of the cork texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speckle {
+    pub center: Pos2,
+    pub radius: f32,
+    /// Darker than the background rather than lighter
+    pub dark: bool,
+}
+
+/// Speckles of the cork texture over `area`, in the same places every time
+pub fn cork_speckles(area: Rect) -> Vec<Speckle> {
+    if !area.is_positive() {
+        return Vec::new();
+    }
+    let (x0, y0) = (
+        (area.min.x / CORK_CELL).floor() as i64,
+        (area.min.y / CORK_CELL).floor() as i64,
+    );
+    let (x1, y1) = (
+        (area.max.x / CORK_CELL).ceil() as i64,
+        (area.max.y / CORK_CELL).ceil() as i64,
+    );
+    if ((x1 - x0) * (y1 - y0)) as usize > MAX_CORK_CELLS {
+        return Vec::new();
+    }
+    let mut speckles = Vec::new();
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let h = cell_hash(x, y);
+            // Two speckles per cell, each placed by its own bits of the hash
+            for bits in [h, h >> 32] {
+                let fraction = |shift: u32| ((bits >> shift) & 0xff) as f32 / 256.0;
+                speckles.push(Speckle {
+                    center: Pos2::new(x as f32, y as f32) * CORK_CELL
+                        + Vec2::new(fraction(0), fraction(8)) * CORK_CELL,
+                    radius: 0.6 + fraction(16) * 1.8,
+                    dark: bits & (1 << 24) != 0,
+                });
+            }
+        }
+    }
+    speckles
+}
+
+/// Scrambled bits for a cell, so speckles look random but stay put
+fn cell_hash(x: i64, y: i64) -> u64 {
+    let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (y as u64).rotate_left(32);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Board;
+
+    #[test]
+    fn cork_stays_put_and_thins_out() {
+        let area = Rect::from_min_size(Pos2::new(-40.0, 10.0), Vec2::new(64.0, 32.0));
+        let speckles = cork_speckles(area);
+        // Cells overlapping the area: 5 across, 3 down, two speckles each
+        assert_eq!(speckles.len(), 30);
+        assert_eq!(speckles, cork_speckles(area));
+        assert!(speckles.iter().any(|s| s.dark) && speckles.iter().any(|s| !s.dark));
+        let huge = Rect::from_min_size(Pos2::ZERO, Vec2::splat(CORK_CELL * 1000.0));
+        assert!(cork_speckles(huge).is_empty());
+    }
+
+    #[test]
+    fn boards_saved_before_styles_keep_their_lines() {
+        let mut json = serde_json::to_value(Board::default()).unwrap();
+        json.as_object_mut().unwrap().remove("background_style");
+        let board: Board = serde_json::from_value(json).unwrap();
+        assert_eq!(board.background_style, BackgroundStyle::Lined);
+        assert!(board.background_style.shows_grid());
+        assert!(!BackgroundStyle::Cork.shows_grid());
+    }
+}
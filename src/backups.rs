@@ -0,0 +1,119 @@
+//! Writing save files so a crash never leaves them half written
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of earlier versions kept next to a save file
+pub const SAVE_BACKUPS: usize = 3;
+
+/// Where the `n`-th most recent earlier version of `path` is kept:
+/// `<file name>.bak<n>` next to it
+pub fn numbered_backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak{n}"));
+    path.with_file_name(name)
+}
+
+/// Replace `path` with `contents` in one step, keeping up to `backups`
+/// earlier versions.
+///
+/// The contents go to a temporary file first, which is only renamed over
+/// `path` once fully on disk, so `path` holds either the old or the new
+/// contents at any moment.
+pub fn write_with_backups(path: &Path, contents: &str, backups: usize) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    let written = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        if backups > 0 && path.exists() {
+            for n in (1..backups).rev() {
+                let older = numbered_backup_path(path, n);
+                if older.exists() {
+                    fs::rename(&older, numbered_backup_path(path, n + 1))?;
+                }
+            }
+            // Copied rather than moved, so `path` never goes missing
+            fs::copy(path, numbered_backup_path(path, 1))?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Rewrite each earlier version of `path` kept by [`write_with_backups`]
+/// with what `rewrite` makes of it, deleting those it fails on
+pub fn rewrite_backups(
+    path: &Path,
+    backups: usize,
+    rewrite: impl Fn(&[u8]) -> io::Result<String>,
+) -> io::Result<()> {
+    for n in 1..=backups {
+        let backup = numbered_backup_path(path, n);
+        let Ok(contents) = fs::read(&backup) else {
+            continue;
+        };
+        match rewrite(&contents) {
+            Ok(rewritten) => write_with_backups(&backup, &rewritten, 0)?,
+            Err(_) => fs::remove_file(&backup)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earlier_versions_rotate_through_the_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("boards.json");
+        for version in 1..=5 {
+            write_with_backups(&path, &format!("v{version}"), 3).unwrap();
+        }
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "v5");
+        assert_eq!(read(numbered_backup_path(&path, 1)), "v4");
+        assert_eq!(read(numbered_backup_path(&path, 3)), "v2");
+        assert!(!numbered_backup_path(&path, 4).exists());
+        assert!(!dir.path().join("boards.json.tmp").exists());
+    }
+
+    #[test]
+    fn failed_writes_leave_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("boards.json");
+        write_with_backups(&path, "kept", 3).unwrap();
+        // A directory in the way of the temporary file makes writing fail
+        fs::create_dir(dir.path().join("boards.json.tmp")).unwrap();
+        assert!(write_with_backups(&path, "lost", 3).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "kept");
+        assert!(!numbered_backup_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn backups_can_be_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("boards.json");
+        for version in ["v1", "bad", "v3"] {
+            write_with_backups(&path, version, 3).unwrap();
+        }
+        rewrite_backups(&path, 3, |contents| match contents {
+            b"bad" => Err(io::Error::other("unreadable")),
+            _ => Ok(String::from_utf8_lossy(contents).to_uppercase()),
+        })
+        .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v3");
+        assert!(!numbered_backup_path(&path, 1).exists());
+        let second = fs::read_to_string(numbered_backup_path(&path, 2)).unwrap();
+        assert_eq!(second, "V1");
+        assert!(!numbered_backup_path(&path, 3).exists());
+    }
+}
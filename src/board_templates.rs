@@ -0,0 +1,274 @@
+//! Board templates: ready-made layouts of frames, lanes and notes that new
+//! boards can start from, bundled with plop or saved by the user
+
+use crate::background::BackgroundStyle;
+use crate::frames::Frame;
+use crate::lanes::{LANE_HEADER_HEIGHT, Lane, lane_span};
+use crate::{Board, CONTENT_MARGIN, DEFAULT_NOTE_COLOR, DEFAULT_NOTE_SIZE, NoteData};
+use egui::{Color32, Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// Folder of the user's board templates, next to the save file
+pub const BOARD_TEMPLATES_DIR: &str = "plop-board-templates";
+
+/// Template files shipped with plop
+const BUILTIN_TEMPLATES: [&str; 4] = [
+    include_str!("../assets/board-templates/kanban.json"),
+    include_str!("../assets/board-templates/retrospective.json"),
+    include_str!("../assets/board-templates/swot.json"),
+    include_str!("../assets/board-templates/weekly-planner.json"),
+];
+
+/// Layout a new board starts with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoardTemplate {
+    pub name: String,
+    /// One line saying what the template is for
+    #[serde(default)]
+    pub description: String,
+    /// Background color, `None` for the usual one
+    #[serde(default)]
+    pub background: Option<Color32>,
+    #[serde(default)]
+    pub background_style: BackgroundStyle,
+    #[serde(default)]
+    pub kanban: bool,
+    #[serde(default)]
+    pub lanes: Vec<Lane>,
+    #[serde(default)]
+    pub frames: Vec<TemplateFrame>,
+    #[serde(default)]
+    pub notes: Vec<TemplateNote>,
+}
+
+/// A frame of a template
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TemplateFrame {
+    pub title: String,
+    pub rect: Rect,
+    pub color: Color32,
+}
+
+/// A note of a template
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TemplateNote {
+    pub text: String,
+    pub pos: Pos2,
+    #[serde(default = "default_note_size")]
+    pub size: Vec2,
+    #[serde(default = "default_note_color")]
+    pub color: Color32,
+}
+
+fn default_note_size() -> Vec2 {
+    DEFAULT_NOTE_SIZE
+}
+
+fn default_note_color() -> Color32 {
+    DEFAULT_NOTE_COLOR
+}
+
+impl BoardTemplate {
+    /// The empty board
+    pub fn blank() -> Self {
+        Self {
+            name: "Blank".into(),
+            description: "An empty board".into(),
+            background: None,
+            background_style: BackgroundStyle::default(),
+            kanban: false,
+            lanes: Vec::new(),
+            frames: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Layout of `board`, to start other boards from
+    pub fn from_board(name: &str, board: &Board) -> Self {
+        Self {
+            name: name.into(),
+            description: format!("Made from board \"{}\"", board.name),
+            background: Some(board.background),
+            background_style: board.background_style,
+            kanban: board.kanban,
+            lanes: board.lanes.clone(),
+            frames: board
+                .frames
+                .iter()
+                .map(|f| TemplateFrame {
+                    title: f.title.clone(),
+                    rect: f.rect,
+                    color: f.color,
+                })
+                .collect(),
+            notes: board
+                .notes
+                .iter()
+                .map(|n| TemplateNote {
+                    text: n.text.clone(),
+                    pos: n.pos,
+                    size: n.size,
+                    color: n.color,
+                })
+                .collect(),
+        }
+    }
+
+    /// A new board named `name` laid out like the template, its view
+    /// showing the whole layout. Notes are numbered from 1, so the board is
+    /// meant to be added with [`crate::AppState::add_board_copy`].
+    pub fn to_board(&self, name: &str) -> Board {
+        let mut board = Board {
+            name: name.into(),
+            background_style: self.background_style,
+            kanban: self.kanban,
+            lanes: self.lanes.clone(),
+            ..Default::default()
+        };
+        if let Some(background) = self.background {
+            board.background = background;
+        }
+        board.frames = (1..)
+            .zip(&self.frames)
+            .map(|(id, f)| Frame {
+                id,
+                title: f.title.clone(),
+                rect: f.rect,
+                color: f.color,
+            })
+            .collect();
+        board.notes = (1..)
+            .zip(&self.notes)
+            .map(|(id, n)| {
+                let mut note = NoteData {
+                    id,
+                    text: n.text.clone(),
+                    pos: n.pos,
+                    size: n.size,
+                    color: n.color,
+                    ..Default::default()
+                };
+                board.place_in_lane(&mut note);
+                note
+            })
+            .collect();
+        if let Some(bounds) = self.bounds() {
+            board.scene_rect = bounds.expand(bounds.size().max_elem() * CONTENT_MARGIN);
+        }
+        board
+    }
+
+    /// Area covered by the frames, lanes and notes, if there are any
+    fn bounds(&self) -> Option<Rect> {
+        let lanes = (!self.lanes.is_empty()).then(|| {
+            let right = lane_span(self.lanes.len() - 1).max;
+            Rect::from_x_y_ranges(0.0..=right, 0.0..=LANE_HEADER_HEIGHT)
+        });
+        self.frames
+            .iter()
+            .map(|f| f.rect)
+            .chain(
+                self.notes
+                    .iter()
+                    .map(|n| Rect::from_min_size(n.pos, n.size)),
+            )
+            .chain(lanes)
+            .reduce(Rect::union)
+    }
+}
+
+/// Read a board template file
+pub fn read_board_template(path: &Path) -> io::Result<BoardTemplate> {
+    let data = std::fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write `template` to a board template file
+pub fn write_board_template(template: &BoardTemplate, path: &Path) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(template).map_err(io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+/// The blank template, the built-in ones, then every template file in
+/// `dir`; unreadable files are skipped
+pub fn board_templates(dir: &Path) -> Vec<BoardTemplate> {
+    let mut templates = vec![BoardTemplate::blank()];
+    templates.extend(
+        BUILTIN_TEMPLATES
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok()),
+    );
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return templates;
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    templates.extend(paths.iter().filter_map(|p| read_board_template(p).ok()));
+    templates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+    use crate::theme::theme_file_name;
+
+    #[test]
+    fn builtin_templates_lay_out_boards() {
+        for json in BUILTIN_TEMPLATES {
+            serde_json::from_str::<BoardTemplate>(json).unwrap();
+        }
+        let templates = board_templates(Path::new("/nonexistent"));
+        assert_eq!(templates.len(), BUILTIN_TEMPLATES.len() + 1);
+        assert_eq!(templates[0].to_board("Empty").notes, []);
+
+        let swot = templates.iter().find(|t| t.name == "SWOT").unwrap();
+        let board = swot.to_board("Plan");
+        assert_eq!(board.frames.len(), 4);
+        assert_eq!(board.next_frame_id(), 5);
+        // Every prompt sits in its frame, and the view shows them all
+        for (frame, note) in board.frames.iter().zip(&board.notes) {
+            assert!(frame.contains(note));
+            assert!(board.scene_rect.contains_rect(frame.rect));
+        }
+
+        // Notes of a kanban template land in their lanes
+        let kanban = templates.iter().find(|t| t.kanban).unwrap();
+        let board = kanban.to_board("Work");
+        assert_eq!(board.notes[0].lane.as_ref(), Some(&board.lanes[0].name));
+
+        let mut state = AppState::default();
+        let index = state.add_board_copy(board);
+        assert_eq!(state.boards[index].name, "Work");
+    }
+
+    #[test]
+    fn boards_save_as_user_templates() {
+        let mut state = AppState::default();
+        state.add_note("standup", Pos2::new(10.0, 20.0));
+        let board = state.board_mut();
+        board.background_style = BackgroundStyle::Cork;
+        board.frames.push(Frame {
+            id: 7,
+            title: "Today".into(),
+            rect: Rect::from_min_size(Pos2::ZERO, Vec2::splat(300.0)),
+            color: Color32::RED,
+        });
+        let template = BoardTemplate::from_board("Daily", board);
+
+        let dir = tempfile::tempdir().unwrap();
+        write_board_template(&template, &dir.path().join(theme_file_name("Daily"))).unwrap();
+        let templates = board_templates(dir.path());
+        assert_eq!(templates.last(), Some(&template));
+
+        let copy = template.to_board("Tuesday");
+        assert_eq!(copy.background_style, BackgroundStyle::Cork);
+        assert_eq!(copy.frames[0].id, 1);
+        assert_eq!(copy.notes[0].text, "standup");
+    }
+}
@@ -0,0 +1,127 @@
+//! Changing every selected note at once: color, tags, text or size
+
+use crate::geometry::MIN_NOTE_SIZE;
+use crate::history::Command;
+use crate::sanitize::{MAX_NOTE_CHARS, sanitize_text};
+use crate::tags::parse_tags;
+use crate::{Board, NoteData};
+use egui::{Color32, Vec2};
+
+/// A change made to all selected notes
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkEdit {
+    Recolor(Color32),
+    /// Add the tags typed, as in the note editor
+    AddTags(String),
+    RemoveTags(String),
+    /// Put this text before the note text, on a line of its own
+    Prepend(String),
+    /// Put this text after the note text, on a line of its own
+    Append(String),
+    /// Give the notes this size; locked notes keep theirs
+    Resize(Vec2),
+}
+
+impl BulkEdit {
+    /// `note` with the change made
+    pub fn apply(&self, note: &NoteData) -> NoteData {
+        let mut note = note.clone();
+        match self {
+            // A color of their own takes the notes out of their category
+            Self::Recolor(color) => {
+                note.color = *color;
+                note.category = None;
+            }
+            Self::AddTags(input) => {
+                for tag in parse_tags(input) {
+                    if !note.tags.contains(&tag) {
+                        note.tags.push(tag);
+                    }
+                }
+            }
+            Self::RemoveTags(input) => {
+                let removed = parse_tags(input);
+                note.tags.retain(|tag| !removed.contains(tag));
+            }
+            Self::Prepend(text) if !text.is_empty() => {
+                note.text = join_lines(text, &note.text);
+            }
+            Self::Append(text) if !text.is_empty() => {
+                note.text = join_lines(&note.text, text);
+            }
+            Self::Prepend(_) | Self::Append(_) => {}
+            Self::Resize(size) if !note.locked => note.size = size.max(MIN_NOTE_SIZE),
+            Self::Resize(_) => {}
+        }
+        note
+    }
+}
+
+/// `first` and `second` on lines of their own, kept within the length limit
+fn join_lines(first: &str, second: &str) -> String {
+    let text = if first.is_empty() || second.is_empty() {
+        format!("{first}{second}")
+    } else {
+        format!("{first}\n{second}")
+    };
+    sanitize_text(&text, MAX_NOTE_CHARS).text
+}
+
+impl Board {
+    /// Make `edit` to the notes `ids`, returning the change for undo, or
+    /// `None` if no note changed
+    pub fn bulk_edit(&mut self, ids: &[u64], edit: &BulkEdit) -> Option<Command> {
+        let mut changes = Vec::new();
+        for note in self.notes.iter_mut().filter(|n| ids.contains(&n.id)) {
+            let after = edit.apply(note);
+            if after != *note {
+                let before = std::mem::replace(note, after.clone());
+                changes.push(Command::Update { before, after });
+            }
+        }
+        Command::batch(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+
+    #[test]
+    fn bulk_edits_are_one_undo_step() {
+        let mut state = AppState::default();
+        let a = state.add_note("first", Pos2::ZERO);
+        let b = state.add_note("", Pos2::ZERO);
+        let untouched = state.add_note("other", Pos2::ZERO);
+        let board = state.board_mut();
+        board.notes[0].tags = vec!["todo".into()];
+        board.notes[1].locked = true;
+
+        let append = BulkEdit::Append("- done".into());
+        let Some(Command::Batch(changes)) = board.bulk_edit(&[a, b], &append) else {
+            panic!("expected one batch");
+        };
+        assert_eq!(changes.len(), 2);
+        assert_eq!(board.notes[0].text, "first\n- done");
+        assert_eq!(board.notes[1].text, "- done");
+        assert_eq!(board.notes[2].text, "other");
+        assert_eq!(board.notes[2].id, untouched);
+
+        board.bulk_edit(&[a, b], &BulkEdit::AddTags("#Todo, later".into()));
+        assert_eq!(board.notes[0].tags, ["todo", "later"]);
+        board.bulk_edit(&[a, b], &BulkEdit::RemoveTags("todo".into()));
+        assert_eq!(board.notes[1].tags, ["later"]);
+
+        let size = board.notes[1].size;
+        board.bulk_edit(&[a, b], &BulkEdit::Resize(Vec2::splat(1.0)));
+        assert_eq!(board.notes[0].size, MIN_NOTE_SIZE);
+        assert_eq!(board.notes[1].size, size);
+        assert!(
+            board
+                .bulk_edit(&[a], &BulkEdit::Resize(Vec2::ZERO))
+                .is_none()
+        );
+    }
+}
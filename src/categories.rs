@@ -0,0 +1,127 @@
+//! Named note categories such as "Bug" or "Idea", each with its own color,
+//! listed in a legend so everyone knows what the colors mean
+
+use crate::{Board, NoteData};
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// A kind of note and the color its notes get
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Category {
+    pub name: String,
+    pub color: Color32,
+}
+
+impl Category {
+    pub fn new(name: &str, color: Color32) -> Self {
+        Self {
+            name: name.into(),
+            color,
+        }
+    }
+}
+
+impl NoteData {
+    /// Put the note in `category`, giving it the category's color, or take
+    /// it out of its category, keeping its color
+    pub fn set_category(&mut self, category: Option<&Category>) {
+        self.category = category.map(|c| c.name.clone());
+        if let Some(category) = category {
+            self.color = category.color;
+        }
+    }
+}
+
+impl Board {
+    /// The category named `name`
+    pub fn category(&self, name: &str) -> Option<&Category> {
+        self.categories.iter().find(|c| c.name == name)
+    }
+
+    /// How many notes are in each category, in the order of the categories
+    pub fn category_counts(&self) -> Vec<usize> {
+        self.categories
+            .iter()
+            .map(|c| {
+                self.notes
+                    .iter()
+                    .filter(|n| n.category.as_deref() == Some(&c.name))
+                    .count()
+            })
+            .collect()
+    }
+
+    /// Rename the category at `index`, along with the notes in it
+    pub fn rename_category(&mut self, index: usize, name: &str) {
+        let Some(category) = self.categories.get_mut(index) else {
+            return;
+        };
+        let old = std::mem::replace(&mut category.name, name.into());
+        for note in &mut self.notes {
+            if note.category.as_deref() == Some(old.as_str()) {
+                note.category = Some(name.into());
+            }
+        }
+    }
+
+    /// Give the category at `index`, and the notes in it, `color`
+    pub fn recolor_category(&mut self, index: usize, color: Color32) {
+        let Some(category) = self.categories.get_mut(index) else {
+            return;
+        };
+        category.color = color;
+        for note in &mut self.notes {
+            if note.category.as_deref() == Some(category.name.as_str()) {
+                note.color = color;
+            }
+        }
+    }
+
+    /// Remove the category at `index`; its notes keep their color
+    pub fn remove_category(&mut self, index: usize) {
+        if index >= self.categories.len() {
+            return;
+        }
+        let category = self.categories.remove(index);
+        for note in &mut self.notes {
+            if note.category.as_deref() == Some(category.name.as_str()) {
+                note.category = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+
+    #[test]
+    fn categories_color_their_notes() {
+        let mut state = AppState::default();
+        state.add_note("crash on save", Pos2::ZERO);
+        state.add_note("dark mode?", Pos2::ZERO);
+        let board = state.board_mut();
+        board.categories = vec![
+            Category::new("Bug", Color32::RED),
+            Category::new("Idea", Color32::YELLOW),
+        ];
+        let bug = board.category("Bug").cloned();
+        board.notes[0].set_category(bug.as_ref());
+        assert_eq!(board.notes[0].color, Color32::RED);
+        assert_eq!(board.category_counts(), [1, 0]);
+
+        // Renaming or recoloring the category carries its notes along
+        board.rename_category(0, "Defect");
+        board.recolor_category(0, Color32::DARK_RED);
+        assert_eq!(board.notes[0].category.as_deref(), Some("Defect"));
+        assert_eq!(board.notes[0].color, Color32::DARK_RED);
+        assert_eq!(board.notes[1].color, Color32::YELLOW);
+        // Removing it leaves the notes their color
+        board.remove_category(0);
+        assert_eq!(board.notes[0].category, None);
+        assert_eq!(board.notes[0].color, Color32::DARK_RED);
+        assert!(board.category("Defect").is_none());
+    }
+}
@@ -0,0 +1,91 @@
+//! Checklists in note text: lines like `[ ] buy milk` or `- [x] done`
+
+/// Checkbox state of a line and the text after the box, or `None` if the
+/// line isn't a checklist item
+pub fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let trimmed = line.trim_start();
+    let trimmed = trimmed.strip_prefix("- ").unwrap_or(trimmed);
+    let (checked, rest) = if let Some(rest) = trimmed.strip_prefix("[ ]") {
+        (false, rest)
+    } else if let Some(rest) = trimmed
+        .strip_prefix("[x]")
+        .or_else(|| trimmed.strip_prefix("[X]"))
+    {
+        (true, rest)
+    } else {
+        return None;
+    };
+    // The box has to stand alone, so `[x]y` stays text
+    match rest.strip_prefix(' ') {
+        Some(text) => Some((checked, text)),
+        None if rest.is_empty() => Some((checked, rest)),
+        None => None,
+    }
+}
+
+/// Whether any line of `text` is a checklist item
+pub fn is_checklist(text: &str) -> bool {
+    text.lines().any(|line| parse_item(line).is_some())
+}
+
+/// Number of checked items and of all items
+pub fn progress(text: &str) -> (usize, usize) {
+    let items: Vec<bool> = text
+        .lines()
+        .filter_map(parse_item)
+        .map(|(checked, _)| checked)
+        .collect();
+    (items.iter().filter(|&&c| c).count(), items.len())
+}
+
+/// `text` with the box on line `line` (counting from 0) checked or
+/// unchecked; other lines are left exactly as they are
+pub fn toggle_item(text: &str, line: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (i, segment) in text.split_inclusive('\n').enumerate() {
+        if i == line
+            && let Some((checked, _)) = parse_item(segment.trim_end_matches(['\n', '\r']))
+        {
+            // The box follows the indentation and an optional bullet
+            let trimmed = segment.trim_start();
+            let mut start = segment.len() - trimmed.len();
+            if trimmed.starts_with("- ") {
+                start += 2;
+            }
+            out.push_str(&segment[..start]);
+            out.push_str(if checked { "[ ]" } else { "[x]" });
+            out.push_str(&segment[start + 3..]);
+            continue;
+        }
+        out.push_str(segment);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_are_recognized() {
+        assert_eq!(parse_item("[ ] milk"), Some((false, "milk")));
+        assert_eq!(parse_item("  - [x] eggs"), Some((true, "eggs")));
+        assert_eq!(parse_item("[X]"), Some((true, "")));
+        assert_eq!(parse_item("[x]y"), None);
+        assert_eq!(parse_item("- milk"), None);
+        assert!(is_checklist("Shopping\n[ ] milk"));
+        assert!(!is_checklist("Shopping\n- milk"));
+        assert_eq!(progress("[x] a\n[ ] b\nc\n- [X] d"), (2, 3));
+    }
+
+    #[test]
+    fn toggling_changes_only_that_box() {
+        let text = "Todo [ ]\n[ ] a [x]\n- [X] b [x]\n";
+        assert_eq!(toggle_item(text, 1), "Todo [ ]\n[x] a [x]\n- [X] b [x]\n");
+        assert_eq!(toggle_item(text, 2), "Todo [ ]\n[ ] a [x]\n- [ ] b [x]\n");
+        // Lines that aren't items stay as they are
+        assert_eq!(toggle_item(text, 0), text);
+        assert_eq!(toggle_item(text, 9), text);
+        assert_eq!(toggle_item("[x]\r\n", 0), "[ ]\r\n");
+    }
+}
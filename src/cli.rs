@@ -0,0 +1,493 @@
+//! Headless commands that run without opening the window
+
+use crate::expiry::now_secs;
+use crate::export::{ExportFormat, export_board, export_workspace};
+use crate::merge::merge_states;
+use crate::sanitize::{MAX_NOTE_CHARS, sanitize_text};
+use crate::search::SearchQuery;
+use crate::{AppState, DEFAULT_NOTE_COLOR, DEFAULT_NOTE_SIZE, NoteData, default_save_path};
+use egui::Pos2;
+use std::path::PathBuf;
+
+/// Usage text printed for invalid arguments
+pub const USAGE: &str = "\
+usage: plop [COMMAND]
+
+Without a command the board window opens.
+
+commands:
+  boards
+      List the saved boards; * marks the one that opens
+  notes [--board B]
+      List the notes of a board: id, position and first line
+  add <TEXT> [--at X,Y] [--board B]
+      Add a note and print its id
+  grep <QUERY> [--board B]
+      Find notes, with the search syntax of the window (tag:, color:, /regex/)
+  export <OUT> [--board B] [--format png|md|svg|html|csv] [--scale N]
+      Export a board; the format follows the extension of OUT by default
+  export-workspace <DIR> <OUT> [--format png|md|svg|html|csv|all] [--scale N]
+      Export every board saved in DIR into the OUT directory
+  merge <OTHER> [--base BASE]
+      Merge the changes of another copy of the save file into it; BASE is
+      the version both copies started from, if there is one
+
+Board commands read the save file of the window, or the one given with
+--file PATH. B is a board's number as listed by `boards` or its name; the
+board that opens is used by default.";
+
+/// A parsed headless command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    ExportWorkspace {
+        dir: PathBuf,
+        out: PathBuf,
+        formats: Vec<ExportFormat>,
+        scale: f32,
+    },
+    Boards {
+        file: PathBuf,
+    },
+    Notes {
+        file: PathBuf,
+        board: Option<String>,
+    },
+    Add {
+        file: PathBuf,
+        board: Option<String>,
+        text: String,
+        at: Pos2,
+    },
+    Grep {
+        file: PathBuf,
+        board: Option<String>,
+        query: String,
+    },
+    Export {
+        file: PathBuf,
+        board: Option<String>,
+        out: PathBuf,
+        format: ExportFormat,
+        scale: f32,
+    },
+    Merge {
+        file: PathBuf,
+        other: PathBuf,
+        base: Option<PathBuf>,
+    },
+}
+
+/// Parse command line arguments (without the program name).
+///
+/// Returns `Ok(None)` when no command was given and the GUI should start.
+pub fn parse(args: &[String]) -> Result<Option<Command>, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    match command.as_str() {
+        "export-workspace" => parse_export_workspace(rest).map(Some),
+        "boards" | "notes" | "add" | "grep" | "export" | "merge" => {
+            parse_board_command(command, rest).map(Some)
+        }
+        other => Err(format!("unknown command `{other}`")),
+    }
+}
+
+fn parse_board_command(command: &str, args: &[String]) -> Result<Command, String> {
+    let mut positional = Vec::new();
+    let mut file = None;
+    let mut board = None;
+    let mut at = None;
+    let mut format = None;
+    let mut scale = 1.0;
+    let mut base = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("{arg} expects a value"));
+        match arg.as_str() {
+            "--file" => file = Some(PathBuf::from(value()?)),
+            "--board" if !matches!(command, "boards" | "merge") => board = Some(value()?.clone()),
+            "--base" if command == "merge" => base = Some(PathBuf::from(value()?)),
+            "--at" if command == "add" => {
+                at = Some(
+                    value()?
+                        .split_once(',')
+                        .and_then(|(x, y)| {
+                            Some(Pos2::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+                        })
+                        .filter(|at| at.is_finite())
+                        .ok_or("--at expects X,Y")?,
+                )
+            }
+            "--format" if command == "export" => {
+                let name = value()?;
+                format = Some(
+                    ExportFormat::from_extension(name)
+                        .ok_or_else(|| format!("invalid --format {name:?}"))?,
+                );
+            }
+            "--scale" if command == "export" => {
+                scale = value()?
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|s| *s > 0.0)
+                    .ok_or("--scale expects a positive number")?;
+            }
+            flag if flag.starts_with("--") => {
+                return Err(format!("{command} does not take {flag}"));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let file = file.unwrap_or_else(default_save_path);
+    if matches!(command, "boards" | "notes") && !positional.is_empty() {
+        return Err(format!("{command} takes no arguments"));
+    }
+    let mut one = |what: &str| match <[String; 1]>::try_from(std::mem::take(&mut positional)) {
+        Ok([value]) => Ok(value),
+        Err(_) => Err(format!("{command} expects {what}")),
+    };
+    Ok(match command {
+        "boards" => Command::Boards { file },
+        "notes" => Command::Notes { file, board },
+        "add" => Command::Add {
+            file,
+            board,
+            text: one("<TEXT>")?,
+            at: at.unwrap_or(Pos2::ZERO),
+        },
+        "grep" => Command::Grep {
+            file,
+            board,
+            query: one("<QUERY>")?,
+        },
+        "merge" => Command::Merge {
+            file,
+            other: PathBuf::from(one("<OTHER>")?),
+            base,
+        },
+        _ => {
+            let out = PathBuf::from(one("<OUT>")?);
+            let format = format
+                .or_else(|| {
+                    let ext = out.extension()?.to_str()?;
+                    ExportFormat::from_extension(ext)
+                })
+                .unwrap_or(ExportFormat::Png);
+            Command::Export {
+                file,
+                board,
+                out,
+                format,
+                scale,
+            }
+        }
+    })
+}
+
+fn parse_export_workspace(args: &[String]) -> Result<Command, String> {
+    let mut positional = Vec::new();
+    let mut formats = vec![ExportFormat::Png, ExportFormat::Markdown];
+    let mut scale = 1.0;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => {
+                formats = match iter.next().map(String::as_str) {
+                    Some("all") => ExportFormat::ALL.to_vec(),
+                    other => match other.and_then(ExportFormat::from_extension) {
+                        Some(format) => vec![format],
+                        None => return Err(format!("invalid --format {other:?}")),
+                    },
+                }
+            }
+            "--scale" => {
+                scale = iter
+                    .next()
+                    .and_then(|s| s.parse::<f32>().ok())
+                    .filter(|s| *s > 0.0)
+                    .ok_or("--scale expects a positive number")?;
+            }
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    let [dir, out] = <[PathBuf; 2]>::try_from(positional)
+        .map_err(|_| "export-workspace expects <DIR> <OUT>".to_string())?;
+    Ok(Command::ExportWorkspace {
+        dir,
+        out,
+        formats,
+        scale,
+    })
+}
+
+/// Run a command, returning a report to print
+pub fn execute(command: Command) -> Result<String, String> {
+    match command {
+        Command::ExportWorkspace {
+            dir,
+            out,
+            formats,
+            scale,
+        } => {
+            let written = export_workspace(&dir, &out, &formats, scale)
+                .map_err(|e| format!("export failed: {e}"))?;
+            Ok(written
+                .iter()
+                .map(|p| format!("wrote {}", p.display()))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Command::Boards { file } => {
+            let state = load(&file)?;
+            Ok(state
+                .boards
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    let marker = if i == state.active { '*' } else { ' ' };
+                    format!("{marker} {}. {} ({} notes)", i + 1, b.name, b.notes.len())
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Command::Notes { file, board } => {
+            let state = load(&file)?;
+            let board = &state.boards[find_board(&state, board.as_deref())?];
+            Ok(board
+                .notes
+                .iter()
+                .map(|n| format!("{}\t{},{}\t{}", n.id, n.pos.x, n.pos.y, first_line(n)))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Command::Add {
+            file,
+            board,
+            text,
+            at,
+        } => {
+            let mut state = load(&file)?;
+            let index = find_board(&state, board.as_deref())?;
+            let id = state.next_note_id;
+            state.next_note_id += 1;
+            let board = &mut state.boards[index];
+            let now = now_secs();
+            let note = NoteData {
+                id,
+                text: sanitize_text(&text, MAX_NOTE_CHARS).text,
+                pos: at,
+                size: DEFAULT_NOTE_SIZE,
+                color: DEFAULT_NOTE_COLOR,
+                z: board.top_z(),
+                created_at: Some(now),
+                modified_at: Some(now),
+                ..Default::default()
+            };
+            board.notes.push(note);
+            state
+                .save_to_file(&file)
+                .map_err(|e| format!("could not save {}: {e}", file.display()))?;
+            Ok(id.to_string())
+        }
+        Command::Grep { file, board, query } => {
+            let state = load(&file)?;
+            let query = SearchQuery::parse(&query).map_err(|e| format!("invalid query: {e}"))?;
+            let boards = match board {
+                Some(board) => vec![find_board(&state, Some(&board))?],
+                None => (0..state.boards.len()).collect(),
+            };
+            let found: Vec<String> = boards
+                .into_iter()
+                .map(|i| &state.boards[i])
+                .flat_map(|board| {
+                    let ids = query.search(board);
+                    board
+                        .notes
+                        .iter()
+                        .filter(move |n| ids.contains(&n.id))
+                        .map(move |n| format!("{}\t{}\t{}", board.name, n.id, first_line(n)))
+                })
+                .collect();
+            if found.is_empty() {
+                return Err("no matching notes".into());
+            }
+            Ok(found.join("\n"))
+        }
+        Command::Export {
+            file,
+            board,
+            out,
+            format,
+            scale,
+        } => {
+            let state = load(&file)?;
+            let board = &state.boards[find_board(&state, board.as_deref())?];
+            export_board(board, &out, format, scale).map_err(|e| format!("export failed: {e}"))?;
+            Ok(format!("wrote {}", out.display()))
+        }
+        Command::Merge { file, other, base } => {
+            let mut state = load(&file)?;
+            let theirs = load(&other)?;
+            let base = base.map(|base| load(&base)).transpose()?;
+            let report = merge_states(base.as_ref(), &mut state, &theirs);
+            state
+                .save_to_file(&file)
+                .map_err(|e| format!("could not save {}: {e}", file.display()))?;
+            Ok(report.summary())
+        }
+    }
+}
+
+/// The boards saved in `file`
+fn load(file: &std::path::Path) -> Result<AppState, String> {
+    AppState::try_load_from_file(file)
+        .map_err(|e| format!("could not read {}: {e}", file.display()))
+}
+
+/// Index of the board given by number (counting from 1) or name, or of the
+/// active one
+fn find_board(state: &AppState, board: Option<&str>) -> Result<usize, String> {
+    let Some(board) = board else {
+        return Ok(state.active);
+    };
+    let by_number = board
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=state.boards.len()).contains(n))
+        .map(|n| n - 1);
+    by_number
+        .or_else(|| {
+            state
+                .boards
+                .iter()
+                .position(|b| b.name.eq_ignore_ascii_case(board))
+        })
+        .ok_or_else(|| format!("no board {board:?}"))
+}
+
+fn first_line(note: &NoteData) -> &str {
+    note.text.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoardOps;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn no_arguments_starts_gui() {
+        assert_eq!(parse(&[]), Ok(None));
+    }
+
+    #[test]
+    fn parses_export_workspace() {
+        let cmd = parse(&args(&[
+            "export-workspace",
+            "boards",
+            "out",
+            "--format",
+            "md",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cmd,
+            Some(Command::ExportWorkspace {
+                dir: "boards".into(),
+                out: "out".into(),
+                formats: vec![ExportFormat::Markdown],
+                scale: 1.0,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&args(&["frobnicate"])).is_err());
+        assert!(parse(&args(&["export-workspace", "only-one"])).is_err());
+        assert!(parse(&args(&["export-workspace", "a", "b", "--scale", "-1"])).is_err());
+        assert!(parse(&args(&["add"])).is_err());
+        assert!(parse(&args(&["add", "a", "--at", "1"])).is_err());
+        assert!(parse(&args(&["add", "a", "--at", "NaN,2"])).is_err());
+        assert!(parse(&args(&["add", "a", "--at", "1,inf"])).is_err());
+        assert!(parse(&args(&["boards", "--at", "1,2"])).is_err());
+    }
+
+    #[test]
+    fn parses_board_commands() {
+        assert_eq!(
+            parse(&args(&[
+                "add", "Buy milk", "--at", "10, 20", "--file", "b.json"
+            ])),
+            Ok(Some(Command::Add {
+                file: "b.json".into(),
+                board: None,
+                text: "Buy milk".into(),
+                at: Pos2::new(10.0, 20.0),
+            }))
+        );
+        assert_eq!(
+            parse(&args(&[
+                "export", "out.md", "--board", "2", "--file", "b.json"
+            ])),
+            Ok(Some(Command::Export {
+                file: "b.json".into(),
+                board: Some("2".into()),
+                out: "out.md".into(),
+                format: ExportFormat::Markdown,
+                scale: 1.0,
+            }))
+        );
+        assert_eq!(
+            parse(&args(&["export", "board.svg"])),
+            Ok(Some(Command::Export {
+                file: default_save_path(),
+                board: None,
+                out: "board.svg".into(),
+                format: ExportFormat::Svg,
+                scale: 1.0,
+            }))
+        );
+    }
+
+    #[test]
+    fn board_commands_edit_and_read_the_save_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("boards.json");
+        let mut state = AppState::default();
+        state.add_board("Ideas");
+        state.save_to_file(&file).unwrap();
+        let run = |list: &[&str]| {
+            let mut list = args(list);
+            list.extend(args(&["--file", file.to_str().unwrap()]));
+            execute(parse(&list).unwrap().unwrap())
+        };
+
+        let id = run(&["add", "Ship it", "--board", "ideas", "--at", "5,6"]).unwrap();
+        assert_eq!(
+            run(&["notes", "--board", "2"]).unwrap(),
+            format!("{id}\t5,6\tShip it")
+        );
+        assert_eq!(
+            run(&["grep", "/^ship/"]).unwrap(),
+            format!("Ideas\t{id}\tShip it")
+        );
+        assert!(run(&["grep", "nothing"]).is_err());
+        assert!(run(&["notes", "--board", "Missing"]).is_err());
+        assert!(run(&["boards"]).unwrap().contains("2. Ideas (1 notes)"));
+
+        let other = dir.path().join("other.json");
+        let mut theirs = AppState::default();
+        theirs.add_note("From elsewhere", Pos2::ZERO);
+        theirs.save_to_file(&other).unwrap();
+        assert_eq!(
+            run(&["merge", other.to_str().unwrap()]).unwrap(),
+            "1 added, 0 updated, 0 removed, 0 conflicting"
+        );
+        assert!(run(&["grep", "elsewhere"]).is_ok());
+    }
+}
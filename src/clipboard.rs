@@ -0,0 +1,149 @@
+//! Copying and pasting notes within plop
+
+use crate::connectors::Connector;
+use crate::{Board, NoteData};
+use egui::{Pos2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// How far from the cursor pasted notes land
+pub const PASTE_OFFSET: Vec2 = Vec2::splat(10.0);
+
+/// Copied notes and the connectors between them
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NoteClipboard {
+    notes: Vec<NoteData>,
+    connectors: Vec<Connector>,
+}
+
+impl NoteClipboard {
+    /// Copy `notes`, keeping the `connectors` that link two of them
+    pub fn copy(notes: Vec<NoteData>, connectors: &[Connector]) -> Self {
+        let copied = |id| notes.iter().any(|n| n.id == id);
+        let connectors = connectors
+            .iter()
+            .filter(|c| copied(c.from_note) && copied(c.to_note))
+            .cloned()
+            .collect();
+        Self { notes, connectors }
+    }
+
+    /// Whether nothing was copied
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// Text of the copied notes, for other programs: one paragraph per note,
+    /// from the top-left
+    pub fn text(&self) -> String {
+        let mut notes: Vec<&NoteData> = self.notes.iter().collect();
+        notes.sort_by(|a, b| {
+            a.pos
+                .y
+                .total_cmp(&b.pos.y)
+                .then(a.pos.x.total_cmp(&b.pos.x))
+        });
+        let texts: Vec<&str> = notes.iter().map(|n| n.text.trim()).collect();
+        texts.join("\n\n")
+    }
+
+    /// Top-left corner of the copied notes
+    pub fn origin(&self) -> Option<Pos2> {
+        self.notes.iter().map(|n| n.pos).reduce(|a, b| a.min(b))
+    }
+
+    /// New copies of the notes with ids from `next_note_id`, moved so their
+    /// top-left corner is at `at` and stacked above the notes of `board`,
+    /// along with their connectors
+    pub fn paste(
+        &self,
+        board: &Board,
+        next_note_id: &mut u64,
+        at: Pos2,
+    ) -> (Vec<NoteData>, Vec<Connector>) {
+        let Some(origin) = self.origin() else {
+            return Default::default();
+        };
+        let mut stacked: Vec<&NoteData> = self.notes.iter().collect();
+        stacked.sort_by_key(|n| n.z);
+        let z = board.top_z();
+        let mut ids = Vec::new();
+        let notes = stacked
+            .into_iter()
+            .zip(z..)
+            .map(|(note, z)| {
+                let id = *next_note_id;
+                *next_note_id += 1;
+                ids.push((note.id, id));
+                NoteData {
+                    id,
+                    pos: at + (note.pos - origin),
+                    z,
+                    rank: None,
+                    ..note.clone()
+                }
+            })
+            .collect();
+        let new_id = |old| ids.iter().find(|(o, _)| *o == old).map(|(_, n)| *n);
+        let connectors = self
+            .connectors
+            .iter()
+            .filter_map(|c| {
+                Some(Connector {
+                    from_note: new_id(c.from_note)?,
+                    to_note: new_id(c.to_note)?,
+                    ..c.clone()
+                })
+            })
+            .collect();
+        (notes, connectors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::ConnectorStyle;
+
+    #[test]
+    fn pasted_notes_get_new_ids_and_keep_their_layout() {
+        let note = |id: u64, x: f32| NoteData {
+            id,
+            text: format!("note {id}"),
+            pos: Pos2::new(x, 50.0),
+            ..Default::default()
+        };
+        let link = |from_note, to_note| Connector {
+            from_note,
+            to_note,
+            style: ConnectorStyle::Line,
+            label: "next".into(),
+        };
+        let board = Board {
+            notes: vec![note(1, 100.0), note(2, 200.0), note(3, 300.0)],
+            connectors: vec![link(1, 2), link(2, 3)],
+            ..Default::default()
+        };
+        let clipboard = NoteClipboard::copy(board.notes[..2].to_vec(), &board.connectors);
+        assert_eq!(clipboard.text(), "note 1\n\nnote 2");
+
+        let mut next_note_id = 10;
+        let (notes, connectors) = clipboard.paste(&board, &mut next_note_id, Pos2::ZERO);
+        assert_eq!(next_note_id, 12);
+        let placed: Vec<(u64, &str, Pos2)> = notes
+            .iter()
+            .map(|n| (n.id, n.text.as_str(), n.pos))
+            .collect();
+        assert_eq!(
+            placed,
+            [
+                (10, "note 1", Pos2::ZERO),
+                (11, "note 2", Pos2::new(100.0, 0.0))
+            ]
+        );
+        assert!(notes.iter().all(|n| n.z > 0));
+        assert_eq!(connectors, [link(10, 11)]);
+
+        let (again, _) = clipboard.paste(&board, &mut next_note_id, Pos2::ZERO);
+        assert_eq!(again[0].id, 12);
+    }
+}
@@ -0,0 +1,56 @@
+//! Discussion under a note: comments with who wrote them and when
+
+use crate::NoteData;
+use serde::{Deserialize, Serialize};
+
+/// A remark left on a note
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub author: String,
+    /// When it was written, in seconds since the Unix epoch
+    pub at: u64,
+    pub text: String,
+}
+
+impl NoteData {
+    /// Add `text` as a comment by `author` at `now`, unless it is blank;
+    /// returns whether it was added
+    pub fn add_comment(&mut self, author: &str, text: &str, now: u64) -> bool {
+        let text = text.trim();
+        if text.is_empty() {
+            return false;
+        }
+        self.comments.push(Comment {
+            author: author.into(),
+            at: now,
+            text: text.into(),
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_comments_are_not_added() {
+        let mut note = NoteData::default();
+        assert!(note.add_comment("Ada", "  Looks good \n", 100));
+        assert!(!note.add_comment("Ada", " \n", 200));
+        assert_eq!(
+            note.comments,
+            [Comment {
+                author: "Ada".into(),
+                at: 100,
+                text: "Looks good".into(),
+            }]
+        );
+
+        // Notes saved before comments have none
+        let mut json = serde_json::to_value(NoteData::default()).unwrap();
+        json.as_object_mut().unwrap().remove("comments");
+        let old: NoteData = serde_json::from_value(json).unwrap();
+        assert!(old.comments.is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! Files of preferences kept apart from the boards: settings, keys, recent
+//! files and the last session, all in `plop` in the platform's config folder
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// App-wide settings, see [`crate::settings::Settings`]
+pub const SETTINGS_FILE: &str = "settings.json";
+/// Keyboard shortcuts, see [`crate::keymap::Keymap`]
+pub const KEYMAP_FILE: &str = "keys.json";
+/// Save files used recently, see [`crate::recent::RecentFiles`]
+pub const RECENT_FILE: &str = "recent.json";
+/// Where the user left off, see [`crate::session::Session`]
+pub const SESSION_FILE: &str = "session.json";
+
+/// File the preferences called `name` are kept in: in the config folder,
+/// or next to the save file at `save_path` where there is none
+pub fn config_path(save_path: &Path, name: &str) -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join("plop").join(name))
+        .unwrap_or_else(|| old_config_path(save_path, name))
+}
+
+/// File the preferences called `name` were kept in before, next to the save
+/// file at `save_path`
+pub fn old_config_path(save_path: &Path, name: &str) -> PathBuf {
+    save_path.with_file_name(format!("plop-{name}"))
+}
+
+/// Read from the JSON file at `path`, or at `old` where it was kept before
+/// while there is none at `path`; `NotFound` if there is neither,
+/// `InvalidData` if it can't be parsed
+pub fn load_json<T: DeserializeOwned>(path: &Path, old: &Path) -> io::Result<T> {
+    let path = if path.exists() { path } else { old };
+    let data = std::fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Save as JSON to `path`, making its folder if needed
+pub fn save_json<T: Serialize>(value: &T, path: &Path) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recent::RecentFiles;
+
+    #[test]
+    fn preferences_carry_over_from_next_to_the_save_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_path = dir.path().join("boards.json");
+        let old = old_config_path(&save_path, RECENT_FILE);
+        assert_eq!(old, dir.path().join("plop-recent.json"));
+        let path = dir.path().join("config").join(RECENT_FILE);
+        let error = load_json::<RecentFiles>(&path, &old).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        let mut recent = RecentFiles::default();
+        recent.add(&save_path);
+        save_json(&recent, &old).unwrap();
+        assert_eq!(load_json::<RecentFiles>(&path, &old).unwrap(), recent);
+
+        // Once saved in the config folder, that copy wins
+        let mut moved = recent.clone();
+        moved.add(&dir.path().join("other.json"));
+        save_json(&moved, &path).unwrap();
+        assert_eq!(load_json::<RecentFiles>(&path, &old).unwrap(), moved);
+
+        std::fs::write(&path, "not json").unwrap();
+        let error = load_json::<RecentFiles>(&path, &old).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
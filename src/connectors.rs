@@ -0,0 +1,258 @@
+//! Arrows between notes that follow them around
+
+use crate::Board;
+use crate::history::Command;
+use egui::{Color32, Pos2, Rect, Shape, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// How a connector is drawn
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectorStyle {
+    /// Solid line with an arrowhead at the target note
+    #[default]
+    Arrow,
+    /// Solid line without an arrowhead
+    Line,
+    /// Dashed line with an arrowhead
+    Dashed,
+}
+
+impl ConnectorStyle {
+    /// Every style, for pickers
+    pub const ALL: [ConnectorStyle; 3] = [
+        ConnectorStyle::Arrow,
+        ConnectorStyle::Line,
+        ConnectorStyle::Dashed,
+    ];
+
+    /// Name shown in the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            ConnectorStyle::Arrow => "Arrow",
+            ConnectorStyle::Line => "Line",
+            ConnectorStyle::Dashed => "Dashed",
+        }
+    }
+
+    /// Whether the target end gets an arrowhead
+    pub fn has_head(self) -> bool {
+        self != ConnectorStyle::Line
+    }
+}
+
+/// A connection from one note to another
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Connector {
+    pub from_note: u64,
+    pub to_note: u64,
+    #[serde(default)]
+    pub style: ConnectorStyle,
+    /// Text drawn at the middle of the connector
+    #[serde(default)]
+    pub label: String,
+}
+
+/// Point where the line from the center of `rect` towards `target` leaves
+/// the rect
+pub fn border_point(rect: Rect, target: Pos2) -> Pos2 {
+    let center = rect.center();
+    let dir = target - center;
+    if dir == Vec2::ZERO {
+        return center;
+    }
+    let half = rect.size() * 0.5;
+    let scale_x = if dir.x == 0.0 {
+        f32::INFINITY
+    } else {
+        half.x / dir.x.abs()
+    };
+    let scale_y = if dir.y == 0.0 {
+        f32::INFINITY
+    } else {
+        half.y / dir.y.abs()
+    };
+    center + dir * scale_x.min(scale_y).min(1.0)
+}
+
+/// End points of a connector between the notes occupying `from` and `to`,
+/// on the borders of the notes
+pub fn connector_ends(from: Rect, to: Rect) -> (Pos2, Pos2) {
+    (
+        border_point(from, to.center()),
+        border_point(to, from.center()),
+    )
+}
+
+/// The two outer points of an arrowhead of `size` whose tip is at `tip`,
+/// for a line coming from `tail`
+pub fn arrow_head(tail: Pos2, tip: Pos2, size: f32) -> [Pos2; 2] {
+    let dir = (tip - tail).normalized();
+    let back = -dir * size;
+    let side = dir.rot90() * size * 0.5;
+    [tip + back + side, tip + back - side]
+}
+
+/// Length of a connector's arrowhead
+pub const ARROW_SIZE: f32 = 12.0;
+
+/// Color connectors are drawn in
+pub const CONNECTOR_COLOR: Color32 = Color32::from_gray(60);
+
+/// Shapes of a connector between notes occupying `from` and `to`, without
+/// its label
+pub fn connector_shapes(from: Rect, to: Rect, style: ConnectorStyle) -> Vec<Shape> {
+    let (start, end) = connector_ends(from, to);
+    let stroke = Stroke::new(2.0, CONNECTOR_COLOR);
+    let mut shapes = if style == ConnectorStyle::Dashed {
+        Shape::dashed_line(&[start, end], stroke, 8.0, 5.0)
+    } else {
+        vec![Shape::line_segment([start, end], stroke)]
+    };
+    if style.has_head() && start != end {
+        let [a, b] = arrow_head(start, end, ARROW_SIZE);
+        shapes.push(Shape::convex_polygon(
+            vec![end, a, b],
+            CONNECTOR_COLOR,
+            Stroke::NONE,
+        ));
+    }
+    shapes
+}
+
+impl Board {
+    /// Add `connector` unless it links a note to itself or the same notes
+    /// are already connected in that direction.
+    ///
+    /// Returns whether it was added.
+    pub fn connect(&mut self, connector: Connector) -> bool {
+        let duplicate = self
+            .connectors
+            .iter()
+            .any(|c| c.from_note == connector.from_note && c.to_note == connector.to_note);
+        if connector.from_note == connector.to_note || duplicate {
+            return false;
+        }
+        self.connectors.push(connector);
+        true
+    }
+
+    /// Indices of the connectors attached to the note `id`
+    pub fn connectors_of(&self, id: u64) -> Vec<usize> {
+        self.connectors
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.from_note == id || c.to_note == id)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Remove the connectors attached to the note `id`, returning commands
+    /// that put them back in place when reverted in order
+    pub fn disconnect_note(&mut self, id: u64) -> Vec<Command> {
+        self.connectors_of(id)
+            .into_iter()
+            .rev()
+            .map(|index| Command::Disconnect {
+                connector: self.connectors.remove(index),
+                index,
+            })
+            .collect()
+    }
+
+    /// Remove connectors whose notes are no longer on the board, returning
+    /// how many were removed
+    pub fn prune_connectors(&mut self) -> usize {
+        let before = self.connectors.len();
+        let notes = &self.notes;
+        self.connectors.retain(|c| {
+            notes.iter().any(|n| n.id == c.from_note) && notes.iter().any(|n| n.id == c.to_note)
+        });
+        before - self.connectors.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+
+    fn connector(from_note: u64, to_note: u64) -> Connector {
+        Connector {
+            from_note,
+            to_note,
+            style: ConnectorStyle::Arrow,
+            label: String::new(),
+        }
+    }
+
+    #[test]
+    fn ends_sit_on_facing_borders() {
+        let from = Rect::from_min_size(Pos2::ZERO, Vec2::new(100.0, 50.0));
+        let to = Rect::from_min_size(Pos2::new(300.0, 0.0), Vec2::new(100.0, 50.0));
+        assert_eq!(
+            connector_ends(from, to),
+            (Pos2::new(100.0, 25.0), Pos2::new(300.0, 25.0))
+        );
+        let below = Rect::from_min_size(Pos2::new(0.0, 200.0), Vec2::new(100.0, 50.0));
+        assert_eq!(border_point(from, below.center()), Pos2::new(50.0, 50.0));
+    }
+
+    #[test]
+    fn arrow_head_points_back_along_the_line() {
+        let [a, b] = arrow_head(Pos2::ZERO, Pos2::new(10.0, 0.0), 4.0);
+        assert_eq!(a.x, 6.0);
+        assert_eq!(b.x, 6.0);
+        assert_eq!((a.y - b.y).abs(), 4.0);
+    }
+
+    #[test]
+    fn connect_rejects_loops_and_duplicates_and_prunes_dangling() {
+        let mut board = Board {
+            notes: [1, 2]
+                .map(|id| NoteData {
+                    id,
+                    ..Default::default()
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        assert!(board.connect(connector(1, 2)));
+        assert!(!board.connect(connector(1, 2)));
+        assert!(!board.connect(connector(1, 1)));
+        assert!(board.connect(connector(2, 1)));
+        assert_eq!(board.connectors_of(1), [0, 1]);
+
+        board.notes.retain(|n| n.id != 2);
+        assert_eq!(board.prune_connectors(), 2);
+        assert!(board.connectors.is_empty());
+    }
+
+    #[test]
+    fn undoing_a_delete_brings_the_connectors_back() {
+        let mut board = Board {
+            notes: [1, 2, 3]
+                .map(|id| NoteData {
+                    id,
+                    ..Default::default()
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        for (from, to) in [(1, 2), (2, 3), (3, 1)] {
+            board.connect(connector(from, to));
+        }
+        let before = board.connectors.clone();
+        let mut removed = board.disconnect_note(2);
+        assert_eq!(board.connectors, [connector(3, 1)]);
+        let index = board.notes.iter().position(|n| n.id == 2).unwrap();
+        let note = board.notes.remove(index);
+        removed.push(Command::Delete { note, index });
+        let delete = Command::batch(removed).unwrap();
+
+        delete.revert(&mut board);
+        assert_eq!(board.connectors, before);
+        assert_eq!(board.notes.len(), 3);
+        delete.apply(&mut board);
+        assert_eq!(board.connectors, [connector(3, 1)]);
+    }
+}
@@ -0,0 +1,81 @@
+//! Due dates and how close notes are to them
+
+use crate::expiry::DAY;
+use crate::{Board, NoteData};
+
+/// How long before its due date a note counts as due soon, in seconds
+pub const DUE_SOON: u64 = DAY;
+
+/// Where a note with a due date stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DueStatus {
+    /// The due date has passed
+    Overdue,
+    /// The due date is less than [`DUE_SOON`] away
+    Soon,
+}
+
+/// Where `note` stands at `now`; `None` without a due date or with plenty
+/// of time left
+pub fn due_status(note: &NoteData, now: u64) -> Option<DueStatus> {
+    let due = note.due?;
+    if due <= now {
+        Some(DueStatus::Overdue)
+    } else if due - now <= DUE_SOON {
+        Some(DueStatus::Soon)
+    } else {
+        None
+    }
+}
+
+impl Board {
+    /// Notes with a due date, the earliest first
+    pub fn notes_by_due(&self) -> Vec<&NoteData> {
+        let mut notes: Vec<&NoteData> = self.notes.iter().filter(|n| n.due.is_some()).collect();
+        notes.sort_by_key(|n| (n.due, n.id));
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: u64, due: Option<u64>) -> NoteData {
+        NoteData {
+            id,
+            due,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn notes_turn_due_soon_then_overdue() {
+        let now = 10 * DAY;
+        assert_eq!(due_status(&note(1, None), now), None);
+        assert_eq!(due_status(&note(1, Some(now + 2 * DAY)), now), None);
+        assert_eq!(
+            due_status(&note(1, Some(now + DAY)), now),
+            Some(DueStatus::Soon)
+        );
+        assert_eq!(
+            due_status(&note(1, Some(now)), now),
+            Some(DueStatus::Overdue)
+        );
+    }
+
+    #[test]
+    fn due_notes_are_listed_earliest_first() {
+        let board = Board {
+            notes: vec![
+                note(1, Some(300)),
+                note(2, None),
+                note(3, Some(100)),
+                note(4, Some(300)),
+            ],
+            ..Default::default()
+        };
+        let ids: Vec<u64> = board.notes_by_due().iter().map(|n| n.id).collect();
+        assert_eq!(ids, [3, 1, 4]);
+    }
+}
@@ -0,0 +1,141 @@
+//! Password-protected save files: the JSON sealed with AES-256-GCM under a
+//! key derived from the password with Argon2
+//!
+//! A sealed file is itself a small JSON object, so it is told apart from a
+//! plain save file by its `format` tag.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Tag of sealed files
+const FORMAT: &str = "plop-sealed-v1";
+
+/// What is stored instead of the plain JSON, all bytes in hex
+#[derive(Serialize, Deserialize, Debug)]
+struct Sealed {
+    format: String,
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+/// Whether `contents` of a save file are sealed with a password
+pub fn is_sealed(contents: &[u8]) -> bool {
+    serde_json::from_slice::<Sealed>(contents).is_ok_and(|s| s.format == FORMAT)
+}
+
+/// `json` sealed with `password`
+pub fn seal(json: &str, password: &str) -> io::Result<String> {
+    let salt: [u8; 16] = rand::random();
+    let nonce: [u8; 12] = rand::random();
+    let data = cipher(password, &salt)?
+        .encrypt(Nonce::from_slice(&nonce), json.as_bytes())
+        .map_err(|_| io::Error::other("encryption failed"))?;
+    let sealed = Sealed {
+        format: FORMAT.into(),
+        salt: to_hex(&salt),
+        nonce: to_hex(&nonce),
+        data: to_hex(&data),
+    };
+    serde_json::to_string(&sealed).map_err(io::Error::other)
+}
+
+/// `json` sealed with `password`, or as it is without one
+pub fn seal_with(json: String, password: Option<&str>) -> io::Result<String> {
+    match password {
+        Some(password) => seal(&json, password),
+        None => Ok(json),
+    }
+}
+
+/// `contents` of a save file, plain or sealed with `old`, sealed anew with
+/// `new`, or plain without one
+pub fn reseal(contents: &[u8], old: Option<&str>, new: Option<&str>) -> io::Result<String> {
+    let json = if is_sealed(contents) {
+        let old = old.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::PermissionDenied, "sealed with a password")
+        })?;
+        open(contents, old)?
+    } else {
+        String::from_utf8(contents.to_vec())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+    };
+    seal_with(json, new)
+}
+
+/// The JSON sealed in `contents`, failing on a wrong password or a damaged
+/// file
+pub fn open(contents: &[u8], password: &str) -> io::Result<String> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+    let sealed: Sealed =
+        serde_json::from_slice(contents).map_err(|_| invalid("not a sealed save file"))?;
+    let (Some(salt), Some(nonce), Some(data)) = (
+        from_hex(&sealed.salt),
+        from_hex(&sealed.nonce).filter(|n| n.len() == 12),
+        from_hex(&sealed.data),
+    ) else {
+        return Err(invalid("damaged sealed save file"));
+    };
+    let json = cipher(password, &salt)?
+        .decrypt(Nonce::from_slice(&nonce), data.as_slice())
+        .map_err(|_| invalid("wrong password or damaged file"))?;
+    String::from_utf8(json).map_err(|_| invalid("damaged sealed save file"))
+}
+
+fn cipher(password: &str, salt: &[u8]) -> io::Result<Aes256Gcm> {
+    let mut key = [0; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    Aes256Gcm::new_from_slice(&key).map_err(|e| io::Error::other(e.to_string()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_json_opens_only_with_its_password() {
+        let json = r#"{"boards":[]}"#;
+        let sealed = seal(json, "hunter2").unwrap();
+        assert!(is_sealed(sealed.as_bytes()));
+        assert!(!is_sealed(json.as_bytes()));
+        assert!(!sealed.contains("boards"));
+        assert_eq!(open(sealed.as_bytes(), "hunter2").unwrap(), json);
+        let wrong = open(sealed.as_bytes(), "hunter3").unwrap_err();
+        assert_eq!(wrong.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(seal_with(json.into(), None).unwrap(), json);
+    }
+
+    #[test]
+    fn resealing_changes_the_password() {
+        let json = r#"{"boards":[]}"#;
+        let sealed = reseal(json.as_bytes(), None, Some("new")).unwrap();
+        assert_eq!(open(sealed.as_bytes(), "new").unwrap(), json);
+        let changed = reseal(sealed.as_bytes(), Some("new"), Some("newer")).unwrap();
+        assert_eq!(open(changed.as_bytes(), "newer").unwrap(), json);
+        assert_eq!(
+            reseal(changed.as_bytes(), Some("newer"), None).unwrap(),
+            json
+        );
+        assert!(reseal(changed.as_bytes(), None, Some("new")).is_err());
+        assert!(reseal(changed.as_bytes(), Some("new"), None).is_err());
+    }
+}
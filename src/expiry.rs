@@ -0,0 +1,241 @@
+//! Optional note expiry dates and archiving of expired notes
+
+use crate::{Board, NoteData};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds in one day
+pub const DAY: u64 = 24 * 60 * 60;
+
+/// Current time as seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Whether `note` has an expiry date that has passed at `now`
+pub fn is_expired(note: &NoteData, now: u64) -> bool {
+    note.expires_at.is_some_and(|at| at <= now)
+}
+
+/// Ids of the board's notes that have expired at `now`
+pub fn expired_notes(board: &Board, now: u64) -> Vec<u64> {
+    board
+        .notes
+        .iter()
+        .filter(|n| is_expired(n, now))
+        .map(|n| n.id)
+        .collect()
+}
+
+/// Move the notes with the given ids into the board's archive.
+///
+/// Returns how many notes were archived.
+pub fn archive_notes(board: &mut Board, ids: &[u64]) -> usize {
+    let (archived, kept): (Vec<NoteData>, Vec<NoteData>) = std::mem::take(&mut board.notes)
+        .into_iter()
+        .partition(|n| ids.contains(&n.id));
+    board.notes = kept;
+    let count = archived.len();
+    board.archived.extend(archived);
+    count
+}
+
+/// Format a timestamp as an ISO `YYYY-MM-DD` date (UTC)
+pub fn format_date(secs: u64) -> String {
+    let (y, m, d) = civil_from_days((secs / DAY) as i64);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM` (UTC)
+pub fn format_datetime(secs: u64) -> String {
+    let minutes = secs % DAY / 60;
+    format!(
+        "{} {:02}:{:02}",
+        format_date(secs),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Seconds the local time zone is ahead of UTC at the timestamp `secs`
+pub fn local_offset(secs: u64) -> i64 {
+    use chrono::{Local, Offset, TimeZone};
+    let local = i64::try_from(secs)
+        .ok()
+        .and_then(|secs| Local.timestamp_opt(secs, 0).single());
+    local.map_or(0, |t| t.offset().fix().local_minus_utc().into())
+}
+
+/// The timestamp that reads as `wall` (a timestamp taken as UTC) in local
+/// time
+fn from_local(wall: u64) -> Option<u64> {
+    // The offset at the result, which differs from the one at `wall` around
+    // a daylight saving change
+    let guess = wall.checked_add_signed(-local_offset(wall))?;
+    wall.checked_add_signed(-local_offset(guess))
+}
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM` in local time
+pub fn format_local_datetime(secs: u64) -> String {
+    format_datetime(secs.saturating_add_signed(local_offset(secs)))
+}
+
+/// Parse `YYYY-MM-DD HH:MM` in local time, or a date alone meaning its
+/// local midnight
+pub fn parse_local_datetime(s: &str) -> Option<u64> {
+    parse_datetime(s).and_then(from_local)
+}
+
+/// Local date and time of a timestamp: year, month, day, hour and minute
+pub fn local_parts(secs: u64) -> (i64, u32, u32, u32, u32) {
+    let wall = secs.saturating_add_signed(local_offset(secs));
+    let (y, m, d) = civil_from_days((wall / DAY) as i64);
+    let minutes = (wall % DAY / 60) as u32;
+    (y, m, d, minutes / 60, minutes % 60)
+}
+
+/// Timestamp of a local date and time, the day cut to the month's last
+pub fn from_local_parts(y: i64, m: u32, d: u32, h: u32, min: u32) -> Option<u64> {
+    if !(1..=12).contains(&m) || h >= 24 || min >= 60 {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(y, m, d.clamp(1, days_in_month(y, m)))).ok()?;
+    from_local(days * DAY + u64::from(h) * 3600 + u64::from(min) * 60)
+}
+
+/// Day of the week the month starts on, from 0 for Monday
+pub fn first_weekday(y: i64, m: u32) -> u32 {
+    // 1970-01-01 was a Thursday
+    (days_from_civil(y, m, 1) + 3).rem_euclid(7) as u32
+}
+
+/// Parse an ISO `YYYY-MM-DD` date into the timestamp of its midnight (UTC)
+pub fn parse_date(s: &str) -> Option<u64> {
+    let mut parts = s.trim().splitn(3, '-');
+    let y: i64 = parts.next()?.parse().ok()?;
+    let m: u32 = parts.next()?.parse().ok()?;
+    let d: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&m) || d == 0 || d > days_in_month(y, m) {
+        return None;
+    }
+    let days = days_from_civil(y, m, d);
+    u64::try_from(days).ok().map(|days| days * DAY)
+}
+
+/// Parse `YYYY-MM-DD HH:MM`, or a date alone meaning its midnight (UTC)
+pub fn parse_datetime(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let Some((date, time)) = s.split_once(' ') else {
+        return parse_date(s);
+    };
+    let (h, m) = time.trim().split_once(':')?;
+    let (h, m): (u64, u64) = (h.parse().ok()?, m.parse().ok()?);
+    if h >= 24 || m >= 60 {
+        return None;
+    }
+    Some(parse_date(date)? + h * 3600 + m * 60)
+}
+
+/// Days in the month `m` of the year `y`
+pub fn days_in_month(y: i64, m: u32) -> u32 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's `days_from_civil` / `civil_from_days`
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_roundtrip() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800));
+        assert_eq!(format_date(1_709_164_800 + 3600), "2024-02-29");
+        assert_eq!(format_datetime(1_709_164_800 + 3720), "2024-02-29 01:02");
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("soon"), None);
+        assert_eq!(
+            parse_datetime("2024-02-29 01:02"),
+            Some(1_709_164_800 + 3720)
+        );
+        assert_eq!(parse_datetime(" 2024-02-29 "), Some(1_709_164_800));
+        assert_eq!(parse_datetime("2024-02-29 24:00"), None);
+    }
+
+    #[test]
+    fn local_times_roundtrip() {
+        let at = 1_709_164_800 + 3720;
+        assert_eq!(parse_local_datetime(&format_local_datetime(at)), Some(at));
+        let (y, m, d, h, min) = local_parts(at);
+        assert_eq!(from_local_parts(y, m, d, h, min), Some(at));
+        assert_eq!(
+            format_local_datetime(at),
+            format!("{y:04}-{m:02}-{d:02} {h:02}:{min:02}")
+        );
+        // A day past the month's end is its last
+        let last = from_local_parts(2023, 2, 31, 12, 0).unwrap();
+        assert_eq!(local_parts(last), (2023, 2, 28, 12, 0));
+        assert_eq!(from_local_parts(2023, 13, 1, 0, 0), None);
+        assert_eq!(first_weekday(2024, 2), 3);
+        assert_eq!(first_weekday(1970, 1), 3);
+        assert_eq!(first_weekday(2024, 4), 0);
+    }
+
+    #[test]
+    fn archives_only_expired_notes() {
+        let mut board = Board {
+            notes: vec![
+                NoteData {
+                    id: 1,
+                    expires_at: Some(100),
+                    ..Default::default()
+                },
+                NoteData {
+                    id: 2,
+                    expires_at: Some(500),
+                    ..Default::default()
+                },
+                NoteData {
+                    id: 3,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let expired = expired_notes(&board, 200);
+        assert_eq!(expired, vec![1]);
+        assert_eq!(archive_notes(&mut board, &expired), 1);
+        assert_eq!(board.notes.iter().map(|n| n.id).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(board.archived[0].id, 1);
+    }
+}
@@ -0,0 +1,765 @@
+//! Exporting boards to other formats (PNG images, Markdown outlines, SVG
+//! drawings, web pages, CSV tables)
+
+use crate::clipboard::NoteClipboard;
+use crate::connectors::{CONNECTOR_COLOR, connector_ends, connector_shapes};
+use crate::frames::title_bar;
+use crate::ink::InkStroke;
+use crate::note_font::FontDefaults;
+use crate::{AppState, Board, NoteData, fitted_font_size, markdown, wrap_to_fit};
+use egui::epaint::text::{FontDefinitions, Fonts};
+use egui::epaint::{Mesh, TessellationOptions, Tessellator};
+use egui::{Color32, Pos2, Rect, Shape, Vec2};
+use image::RgbaImage;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Extension of exported selections that can be imported onto a board
+pub const SELECTION_EXTENSION: &str = "json";
+
+/// Empty space around the notes in an exported image
+const IMAGE_MARGIN: f32 = 40.0;
+
+/// Largest side (in pixels) of an exported image; bigger boards are scaled down
+pub const MAX_IMAGE_SIDE: u32 = 8192;
+
+/// Resolutions offered for PNG exports, in pixels per board unit
+pub const PNG_SCALES: [f32; 3] = [1.0, 2.0, 4.0];
+
+/// Formats a board can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Markdown,
+    Svg,
+    Html,
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Png,
+        ExportFormat::Markdown,
+        ExportFormat::Svg,
+        ExportFormat::Html,
+        ExportFormat::Csv,
+    ];
+
+    /// The format whose files have the extension `ext`
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.extension() == ext)
+    }
+
+    /// File extension used for this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Html => "html",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Notes in reading order: top to bottom, then left to right
+fn reading_order(board: &Board) -> Vec<&NoteData> {
+    let mut notes: Vec<&NoteData> = board.notes.iter().collect();
+    notes.sort_by(|a, b| {
+        a.pos
+            .y
+            .total_cmp(&b.pos.y)
+            .then(a.pos.x.total_cmp(&b.pos.x))
+    });
+    notes
+}
+
+/// How notes are grouped under subheadings in a Markdown outline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutlineGrouping {
+    /// One list in reading order
+    #[default]
+    None,
+    /// A section per note color
+    Color,
+    /// A section per column of notes, left to right
+    Column,
+    /// A section per kanban lane, then the notes outside every lane
+    Lane,
+}
+
+impl OutlineGrouping {
+    /// Every grouping, for pickers
+    pub const ALL: [OutlineGrouping; 4] = [
+        OutlineGrouping::None,
+        OutlineGrouping::Color,
+        OutlineGrouping::Column,
+        OutlineGrouping::Lane,
+    ];
+
+    /// Name shown in the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            OutlineGrouping::None => "Single list",
+            OutlineGrouping::Color => "By color",
+            OutlineGrouping::Column => "By column",
+            OutlineGrouping::Lane => "By lane",
+        }
+    }
+}
+
+/// Notes split into columns: a note starts a new column when it lies right
+/// of every note in the current one. Columns are read top to bottom.
+fn columns(board: &Board) -> Vec<Vec<&NoteData>> {
+    let mut notes: Vec<&NoteData> = board.notes.iter().collect();
+    notes.sort_by(|a, b| a.pos.x.total_cmp(&b.pos.x));
+    let mut columns: Vec<Vec<&NoteData>> = Vec::new();
+    let mut right = f32::NEG_INFINITY;
+    for note in notes {
+        if note.pos.x >= right || columns.is_empty() {
+            columns.push(Vec::new());
+        }
+        right = right.max(note.pos.x + note.size.x);
+        columns.last_mut().expect("a column was pushed").push(note);
+    }
+    for column in &mut columns {
+        column.sort_by(|a, b| a.pos.y.total_cmp(&b.pos.y));
+    }
+    columns
+}
+
+/// Append `note` as a bullet, continuation lines indented under it
+fn push_bullet(out: &mut String, note: &NoteData) {
+    let mut lines = note.text.lines();
+    out.push_str("- ");
+    out.push_str(lines.next().unwrap_or_default());
+    out.push('\n');
+    for line in lines {
+        out.push_str("  ");
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// Write the board as a Markdown outline: a heading and one bullet per note
+pub fn board_to_markdown(board: &Board) -> String {
+    board_to_markdown_grouped(board, OutlineGrouping::None)
+}
+
+/// Write the board as a Markdown outline with the notes grouped under
+/// subheadings
+pub fn board_to_markdown_grouped(board: &Board, grouping: OutlineGrouping) -> String {
+    let mut out = format!("# {}\n\n", board.name);
+    let groups: Vec<(String, Vec<&NoteData>)> = match grouping {
+        OutlineGrouping::None => vec![(String::new(), reading_order(board))],
+        OutlineGrouping::Color => {
+            let mut groups: Vec<(Color32, Vec<&NoteData>)> = Vec::new();
+            for note in reading_order(board) {
+                match groups.iter_mut().find(|(color, _)| *color == note.color) {
+                    Some((_, notes)) => notes.push(note),
+                    None => groups.push((note.color, vec![note])),
+                }
+            }
+            groups
+                .into_iter()
+                .map(|(color, notes)| {
+                    let [r, g, b, _] = color.to_array();
+                    (format!("#{r:02x}{g:02x}{b:02x}"), notes)
+                })
+                .collect()
+        }
+        OutlineGrouping::Column => columns(board)
+            .into_iter()
+            .enumerate()
+            .map(|(i, notes)| (format!("Column {}", i + 1), notes))
+            .collect(),
+        OutlineGrouping::Lane => {
+            let notes = reading_order(board);
+            let mut groups: Vec<(String, Vec<&NoteData>)> = board
+                .lanes
+                .iter()
+                .map(|lane| {
+                    let name = Some(lane.name.as_str());
+                    let lane_notes = notes.iter().filter(|n| n.lane.as_deref() == name);
+                    (lane.name.clone(), lane_notes.copied().collect())
+                })
+                .collect();
+            let unsorted: Vec<&NoteData> = notes
+                .iter()
+                .filter(|n| !board.lanes.iter().any(|l| n.lane.as_ref() == Some(&l.name)))
+                .copied()
+                .collect();
+            if !unsorted.is_empty() {
+                groups.push(("No lane".into(), unsorted));
+            }
+            groups
+        }
+    };
+    for (i, (heading, notes)) in groups.iter().enumerate() {
+        if !heading.is_empty() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!("## {heading}\n\n"));
+        }
+        for note in notes {
+            push_bullet(&mut out, note);
+        }
+    }
+    out
+}
+
+/// Region of the board covered by notes, ink and frames, plus a margin
+pub fn board_extent(board: &Board) -> Rect {
+    let mut notes = board
+        .notes
+        .iter()
+        .map(|n| Rect::from_min_size(n.pos, n.size))
+        .chain(board.strokes.iter().map(InkStroke::bounding_rect))
+        .chain(board.frames.iter().map(|f| f.rect));
+    match notes.next() {
+        Some(first) => notes
+            .fold(first, |acc, r| acc.union(r))
+            .expand(IMAGE_MARGIN),
+        None => Rect::from_min_size(Pos2::ZERO, Vec2::new(400.0, 300.0)),
+    }
+}
+
+/// Shapes drawing the board in board coordinates
+fn board_shapes(fonts: &Fonts, board: &Board, extent: Rect) -> Vec<Shape> {
+    let mut shapes = vec![Shape::rect_filled(extent, 0.0, board.background)];
+    for frame in &board.frames {
+        let bar = title_bar(frame.rect);
+        shapes.push(Shape::rect_filled(frame.rect, 4.0, frame.fill()));
+        shapes.push(Shape::rect_filled(bar, 4.0, frame.color));
+        let galley = fonts.layout_no_wrap(
+            frame.title.clone(),
+            egui::FontId::proportional(14.0),
+            Color32::WHITE,
+        );
+        let pos = bar.left_center() + Vec2::new(8.0, -galley.size().y * 0.5);
+        shapes.push(Shape::galley(pos, galley, Color32::WHITE));
+    }
+    shapes.extend(board.strokes.iter().map(|s| s.shape(|p| p)));
+    let rect_of = |id: u64| {
+        board
+            .notes
+            .iter()
+            .find(|n| n.id == id)
+            .map(|n| Rect::from_min_size(n.pos, n.size))
+    };
+    for connector in &board.connectors {
+        let (Some(from), Some(to)) = (rect_of(connector.from_note), rect_of(connector.to_note))
+        else {
+            continue;
+        };
+        shapes.extend(connector_shapes(from, to, connector.style));
+        if !connector.label.is_empty() {
+            let (start, end) = connector_ends(from, to);
+            let galley = fonts.layout_no_wrap(
+                connector.label.clone(),
+                egui::FontId::proportional(12.0),
+                CONNECTOR_COLOR,
+            );
+            let middle = start + (end - start) * 0.5;
+            let pos = middle - Vec2::new(galley.size().x * 0.5, galley.size().y);
+            shapes.push(Shape::galley(pos, galley, CONNECTOR_COLOR));
+        }
+    }
+    for note in board.notes_by_z() {
+        let rect = Rect::from_min_size(note.pos, note.size);
+        shapes.push(Shape::rect_filled(rect, 0.0, note.color));
+        let font = note.font.resolve(&FontDefaults::default());
+        let font_size = fitted_font_size(fonts, &note.text, note.size, &font);
+        let mut job = markdown::layout_job(&note.text, &font.id(font_size), font.bold, None);
+        wrap_to_fit(fonts, &mut job, note.size);
+        let galley = fonts.layout_job(job);
+        let pos = rect.center() - galley.size() * 0.5;
+        shapes.push(Shape::galley(pos, galley, Color32::BLACK));
+    }
+    shapes
+}
+
+/// Rasterize the whole board, independent of any viewport.
+///
+/// `scale` is the number of pixels per board unit; it is reduced if the image
+/// would exceed [`MAX_IMAGE_SIDE`].
+pub fn render_board_png(board: &Board, scale: f32) -> RgbaImage {
+    let extent = board_extent(board);
+    let scale = scale
+        .max(0.01)
+        .min(MAX_IMAGE_SIDE as f32 / extent.width().max(extent.height()));
+    let width = (extent.width() * scale).ceil() as u32;
+    let height = (extent.height() * scale).ceil() as u32;
+
+    let fonts = Fonts::new(scale, MAX_IMAGE_SIDE as usize, FontDefinitions::default());
+    fonts.begin_pass(scale, MAX_IMAGE_SIDE as usize);
+    let shapes = board_shapes(&fonts, board, extent);
+
+    // Text layout fills the font atlas, so grab it only after laying out
+    let atlas = fonts.texture_atlas();
+    let mut tessellator = Tessellator::new(
+        scale,
+        TessellationOptions::default(),
+        fonts.font_image_size(),
+        atlas.lock().prepared_discs(),
+    );
+    let mut mesh = Mesh::default();
+    for shape in shapes {
+        tessellator.tessellate_shape(shape, &mut mesh);
+    }
+    let font_image = fonts.image();
+    let texture = Texture {
+        size: font_image.size,
+        pixels: font_image.srgba_pixels(None).collect(),
+    };
+
+    let mut canvas = Canvas::new(width, height);
+    let to_pixels = |p: Pos2| ((p - extent.min) * scale).to_pos2();
+    for tri in mesh.indices.chunks_exact(3) {
+        let v = [
+            mesh.vertices[tri[0] as usize],
+            mesh.vertices[tri[1] as usize],
+            mesh.vertices[tri[2] as usize],
+        ];
+        canvas.fill_triangle(v.map(|v| (to_pixels(v.pos), v.uv, v.color)), &texture);
+    }
+    canvas.into_image()
+}
+
+/// The font atlas as premultiplied colors
+struct Texture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+}
+
+impl Texture {
+    /// Bilinear sample at normalized coordinates, as premultiplied rgba in 0..=1
+    fn sample(&self, uv: Pos2) -> [f32; 4] {
+        let [w, h] = self.size;
+        let x = (uv.x * w as f32 - 0.5).clamp(0.0, (w - 1) as f32);
+        let y = (uv.y * h as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let texel = |x: usize, y: usize| self.pixels[y * w + x].to_array();
+        let mut out = [0.0; 4];
+        for (c, out) in out.iter_mut().enumerate() {
+            let top = texel(x0, y0)[c] as f32 * (1.0 - fx) + texel(x1, y0)[c] as f32 * fx;
+            let bottom = texel(x0, y1)[c] as f32 * (1.0 - fx) + texel(x1, y1)[c] as f32 * fx;
+            *out = (top * (1.0 - fy) + bottom * fy) / 255.0;
+        }
+        out
+    }
+}
+
+/// Premultiplied rgba pixels being painted into
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; (width * height) as usize],
+        }
+    }
+
+    /// Blend a textured, vertex-colored triangle over the canvas
+    fn fill_triangle(&mut self, v: [(Pos2, Pos2, Color32); 3], texture: &Texture) {
+        let [(a, ..), (b, ..), (c, ..)] = v;
+        let area = (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil() as u32).min(self.width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil() as u32).min(self.height);
+        let edge = |p: Pos2, q: Pos2, r: Pos2| (q - p).x * (r - p).y - (q - p).y * (r - p).x;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(b, c, p) / area;
+                let w1 = edge(c, a, p) / area;
+                let w2 = edge(a, b, p) / area;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+                let uv = (v[0].1.to_vec2() * w0 + v[1].1.to_vec2() * w1 + v[2].1.to_vec2() * w2)
+                    .to_pos2();
+                let tex = texture.sample(uv);
+                let colors = [v[0].2, v[1].2, v[2].2].map(|c| c.to_array());
+                let dst = &mut self.pixels[(y * self.width + x) as usize];
+                let mut src = [0.0; 4];
+                for ch in 0..4 {
+                    let color = (colors[0][ch] as f32 * w0
+                        + colors[1][ch] as f32 * w1
+                        + colors[2][ch] as f32 * w2)
+                        / 255.0;
+                    src[ch] = color * tex[ch];
+                }
+                let keep = 1.0 - src[3];
+                for ch in 0..4 {
+                    dst[ch] = src[ch] + dst[ch] * keep;
+                }
+            }
+        }
+    }
+
+    /// Convert to a straight-alpha image
+    fn into_image(self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width, self.height);
+        for (px, [r, g, b, a]) in image.pixels_mut().zip(self.pixels) {
+            let unmultiply = |c: f32| {
+                if a > 0.0 {
+                    (c / a * 255.0).round().clamp(0.0, 255.0) as u8
+                } else {
+                    0
+                }
+            };
+            px.0 = [
+                unmultiply(r),
+                unmultiply(g),
+                unmultiply(b),
+                (a * 255.0).round().clamp(0.0, 255.0) as u8,
+            ];
+        }
+        image
+    }
+}
+
+/// Cut the part of a screen capture covered by `rect` (in points).
+///
+/// Returns `None` if the rect lies outside the image.
+pub fn crop_to_rect(image: &RgbaImage, rect: Rect, pixels_per_point: f32) -> Option<RgbaImage> {
+    let min_x = (rect.min.x * pixels_per_point).round().max(0.0) as u32;
+    let min_y = (rect.min.y * pixels_per_point).round().max(0.0) as u32;
+    let max_x = ((rect.max.x * pixels_per_point).round().max(0.0) as u32).min(image.width());
+    let max_y = ((rect.max.y * pixels_per_point).round().max(0.0) as u32).min(image.height());
+    if max_x <= min_x || max_y <= min_y {
+        return None;
+    }
+    Some(image::imageops::crop_imm(image, min_x, min_y, max_x - min_x, max_y - min_y).to_image())
+}
+
+/// File name for `board` exported in `format`, safe on every platform
+pub fn board_file_name(board: &Board, format: ExportFormat) -> String {
+    let stem: String = board
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{stem}.{}", format.extension())
+}
+
+/// Export `board` to `path` in the given format
+pub fn export_board(
+    board: &Board,
+    path: &Path,
+    format: ExportFormat,
+    scale: f32,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Png => render_board_png(board, scale)
+            .save(path)
+            .map_err(io::Error::other),
+        ExportFormat::Markdown => std::fs::write(path, board_to_markdown(board)),
+        ExportFormat::Svg => std::fs::write(path, crate::svg::board_to_svg(board)),
+        ExportFormat::Html => std::fs::write(path, crate::html::board_to_html(board)),
+        ExportFormat::Csv => std::fs::write(path, crate::spreadsheet::board_to_csv(board)),
+    }
+}
+
+/// The notes `ids` of `board` and the connectors between them on a board of
+/// their own, moved so the top-left corner of the notes is at the origin
+pub fn selection_board(board: &Board, ids: &[u64]) -> Board {
+    let selected = |id| ids.contains(&id);
+    let origin = board
+        .notes
+        .iter()
+        .filter(|n| selected(n.id))
+        .map(|n| n.pos)
+        .reduce(|a, b| a.min(b))
+        .unwrap_or(Pos2::ZERO);
+    Board {
+        name: format!("{} (selection)", board.name),
+        background: board.background,
+        background_style: board.background_style,
+        notes: board
+            .notes
+            .iter()
+            .filter(|n| selected(n.id))
+            .map(|n| NoteData {
+                pos: n.pos - origin.to_vec2(),
+                ..n.clone()
+            })
+            .collect(),
+        connectors: board
+            .connectors
+            .iter()
+            .filter(|c| selected(c.from_note) && selected(c.to_note))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Export the notes `ids` of `board` to `path`, in the format its extension
+/// names. A [`SELECTION_EXTENSION`] file keeps the notes and their
+/// connectors to paste onto another board.
+pub fn export_selection(board: &Board, ids: &[u64], path: &Path, scale: f32) -> io::Result<()> {
+    let selection = selection_board(board, ids);
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if ext == SELECTION_EXTENSION {
+        let notes = NoteClipboard::copy(selection.notes, &selection.connectors);
+        let json = serde_json::to_string_pretty(&notes).map_err(io::Error::other)?;
+        return std::fs::write(path, json);
+    }
+    match ExportFormat::from_extension(&ext) {
+        Some(format) => export_board(&selection, path, format, scale),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can't export to .{ext} files"),
+        )),
+    }
+}
+
+/// Notes exported with [`export_selection`], to paste onto a board
+pub fn read_selection(path: &Path) -> io::Result<NoteClipboard> {
+    let data = std::fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Export every board saved in the workspace folder `dir` into `out`.
+///
+/// Each `*.json` file holding boards becomes `<file stem>.<ext>` per format,
+/// or `<file stem>-<n>.<ext>` for its n-th board if it holds several; other
+/// JSON files are skipped. Returns the written files.
+pub fn export_workspace(
+    dir: &Path,
+    out: &Path,
+    formats: &[ExportFormat],
+    scale: f32,
+) -> io::Result<Vec<PathBuf>> {
+    let mut sources: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    sources.sort();
+    export_files(&sources, out, formats, scale)
+}
+
+/// Export every board of the save files `sources` into `out`, named as by
+/// [`export_workspace`]; files that can't be read as boards are skipped.
+/// Returns the written files.
+pub fn export_files(
+    sources: &[PathBuf],
+    out: &Path,
+    formats: &[ExportFormat],
+    scale: f32,
+) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(out)?;
+    let mut written = Vec::new();
+    for source in sources {
+        let Ok(state) = AppState::try_load_from_file(source) else {
+            continue;
+        };
+        let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+        for (index, board) in state.boards.iter().enumerate() {
+            let name = if state.boards.len() == 1 {
+                stem.to_string()
+            } else {
+                format!("{stem}-{}", index + 1)
+            };
+            for &format in formats {
+                let target = out.join(format!("{name}.{}", format.extension()));
+                export_board(board, &target, format, scale)?;
+                written.push(target);
+            }
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: u64, text: &str, pos: Pos2) -> NoteData {
+        NoteData {
+            id,
+            text: text.into(),
+            pos,
+            size: Vec2::new(120.0, 80.0),
+            color: Color32::YELLOW,
+            ..Default::default()
+        }
+    }
+
+    fn sample_board() -> Board {
+        Board {
+            name: "Ideas".into(),
+            background: Color32::WHITE,
+            notes: vec![
+                note(1, "second", Pos2::new(200.0, 0.0)),
+                note(2, "third\nmore", Pos2::new(0.0, 100.0)),
+                note(3, "first", Pos2::new(0.0, 0.0)),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn markdown_lists_notes_in_reading_order() {
+        let md = board_to_markdown(&sample_board());
+        assert_eq!(md, "# Ideas\n\n- first\n- second\n- third\n  more\n");
+    }
+
+    #[test]
+    fn markdown_groups_by_color_and_column() {
+        let mut board = sample_board();
+        board.notes[0].color = Color32::RED;
+        let by_color = board_to_markdown_grouped(&board, OutlineGrouping::Color);
+        assert_eq!(
+            by_color,
+            "# Ideas\n\n## #ffff00\n\n- first\n- third\n  more\n\n## #ff0000\n\n- second\n"
+        );
+        let by_column = board_to_markdown_grouped(&board, OutlineGrouping::Column);
+        assert_eq!(
+            by_column,
+            "# Ideas\n\n## Column 1\n\n- first\n- third\n  more\n\n## Column 2\n\n- second\n"
+        );
+        board.lanes = crate::lanes::default_lanes();
+        board.notes[0].lane = Some("Done".into());
+        board.notes[2].lane = Some("Backlog".into());
+        let by_lane = board_to_markdown_grouped(&board, OutlineGrouping::Lane);
+        assert_eq!(
+            by_lane,
+            "# Ideas\n\n## Backlog\n\n- first\n\n## Doing\n\n\n## Done\n\n- second\n\n## No lane\n\n- third\n  more\n"
+        );
+    }
+
+    #[test]
+    fn png_covers_notes_with_their_colors() {
+        let board = sample_board();
+        let image = render_board_png(&board, 1.0);
+        let extent = board_extent(&board);
+        assert_eq!(image.width(), extent.width() as u32);
+        assert_eq!(image.height(), extent.height() as u32);
+        // Corner pixel is background, a spot inside a note (away from text) is the note color
+        assert_eq!(image.get_pixel(1, 1).0, [255, 255, 255, 255]);
+        let inside = Pos2::new(5.0, 5.0) - extent.min;
+        let px = image.get_pixel(inside.x as u32, inside.y as u32).0;
+        assert_eq!(px, Color32::YELLOW.to_array());
+    }
+
+    #[test]
+    fn png_draws_connectors_between_notes() {
+        let mut board = sample_board();
+        board.connectors.push(crate::connectors::Connector {
+            from_note: 3,
+            to_note: 1,
+            style: Default::default(),
+            label: String::new(),
+        });
+        let image = render_board_png(&board, 1.0);
+        let extent = board_extent(&board);
+        // Halfway between "first" (0..120) and "second" (200..320) near mid height
+        let between = Pos2::new(160.0, 40.0) - extent.min;
+        let dark = (between.y as u32 - 3..between.y as u32 + 3)
+            .any(|y| image.get_pixel(between.x as u32, y).0[0] < 128);
+        assert!(dark);
+        let above = image.get_pixel(between.x as u32, between.y as u32 - 20).0;
+        assert_eq!(above, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn file_names_are_sanitized() {
+        let board = Board {
+            name: "Q3 / plans".into(),
+            ..Default::default()
+        };
+        assert_eq!(board_file_name(&board, ExportFormat::Png), "Q3___plans.png");
+    }
+
+    #[test]
+    fn crop_uses_physical_pixels() {
+        let mut image = RgbaImage::new(200, 100);
+        image.put_pixel(20, 10, image::Rgba([1, 2, 3, 255]));
+        let rect = Rect::from_min_size(Pos2::new(10.0, 5.0), Vec2::new(500.0, 20.0));
+        let cropped = crop_to_rect(&image, rect, 2.0).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (180, 40));
+        assert_eq!(cropped.get_pixel(0, 0).0, [1, 2, 3, 255]);
+        let outside = Rect::from_min_size(Pos2::new(300.0, 0.0), Vec2::new(10.0, 10.0));
+        assert!(crop_to_rect(&image, outside, 1.0).is_none());
+    }
+
+    #[test]
+    fn selections_export_apart_from_the_board() {
+        let mut board = sample_board();
+        board.connectors.push(crate::connectors::Connector {
+            from_note: 1,
+            to_note: 2,
+            style: Default::default(),
+            label: String::new(),
+        });
+        let selection = selection_board(&board, &[1, 2]);
+        // Placed relative to the selection's top-left corner
+        let positions: Vec<Pos2> = selection.notes.iter().map(|n| n.pos).collect();
+        assert_eq!(positions, [Pos2::new(200.0, 0.0), Pos2::new(0.0, 100.0)]);
+        assert_eq!(selection.connectors.len(), 1);
+        assert_eq!(
+            board_to_markdown(&selection),
+            "# Ideas (selection)\n\n- second\n- third\n  more\n"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cluster.json");
+        export_selection(&board, &[1, 2], &path, 1.0).unwrap();
+        let mut next_note_id = 10;
+        let (notes, connectors) = read_selection(&path).unwrap().paste(
+            &Board::default(),
+            &mut next_note_id,
+            Pos2::new(50.0, 50.0),
+        );
+        assert_eq!(notes.len(), 2);
+        assert_eq!((connectors[0].from_note, connectors[0].to_note), (10, 11));
+        assert!(export_selection(&board, &[1], &dir.path().join("x.doc"), 1.0).is_err());
+    }
+
+    #[test]
+    fn workspace_export_skips_foreign_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let state = AppState {
+            boards: vec![sample_board()],
+            ..Default::default()
+        };
+        state.save_to_file(&dir.path().join("ideas.json")).unwrap();
+        std::fs::write(dir.path().join("other.json"), "{\"foo\": 1}").unwrap();
+
+        let written = export_workspace(dir.path(), &out, &[ExportFormat::Markdown], 1.0).unwrap();
+        assert_eq!(written, vec![out.join("ideas.md")]);
+        let md = std::fs::read_to_string(out.join("ideas.md")).unwrap();
+        assert!(md.starts_with("# Ideas"));
+
+        // Only the files asked for are exported
+        let written = export_files(
+            &[dir.path().join("other.json"), dir.path().join("gone.json")],
+            &out.join("none"),
+            &[ExportFormat::Markdown],
+            1.0,
+        )
+        .unwrap();
+        assert!(written.is_empty());
+    }
+}
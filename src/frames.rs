@@ -0,0 +1,147 @@
+//! Titled regions of the board that carry the notes inside them along
+
+use crate::{Board, NoteData};
+use egui::{Color32, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Height of the title strip along the top of a frame
+pub const FRAME_TITLE_HEIGHT: f32 = 28.0;
+/// Space left between a new frame and the notes it is put around
+pub const FRAME_PADDING: f32 = 20.0;
+/// Size of a frame made with no notes selected
+pub const DEFAULT_FRAME_SIZE: Vec2 = Vec2::new(400.0, 300.0);
+/// Smallest size a frame can be resized to
+pub const MIN_FRAME_SIZE: Vec2 = Vec2::new(120.0, 80.0);
+/// Color of new frames
+pub const DEFAULT_FRAME_COLOR: Color32 = Color32::from_rgb(120, 130, 150);
+
+/// A titled rectangle; notes whose center lies inside belong to it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub id: u64,
+    pub title: String,
+    /// Area covered, in board coordinates
+    pub rect: Rect,
+    pub color: Color32,
+}
+
+impl Frame {
+    /// Whether `note` belongs to the frame
+    pub fn contains(&self, note: &NoteData) -> bool {
+        self.rect
+            .contains(Rect::from_min_size(note.pos, note.size).center())
+    }
+
+    /// Translucent color the frame's area is filled with
+    pub fn fill(&self) -> Color32 {
+        self.color.gamma_multiply(0.15)
+    }
+}
+
+/// Strip holding the title of a frame drawn at `rect`, by which it is dragged
+pub fn title_bar(rect: Rect) -> Rect {
+    Rect::from_min_size(
+        rect.min,
+        Vec2::new(rect.width(), FRAME_TITLE_HEIGHT.min(rect.height())),
+    )
+}
+
+impl Board {
+    /// Id for a new frame
+    pub fn next_frame_id(&self) -> u64 {
+        self.frames.iter().map(|f| f.id).max().unwrap_or(0) + 1
+    }
+
+    /// Ids of the notes inside `frame`
+    pub fn frame_notes(&self, frame: &Frame) -> Vec<u64> {
+        self.notes
+            .iter()
+            .filter(|n| frame.contains(n))
+            .map(|n| n.id)
+            .collect()
+    }
+
+    /// A new frame around the notes `ids`, with room for its title, or
+    /// `None` if none of them are on the board
+    pub fn frame_around(&self, ids: &[u64], title: &str) -> Option<Frame> {
+        let bounds = self
+            .notes
+            .iter()
+            .filter(|n| ids.contains(&n.id))
+            .map(|n| Rect::from_min_size(n.pos, n.size))
+            .reduce(Rect::union)?;
+        let mut rect = bounds.expand(FRAME_PADDING);
+        rect.min.y -= FRAME_TITLE_HEIGHT;
+        Some(Frame {
+            id: self.next_frame_id(),
+            title: title.into(),
+            rect,
+            color: DEFAULT_FRAME_COLOR,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Command;
+    use egui::Pos2;
+
+    fn board() -> Board {
+        let note = |id: u64, x: f32, y: f32| NoteData {
+            id,
+            pos: Pos2::new(x, y),
+            size: Vec2::new(100.0, 80.0),
+            ..Default::default()
+        };
+        Board {
+            notes: vec![note(1, 0.0, 0.0), note(2, 150.0, 0.0), note(3, 600.0, 0.0)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn frames_fit_around_notes_and_hold_them() {
+        let board = board();
+        let frame = board.frame_around(&[1, 2, 9], "Todo").unwrap();
+        assert_eq!(frame.id, 1);
+        assert_eq!(
+            frame.rect,
+            Rect::from_min_max(Pos2::new(-20.0, -48.0), Pos2::new(270.0, 100.0))
+        );
+        assert_eq!(board.frame_notes(&frame), [1, 2]);
+        assert_eq!(board.frame_around(&[9], "Nothing"), None);
+        assert_eq!(title_bar(frame.rect).height(), FRAME_TITLE_HEIGHT);
+    }
+
+    #[test]
+    fn frame_changes_can_be_undone() {
+        let mut board = board();
+        let frame = board.frame_around(&[3], "Done").unwrap();
+        let add = Command::AddFrame {
+            frame: frame.clone(),
+        };
+        add.apply(&mut board);
+        assert_eq!(board.next_frame_id(), 2);
+
+        let moved = Frame {
+            rect: frame.rect.translate(Vec2::splat(50.0)),
+            ..frame.clone()
+        };
+        let update = Command::UpdateFrame {
+            before: frame.clone(),
+            after: moved.clone(),
+        };
+        update.apply(&mut board);
+        assert_eq!(board.frames, [moved]);
+        update.revert(&mut board);
+        assert_eq!(board.frames, [frame.clone()]);
+
+        let remove = Command::RemoveFrame { frame, index: 0 };
+        remove.apply(&mut board);
+        assert!(board.frames.is_empty());
+        remove.revert(&mut board);
+        add.revert(&mut board);
+        assert!(board.frames.is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Driving the board with a game controller, for a board shown on a TV:
+//! the sticks pan and zoom, the d-pad moves between notes
+
+use crate::{Board, NoteData};
+use egui::{Pos2, Rect, Vec2};
+
+/// Stick deflection ignored as drift
+pub const STICK_DEADZONE: f32 = 0.15;
+/// Screens panned per second with a stick pushed all the way
+const PAN_SPEED: f32 = 1.0;
+/// How many times closer the view gets per second with a stick pushed all
+/// the way
+const ZOOM_SPEED: f32 = 3.0;
+
+/// `stick` with drift ignored, the rest rescaled to grow from zero at the
+/// edge of the deadzone
+pub fn stick_input(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length <= STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+    stick / length * (length.min(1.0) - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)
+}
+
+/// `scene_rect` panned for `dt` seconds by a stick pushed `stick` (screen
+/// directions), as fast on screen at every zoom
+pub fn pan_scene_rect(scene_rect: Rect, stick: Vec2, dt: f32) -> Rect {
+    scene_rect.translate(stick * scene_rect.size() * PAN_SPEED * dt)
+}
+
+/// Zoom factor for a stick pushed `amount` towards zooming in, or out when
+/// negative, for `dt` seconds
+pub fn zoom_factor(amount: f32, dt: f32) -> f32 {
+    ZOOM_SPEED.powf(amount * dt)
+}
+
+/// The note whose center is nearest `from` (board coordinates)
+pub fn nearest_note(board: &Board, from: Pos2) -> Option<u64> {
+    board
+        .notes
+        .iter()
+        .min_by(|a, b| {
+            let distance = |n: &NoteData| (n.pos + n.size / 2.0).distance_sq(from);
+            distance(a).total_cmp(&distance(b))
+        })
+        .map(|n| n.id)
+}
+
+/// The note next to `current` in `direction` (board coordinates), notes
+/// straight ahead winning over nearer ones off to the side; `None` if no
+/// note lies that way
+pub fn note_towards(board: &Board, current: u64, direction: Vec2) -> Option<u64> {
+    let center = |n: &NoteData| n.pos + n.size / 2.0;
+    let from = center(board.notes.iter().find(|n| n.id == current)?);
+    let direction = direction.normalized();
+    board
+        .notes
+        .iter()
+        .filter(|n| n.id != current)
+        .filter_map(|n| {
+            let offset = center(n) - from;
+            let ahead = offset.dot(direction);
+            let aside = (offset - direction * ahead).length();
+            (ahead > 0.0).then_some((ahead + 2.0 * aside, n.id))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, id)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sticks_ignore_drift_and_move_the_view() {
+        assert_eq!(stick_input(Vec2::new(0.1, -0.1)), Vec2::ZERO);
+        assert_eq!(stick_input(Vec2::new(1.0, 0.0)), Vec2::new(1.0, 0.0));
+        assert!(stick_input(Vec2::new(0.0, 0.2)).y < 0.1);
+
+        let view = Rect::from_min_size(Pos2::ZERO, Vec2::new(200.0, 100.0));
+        let panned = pan_scene_rect(view, Vec2::new(1.0, 0.0), 0.5);
+        assert_eq!(panned.min, Pos2::new(100.0, 0.0));
+        assert_eq!(panned.size(), view.size());
+        assert!(zoom_factor(1.0, 0.1) > 1.0);
+        assert!(zoom_factor(-1.0, 0.1) < 1.0);
+    }
+
+    #[test]
+    fn the_dpad_moves_to_the_note_that_way() {
+        let note = |id: u64, x: f32, y: f32| NoteData {
+            id,
+            pos: Pos2::new(x, y),
+            size: Vec2::splat(50.0),
+            ..Default::default()
+        };
+        let board = Board {
+            notes: vec![
+                note(1, 0.0, 0.0),
+                note(2, 200.0, 10.0),
+                note(3, 120.0, 150.0),
+                note(4, 0.0, 300.0),
+            ],
+            ..Default::default()
+        };
+        let right = Vec2::new(1.0, 0.0);
+        assert_eq!(note_towards(&board, 1, right), Some(2));
+        // Straight ahead wins over nearer but off to the side
+        assert_eq!(note_towards(&board, 1, Vec2::new(0.0, 1.0)), Some(4));
+        assert_eq!(note_towards(&board, 2, right), None);
+        assert_eq!(note_towards(&board, 9, right), None);
+        assert_eq!(nearest_note(&board, Pos2::new(140.0, 160.0)), Some(3));
+        assert_eq!(nearest_note(&Board::default(), Pos2::ZERO), None);
+    }
+}
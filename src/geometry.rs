@@ -0,0 +1,410 @@
+//! Shape of a note as drawn: skew, tilt, drag wiggle, view rotation,
+//! hit-testing and resizing
+//!
+//! All functions work in board coordinates unless they take a view rotation.
+
+use crate::{inverse_quarter_turns, rotate_quarter_turns};
+use egui::emath::Rot2;
+use egui::{Pos2, Rect, Vec2};
+
+/// How strongly drag speed turns into skew
+pub const DRAG_SKEW_FACTOR: f32 = 0.02;
+/// Fraction of the remaining skew caught up with per frame while dragging
+pub const SKEW_EASE_DRAGGING: f32 = 0.5;
+/// Fraction of the skew relaxed per frame after a drag
+pub const SKEW_EASE_RELEASED: f32 = 0.2;
+/// Horizontal amplitude of the drag wiggle, in points
+pub const WIGGLE_AMPLITUDE: f32 = 3.0;
+/// Angular frequency of the drag wiggle, in radians per second
+pub const WIGGLE_FREQUENCY: f32 = 15.0;
+/// Smallest size a note can be resized to
+pub const MIN_NOTE_SIZE: Vec2 = Vec2::new(40.0, 30.0);
+/// Width of the resize handles along the inside of a note's border
+pub const RESIZE_HANDLE_SIZE: f32 = 8.0;
+/// How far above a note its rotate handle sits
+pub const ROTATE_HANDLE_DISTANCE: f32 = 16.0;
+/// Steps a rotation snaps to while Shift is held, in radians (15°)
+pub const ROTATION_SNAP: f32 = std::f32::consts::PI / 12.0;
+/// Largest tilt given to new notes on boards that tilt them, in radians (3°)
+pub const MAX_NEW_NOTE_TILT: f32 = std::f32::consts::PI / 60.0;
+
+/// Part of a note's border that resizes the note when dragged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResizeHandle {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ResizeHandle {
+    /// Every handle; corners come last so they win where they overlap edges
+    pub const ALL: [ResizeHandle; 8] = [
+        ResizeHandle::Left,
+        ResizeHandle::Right,
+        ResizeHandle::Top,
+        ResizeHandle::Bottom,
+        ResizeHandle::TopLeft,
+        ResizeHandle::TopRight,
+        ResizeHandle::BottomLeft,
+        ResizeHandle::BottomRight,
+    ];
+
+    /// Which side the handle moves per axis: -1 the left/top, 1 the
+    /// right/bottom, 0 neither
+    pub fn sides(self) -> (i8, i8) {
+        match self {
+            ResizeHandle::Left => (-1, 0),
+            ResizeHandle::Right => (1, 0),
+            ResizeHandle::Top => (0, -1),
+            ResizeHandle::Bottom => (0, 1),
+            ResizeHandle::TopLeft => (-1, -1),
+            ResizeHandle::TopRight => (1, -1),
+            ResizeHandle::BottomLeft => (-1, 1),
+            ResizeHandle::BottomRight => (1, 1),
+        }
+    }
+
+    /// Area of the handle on a note occupying `note`, `width` thick
+    pub fn rect(self, note: Rect, width: f32) -> Rect {
+        let span = |side: i8, min: f32, max: f32| match side {
+            -1 => (min, min + width),
+            1 => (max - width, max),
+            _ => (min + width, max - width),
+        };
+        let (sx, sy) = self.sides();
+        let (x0, x1) = span(sx, note.min.x, note.max.x);
+        let (y0, y1) = span(sy, note.min.y, note.max.y);
+        Rect::from_min_max(Pos2::new(x0, y0), Pos2::new(x1, y1))
+    }
+}
+
+/// `note` after dragging `handle` by `delta`, never smaller than `min_size`
+pub fn resize_rect(note: Rect, handle: ResizeHandle, delta: Vec2, min_size: Vec2) -> Rect {
+    let mut rect = note;
+    let (sx, sy) = handle.sides();
+    match sx {
+        -1 => rect.min.x = (rect.min.x + delta.x).min(rect.max.x - min_size.x),
+        1 => rect.max.x = (rect.max.x + delta.x).max(rect.min.x + min_size.x),
+        _ => {}
+    }
+    match sy {
+        -1 => rect.min.y = (rect.min.y + delta.y).min(rect.max.y - min_size.y),
+        1 => rect.max.y = (rect.max.y + delta.y).max(rect.min.y + min_size.y),
+        _ => {}
+    }
+    rect
+}
+
+/// Round a size to whole grid steps, keeping at least one step per axis; a
+/// grid of 0 leaves it as it is
+pub fn snap_size_to_grid(size: Vec2, grid: f32) -> Vec2 {
+    if grid <= 0.0 {
+        return size;
+    }
+    let snap = |v: f32| ((v / grid).round() * grid).max(grid);
+    Vec2::new(snap(size.x), snap(size.y))
+}
+
+/// Corners of a note skewed by `skew`, turned `rotation` radians clockwise
+/// around its center and shifted by `offset`, clockwise from the top-left.
+///
+/// `skew.x` shears the bottom edge sideways and `skew.y` shears the right
+/// edge downwards, both proportional to the note size.
+pub fn note_corners(pos: Pos2, size: Vec2, skew: Vec2, rotation: f32, offset: Vec2) -> [Pos2; 4] {
+    let (w, h) = (size.x, size.y);
+    let center = pos + offset + size / 2.0;
+    [
+        pos + offset,
+        pos + offset + Vec2::new(w, w * skew.y),
+        pos + offset + Vec2::new(w + h * skew.x, h + w * skew.y),
+        pos + offset + Vec2::new(h * skew.x, h),
+    ]
+    .map(|p| rotate_about(p, center, rotation))
+}
+
+/// `p` turned `angle` radians clockwise (on screen) around `center`
+pub fn rotate_about(p: Pos2, center: Pos2, angle: f32) -> Pos2 {
+    if angle == 0.0 {
+        return p;
+    }
+    center + Rot2::from_angle(angle) * (p - center)
+}
+
+/// Rotation that points a note's top towards `p` from its `center`, in
+/// radians, snapped to multiples of `snap` unless it is 0
+pub fn rotation_towards(center: Pos2, p: Pos2, snap: f32) -> f32 {
+    let angle = (p - center).angle() + std::f32::consts::FRAC_PI_2;
+    // Keep the angle in -π..=π so small tilts stay small numbers
+    let angle =
+        (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+    if snap > 0.0 {
+        (angle / snap).round() * snap
+    } else {
+        angle
+    }
+}
+
+/// Average of the corners
+pub fn quad_center(corners: &[Pos2; 4]) -> Pos2 {
+    let sum = corners.iter().fold(Vec2::ZERO, |acc, p| acc + p.to_vec2());
+    (sum / 4.0).to_pos2()
+}
+
+/// Whether `p` lies inside (or on the edge of) the convex quad `corners`
+pub fn point_in_quad(p: Pos2, corners: &[Pos2; 4]) -> bool {
+    let mut sign = 0.0_f32;
+    for i in 0..4 {
+        let a = corners[i];
+        let b = corners[(i + 1) % 4];
+        let cross = (b - a).x * (p - a).y - (b - a).y * (p - a).x;
+        if cross == 0.0 {
+            continue;
+        }
+        if sign == 0.0 {
+            sign = cross.signum();
+        } else if cross.signum() != sign {
+            return false;
+        }
+    }
+    true
+}
+
+/// Sideways offset of a dragged note at app time `time` (seconds)
+pub fn wiggle_offset(time: f32) -> Vec2 {
+    Vec2::new(WIGGLE_AMPLITUDE * (time * WIGGLE_FREQUENCY).sin(), 0.0)
+}
+
+/// Skew a note leans towards while being dragged by `delta` per frame
+pub fn drag_skew_target(delta: Vec2) -> Vec2 {
+    delta * DRAG_SKEW_FACTOR
+}
+
+/// Move `current` skew the fraction `ease` of the way to `target`
+pub fn ease_skew(current: Vec2, target: Vec2, ease: f32) -> Vec2 {
+    current + (target - current) * ease
+}
+
+/// Corners of the drawn note in view coordinates, after rotating the board
+/// view by `view_turns` around `center`
+pub fn view_corners(
+    pos: Pos2,
+    size: Vec2,
+    skew: Vec2,
+    rotation: f32,
+    offset: Vec2,
+    view_turns: u8,
+    center: Pos2,
+) -> [Pos2; 4] {
+    note_corners(pos, size, skew, rotation, offset)
+        .map(|p| rotate_quarter_turns(p, center, view_turns))
+}
+
+/// Whether the point `p` in view coordinates hits the drawn (skewed,
+/// tilted) note
+pub fn hit_test(
+    p: Pos2,
+    pos: Pos2,
+    size: Vec2,
+    skew: Vec2,
+    rotation: f32,
+    view_turns: u8,
+    center: Pos2,
+) -> bool {
+    let on_board = rotate_quarter_turns(p, center, inverse_quarter_turns(view_turns));
+    point_in_quad(
+        on_board,
+        &note_corners(pos, size, skew, rotation, Vec2::ZERO),
+    )
+}
+
+/// Axis-aligned bounds of the corners
+pub fn quad_bounds(corners: &[Pos2; 4]) -> Rect {
+    Rect::from_points(corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POS: Pos2 = Pos2::new(10.0, 20.0);
+    const SIZE: Vec2 = Vec2::new(100.0, 50.0);
+
+    #[test]
+    fn unskewed_corners_form_the_rect() {
+        let corners = note_corners(POS, SIZE, Vec2::ZERO, 0.0, Vec2::ZERO);
+        assert_eq!(
+            corners,
+            [
+                Pos2::new(10.0, 20.0),
+                Pos2::new(110.0, 20.0),
+                Pos2::new(110.0, 70.0),
+                Pos2::new(10.0, 70.0),
+            ]
+        );
+        assert_eq!(quad_center(&corners), Pos2::new(60.0, 45.0));
+        assert_eq!(quad_bounds(&corners), Rect::from_min_size(POS, SIZE));
+    }
+
+    #[test]
+    fn skew_shears_edges_and_offset_shifts() {
+        let corners = note_corners(POS, SIZE, Vec2::new(0.2, 0.1), 0.0, Vec2::new(3.0, 0.0));
+        assert_eq!(corners[0], Pos2::new(13.0, 20.0));
+        assert_eq!(corners[1], Pos2::new(113.0, 30.0));
+        assert_eq!(corners[2], Pos2::new(123.0, 80.0));
+        assert_eq!(corners[3], Pos2::new(23.0, 70.0));
+    }
+
+    #[test]
+    fn rotation_turns_around_the_center() {
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let corners = note_corners(POS, SIZE, Vec2::ZERO, quarter, Vec2::ZERO);
+        // A quarter turn clockwise stands the 100 x 50 note upright
+        let bounds = quad_bounds(&corners);
+        assert!((bounds.width() - 50.0).abs() < 1e-3);
+        assert!((bounds.height() - 100.0).abs() < 1e-3);
+        assert!((quad_center(&corners) - Pos2::new(60.0, 45.0)).length() < 1e-3);
+        // The top-left corner ends up top-right
+        assert!((corners[0] - Pos2::new(85.0, -5.0)).length() < 1e-3);
+        // The note's corner is no longer there, its old side is
+        assert!(!hit_test(
+            POS + Vec2::splat(2.0),
+            POS,
+            SIZE,
+            Vec2::ZERO,
+            quarter,
+            0,
+            Pos2::ZERO
+        ));
+        assert!(hit_test(
+            Pos2::new(60.0, 0.0),
+            POS,
+            SIZE,
+            Vec2::ZERO,
+            quarter,
+            0,
+            Pos2::ZERO
+        ));
+    }
+
+    #[test]
+    fn handles_point_notes_towards_the_pointer() {
+        let center = Pos2::new(10.0, 10.0);
+        assert_eq!(rotation_towards(center, Pos2::new(10.0, 0.0), 0.0), 0.0);
+        let right = rotation_towards(center, Pos2::new(20.0, 10.0), 0.0);
+        assert!((right - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        let left = rotation_towards(center, Pos2::new(0.0, 10.0), 0.0);
+        assert!((left + std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        // 20° off upright snaps to 15°
+        let tilted = center + Rot2::from_angle(0.35) * Vec2::new(0.0, -10.0);
+        assert!((rotation_towards(center, tilted, ROTATION_SNAP) - ROTATION_SNAP).abs() < 1e-6);
+    }
+
+    #[test]
+    fn point_in_quad_handles_both_windings() {
+        let corners = note_corners(POS, SIZE, Vec2::ZERO, 0.0, Vec2::ZERO);
+        assert!(point_in_quad(Pos2::new(50.0, 50.0), &corners));
+        assert!(point_in_quad(POS, &corners));
+        assert!(!point_in_quad(Pos2::new(5.0, 50.0), &corners));
+        let mut reversed = corners;
+        reversed.reverse();
+        assert!(point_in_quad(Pos2::new(50.0, 50.0), &reversed));
+        assert!(!point_in_quad(Pos2::new(50.0, 75.0), &reversed));
+    }
+
+    #[test]
+    fn hit_test_follows_skew() {
+        let skew = Vec2::new(0.4, 0.0);
+        // The bottom edge leans right, so a point left of it near the bottom misses
+        assert!(!hit_test(
+            Pos2::new(15.0, 68.0),
+            POS,
+            SIZE,
+            skew,
+            0.0,
+            0,
+            Pos2::ZERO
+        ));
+        assert!(hit_test(
+            Pos2::new(125.0, 68.0),
+            POS,
+            SIZE,
+            skew,
+            0.0,
+            0,
+            Pos2::ZERO
+        ));
+        assert!(!hit_test(
+            Pos2::new(125.0, 68.0),
+            POS,
+            SIZE,
+            Vec2::ZERO,
+            0.0,
+            0,
+            Pos2::ZERO
+        ));
+    }
+
+    #[test]
+    fn hit_test_follows_view_rotation() {
+        let center = Pos2::ZERO;
+        for turns in 0..4 {
+            let inside = rotate_quarter_turns(Pos2::new(50.0, 50.0), center, turns);
+            assert!(hit_test(inside, POS, SIZE, Vec2::ZERO, 0.0, turns, center));
+            let corners = view_corners(POS, SIZE, Vec2::ZERO, 0.0, Vec2::ZERO, turns, center);
+            assert!(point_in_quad(inside, &corners));
+        }
+        let outside = rotate_quarter_turns(Pos2::new(50.0, 50.0), center, 1);
+        assert!(!hit_test(outside, POS, SIZE, Vec2::ZERO, 0.0, 0, center));
+    }
+
+    #[test]
+    fn resizing_moves_only_the_dragged_sides() {
+        let note = Rect::from_min_size(POS, SIZE);
+        let delta = Vec2::new(20.0, 10.0);
+        let grown = resize_rect(note, ResizeHandle::BottomRight, delta, MIN_NOTE_SIZE);
+        assert_eq!(grown, Rect::from_min_size(POS, SIZE + delta));
+        let left = resize_rect(note, ResizeHandle::Left, delta, MIN_NOTE_SIZE);
+        assert_eq!(left.min, Pos2::new(30.0, 20.0));
+        assert_eq!(left.max, note.max);
+        // Shrinking stops at the minimum size, keeping the opposite side
+        let tiny = resize_rect(note, ResizeHandle::TopLeft, SIZE, MIN_NOTE_SIZE);
+        assert_eq!(tiny.size(), MIN_NOTE_SIZE);
+        assert_eq!(tiny.max, note.max);
+    }
+
+    #[test]
+    fn handles_sit_inside_the_note() {
+        let note = Rect::from_min_size(POS, SIZE);
+        for handle in ResizeHandle::ALL {
+            let rect = handle.rect(note, RESIZE_HANDLE_SIZE);
+            assert!(note.contains_rect(rect), "{handle:?}");
+        }
+        assert_eq!(
+            ResizeHandle::BottomRight.rect(note, 8.0),
+            Rect::from_min_max(Pos2::new(102.0, 62.0), note.max)
+        );
+    }
+
+    #[test]
+    fn size_snaps_to_whole_steps() {
+        assert_eq!(
+            snap_size_to_grid(Vec2::new(130.0, 14.0), 20.0),
+            Vec2::new(140.0, 20.0)
+        );
+    }
+
+    #[test]
+    fn skew_eases_towards_target() {
+        let target = drag_skew_target(Vec2::new(50.0, -25.0));
+        assert!((target - Vec2::new(1.0, -0.5)).length() < 1e-6);
+        let eased = ease_skew(Vec2::ZERO, target, SKEW_EASE_DRAGGING);
+        assert_eq!(eased, target * 0.5);
+        assert_eq!(ease_skew(eased, eased, SKEW_EASE_RELEASED), eased);
+        assert_eq!(wiggle_offset(0.0), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,142 @@
+//! Smart guides: lining dragged notes up with the edges and centers of the
+//! notes around them
+
+use crate::Board;
+use crate::spatial::SpatialGrid;
+use egui::{Pos2, Rect, Vec2};
+
+/// How close a dragged note's edge or center has to come to another note's
+/// to snap to it, in screen points
+pub const GUIDE_SNAP_DISTANCE: f32 = 6.0;
+
+/// How far around the dragged notes others are looked for, in board units
+pub const GUIDE_RANGE: f32 = 600.0;
+
+/// A line showing what a dragged note lines up with, in board coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guide {
+    pub from: Pos2,
+    pub to: Pos2,
+}
+
+/// Where guides move dragged notes and the lines showing why
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GuideSnap {
+    pub offset: Vec2,
+    pub guides: Vec<Guide>,
+}
+
+/// Start, center and end of a span
+fn lines(min: f32, max: f32) -> [f32; 3] {
+    [min, (min + max) / 2.0, max]
+}
+
+/// Smallest shift within `distance` putting one of the `moving` lines onto
+/// one of the `others`
+fn nearest_shift(moving: [f32; 3], others: &[[f32; 3]], distance: f32) -> Option<f32> {
+    others
+        .iter()
+        .flatten()
+        .flat_map(|&target| moving.map(|line| target - line))
+        .filter(|shift| shift.abs() <= distance)
+        .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+}
+
+/// Snap `rect` into line with the nearest edges or centers of `others`, on
+/// each axis on its own
+pub fn snap_to_guides(rect: Rect, others: &[Rect], distance: f32) -> GuideSnap {
+    let columns: Vec<_> = others.iter().map(|r| lines(r.min.x, r.max.x)).collect();
+    let rows: Vec<_> = others.iter().map(|r| lines(r.min.y, r.max.y)).collect();
+    let dx = nearest_shift(lines(rect.min.x, rect.max.x), &columns, distance);
+    let dy = nearest_shift(lines(rect.min.y, rect.max.y), &rows, distance);
+    let offset = Vec2::new(dx.unwrap_or(0.0), dy.unwrap_or(0.0));
+    let snapped = rect.translate(offset);
+    let lined_up = |a: f32, b: f32| (a - b).abs() < 0.01;
+    let mut guides = Vec::new();
+    for other in others {
+        if dx.is_some() {
+            for x in lines(snapped.min.x, snapped.max.x) {
+                if lines(other.min.x, other.max.x)
+                    .iter()
+                    .any(|&o| lined_up(o, x))
+                {
+                    guides.push(Guide {
+                        from: Pos2::new(x, snapped.min.y.min(other.min.y)),
+                        to: Pos2::new(x, snapped.max.y.max(other.max.y)),
+                    });
+                }
+            }
+        }
+        if dy.is_some() {
+            for y in lines(snapped.min.y, snapped.max.y) {
+                if lines(other.min.y, other.max.y)
+                    .iter()
+                    .any(|&o| lined_up(o, y))
+                {
+                    guides.push(Guide {
+                        from: Pos2::new(snapped.min.x.min(other.min.x), y),
+                        to: Pos2::new(snapped.max.x.max(other.max.x), y),
+                    });
+                }
+            }
+        }
+    }
+    GuideSnap { offset, guides }
+}
+
+impl Board {
+    /// How the notes `moving` snap into line with the notes near them, with
+    /// `spatial` holding where the notes are; `None` if nothing lines up
+    pub fn guide_snap(
+        &self,
+        spatial: &SpatialGrid,
+        moving: &[u64],
+        distance: f32,
+    ) -> Option<GuideSnap> {
+        let index = self.note_index();
+        let rect_of = |id: &u64| {
+            let note = &self.notes[*index.get(id)?];
+            Some(Rect::from_min_size(note.pos, note.size))
+        };
+        let rect = moving.iter().filter_map(rect_of).reduce(Rect::union)?;
+        let buried = self.buried_notes();
+        let others: Vec<Rect> = spatial
+            .query(rect.expand(GUIDE_RANGE))
+            .into_iter()
+            .filter(|id| !moving.contains(id) && !buried.contains(id))
+            .filter_map(|id| rect_of(&id))
+            .collect();
+        let snap = snap_to_guides(rect, &others, distance);
+        (!snap.guides.is_empty()).then_some(snap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearby_edges_and_centers_snap_together() {
+        let other = Rect::from_min_size(Pos2::new(0.0, 0.0), Vec2::new(100.0, 80.0));
+        // Left edges 3 apart
+        let dragged = Rect::from_min_size(Pos2::new(3.0, 200.0), Vec2::new(60.0, 50.0));
+        let snap = snap_to_guides(dragged, &[other], 6.0);
+        assert_eq!(snap.offset, Vec2::new(-3.0, 0.0));
+        assert_eq!(
+            snap.guides,
+            [Guide {
+                from: Pos2::new(0.0, 0.0),
+                to: Pos2::new(0.0, 250.0),
+            }]
+        );
+
+        // Centers 4 apart vertically
+        let beside = Rect::from_min_size(Pos2::new(300.0, 19.0), Vec2::new(50.0, 50.0));
+        let snap = snap_to_guides(beside, &[other], 6.0);
+        assert_eq!(snap.offset, Vec2::new(0.0, -4.0));
+        assert_eq!(snap.guides[0].from, Pos2::new(0.0, 40.0));
+
+        let far = Rect::from_min_size(Pos2::new(200.0, 200.0), Vec2::new(50.0, 50.0));
+        assert!(snap_to_guides(far, &[other], 6.0).guides.is_empty());
+    }
+}
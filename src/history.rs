@@ -0,0 +1,403 @@
+//! Undo/redo: note changes as reversible commands
+
+use crate::connectors::Connector;
+use crate::expiry::now_secs;
+use crate::frames::Frame;
+use crate::ink::InkStroke;
+use crate::{AppState, Board, NoteData};
+
+/// Most commands kept for undoing
+pub const MAX_UNDO: usize = 500;
+
+/// A reversible change to the notes of a board
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// A note was added at the end of the board
+    Create { note: NoteData },
+    /// The note at `index` was removed, into the trash
+    Delete { note: NoteData, index: usize },
+    /// A note changed, e.g. it moved, its text was edited or recolored
+    Update { before: NoteData, after: NoteData },
+    /// A connector was added at the end of the board's connectors
+    Connect { connector: Connector },
+    /// The connector at `index` was removed
+    Disconnect { connector: Connector, index: usize },
+    /// An ink stroke was added at the end of the board's strokes
+    Draw { stroke: InkStroke },
+    /// The ink stroke at `index` was removed
+    Erase { stroke: InkStroke, index: usize },
+    /// A frame was added at the end of the board's frames
+    AddFrame { frame: Frame },
+    /// The frame at `index` was removed; its notes stay
+    RemoveFrame { frame: Frame, index: usize },
+    /// A frame was moved, resized, renamed or recolored
+    UpdateFrame { before: Frame, after: Frame },
+    /// Several commands undone and redone together
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    /// Short description for menus and status messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            Command::Create { .. } => "create note",
+            Command::Delete { .. } => "delete note",
+            Command::Update { before, after } => {
+                if before.text != after.text {
+                    "edit note"
+                } else if before.color != after.color {
+                    "change color"
+                } else if before.size != after.size {
+                    "resize note"
+                } else if before.pos != after.pos {
+                    "move note"
+                } else if before.image != after.image {
+                    "change image"
+                } else if before.rotation != after.rotation {
+                    "rotate note"
+                } else if before.due != after.due {
+                    "change due date"
+                } else if !before.locked && after.locked {
+                    "lock note"
+                } else if before.locked && !after.locked {
+                    "unlock note"
+                } else {
+                    "change note"
+                }
+            }
+            Command::Connect { .. } => "connect notes",
+            Command::Disconnect { .. } => "remove connector",
+            Command::Draw { .. } => "draw",
+            Command::Erase { .. } => "erase ink",
+            Command::AddFrame { .. } => "add frame",
+            Command::RemoveFrame { .. } => "remove frame",
+            Command::UpdateFrame { .. } => "change frame",
+            Command::Batch(_) => "change notes",
+        }
+    }
+
+    /// Command turning the notes `before` into the notes `after`, matching
+    /// notes by id. New notes are expected at the end.
+    pub fn diff(before: &[NoteData], after: &[NoteData]) -> Command {
+        let mut commands = Vec::new();
+        for (index, note) in before.iter().enumerate().rev() {
+            if !after.iter().any(|n| n.id == note.id) {
+                commands.push(Command::Delete {
+                    note: note.clone(),
+                    index,
+                });
+            }
+        }
+        for note in after {
+            match before.iter().find(|n| n.id == note.id) {
+                Some(old) if old != note => commands.push(Command::Update {
+                    before: old.clone(),
+                    after: note.clone(),
+                }),
+                Some(_) => {}
+                None => commands.push(Command::Create { note: note.clone() }),
+            }
+        }
+        Command::batch(commands).unwrap_or(Command::Batch(Vec::new()))
+    }
+
+    /// Combine `commands` into one, or `None` if there are none
+    pub fn batch(mut commands: Vec<Command>) -> Option<Command> {
+        match commands.len() {
+            0 => None,
+            1 => commands.pop(),
+            _ => Some(Command::Batch(commands)),
+        }
+    }
+
+    /// Whether the command changes nothing
+    pub fn is_noop(&self) -> bool {
+        match self {
+            Command::Update { before, after } => before == after,
+            Command::UpdateFrame { before, after } => before == after,
+            Command::Batch(commands) => commands.iter().all(Command::is_noop),
+            _ => false,
+        }
+    }
+
+    /// Perform the command on `board`
+    pub fn apply(&self, board: &mut Board) {
+        match self {
+            Command::Create { note } => board.notes.push(note.clone()),
+            Command::Delete { note, .. } => {
+                if let Some(index) = board.notes.iter().position(|n| n.id == note.id) {
+                    let note = board.notes.remove(index);
+                    board.trash_note(note, now_secs());
+                }
+            }
+            Command::Update { after, .. } => replace_note(board, after),
+            Command::Connect { connector } => board.connectors.push(connector.clone()),
+            Command::Disconnect { connector, .. } => board.connectors.retain(|c| c != connector),
+            Command::Draw { stroke } => board.strokes.push(stroke.clone()),
+            Command::Erase { stroke, .. } => board.strokes.retain(|s| s != stroke),
+            Command::AddFrame { frame } => board.frames.push(frame.clone()),
+            Command::RemoveFrame { frame, .. } => board.frames.retain(|f| f.id != frame.id),
+            Command::UpdateFrame { after, .. } => replace_frame(board, after),
+            Command::Batch(commands) => commands.iter().for_each(|c| c.apply(board)),
+        }
+    }
+
+    /// Take the command back on `board`
+    pub fn revert(&self, board: &mut Board) {
+        match self {
+            Command::Create { note } => board.notes.retain(|n| n.id != note.id),
+            Command::Delete { note, index } => {
+                board.untrash(note.id);
+                // The note may have been restored from the trash meanwhile
+                if !board.notes.iter().any(|n| n.id == note.id) {
+                    let index = (*index).min(board.notes.len());
+                    board.notes.insert(index, note.clone());
+                }
+            }
+            Command::Update { before, .. } => replace_note(board, before),
+            Command::Connect { connector } => board.connectors.retain(|c| c != connector),
+            Command::Disconnect { connector, index } => {
+                let index = (*index).min(board.connectors.len());
+                board.connectors.insert(index, connector.clone());
+            }
+            Command::Draw { stroke } => board.strokes.retain(|s| s != stroke),
+            Command::Erase { stroke, index } => {
+                let index = (*index).min(board.strokes.len());
+                board.strokes.insert(index, stroke.clone());
+            }
+            Command::AddFrame { frame } => board.frames.retain(|f| f.id != frame.id),
+            Command::RemoveFrame { frame, index } => {
+                let index = (*index).min(board.frames.len());
+                board.frames.insert(index, frame.clone());
+            }
+            Command::UpdateFrame { before, .. } => replace_frame(board, before),
+            Command::Batch(commands) => commands.iter().rev().for_each(|c| c.revert(board)),
+        }
+    }
+}
+
+fn replace_note(board: &mut Board, note: &NoteData) {
+    if let Some(n) = board.notes.iter_mut().find(|n| n.id == note.id) {
+        *n = note.clone();
+    }
+}
+
+fn replace_frame(board: &mut Board, frame: &Frame) {
+    if let Some(f) = board.frames.iter_mut().find(|f| f.id == frame.id) {
+        *f = frame.clone();
+    }
+}
+
+/// A command and the id of the board it was done on
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    board_id: u64,
+    command: Command,
+}
+
+/// Commands that can be undone and redone
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl UndoStack {
+    /// Apply `command` to the active board and remember it
+    pub fn execute(&mut self, state: &mut AppState, command: Command) {
+        command.apply(state.board_mut());
+        self.record(state.board().id, command);
+    }
+
+    /// Remember a command that was already applied to board `board_id`
+    pub fn record(&mut self, board_id: u64, command: Command) {
+        if command.is_noop() {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push(Entry { board_id, command });
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Undo the last command, switching to its board.
+    ///
+    /// Returns what was undone.
+    pub fn undo(&mut self, state: &mut AppState) -> Option<&'static str> {
+        let entry = self.undo.pop()?;
+        let label = entry.command.label();
+        if let Some(index) = state.boards.iter().position(|b| b.id == entry.board_id) {
+            state.active = index;
+            entry.command.revert(state.board_mut());
+        }
+        self.redo.push(entry);
+        Some(label)
+    }
+
+    /// Redo the last undone command, switching to its board.
+    ///
+    /// Returns what was redone.
+    pub fn redo(&mut self, state: &mut AppState) -> Option<&'static str> {
+        let entry = self.redo.pop()?;
+        let label = entry.command.label();
+        if let Some(index) = state.boards.iter().position(|b| b.id == entry.board_id) {
+            state.active = index;
+            entry.command.apply(state.board_mut());
+        }
+        self.undo.push(entry);
+        Some(label)
+    }
+
+    /// Whether there is anything to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Whether there is anything to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoardOps;
+    use egui::{Color32, Pos2};
+
+    #[test]
+    fn undo_and_redo_every_kind_of_command() {
+        let mut state = AppState::default();
+        let mut stack = UndoStack::default();
+        let id = state.add_note("hi", Pos2::ZERO);
+        let note = state.board().notes[0].clone();
+        stack.record(state.board().id, Command::Create { note: note.clone() });
+
+        let moved = NoteData {
+            pos: Pos2::new(50.0, 0.0),
+            ..note.clone()
+        };
+        let recolored = NoteData {
+            color: Color32::RED,
+            ..moved.clone()
+        };
+        stack.execute(
+            &mut state,
+            Command::Update {
+                before: note.clone(),
+                after: moved.clone(),
+            },
+        );
+        stack.execute(
+            &mut state,
+            Command::Update {
+                before: moved.clone(),
+                after: recolored.clone(),
+            },
+        );
+        stack.execute(
+            &mut state,
+            Command::Delete {
+                note: recolored.clone(),
+                index: 0,
+            },
+        );
+        assert!(state.board().notes.is_empty());
+
+        assert_eq!(stack.undo(&mut state), Some("delete note"));
+        assert_eq!(state.board().notes, [recolored.clone()]);
+        assert_eq!(stack.undo(&mut state), Some("change color"));
+        assert_eq!(stack.undo(&mut state), Some("move note"));
+        assert_eq!(state.board().notes, [note]);
+        assert_eq!(stack.undo(&mut state), Some("create note"));
+        assert!(state.board().notes.is_empty());
+        assert_eq!(stack.undo(&mut state), None);
+
+        assert_eq!(stack.redo(&mut state), Some("create note"));
+        assert_eq!(stack.redo(&mut state), Some("move note"));
+        assert_eq!(state.board().notes[0].pos, Pos2::new(50.0, 0.0));
+        assert_eq!(state.board().notes[0].id, id);
+    }
+
+    #[test]
+    fn locking_is_undone_by_name() {
+        let note = NoteData::default();
+        let locked = NoteData {
+            locked: true,
+            ..note.clone()
+        };
+        let lock = Command::Update {
+            before: note.clone(),
+            after: locked.clone(),
+        };
+        assert_eq!(lock.label(), "lock note");
+        let unlock = Command::Update {
+            before: locked,
+            after: note,
+        };
+        assert_eq!(unlock.label(), "unlock note");
+    }
+
+    #[test]
+    fn new_command_clears_redo_and_noops_are_ignored() {
+        let mut state = AppState::default();
+        let mut stack = UndoStack::default();
+        let note = NoteData {
+            id: 1,
+            ..Default::default()
+        };
+        stack.execute(&mut state, Command::Create { note: note.clone() });
+        stack.undo(&mut state);
+        assert!(stack.can_redo());
+        stack.execute(&mut state, Command::Create { note: note.clone() });
+        assert!(!stack.can_redo());
+
+        stack.record(
+            1,
+            Command::Update {
+                before: note.clone(),
+                after: note,
+            },
+        );
+        assert_eq!(stack.undo(&mut state), Some("create note"));
+        assert!(!stack.can_undo());
+    }
+
+    #[test]
+    fn diff_reverts_to_the_old_notes() {
+        let note = |id: u64, text: &str| NoteData {
+            id,
+            text: text.into(),
+            ..Default::default()
+        };
+        let before = vec![note(1, "a"), note(2, "b"), note(3, "c")];
+        let after = vec![note(2, "B"), note(4, "d")];
+        let command = Command::diff(&before, &after);
+
+        let mut board = Board {
+            notes: before.clone(),
+            ..Default::default()
+        };
+        command.apply(&mut board);
+        assert_eq!(board.notes, after);
+        command.revert(&mut board);
+        assert_eq!(board.notes, before);
+        assert!(Command::diff(&before, &before).is_noop());
+    }
+
+    #[test]
+    fn undo_switches_to_the_commands_board() {
+        let mut state = AppState::default();
+        let mut stack = UndoStack::default();
+        let note = NoteData {
+            id: 9,
+            ..Default::default()
+        };
+        stack.execute(&mut state, Command::Create { note });
+        state.active = state.add_board("Other");
+        stack.undo(&mut state);
+        assert_eq!(state.active, 0);
+        assert!(state.boards[0].notes.is_empty());
+    }
+}
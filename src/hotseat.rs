@@ -0,0 +1,76 @@
+//! Several people taking turns adding notes on one machine
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// A participant of a hot-seat session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub color: Color32,
+}
+
+/// How strongly an author's color tints their notes
+pub const AUTHOR_TINT: f32 = 0.35;
+
+/// Colors handed out to new members in turn
+pub const MEMBER_COLORS: [Color32; 6] = [
+    Color32::from_rgb(230, 80, 80),
+    Color32::from_rgb(70, 130, 230),
+    Color32::from_rgb(60, 170, 90),
+    Color32::from_rgb(160, 90, 200),
+    Color32::from_rgb(240, 150, 40),
+    Color32::from_rgb(40, 170, 180),
+];
+
+/// Mix `amount` of `tint` into the opaque `base` color
+pub fn tint(base: Color32, tint: Color32, amount: f32) -> Color32 {
+    let amount = amount.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    Color32::from_rgb(
+        mix(base.r(), tint.r()),
+        mix(base.g(), tint.g()),
+        mix(base.b(), tint.b()),
+    )
+}
+
+/// Color of a note created by `author` in place of `base`
+pub fn author_color(base: Color32, author: &Member) -> Color32 {
+    tint(base, author.color, AUTHOR_TINT)
+}
+
+/// Short label for a member: the initials of their name
+pub fn initials(name: &str) -> String {
+    name.split_whitespace()
+        .filter_map(|w| w.chars().next())
+        .flat_map(char::to_uppercase)
+        .take(3)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_mixes_channels() {
+        let white = Color32::WHITE;
+        assert_eq!(tint(white, Color32::BLACK, 0.0), white);
+        assert_eq!(tint(white, Color32::BLACK, 1.0), Color32::BLACK);
+        assert_eq!(
+            tint(
+                Color32::from_rgb(200, 100, 0),
+                Color32::from_rgb(0, 100, 200),
+                0.5
+            ),
+            Color32::from_rgb(100, 100, 100)
+        );
+    }
+
+    #[test]
+    fn initials_of_names() {
+        assert_eq!(initials("ada lovelace"), "AL");
+        assert_eq!(initials("  Bob "), "B");
+        assert_eq!(initials(""), "");
+    }
+}
@@ -0,0 +1,110 @@
+//! Exporting boards as a single read-only web page: the SVG drawing of the
+//! board with a little script to pan and zoom it, for people without plop
+
+use crate::Board;
+use crate::svg::{board_to_svg, escape};
+
+/// Script panning the board on drag, zooming it around the pointer on the
+/// wheel and fitting it back in view on double-click, by moving the
+/// drawing's view box
+const PAN_ZOOM_SCRIPT: &str = r#"
+const svg = document.querySelector("svg");
+const home = svg.viewBox.baseVal;
+let view = { x: home.x, y: home.y, w: home.width, h: home.height };
+const initial = { ...view };
+function show() {
+  svg.setAttribute("viewBox", `${view.x} ${view.y} ${view.w} ${view.h}`);
+}
+function toBoard(event) {
+  const box = svg.getBoundingClientRect();
+  const scale = Math.max(view.w / box.width, view.h / box.height);
+  return {
+    x: view.x + (event.clientX - box.left - (box.width - view.w / scale) / 2) * scale,
+    y: view.y + (event.clientY - box.top - (box.height - view.h / scale) / 2) * scale,
+    scale,
+  };
+}
+svg.addEventListener("wheel", (event) => {
+  event.preventDefault();
+  const at = toBoard(event);
+  const factor = Math.exp(event.deltaY * 0.001);
+  view.x = at.x - (at.x - view.x) * factor;
+  view.y = at.y - (at.y - view.y) * factor;
+  view.w *= factor;
+  view.h *= factor;
+  show();
+}, { passive: false });
+let drag = null;
+svg.addEventListener("pointerdown", (event) => {
+  drag = { x: event.clientX, y: event.clientY, scale: toBoard(event).scale };
+  svg.setPointerCapture(event.pointerId);
+});
+svg.addEventListener("pointermove", (event) => {
+  if (!drag) return;
+  view.x -= (event.clientX - drag.x) * drag.scale;
+  view.y -= (event.clientY - drag.y) * drag.scale;
+  drag.x = event.clientX;
+  drag.y = event.clientY;
+  show();
+});
+svg.addEventListener("pointerup", () => { drag = null; });
+svg.addEventListener("dblclick", () => { view = { ...initial }; show(); });
+"#;
+
+/// The whole board as a self-contained HTML page that can be panned and
+/// zoomed but not edited
+pub fn board_to_html(board: &Board) -> String {
+    let title = escape(&board.name);
+    let svg = board_to_svg(board);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+  html, body {{ margin: 0; height: 100%; overflow: hidden; background: #444; }}
+  svg {{ display: block; width: 100%; height: 100%; cursor: grab; touch-action: none; }}
+  svg:active {{ cursor: grabbing; }}
+  .hint {{ position: fixed; bottom: 8px; left: 8px; font: 12px sans-serif; color: #fff; opacity: 0.7; }}
+</style>
+</head>
+<body>
+{svg}<div class="hint">{title} — drag to pan, scroll to zoom, double-click to fit</div>
+<script>{PAN_ZOOM_SCRIPT}</script>
+</body>
+</html>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn page_embeds_the_drawing_and_its_script() {
+        let board = Board {
+            name: "Q3 <plans>".into(),
+            notes: vec![NoteData {
+                id: 1,
+                text: "Ship it".into(),
+                pos: Pos2::ZERO,
+                size: Vec2::new(100.0, 80.0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let html = board_to_html(&board);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Q3 &lt;plans&gt;</title>"));
+        assert!(html.contains("<svg xmlns="));
+        assert!(html.contains("Ship it"));
+        assert!(html.contains("addEventListener(\"wheel\""));
+        // Nothing is fetched from elsewhere
+        assert!(!html.contains("src="));
+    }
+}
@@ -0,0 +1,98 @@
+//! Icons for notes: an emoji in the corner says what a note is about
+//! without reading it
+
+/// Emoji the picker offers, each with words to find it by
+pub const ICONS: &[(&str, &str)] = &[
+    ("⭐", "star favorite important"),
+    ("❗", "exclamation important urgent alert"),
+    ("❓", "question ask unknown"),
+    ("✅", "check done complete yes"),
+    ("❌", "cross no cancel wrong"),
+    ("⚠", "warning caution risk"),
+    ("🔥", "fire hot urgent"),
+    ("💡", "idea bulb light"),
+    ("📌", "pin pinned keep"),
+    ("📅", "calendar date event meeting"),
+    ("⏰", "alarm clock time reminder"),
+    ("⏳", "hourglass waiting pending"),
+    ("🐛", "bug issue defect"),
+    ("🔧", "wrench fix tool repair"),
+    ("🔒", "lock secure private"),
+    ("🔑", "key password access"),
+    ("📝", "memo note write draft"),
+    ("📎", "paperclip attachment"),
+    ("📚", "books read study learn"),
+    ("📈", "chart up growth increase"),
+    ("📉", "chart down decline decrease"),
+    ("💰", "money bag cost budget"),
+    ("🛒", "cart shopping buy"),
+    ("📞", "phone call"),
+    ("✉", "envelope mail email letter"),
+    ("💬", "speech chat talk discuss"),
+    ("👤", "person user someone"),
+    ("👥", "people team group"),
+    ("🏠", "house home"),
+    ("🏢", "office building work"),
+    ("✈", "airplane travel flight trip"),
+    ("🚗", "car drive travel"),
+    ("🎯", "target goal aim"),
+    ("🏆", "trophy win prize achievement"),
+    ("🎉", "party celebrate"),
+    ("🎁", "gift present birthday"),
+    ("❤", "heart love like"),
+    ("👍", "thumbs up good yes approve"),
+    ("👎", "thumbs down bad no reject"),
+    ("😀", "smile happy face"),
+    ("😢", "sad cry face"),
+    ("🤔", "thinking hmm consider"),
+    ("🚀", "rocket launch ship release"),
+    ("🔍", "magnifier search find research"),
+    ("🧪", "test tube experiment"),
+    ("🎨", "palette art design"),
+    ("🎵", "music note song"),
+    ("📷", "camera photo picture"),
+    ("💻", "laptop computer code"),
+    ("🌱", "seedling grow new start"),
+    ("🍎", "apple food fruit"),
+    ("☕", "coffee break drink"),
+    ("💤", "sleep zzz later"),
+    ("🚧", "construction wip in progress"),
+    ("🛑", "stop sign blocked"),
+];
+
+/// Icons with a word starting with each word of `query`, in picker order;
+/// every icon for a blank query
+pub fn search_icons(query: &str) -> Vec<&'static str> {
+    let query = query.to_lowercase();
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    ICONS
+        .iter()
+        .filter(|(icon, words)| {
+            terms
+                .iter()
+                .all(|term| *icon == *term || words.split_whitespace().any(|w| w.starts_with(term)))
+        })
+        .map(|(icon, _)| *icon)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+
+    #[test]
+    fn icons_are_found_by_their_words() {
+        assert_eq!(search_icons("").len(), ICONS.len());
+        assert_eq!(search_icons("bug"), ["🐛"]);
+        assert_eq!(search_icons("Thumbs UP"), ["👍"]);
+        assert!(search_icons("urg").contains(&"🔥"));
+        assert!(search_icons("zebra").is_empty());
+
+        // Notes saved before icons have none
+        let mut json = serde_json::to_value(NoteData::default()).unwrap();
+        json.as_object_mut().unwrap().remove("icon");
+        let old: NoteData = serde_json::from_value(json).unwrap();
+        assert_eq!(old.icon, None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Pictures attached to notes
+
+use egui::{ColorImage, Pos2, Rect, Vec2};
+use std::path::Path;
+
+/// File extensions of the images notes can show
+pub const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Largest side, in pixels, images are kept at; bigger ones are scaled down
+pub const MAX_TEXTURE_SIDE: u32 = 1024;
+
+/// Share of a note's height left for the text below its image
+pub const IMAGE_TEXT_SHARE: f32 = 0.3;
+
+/// Space between a note's border and its image
+const IMAGE_PADDING: f32 = 6.0;
+
+/// Whether `path` looks like an image a note can show
+pub fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Read the image at `path`, scaled down to at most [`MAX_TEXTURE_SIDE`]
+pub fn load_image(path: &Path) -> Result<ColorImage, String> {
+    let image = image::open(path).map_err(|e| e.to_string())?;
+    let image = if image.width().max(image.height()) > MAX_TEXTURE_SIDE {
+        image.thumbnail(MAX_TEXTURE_SIDE, MAX_TEXTURE_SIDE)
+    } else {
+        image
+    };
+    let rgba = image.to_rgba8();
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+}
+
+/// Where an image of `image_size` goes on a note occupying `note`: as
+/// large as fits above the text, keeping its proportions, centered. With no
+/// text the image may take the whole note.
+pub fn image_rect(note: Rect, image_size: Vec2, has_text: bool) -> Rect {
+    let mut area = note.shrink(IMAGE_PADDING);
+    if has_text {
+        area.max.y -= note.height() * IMAGE_TEXT_SHARE;
+    }
+    if !area.is_positive() || image_size.x <= 0.0 || image_size.y <= 0.0 {
+        return Rect::from_min_size(area.center_top(), Vec2::ZERO);
+    }
+    let scale = (area.width() / image_size.x).min(area.height() / image_size.y);
+    let size = image_size * scale;
+    Rect::from_min_size(Pos2::new(area.center().x - size.x / 2.0, area.min.y), size)
+}
+
+/// Center of the text of a note occupying `note` when it shows an image
+pub fn text_center_below_image(note: Rect) -> Pos2 {
+    Pos2::new(
+        note.center().x,
+        note.max.y - note.height() * IMAGE_TEXT_SHARE / 2.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_fit_above_the_text() {
+        let note = Rect::from_min_size(Pos2::ZERO, Vec2::new(212.0, 112.0));
+        // The area above the text is 200 x 66.4; a wide image fills its width
+        let wide = image_rect(note, Vec2::new(400.0, 100.0), true);
+        assert_eq!(wide.size(), Vec2::new(200.0, 50.0));
+        assert_eq!(wide.min, Pos2::new(6.0, 6.0));
+        // A tall image fills the height and is centered
+        let tall = image_rect(note, Vec2::new(50.0, 100.0), false);
+        assert_eq!(tall.size(), Vec2::new(50.0, 100.0));
+        assert_eq!(tall.center().x, note.center().x);
+        assert!(image_rect(note, Vec2::ZERO, true).size() == Vec2::ZERO);
+    }
+
+    #[test]
+    fn images_load_scaled_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.PNG");
+        image::RgbaImage::from_pixel(2048, 512, image::Rgba([255, 0, 0, 255]))
+            .save(&path)
+            .unwrap();
+        assert!(is_image_path(&path));
+        assert!(!is_image_path(Path::new("notes.txt")));
+        let loaded = load_image(&path).unwrap();
+        assert_eq!(loaded.size, [1024, 256]);
+        assert!(load_image(&dir.path().join("missing.png")).is_err());
+    }
+}
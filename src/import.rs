@@ -0,0 +1,395 @@
+//! Turning outlines and boards from other tools into notes
+
+use crate::NoteData;
+use crate::frames::{DEFAULT_FRAME_COLOR, FRAME_PADDING, FRAME_TITLE_HEIGHT, Frame};
+use crate::tags::parse_tags;
+use egui::{Color32, Pos2, Rect, Vec2};
+use serde::Deserialize;
+
+/// Notes per row when importing a list as a grid
+pub const IMPORT_COLUMNS: usize = 5;
+
+/// Space between imported notes
+const IMPORT_GAP: f32 = 20.0;
+
+/// Items of a Markdown list: each top-level bullet becomes one item; lines
+/// indented under it, nested bullets included, become further lines of its
+/// text. `##` headings start a new group.
+fn outline_groups(markdown: &str) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = vec![Vec::new()];
+    let mut in_item = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(item) = list_item(trimmed) {
+                groups
+                    .last_mut()
+                    .expect("never empty")
+                    .push(item.to_owned());
+                in_item = true;
+                continue;
+            }
+            if trimmed.starts_with("##") && !groups.last().expect("never empty").is_empty() {
+                groups.push(Vec::new());
+            }
+            in_item = false;
+        } else if in_item {
+            let item = groups
+                .last_mut()
+                .and_then(|g| g.last_mut())
+                .expect("an item is open");
+            // Keep nesting below the item itself
+            let nested = line
+                .strip_prefix("  ")
+                .or_else(|| line.strip_prefix('\t'))
+                .unwrap_or(trimmed);
+            item.push('\n');
+            item.push_str(nested);
+        }
+    }
+    groups.retain(|g| !g.is_empty());
+    groups
+}
+
+/// Text of a bullet (`-`, `*`, `+`) or numbered (`1.`) list item
+fn list_item(line: &str) -> Option<&str> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(marker) {
+            return Some(item);
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        return line[digits..].strip_prefix(". ");
+    }
+    None
+}
+
+/// Notes for the items of a Markdown list, laid out in a grid from `origin`
+/// with ids taken from `next_note_id`. Each `##` group starts a new row.
+pub fn markdown_to_notes(
+    markdown: &str,
+    origin: Pos2,
+    size: Vec2,
+    color: Color32,
+    next_note_id: &mut u64,
+) -> Vec<NoteData> {
+    let step = size + Vec2::splat(IMPORT_GAP);
+    let mut notes = Vec::new();
+    let mut row = 0;
+    for group in outline_groups(markdown) {
+        for (i, text) in group.into_iter().enumerate() {
+            if i > 0 && i % IMPORT_COLUMNS == 0 {
+                row += 1;
+            }
+            let column = i % IMPORT_COLUMNS;
+            let id = *next_note_id;
+            *next_note_id += 1;
+            notes.push(NoteData {
+                id,
+                text,
+                pos: origin + Vec2::new(column as f32 * step.x, row as f32 * step.y),
+                size,
+                color,
+                ..Default::default()
+            });
+        }
+        row += 1;
+    }
+    notes
+}
+
+/// Paragraphs of pasted text: runs of lines between blank lines, trimmed
+pub fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.lines().chain([""]) {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join("\n").trim().to_owned());
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    paragraphs
+}
+
+/// A note for each of `texts`, laid out in rows of [`IMPORT_COLUMNS`] from
+/// `origin` with ids taken from `next_note_id`
+pub fn texts_to_notes(
+    texts: Vec<String>,
+    origin: Pos2,
+    size: Vec2,
+    color: Color32,
+    next_note_id: &mut u64,
+) -> Vec<NoteData> {
+    let step = size + Vec2::splat(IMPORT_GAP);
+    texts
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let (row, column) = (i / IMPORT_COLUMNS, i % IMPORT_COLUMNS);
+            let id = *next_note_id;
+            *next_note_id += 1;
+            NoteData {
+                id,
+                text,
+                pos: origin + Vec2::new(column as f32 * step.x, row as f32 * step.y),
+                size,
+                color,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// The parts of a Trello board export that become notes
+#[derive(Deserialize)]
+struct TrelloBoard {
+    #[serde(default)]
+    lists: Vec<TrelloList>,
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+}
+
+#[derive(Deserialize)]
+struct TrelloList {
+    id: String,
+    name: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    name: String,
+    id_list: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+}
+
+#[derive(Deserialize)]
+struct TrelloLabel {
+    #[serde(default)]
+    name: String,
+    color: Option<String>,
+}
+
+/// Note color for a Trello label color, a light shade so text stays readable
+pub fn trello_label_color(color: &str) -> Option<Color32> {
+    // Dark and light variants like `green_dark` map to the base color
+    let base = color.split('_').next().unwrap_or_default();
+    let rgb = match base {
+        "green" => (170, 230, 170),
+        "yellow" => (255, 240, 140),
+        "orange" => (255, 200, 130),
+        "red" => (250, 160, 150),
+        "purple" => (210, 180, 240),
+        "blue" => (170, 200, 255),
+        "sky" => (160, 230, 240),
+        "lime" => (190, 245, 170),
+        "pink" => (255, 170, 200),
+        "black" => (190, 195, 205),
+        _ => return None,
+    };
+    Some(Color32::from_rgb(rgb.0, rgb.1, rgb.2))
+}
+
+/// Notes for the cards of a Trello board export: each open list becomes a
+/// column of notes in a frame titled with the list's name, starting at
+/// `origin`. Cards take the color of their first colored label (else
+/// `color`) and their label names as tags. Note ids come from
+/// `next_note_id`, frame ids count up from `first_frame_id`.
+pub fn trello_to_notes(
+    json: &str,
+    origin: Pos2,
+    size: Vec2,
+    color: Color32,
+    next_note_id: &mut u64,
+    first_frame_id: u64,
+) -> serde_json::Result<(Vec<NoteData>, Vec<Frame>)> {
+    let trello: TrelloBoard = serde_json::from_str(json)?;
+    let mut lists: Vec<&TrelloList> = trello.lists.iter().filter(|l| !l.closed).collect();
+    lists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    let column_step = size.x + 2.0 * FRAME_PADDING + IMPORT_GAP;
+    let row_step = size.y + IMPORT_GAP;
+    let mut notes = Vec::new();
+    let mut frames = Vec::new();
+    for (column, list) in lists.into_iter().enumerate() {
+        let mut cards: Vec<&TrelloCard> = trello
+            .cards
+            .iter()
+            .filter(|c| !c.closed && c.id_list == list.id)
+            .collect();
+        cards.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        let left = origin + Vec2::new(column as f32 * column_step, 0.0);
+        for (row, card) in cards.iter().enumerate() {
+            let id = *next_note_id;
+            *next_note_id += 1;
+            // Tags can't hold spaces
+            let names: Vec<String> = card
+                .labels
+                .iter()
+                .map(|l| l.name.replace(' ', "-"))
+                .collect();
+            notes.push(NoteData {
+                id,
+                text: card.name.clone(),
+                pos: left + Vec2::new(0.0, row as f32 * row_step),
+                size,
+                color: card
+                    .labels
+                    .iter()
+                    .find_map(|l| l.color.as_deref().and_then(trello_label_color))
+                    .unwrap_or(color),
+                tags: parse_tags(&names.join(",")),
+                ..Default::default()
+            });
+        }
+        let height = cards.len().max(1) as f32 * row_step - IMPORT_GAP;
+        let mut rect = Rect::from_min_size(left, Vec2::new(size.x, height)).expand(FRAME_PADDING);
+        rect.min.y -= FRAME_TITLE_HEIGHT;
+        frames.push(Frame {
+            id: first_frame_id + column as u64,
+            title: list.name.clone(),
+            rect,
+            color: DEFAULT_FRAME_COLOR,
+        });
+    }
+    Ok((notes, frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Board;
+    use crate::export::board_to_markdown;
+
+    #[test]
+    fn list_items_become_a_grid_of_notes() {
+        let markdown = "# Plan\n\nintro text\n\n- one\n  more about one\n  - sub\n* two\n\n## Later\n\n1. three\n";
+        let mut next_note_id = 7;
+        let size = Vec2::new(100.0, 50.0);
+        let notes = markdown_to_notes(
+            markdown,
+            Pos2::ZERO,
+            size,
+            Color32::YELLOW,
+            &mut next_note_id,
+        );
+        let texts: Vec<&str> = notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(texts, ["one\nmore about one\n- sub", "two", "three"]);
+        assert_eq!(next_note_id, 10);
+        assert_eq!(notes[1].pos, Pos2::new(120.0, 0.0));
+        // The second group starts a new row
+        assert_eq!(notes[2].pos, Pos2::new(0.0, 70.0));
+    }
+
+    #[test]
+    fn pasted_paragraphs_become_a_grid_of_notes() {
+        let text = "\n  First idea\ncontinued\n\n\n\tSecond\n \nThird\n";
+        let texts = paragraphs(text);
+        assert_eq!(texts, ["First idea\ncontinued", "Second", "Third"]);
+        assert!(paragraphs(" \n\n").is_empty());
+
+        let mut next_note_id = 3;
+        let size = Vec2::splat(10.0);
+        let many: Vec<String> = (0..6).map(|i| i.to_string()).collect();
+        let notes = texts_to_notes(many, Pos2::ZERO, size, Color32::YELLOW, &mut next_note_id);
+        assert_eq!(next_note_id, 9);
+        assert_eq!(notes[1].pos, Pos2::new(30.0, 0.0));
+        assert_eq!(notes[5].pos, Pos2::new(0.0, 30.0));
+        assert_eq!(notes[5].text, "5");
+    }
+
+    #[test]
+    fn long_lists_wrap_and_exports_roundtrip() {
+        let items: String = (1..=7).map(|i| format!("- item {i}\n")).collect();
+        let mut next_note_id = 1;
+        let size = Vec2::splat(10.0);
+        let notes = markdown_to_notes(&items, Pos2::ZERO, size, Color32::YELLOW, &mut next_note_id);
+        assert_eq!(notes[5].pos, Pos2::new(0.0, 30.0));
+
+        let board = Board {
+            name: "Items".into(),
+            notes,
+            ..Default::default()
+        };
+        let again = markdown_to_notes(
+            &board_to_markdown(&board),
+            Pos2::ZERO,
+            size,
+            Color32::YELLOW,
+            &mut next_note_id,
+        );
+        let texts = |notes: &[NoteData]| notes.iter().map(|n| n.text.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(&again), texts(&board.notes));
+    }
+
+    #[test]
+    fn trello_lists_become_framed_columns() {
+        let json = r#"{
+            "name": "Sprint",
+            "lists": [
+                {"id": "b", "name": "Done", "closed": false, "pos": 2},
+                {"id": "a", "name": "Todo", "closed": false, "pos": 1},
+                {"id": "c", "name": "Old", "closed": true, "pos": 3}
+            ],
+            "cards": [
+                {"name": "second", "idList": "a", "closed": false, "pos": 20, "labels": []},
+                {"name": "first", "idList": "a", "closed": false, "pos": 10,
+                 "labels": [{"name": "High priority", "color": "red_dark"}, {"name": "ui", "color": "blue"}]},
+                {"name": "shipped", "idList": "b", "closed": false, "pos": 1, "labels": [{"name": "", "color": null}]},
+                {"name": "archived", "idList": "a", "closed": true, "pos": 5, "labels": []},
+                {"name": "forgotten", "idList": "c", "closed": false, "pos": 1, "labels": []}
+            ]
+        }"#;
+        let mut next_note_id = 1;
+        let size = Vec2::new(100.0, 50.0);
+        let (notes, frames) = trello_to_notes(
+            json,
+            Pos2::ZERO,
+            size,
+            Color32::YELLOW,
+            &mut next_note_id,
+            4,
+        )
+        .unwrap();
+        let texts: Vec<&str> = notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(texts, ["first", "second", "shipped"]);
+        assert_eq!(notes[0].color, trello_label_color("red").unwrap());
+        assert_eq!(notes[0].tags, ["high-priority", "ui"]);
+        assert_eq!(notes[1].pos, Pos2::new(0.0, 70.0));
+        assert_eq!(notes[2].pos, Pos2::new(160.0, 0.0));
+        assert_eq!(notes[2].color, Color32::YELLOW);
+
+        let titles: Vec<&str> = frames.iter().map(|f| f.title.as_str()).collect();
+        assert_eq!(titles, ["Todo", "Done"]);
+        assert_eq!(frames[1].id, 5);
+        assert!(notes.iter().take(2).all(|n| frames[0].contains(n)));
+        assert!(!frames[0].contains(&notes[2]));
+        assert!(
+            trello_to_notes(
+                "[]",
+                Pos2::ZERO,
+                size,
+                Color32::YELLOW,
+                &mut next_note_id,
+                1
+            )
+            .is_err()
+        );
+    }
+}
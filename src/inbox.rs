@@ -0,0 +1,128 @@
+//! The inbox: a frame on the board that quickly captured notes land in, to
+//! be sorted out later
+
+use crate::frames::{DEFAULT_FRAME_COLOR, FRAME_PADDING, FRAME_TITLE_HEIGHT, Frame};
+use crate::history::Command;
+use crate::{Board, NoteData};
+use egui::{Pos2, Rect, Vec2};
+
+/// Title of the frame captured notes go into
+pub const INBOX_TITLE: &str = "Inbox";
+/// Notes side by side in a new inbox
+const INBOX_COLUMNS: f32 = 4.0;
+/// Room between notes in the inbox
+const INBOX_GAP: f32 = 10.0;
+
+impl Board {
+    /// Put `note` in the next free spot of the inbox, making the inbox to
+    /// the right of everything else if the board has none and growing it
+    /// when it is full. Returns the change for undo.
+    pub fn capture(&mut self, mut note: NoteData) -> Command {
+        let mut changes = Vec::new();
+        let index = match self.frames.iter().position(|f| f.title == INBOX_TITLE) {
+            Some(index) => index,
+            None => {
+                let frame = self.new_inbox(note.size);
+                self.frames.push(frame.clone());
+                changes.push(Command::AddFrame { frame });
+                self.frames.len() - 1
+            }
+        };
+        let inbox = &self.frames[index];
+        let taken = self.notes.iter().filter(|n| inbox.contains(n)).count() as f32;
+        let step = note.size + Vec2::splat(INBOX_GAP);
+        let room = inbox.rect.width() - 2.0 * FRAME_PADDING + INBOX_GAP;
+        let columns = (room / step.x).floor().max(1.0);
+        let (row, column) = ((taken / columns).floor(), taken % columns);
+        note.pos = inbox.rect.min
+            + Vec2::new(FRAME_PADDING, FRAME_TITLE_HEIGHT + FRAME_PADDING)
+            + Vec2::new(column * step.x, row * step.y);
+        let bottom = note.pos.y + note.size.y + FRAME_PADDING;
+        if bottom > inbox.rect.max.y {
+            let before = inbox.clone();
+            let inbox = &mut self.frames[index];
+            inbox.rect.max.y = bottom;
+            changes.push(Command::UpdateFrame {
+                before,
+                after: inbox.clone(),
+            });
+        }
+        note.z = self.top_z();
+        self.place_in_lane(&mut note);
+        self.notes.push(note.clone());
+        changes.push(Command::Create { note });
+        Command::batch(changes).expect("a note was created")
+    }
+
+    /// An empty inbox for notes of `note_size`, to the right of the notes
+    /// and frames of the board
+    fn new_inbox(&self, note_size: Vec2) -> Frame {
+        let content = self
+            .notes
+            .iter()
+            .map(|n| Rect::from_min_size(n.pos, n.size))
+            .chain(self.frames.iter().map(|f| f.rect))
+            .reduce(Rect::union);
+        let min = content.map_or(Pos2::ZERO, |r| {
+            Pos2::new(r.max.x + 2.0 * FRAME_PADDING, r.min.y)
+        });
+        let size = Vec2::new(
+            2.0 * FRAME_PADDING + INBOX_COLUMNS * note_size.x + (INBOX_COLUMNS - 1.0) * INBOX_GAP,
+            FRAME_TITLE_HEIGHT + 2.0 * FRAME_PADDING + note_size.y,
+        );
+        Frame {
+            id: self.next_frame_id(),
+            title: INBOX_TITLE.into(),
+            rect: Rect::from_min_size(min, size),
+            color: DEFAULT_FRAME_COLOR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captured(id: u64) -> NoteData {
+        NoteData {
+            id,
+            text: format!("thought {id}"),
+            size: Vec2::splat(100.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn captured_notes_fill_the_inbox() {
+        let mut board = Board {
+            notes: vec![NoteData {
+                id: 1,
+                pos: Pos2::new(0.0, 50.0),
+                size: Vec2::splat(100.0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let Command::Batch(first) = board.capture(captured(2)) else {
+            panic!("expected the inbox and the note");
+        };
+        assert!(matches!(first[0], Command::AddFrame { .. }));
+        let inbox = board.frames[0].clone();
+        assert_eq!(inbox.title, INBOX_TITLE);
+        assert!(inbox.rect.min.x > 100.0);
+        assert!(inbox.contains(&board.notes[1]));
+
+        // Four notes fit in a row; the fifth starts a new one, growing the
+        // inbox
+        for id in 3..=6 {
+            board.capture(captured(id));
+        }
+        assert_eq!(board.frames.len(), 1);
+        let notes = &board.notes;
+        assert_eq!(notes[4].pos.y, notes[1].pos.y);
+        assert_eq!(notes[5].pos.x, notes[1].pos.x);
+        assert!(notes[5].pos.y > notes[1].pos.y);
+        assert!(board.frames[0].contains(&notes[5]));
+        assert!(board.frames[0].rect.height() > inbox.rect.height());
+    }
+}
@@ -0,0 +1,118 @@
+//! Freehand strokes drawn on the board with the pen
+
+use crate::Board;
+use egui::{Color32, Pos2, Rect, Shape, Stroke};
+use serde::{Deserialize, Serialize};
+
+/// Smallest distance between consecutive points of a stroke
+pub const MIN_POINT_SPACING: f32 = 2.0;
+
+/// A polyline drawn with the pen, in board coordinates
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InkStroke {
+    pub points: Vec<Pos2>,
+    pub color: Color32,
+    pub width: f32,
+}
+
+impl InkStroke {
+    pub fn new(color: Color32, width: f32) -> Self {
+        Self {
+            points: Vec::new(),
+            color,
+            width,
+        }
+    }
+
+    /// Continue the stroke to `p`, skipping points too close to the last
+    /// one; returns whether `p` was added
+    pub fn extend_to(&mut self, p: Pos2) -> bool {
+        if self
+            .points
+            .last()
+            .is_some_and(|last| last.distance(p) < MIN_POINT_SPACING)
+        {
+            return false;
+        }
+        self.points.push(p);
+        true
+    }
+
+    /// Area the stroke covers, including its width
+    pub fn bounding_rect(&self) -> Rect {
+        Rect::from_points(&self.points).expand(self.width * 0.5)
+    }
+
+    /// Whether the stroke passes within `radius` of `p`
+    pub fn passes_near(&self, p: Pos2, radius: f32) -> bool {
+        let reach = radius + self.width * 0.5;
+        match self.points.as_slice() {
+            [] => false,
+            [only] => only.distance(p) <= reach,
+            points => points.windows(2).any(|segment| {
+                let (a, b) = (segment[0], segment[1]);
+                let ab = b - a;
+                let t = ((p - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0);
+                (a + ab * t).distance(p) <= reach
+            }),
+        }
+    }
+
+    /// Shape drawing the stroke; `transform` maps its points to the screen
+    pub fn shape(&self, transform: impl Fn(Pos2) -> Pos2) -> Shape {
+        let points: Vec<Pos2> = self.points.iter().map(|&p| transform(p)).collect();
+        match points.as_slice() {
+            [only] => Shape::circle_filled(*only, self.width * 0.5, self.color),
+            _ => Shape::line(points, Stroke::new(self.width, self.color)),
+        }
+    }
+}
+
+impl Board {
+    /// Index of the topmost stroke passing within `radius` of `p`
+    pub fn stroke_at(&self, p: Pos2, radius: f32) -> Option<usize> {
+        self.strokes.iter().rposition(|s| s.passes_near(p, radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Command;
+
+    #[test]
+    fn strokes_skip_close_points_and_hit_along_segments() {
+        let mut stroke = InkStroke::new(Color32::BLACK, 4.0);
+        assert!(stroke.extend_to(Pos2::new(0.0, 0.0)));
+        assert!(!stroke.extend_to(Pos2::new(1.0, 0.0)));
+        assert!(stroke.extend_to(Pos2::new(100.0, 0.0)));
+        assert_eq!(stroke.points.len(), 2);
+        assert_eq!(
+            stroke.bounding_rect(),
+            Rect::from_min_max(Pos2::new(-2.0, -2.0), Pos2::new(102.0, 2.0))
+        );
+        assert!(stroke.passes_near(Pos2::new(50.0, 5.0), 3.0));
+        assert!(!stroke.passes_near(Pos2::new(50.0, 10.0), 3.0));
+        assert!(!stroke.passes_near(Pos2::new(110.0, 0.0), 3.0));
+    }
+
+    #[test]
+    fn drawing_and_erasing_can_be_undone() {
+        let mut board = Board::default();
+        let mut stroke = InkStroke::new(Color32::RED, 2.0);
+        stroke.extend_to(Pos2::new(10.0, 10.0));
+        stroke.extend_to(Pos2::new(10.0, 50.0));
+        let draw = Command::Draw {
+            stroke: stroke.clone(),
+        };
+        draw.apply(&mut board);
+        assert_eq!(board.stroke_at(Pos2::new(11.0, 30.0), 1.0), Some(0));
+
+        let erase = Command::Erase { stroke, index: 0 };
+        erase.apply(&mut board);
+        assert!(board.strokes.is_empty());
+        erase.revert(&mut board);
+        draw.revert(&mut board);
+        assert!(board.strokes.is_empty());
+    }
+}
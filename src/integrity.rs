@@ -0,0 +1,137 @@
+//! Keeping note entities in line with the board they were spawned from
+
+use crate::{AppState, Board, NoteData};
+use std::collections::HashSet;
+
+/// Repairs that bring the note entities back in sync with the board.
+///
+/// Generic over the entity handle so the check runs without an ECS world.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation<E> {
+    /// Entities whose note is missing from the board or already has an entity
+    pub despawn: Vec<E>,
+    /// Board notes that have no entity yet
+    pub spawn: Vec<NoteData>,
+    /// Human readable description of each fix
+    pub fixes: Vec<String>,
+}
+
+impl<E> Reconciliation<E> {
+    /// Whether everything was already consistent
+    pub fn is_clean(&self) -> bool {
+        self.fixes.is_empty()
+    }
+}
+
+/// Compare the note `entities` (handle and note id) with the active board
+/// and repair the board in place.
+///
+/// Duplicate note ids on the board get fresh ids and `next_note_id` is moved
+/// past every id in use. The returned entity changes still need applying.
+pub fn reconcile<E: Copy>(state: &mut AppState, entities: &[(E, u64)]) -> Reconciliation<E> {
+    let mut fixes = Vec::new();
+
+    // Ids are unique across boards, so the counter must pass all of them
+    let max_id = state
+        .boards
+        .iter()
+        .flat_map(Board::stored_notes)
+        .map(|n| n.id)
+        .max()
+        .unwrap_or(0);
+    if state.next_note_id <= max_id {
+        fixes.push(format!(
+            "next note id {} was already in use, moved to {}",
+            state.next_note_id,
+            max_id + 1
+        ));
+        state.next_note_id = max_id + 1;
+    }
+
+    let board = &mut state.boards[state.active];
+    let mut seen = HashSet::new();
+    for note in board.notes.iter_mut() {
+        if !seen.insert(note.id) {
+            let id = state.next_note_id;
+            state.next_note_id += 1;
+            fixes.push(format!("duplicate note id {} renumbered to {id}", note.id));
+            note.id = id;
+            seen.insert(id);
+        }
+    }
+
+    let mut claimed = HashSet::new();
+    let mut despawn = Vec::new();
+    for &(entity, id) in entities {
+        if !seen.contains(&id) {
+            fixes.push(format!(
+                "removed entity of note {id}, which is not on the board"
+            ));
+            despawn.push(entity);
+        } else if !claimed.insert(id) {
+            fixes.push(format!("removed duplicate entity of note {id}"));
+            despawn.push(entity);
+        }
+    }
+
+    let spawn: Vec<NoteData> = board
+        .notes
+        .iter()
+        .filter(|n| !claimed.contains(&n.id))
+        .cloned()
+        .collect();
+    for note in &spawn {
+        fixes.push(format!("spawned missing entity for note {}", note.id));
+    }
+
+    Reconciliation {
+        despawn,
+        spawn,
+        fixes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Board;
+
+    fn state(ids: &[u64], next_note_id: u64) -> AppState {
+        AppState {
+            boards: vec![Board {
+                notes: ids
+                    .iter()
+                    .map(|&id| NoteData {
+                        id,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }],
+            active: 0,
+            next_note_id,
+            templates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn consistent_state_needs_no_fixes() {
+        let mut state = state(&[1, 2], 3);
+        let fix = reconcile(&mut state, &[("a", 1), ("b", 2)]);
+        assert!(fix.is_clean());
+        assert!(fix.despawn.is_empty() && fix.spawn.is_empty());
+    }
+
+    #[test]
+    fn repairs_orphans_missing_entities_and_stale_ids() {
+        let mut state = state(&[1, 2, 2], 2);
+        let fix = reconcile(&mut state, &[("a", 1), ("b", 1), ("c", 9)]);
+        assert_eq!(fix.despawn, vec!["b", "c"]);
+        // The stale counter moved past 2, so the duplicate became 3
+        let ids: Vec<u64> = state.board().notes.iter().map(|n| n.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!(state.next_note_id, 4);
+        let spawned: Vec<u64> = fix.spawn.iter().map(|n| n.id).collect();
+        assert_eq!(spawned, [2, 3]);
+    }
+}
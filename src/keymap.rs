@@ -0,0 +1,254 @@
+//! Keyboard shortcuts, remappable through a JSON file
+//!
+//! Bindings are written like `Ctrl+Shift+Z`; `Ctrl` stands for Cmd on macOS.
+
+use egui::{Event, Key, Modifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Everything that can be bound to a key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Undo,
+    Redo,
+    Copy,
+    Paste,
+    Duplicate,
+    Delete,
+    NudgeLeft,
+    NudgeRight,
+    NudgeUp,
+    NudgeDown,
+    NextNote,
+    PreviousNote,
+    EditNote,
+    NewNote,
+    Find,
+    RaisePriority,
+    LowerPriority,
+}
+
+/// A key together with the modifiers that must be held, no more and no less
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Binding {
+    pub key: Key,
+    /// Ctrl, or Cmd on macOS
+    pub command: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Binding {
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            command: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub const fn command(key: Key) -> Self {
+        Self {
+            command: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub const fn with_shift(self) -> Self {
+        Self {
+            shift: true,
+            ..self
+        }
+    }
+
+    pub const fn with_alt(self) -> Self {
+        Self { alt: true, ..self }
+    }
+
+    /// Whether pressing `key` while holding `modifiers` triggers the binding
+    pub fn matches(&self, key: Key, modifiers: Modifiers) -> bool {
+        key == self.key
+            && modifiers.command == self.command
+            && modifiers.shift == self.shift
+            && modifiers.alt == self.alt
+    }
+
+    /// Parse a binding written like `Ctrl+Shift+Z`
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = Key::from_name(parts.pop()?)?;
+        let mut binding = Self::new(key);
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "cmd" | "command" => binding.command = true,
+                "shift" => binding.shift = true,
+                "alt" | "option" => binding.alt = true,
+                _ => return None,
+            }
+        }
+        Some(binding)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.command {
+            f.write_str("Ctrl+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        f.write_str(self.key.name())
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Self::parse(&text).ok_or_else(|| format!("unknown key binding {text:?}"))
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// Keys bound to each action; an action can have several or none
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Keymap {
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        let defaults = [
+            (Undo, Binding::command(Key::Z)),
+            (Redo, Binding::command(Key::Z).with_shift()),
+            (Redo, Binding::command(Key::Y)),
+            (Copy, Binding::command(Key::C)),
+            (Paste, Binding::command(Key::V)),
+            (Duplicate, Binding::command(Key::D)),
+            (Delete, Binding::new(Key::Delete)),
+            (Delete, Binding::new(Key::Backspace)),
+            (NudgeLeft, Binding::new(Key::ArrowLeft)),
+            (NudgeRight, Binding::new(Key::ArrowRight)),
+            (NudgeUp, Binding::new(Key::ArrowUp)),
+            (NudgeDown, Binding::new(Key::ArrowDown)),
+            (NextNote, Binding::new(Key::Tab)),
+            (PreviousNote, Binding::new(Key::Tab).with_shift()),
+            (EditNote, Binding::new(Key::Enter)),
+            (NewNote, Binding::command(Key::N)),
+            (Find, Binding::command(Key::F)),
+            (RaisePriority, Binding::new(Key::ArrowUp).with_alt()),
+            (LowerPriority, Binding::new(Key::ArrowDown).with_alt()),
+        ];
+        let mut bindings: BTreeMap<Action, Vec<Binding>> = BTreeMap::new();
+        for (action, binding) in defaults {
+            bindings.entry(action).or_default().push(binding);
+        }
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Remove the first key press in `events` bound to `action`, returning
+    /// whether there was one
+    pub fn take(&self, events: &mut Vec<Event>, action: Action) -> bool {
+        let bindings = self
+            .bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let found = events.iter().position(|event| {
+            matches!(event, Event::Key { key, pressed: true, modifiers, .. }
+                if bindings.iter().any(|b| b.matches(*key, *modifiers)))
+        });
+        found.map(|index| events.remove(index)).is_some()
+    }
+
+    /// The bindings of `action` for tooltips, like `Ctrl+Z`
+    pub fn label(&self, action: Action) -> String {
+        let bindings = self
+            .bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let labels: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+        labels.join(" / ")
+    }
+
+    /// These bindings, with the actions they don't mention keeping their
+    /// default keys, as for a file remapping only some
+    pub fn over_defaults(self) -> Self {
+        let mut keymap = Self::default();
+        keymap.bindings.extend(self.bindings);
+        keymap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{KEYMAP_FILE, load_json, save_json};
+
+    fn press(key: Key, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn bindings_parse_and_print() {
+        let redo = Binding::parse("ctrl + shift + Z").unwrap();
+        assert_eq!(redo, Binding::command(Key::Z).with_shift());
+        assert_eq!(redo.to_string(), "Ctrl+Shift+Z");
+        assert_eq!(Binding::parse("Hyper+Z"), None);
+        assert_eq!(Binding::parse("Ctrl+Nope"), None);
+    }
+
+    #[test]
+    fn modifiers_must_match_exactly() {
+        let keymap = Keymap::default();
+        let mut events = vec![press(Key::Z, Modifiers::COMMAND.plus(Modifiers::SHIFT))];
+        assert!(!keymap.take(&mut events, Action::Undo));
+        assert!(keymap.take(&mut events, Action::Redo));
+        assert!(events.is_empty());
+        let mut events = vec![press(Key::ArrowLeft, Modifiers::NONE)];
+        assert!(keymap.take(&mut events, Action::NudgeLeft));
+        assert!(!keymap.take(&mut events, Action::NudgeLeft));
+        let mut events = vec![press(Key::ArrowUp, Modifiers::ALT)];
+        assert!(!keymap.take(&mut events, Action::NudgeUp));
+        assert!(keymap.take(&mut events, Action::RaisePriority));
+    }
+
+    #[test]
+    fn files_remap_some_actions_and_keep_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KEYMAP_FILE);
+        std::fs::write(&path, r#"{"NewNote": ["Alt+N"], "Delete": []}"#).unwrap();
+        let keymap = load_json::<Keymap>(&path, &path).unwrap().over_defaults();
+        assert_eq!(keymap.label(Action::NewNote), "Alt+N");
+        assert_eq!(keymap.label(Action::Delete), "");
+        assert_eq!(keymap.label(Action::Undo), "Ctrl+Z");
+
+        save_json(&keymap, &path).unwrap();
+        assert_eq!(load_json::<Keymap>(&path, &path).unwrap(), keymap);
+        std::fs::write(&path, r#"{"Undo": ["Ctrl+Nope"]}"#).unwrap();
+        assert!(load_json::<Keymap>(&path, &path).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Kanban lanes: named columns side by side that dropped notes snap into
+
+use crate::{Board, NoteData};
+use egui::{Color32, Rangef};
+use serde::{Deserialize, Serialize};
+
+/// Width of every lane, in board units
+pub const LANE_WIDTH: f32 = 280.0;
+/// Height of the strip holding a lane's name
+pub const LANE_HEADER_HEIGHT: f32 = 32.0;
+
+/// A named column of the board
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lane {
+    pub name: String,
+    pub color: Color32,
+}
+
+impl Lane {
+    pub fn new(name: &str, color: Color32) -> Self {
+        Self {
+            name: name.into(),
+            color,
+        }
+    }
+
+    /// Translucent color the lane's area is filled with
+    pub fn fill(&self) -> Color32 {
+        self.color.gamma_multiply(0.12)
+    }
+}
+
+/// Lanes a board starts with when they are first shown
+pub fn default_lanes() -> Vec<Lane> {
+    vec![
+        Lane::new("Backlog", Color32::from_rgb(140, 140, 150)),
+        Lane::new("Doing", Color32::from_rgb(70, 130, 220)),
+        Lane::new("Done", Color32::from_rgb(80, 170, 90)),
+    ]
+}
+
+/// Horizontal extent of the lane at `index`; lanes start at x = 0
+pub fn lane_span(index: usize) -> Rangef {
+    let left = index as f32 * LANE_WIDTH;
+    Rangef::new(left, left + LANE_WIDTH)
+}
+
+impl Board {
+    /// Index of the lane covering `x`, if lanes are shown
+    pub fn lane_at(&self, x: f32) -> Option<usize> {
+        if !self.kanban || x < 0.0 {
+            return None;
+        }
+        let index = (x / LANE_WIDTH) as usize;
+        (index < self.lanes.len()).then_some(index)
+    }
+
+    /// Center `note` in the lane under its center and record the lane on
+    /// it; a note dropped outside every lane loses its lane
+    pub fn place_in_lane(&self, note: &mut NoteData) {
+        if !self.kanban {
+            return;
+        }
+        match self.lane_at(note.pos.x + note.size.x / 2.0) {
+            Some(index) => {
+                note.pos.x = lane_span(index).center() - note.size.x / 2.0;
+                note.lane = Some(self.lanes[index].name.clone());
+            }
+            None => note.lane = None,
+        }
+    }
+
+    /// Rename the lane at `index`, along with the notes in it
+    pub fn rename_lane(&mut self, index: usize, name: &str) {
+        let Some(lane) = self.lanes.get_mut(index) else {
+            return;
+        };
+        let old = std::mem::replace(&mut lane.name, name.into());
+        for note in &mut self.notes {
+            if note.lane.as_deref() == Some(old.as_str()) {
+                note.lane = Some(name.into());
+            }
+        }
+    }
+
+    /// Remove the lane at `index`; its notes stay where they are
+    pub fn remove_lane(&mut self, index: usize) {
+        if index >= self.lanes.len() {
+            return;
+        }
+        let lane = self.lanes.remove(index);
+        for note in &mut self.notes {
+            if note.lane.as_deref() == Some(lane.name.as_str()) {
+                note.lane = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Pos2, Vec2};
+
+    fn note_at(x: f32) -> NoteData {
+        NoteData {
+            pos: Pos2::new(x, 100.0),
+            size: Vec2::new(100.0, 80.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dropped_notes_snap_into_their_lane() {
+        let mut board = Board {
+            lanes: default_lanes(),
+            ..Default::default()
+        };
+        let mut note = note_at(300.0);
+        board.place_in_lane(&mut note);
+        // Lanes only catch notes while they are shown
+        assert_eq!(note.lane, None);
+        assert_eq!(note.pos.x, 300.0);
+
+        board.kanban = true;
+        board.place_in_lane(&mut note);
+        assert_eq!(note.lane.as_deref(), Some("Doing"));
+        assert_eq!(note.pos, Pos2::new(370.0, 100.0));
+
+        let mut outside = note_at(900.0);
+        outside.lane = Some("Done".into());
+        board.place_in_lane(&mut outside);
+        assert_eq!(outside.lane, None);
+        assert_eq!(outside.pos.x, 900.0);
+    }
+
+    #[test]
+    fn notes_follow_their_lane_when_renamed_or_removed() {
+        let mut note = note_at(0.0);
+        note.lane = Some("Doing".into());
+        let mut board = Board {
+            kanban: true,
+            lanes: default_lanes(),
+            notes: vec![note],
+            ..Default::default()
+        };
+        board.rename_lane(1, "In progress");
+        assert_eq!(board.notes[0].lane.as_deref(), Some("In progress"));
+        board.remove_lane(1);
+        assert_eq!(board.lanes.len(), 2);
+        assert_eq!(board.notes[0].lane, None);
+    }
+}
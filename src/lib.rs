@@ -1,16 +1,167 @@
 use bevy::prelude::Component;
+use egui::emath::TSTransform;
+use egui::epaint::text::{Fonts, LayoutJob};
 use egui::{Color32, Pos2, Rect, Vec2};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub mod accessibility;
+pub mod attachments;
+pub mod autosave;
+pub mod background;
+pub mod backups;
+pub mod board_templates;
+pub mod bulk_edit;
+pub mod categories;
+pub mod checklist;
+pub mod cli;
+pub mod clipboard;
+pub mod comments;
+pub mod config;
+pub mod connectors;
+pub mod due;
+pub mod encryption;
+pub mod expiry;
+pub mod export;
+pub mod frames;
+pub mod gamepad;
+pub mod geometry;
+pub mod guides;
+pub mod history;
+pub mod hotseat;
+pub mod html;
+pub mod icons;
+pub mod images;
+pub mod import;
+pub mod inbox;
+pub mod ink;
+pub mod integrity;
+pub mod keymap;
+pub mod lanes;
+pub mod links;
+pub mod markdown;
+pub mod merge;
+pub mod migrate;
+pub mod mind_map;
+pub mod note_font;
+pub mod note_list;
+pub mod palette;
+pub mod piles;
+pub mod presentation;
+pub mod priority;
+pub mod recent;
+pub mod remote;
+pub mod replace;
+pub mod revisions;
+pub mod sanitize;
+pub mod scripting;
+pub mod search;
+pub mod selection;
+pub mod session;
+pub mod settings;
+pub mod shapes;
+pub mod snapshots;
+pub mod sounds;
+pub mod spatial;
+pub mod split;
+pub mod spreadsheet;
+pub mod stats;
+pub mod storage;
+pub mod svg;
+pub mod sync;
+pub mod tags;
+pub mod templates;
+pub mod theme;
+pub mod tidy;
+pub mod timers;
+pub mod toss;
+pub mod trash;
+pub mod triage;
+pub mod vault;
+pub mod voting;
+pub mod watch;
+
+/// Size of a newly created note
+pub const DEFAULT_NOTE_SIZE: Vec2 = Vec2::new(120.0, 80.0);
+
+/// Color of a newly created note
+pub const DEFAULT_NOTE_COLOR: Color32 = Color32::YELLOW;
 
 /// Data for a single Post-It note
-#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct NoteData {
     pub id: u64,
     pub text: String,
     pub pos: Pos2,
     pub size: Vec2,
     pub color: Color32,
+    /// When the note expires, in seconds since the Unix epoch
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Position in the triage order, 1 being the most important
+    #[serde(default)]
+    pub rank: Option<u32>,
+    /// Hot-seat member who created the note
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Stacking level; notes with a higher `z` are drawn on top
+    #[serde(default)]
+    pub z: i32,
+    /// Lowercase labels for filtering, without a leading `#`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Picture shown above the text
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+    /// When the note is due, in seconds since the Unix epoch
+    #[serde(default)]
+    pub due: Option<u64>,
+    /// Clockwise tilt around the note's center, in radians
+    #[serde(default)]
+    pub rotation: f32,
+    /// Kept from being moved, resized or deleted until unlocked
+    #[serde(default)]
+    pub locked: bool,
+    /// Name of the kanban lane the note was last dropped into
+    #[serde(default)]
+    pub lane: Option<String>,
+    /// Earlier texts, oldest first
+    #[serde(default)]
+    pub revisions: Vec<revisions::Revision>,
+    /// Font choices of the note's own
+    #[serde(default)]
+    pub font: note_font::NoteFont,
+    /// When the note was made, in seconds since the Unix epoch
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// When the note last changed, in seconds since the Unix epoch
+    #[serde(default)]
+    pub modified_at: Option<u64>,
+    /// Outline the note is drawn with
+    #[serde(default)]
+    pub shape: shapes::NoteShape,
+    /// Dots put on the note in dot voting
+    #[serde(default)]
+    pub votes: u32,
+    /// Files and web links attached beside the text
+    #[serde(default)]
+    pub attachments: Vec<attachments::Attachment>,
+    /// Name of the board category the note is in
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Countdown shown on the note, making it a timer note
+    #[serde(default)]
+    pub timer: Option<timers::NoteTimer>,
+    /// Discussion of the note, oldest first
+    #[serde(default)]
+    pub comments: Vec<comments::Comment>,
+    /// Emoji shown in the note's top-left corner
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// How pressing the note is
+    #[serde(default)]
+    pub priority: priority::Priority,
 }
 
 /// Virtual board containing multiple notes
@@ -21,54 +172,665 @@ pub struct Board {
     pub background: Color32,
     pub notes: Vec<NoteData>,
     pub scene_rect: Rect,
+    /// Rotation of the board view in clockwise quarter turns
+    #[serde(default)]
+    pub view_quarter_turns: u8,
+    /// Notes moved out of the way, e.g. after they expired
+    #[serde(default)]
+    pub archived: Vec<NoteData>,
+    /// Deleted notes, until they are restored or purged
+    #[serde(default)]
+    pub trash: Vec<trash::Trashed>,
+    /// Name of the theme from the theme library the board uses
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// Participants of hot-seat sessions on this board
+    #[serde(default)]
+    pub members: Vec<hotseat::Member>,
+    /// Arrows between notes
+    #[serde(default)]
+    pub connectors: Vec<connectors::Connector>,
+    /// Named note colors offered on this board; empty to use the theme's
+    #[serde(default)]
+    pub palette: Vec<palette::NamedColor>,
+    /// Color of new notes on this board, overriding the theme's
+    #[serde(default)]
+    pub default_note_color: Option<Color32>,
+    /// Freehand pen strokes, drawn under the notes
+    #[serde(default)]
+    pub strokes: Vec<ink::InkStroke>,
+    /// Titled regions that move the notes inside them
+    #[serde(default)]
+    pub frames: Vec<frames::Frame>,
+    /// Pattern drawn over the background color
+    #[serde(default)]
+    pub background_style: background::BackgroundStyle,
+    /// Ids of the notes in the order they are presented; empty for reading
+    /// order
+    #[serde(default)]
+    pub presentation: Vec<u64>,
+    /// Grid spacing on this board, overriding the app-wide one
+    #[serde(default)]
+    pub grid_size: Option<f32>,
+    /// Give new notes a slight random tilt, like notes stuck on by hand
+    #[serde(default)]
+    pub tilt_new_notes: bool,
+    /// Show the kanban lanes and snap dropped notes into them
+    #[serde(default)]
+    pub kanban: bool,
+    /// Kanban lanes from left to right
+    #[serde(default)]
+    pub lanes: Vec<lanes::Lane>,
+    /// Notes stacked onto each other
+    #[serde(default)]
+    pub piles: Vec<piles::Pile>,
+    /// Named note colors explained in the legend
+    #[serde(default)]
+    pub categories: Vec<categories::Category>,
+    /// Folder the notes are kept in sync with as Markdown files
+    #[serde(default)]
+    pub vault: Option<PathBuf>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            id: 1,
+            name: "Board".into(),
+            background: Color32::LIGHT_BLUE,
+            notes: Vec::new(),
+            scene_rect: Rect::from_min_size(Pos2::ZERO, Vec2::ZERO),
+            view_quarter_turns: 0,
+            archived: Vec::new(),
+            trash: Vec::new(),
+            theme: None,
+            members: Vec::new(),
+            connectors: Vec::new(),
+            palette: Vec::new(),
+            default_note_color: None,
+            strokes: Vec::new(),
+            frames: Vec::new(),
+            background_style: Default::default(),
+            presentation: Vec::new(),
+            grid_size: None,
+            tilt_new_notes: false,
+            kanban: false,
+            lanes: Vec::new(),
+            piles: Vec::new(),
+            categories: Vec::new(),
+            vault: None,
+        }
+    }
 }
 
-/// Global application state containing a single board
+/// Grid spacings that can be picked
+pub const GRID_SIZE_RANGE: std::ops::RangeInclusive<f32> = 5.0..=200.0;
+
+impl Board {
+    /// Grid spacing notes snap to on this board: its own, or `default`
+    pub fn grid(&self, default: f32) -> f32 {
+        self.grid_size
+            .unwrap_or(default)
+            .clamp(*GRID_SIZE_RANGE.start(), *GRID_SIZE_RANGE.end())
+    }
+
+    /// Stacking level for a new note, above all existing ones
+    pub fn top_z(&self) -> i32 {
+        self.notes.iter().map(|n| n.z + 1).max().unwrap_or(0)
+    }
+
+    /// Stacking level putting note `id` above all other notes, or `None` if
+    /// it already is
+    pub fn raise_z(&self, id: u64) -> Option<i32> {
+        let z = self.notes.iter().find(|n| n.id == id)?.z;
+        let top = self
+            .notes
+            .iter()
+            .filter(|n| n.id != id)
+            .map(|n| n.z)
+            .max()?;
+        (top >= z).then_some(top + 1)
+    }
+
+    /// Stacking level putting note `id` below all other notes, or `None` if
+    /// it already is
+    pub fn lower_z(&self, id: u64) -> Option<i32> {
+        let z = self.notes.iter().find(|n| n.id == id)?.z;
+        let bottom = self
+            .notes
+            .iter()
+            .filter(|n| n.id != id)
+            .map(|n| n.z)
+            .min()?;
+        (bottom <= z).then_some(bottom - 1)
+    }
+
+    /// Notes from the bottom of the stack to the top
+    pub fn notes_by_z(&self) -> Vec<&NoteData> {
+        let mut notes: Vec<&NoteData> = self.notes.iter().collect();
+        notes.sort_by_key(|n| n.z);
+        notes
+    }
+}
+
+/// Global application state: all boards and which one is shown
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(from = "StoredState")]
 pub struct AppState {
-    pub board: Board,
+    /// Never empty
+    pub boards: Vec<Board>,
+    /// Index of the board being shown
+    pub active: usize,
+    /// Note ids are unique across all boards
     pub next_note_id: u64,
+    /// Presets offered when adding a note
+    pub templates: Vec<templates::NoteTemplate>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            board: Board {
-                id: 1,
-                name: "Board".into(),
-                background: Color32::LIGHT_BLUE,
-                notes: Vec::new(),
-                scene_rect: Rect::from_min_size(Pos2::ZERO, Vec2::ZERO),
-            },
+            boards: vec![Board::default()],
+            active: 0,
             next_note_id: 1,
+            templates: templates::default_templates(),
+        }
+    }
+}
+
+/// Layouts of the save file, older ones first
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredState {
+    Boards {
+        boards: Vec<Board>,
+        #[serde(default)]
+        active: usize,
+        next_note_id: u64,
+        #[serde(default = "templates::default_templates")]
+        templates: Vec<templates::NoteTemplate>,
+    },
+    /// Before multiple boards were supported
+    SingleBoard { board: Board, next_note_id: u64 },
+}
+
+impl From<StoredState> for AppState {
+    fn from(stored: StoredState) -> Self {
+        let (mut boards, active, next_note_id, templates) = match stored {
+            StoredState::Boards {
+                boards,
+                active,
+                next_note_id,
+                templates,
+            } => (boards, active, next_note_id, templates),
+            StoredState::SingleBoard {
+                board,
+                next_note_id,
+            } => (vec![board], 0, next_note_id, templates::default_templates()),
+        };
+        if boards.is_empty() {
+            boards.push(Board::default());
+        }
+        Self {
+            active: active.min(boards.len() - 1),
+            boards,
+            next_note_id,
+            templates,
         }
     }
 }
 
 impl AppState {
-    /// Save to JSON file
-    pub fn save_to_file(&self, path: &PathBuf) {
-        if let Ok(json) = serde_json::to_string_pretty(self) {
-            let _ = std::fs::write(path, json);
+    /// The board being shown
+    pub fn board(&self) -> &Board {
+        &self.boards[self.active]
+    }
+
+    /// The board being shown
+    pub fn board_mut(&mut self) -> &mut Board {
+        &mut self.boards[self.active]
+    }
+
+    /// Add an empty board named `name` and return its index
+    pub fn add_board(&mut self, name: &str) -> usize {
+        self.add_board_copy(Board {
+            name: name.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Add `board` as a new board, giving it and its notes fresh ids, and
+    /// return its index
+    pub fn add_board_copy(&mut self, mut board: Board) -> usize {
+        board.id = self.boards.iter().map(|b| b.id).max().unwrap_or(0) + 1;
+        let mut renumbered = Vec::new();
+        for note in board.stored_notes_mut() {
+            renumbered.push((note.id, self.next_note_id));
+            note.id = self.next_note_id;
+            self.next_note_id += 1;
         }
+        let new_id = |old: u64| renumbered.iter().find(|(o, _)| *o == old).map(|(_, n)| *n);
+        board
+            .connectors
+            .retain_mut(|c| match (new_id(c.from_note), new_id(c.to_note)) {
+                (Some(from), Some(to)) => {
+                    c.from_note = from;
+                    c.to_note = to;
+                    true
+                }
+                _ => false,
+            });
+        self.boards.push(board);
+        self.boards.len() - 1
     }
 
-    /// Load from JSON file
-    pub fn load_from_file(path: &PathBuf) -> Self {
-        if let Ok(data) = std::fs::read_to_string(path) {
-            if let Ok(state) = serde_json::from_str(&data) {
-                return state;
+    /// Delete the board at `index`, unless it is the last one left
+    pub fn remove_board(&mut self, index: usize) -> Option<Board> {
+        if self.boards.len() <= 1 || index >= self.boards.len() {
+            return None;
+        }
+        let board = self.boards.remove(index);
+        if self.active > index || self.active == self.boards.len() {
+            self.active -= 1;
+        }
+        Some(board)
+    }
+
+    /// Save to JSON file, replacing it in one step and keeping
+    /// [`backups::SAVE_BACKUPS`] earlier versions; in the browser it goes to
+    /// local storage instead
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        self.save_to(&storage::platform_storage(), path)
+    }
+
+    /// Save like [`AppState::save_to_file`], sealed with `password` if there
+    /// is one
+    pub fn save_sealed_file(&self, path: &Path, password: Option<&str>) -> io::Result<()> {
+        let saved = self.to_saved(password)?;
+        storage::Storage::write(&storage::platform_storage(), path, &saved)
+    }
+
+    /// Save as JSON under `path` in `storage`
+    pub fn save_to(&self, storage: &impl storage::Storage, path: &Path) -> io::Result<()> {
+        let json = self.to_json().map_err(io::Error::other)?;
+        storage.write(path, &json)
+    }
+
+    /// The save file contents, sealed with `password` if there is one
+    pub fn to_saved(&self, password: Option<&str>) -> io::Result<String> {
+        let json = self.to_json().map_err(io::Error::other)?;
+        encryption::seal_with(json, password)
+    }
+
+    /// The state as written to the save file, tagged with the format version
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let value = migrate::with_version(serde_json::to_value(self)?);
+        serde_json::to_string_pretty(&value)
+    }
+
+    /// Load from JSON file, upgrading older formats.
+    ///
+    /// Starts fresh if the file is missing or unreadable; an unreadable file
+    /// is first copied to its [`backup_path`] so saving doesn't lose it.
+    pub fn load_from_file(path: &Path) -> Self {
+        Self::load_from(&storage::platform_storage(), path)
+    }
+
+    /// Load from JSON under `path` in `storage`, starting fresh like
+    /// [`AppState::load_from_file`] if it can't be read
+    pub fn load_from(storage: &impl storage::Storage, path: &Path) -> Self {
+        match Self::try_load_from(storage, path) {
+            Ok(state) => state,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            // A sealed file is fine, just not readable without its password
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Self::default(),
+            Err(_) => {
+                let _ = storage.copy(path, &backup_path(path));
+                Self::default()
             }
         }
-        AppState::default()
     }
+
+    /// Load from JSON file, reporting why it could not be read.
+    ///
+    /// Invalid UTF-8 is replaced rather than failing the whole load.
+    pub fn try_load_from_file(path: &Path) -> io::Result<Self> {
+        Self::try_load_from(&storage::platform_storage(), path)
+    }
+
+    /// Load from a file sealed with `password`, or a plain one
+    pub fn try_load_sealed_file(path: &Path, password: Option<&str>) -> io::Result<Self> {
+        Self::try_load_sealed_from(&storage::platform_storage(), path, password)
+    }
+
+    /// Load from JSON under `path` in `storage`, reporting why it could not
+    /// be read; `PermissionDenied` if it is sealed with a password
+    pub fn try_load_from(storage: &impl storage::Storage, path: &Path) -> io::Result<Self> {
+        Self::try_load_sealed_from(storage, path, None)
+    }
+
+    /// Load from JSON under `path` in `storage`, opening it with `password`
+    /// if it is sealed
+    pub fn try_load_sealed_from(
+        storage: &impl storage::Storage,
+        path: &Path,
+        password: Option<&str>,
+    ) -> io::Result<Self> {
+        Self::from_saved(storage.read(path)?, password)
+    }
+
+    /// The state in save file contents, opening them with `password` if they
+    /// are sealed
+    pub fn from_saved(mut bytes: Vec<u8>, password: Option<&str>) -> io::Result<Self> {
+        if encryption::is_sealed(&bytes) {
+            let Some(password) = password else {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "the save file is locked with a password",
+                ));
+            };
+            bytes = encryption::open(&bytes, password)?.into_bytes();
+        }
+        let data = String::from_utf8_lossy(&bytes);
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let value = serde_json::from_str(&data).map_err(|e| invalid(e.to_string()))?;
+        let value = migrate::migrate(value).map_err(invalid)?;
+        serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+/// Operations on the board model, for driving plop without its UI.
+///
+/// Notes are addressed by id; operations on an unknown id do nothing and
+/// report it through their return value.
+pub trait BoardOps {
+    /// Add a note with `text` at `pos` and return its id
+    fn add_note(&mut self, text: &str, pos: Pos2) -> u64;
+
+    /// Move a note so its top-left corner is at `pos`
+    fn move_note(&mut self, id: u64, pos: Pos2) -> bool;
+
+    /// Replace the text of a note
+    fn edit_text(&mut self, id: u64, text: &str) -> bool;
+
+    /// Remove a note from the board, returning it
+    fn delete(&mut self, id: u64) -> Option<NoteData>;
+
+    /// Ids of the notes matching `query` (see [`search::SearchQuery`]),
+    /// none if it is invalid
+    fn search(&self, query: &str) -> Vec<u64>;
+
+    /// Copy of the board as it is now
+    fn snapshot(&self) -> Board;
+}
+
+impl AppState {
+    fn note_mut(&mut self, id: u64) -> Option<&mut NoteData> {
+        self.board_mut().notes.iter_mut().find(|n| n.id == id)
+    }
+}
+
+impl BoardOps for AppState {
+    fn add_note(&mut self, text: &str, pos: Pos2) -> u64 {
+        let id = self.next_note_id;
+        self.next_note_id += 1;
+        self.board_mut().notes.push(NoteData {
+            id,
+            text: text.into(),
+            pos,
+            size: DEFAULT_NOTE_SIZE,
+            color: DEFAULT_NOTE_COLOR,
+            ..Default::default()
+        });
+        id
+    }
+
+    fn move_note(&mut self, id: u64, pos: Pos2) -> bool {
+        self.note_mut(id).map(|n| n.pos = pos).is_some()
+    }
+
+    fn edit_text(&mut self, id: u64, text: &str) -> bool {
+        self.note_mut(id).map(|n| n.text = text.into()).is_some()
+    }
+
+    fn delete(&mut self, id: u64) -> Option<NoteData> {
+        let notes = &mut self.board_mut().notes;
+        let index = notes.iter().position(|n| n.id == id)?;
+        Some(notes.remove(index))
+    }
+
+    fn search(&self, query: &str) -> Vec<u64> {
+        search::SearchQuery::parse(query)
+            .map(|q| q.search(self.board()))
+            .unwrap_or_default()
+    }
+
+    fn snapshot(&self) -> Board {
+        self.board().clone()
+    }
+}
+
+/// File the window keeps the boards in: `egui_postit_state.json` in the
+/// home folder
+pub fn default_save_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join("egui_postit_state.json")
+}
+
+/// Where an unreadable save file is kept: `<file name>.bak` next to it
+pub fn backup_path(path: &Path) -> std::path::PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
 }
 
-/// Snap a `Pos2` to the nearest grid cell defined by `grid`.
+/// Snap a `Pos2` to the nearest grid cell defined by `grid`; a grid of 0
+/// leaves it where it is.
 pub fn snap_to_grid(pos: Pos2, grid: f32) -> Pos2 {
+    if grid <= 0.0 {
+        return pos;
+    }
     Pos2::new((pos.x / grid).round() * grid, (pos.y / grid).round() * grid)
 }
 
+/// Rotate `v` clockwise by `quarter_turns` × 90° (y axis pointing down).
+pub fn rotate_vec_quarter_turns(v: Vec2, quarter_turns: u8) -> Vec2 {
+    match quarter_turns % 4 {
+        0 => v,
+        1 => Vec2::new(-v.y, v.x),
+        2 => -v,
+        _ => Vec2::new(v.y, -v.x),
+    }
+}
+
+/// Rotate `pos` clockwise by `quarter_turns` × 90° around `center`.
+pub fn rotate_quarter_turns(pos: Pos2, center: Pos2, quarter_turns: u8) -> Pos2 {
+    center + rotate_vec_quarter_turns(pos - center, quarter_turns)
+}
+
+/// Rotate an axis-aligned `rect` around `center`; the result stays axis-aligned.
+pub fn rotate_rect_quarter_turns(rect: Rect, center: Pos2, quarter_turns: u8) -> Rect {
+    Rect::from_two_pos(
+        rotate_quarter_turns(rect.min, center, quarter_turns),
+        rotate_quarter_turns(rect.max, center, quarter_turns),
+    )
+}
+
+/// Number of quarter turns undoing a rotation by `quarter_turns`.
+pub fn inverse_quarter_turns(quarter_turns: u8) -> u8 {
+    (4 - quarter_turns % 4) % 4
+}
+
+/// Transform that fits `content` centered inside `frame`, keeping its aspect ratio.
+pub fn fit_transform(content: Rect, frame: Rect) -> TSTransform {
+    if content.width() <= 0.0 || content.height() <= 0.0 {
+        return TSTransform::from_translation(frame.center() - content.center());
+    }
+    let scale = (frame.width() / content.width()).min(frame.height() / content.height());
+    let translation = frame.center().to_vec2() - content.center().to_vec2() * scale;
+    TSTransform::new(translation, scale)
+}
+
+/// Velocity (points per second) to pan the view while dragging near its edge.
+///
+/// Inside `margin` of an edge the speed ramps up linearly to `max_speed`
+/// (reached at the edge and beyond), pointing towards that edge.
+pub fn auto_pan_velocity(pointer: Pos2, viewport: Rect, margin: f32, max_speed: f32) -> Vec2 {
+    if margin <= 0.0 {
+        return Vec2::ZERO;
+    }
+    let ramp = |depth: f32| (depth / margin).clamp(0.0, 1.0) * max_speed;
+    let x = ramp(pointer.x - (viewport.max.x - margin)) - ramp(viewport.min.x + margin - pointer.x);
+    let y = ramp(pointer.y - (viewport.max.y - margin)) - ramp(viewport.min.y + margin - pointer.y);
+    Vec2::new(x, y)
+}
+
+/// Room kept between a note's text and its edges, across both sides
+const TEXT_MARGIN: f32 = 8.0;
+
+/// Wrap `job` to the width of a note of size `max`; rows that don't fit
+/// below are cut off, the last one shown ending in an ellipsis
+pub fn wrap_to_fit(fonts: &Fonts, job: &mut LayoutJob, max: Vec2) {
+    job.wrap.max_width = (max.x - TEXT_MARGIN).max(1.0);
+    let height = max.y - TEXT_MARGIN;
+    let galley = fonts.layout_job(job.clone());
+    if galley.size().y <= height {
+        return;
+    }
+    // Start from the rows an average row height allows and drop more if
+    // taller (heading) rows still overflow
+    let row_height = galley.size().y / galley.rows.len().max(1) as f32;
+    let mut rows = ((height / row_height) as usize).clamp(1, galley.rows.len());
+    job.wrap.overflow_character = Some('…');
+    loop {
+        job.wrap.max_rows = rows;
+        if rows == 1 || fonts.layout_job(job.clone()).size().y <= height {
+            break;
+        }
+        rows -= 1;
+    }
+}
+
+/// Calculate a font size so the text, wrapped to the note's width, fits
+/// inside the note rectangle, within the sizes `font` allows
+pub fn fitted_font_size(fonts: &Fonts, text: &str, max: Vec2, font: &note_font::TextFont) -> f32 {
+    let fits = |size: f32| {
+        let mut job = markdown::layout_job(text, &font.id(size), font.bold, None);
+        job.wrap.max_width = (max.x - TEXT_MARGIN).max(1.0);
+        let text_size = fonts.layout_job(job).size();
+        text_size.x <= max.x - TEXT_MARGIN && text_size.y <= max.y - TEXT_MARGIN
+    };
+    if fits(font.max_size) {
+        return font.max_size;
+    }
+    // Wrapped height only grows with the size, so search between the
+    // smallest size and the largest, which is known not to fit
+    let (mut low, mut high) = (font.min_size, font.max_size);
+    while high - low > 0.5 {
+        let middle = (low + high) / 2.0;
+        if fits(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Smallest on-screen spacing (in points) between two grid lines.
+pub const MIN_GRID_SPACING: f32 = 16.0;
+
+/// How many times the base grid may be halved when zooming in.
+pub const MAX_GRID_SUBDIVISIONS: u32 = 3;
+
+/// Scale factor of a `Scene` showing `scene_rect` inside `viewport`.
+///
+/// Mirrors how the scene fits its rect: the smaller axis ratio wins.
+pub fn view_zoom(viewport: Rect, scene_rect: Rect) -> f32 {
+    let size = scene_rect.size();
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return 1.0;
+    }
+    (viewport.width() / size.x).min(viewport.height() / size.y)
+}
+
+/// Part of the scene a `Scene` showing `scene_rect` inside `viewport`
+/// actually shows; it extends past `scene_rect` along the axis with room
+/// to spare.
+pub fn visible_scene_rect(viewport: Rect, scene_rect: Rect) -> Rect {
+    let zoom = view_zoom(viewport, scene_rect);
+    Rect::from_center_size(scene_rect.center(), viewport.size() / zoom)
+}
+
+/// Point of the scene under `p`, a point of `viewport`, where a `Scene`
+/// shows `scene_rect` inside it
+pub fn viewport_to_scene(viewport: Rect, scene_rect: Rect, p: Pos2) -> Pos2 {
+    let zoom = view_zoom(viewport, scene_rect);
+    let visible = visible_scene_rect(viewport, scene_rect);
+    visible.min + (p - viewport.min) / zoom
+}
+
+/// Room left around the notes by [`content_view`], as a share of their
+/// extent
+pub const CONTENT_MARGIN: f32 = 0.1;
+
+/// Part of the board to show when it has no view yet: all its notes with
+/// some room around them, or `fallback` if there are none
+pub fn content_view(board: &Board, fallback: Rect) -> Rect {
+    framed(board.notes.iter()).unwrap_or(fallback)
+}
+
+/// The notes `ids` of `board` with some room around them, `None` if none
+/// of them is on it
+pub fn notes_view(board: &Board, ids: &[u64]) -> Option<Rect> {
+    framed(board.notes.iter().filter(|n| ids.contains(&n.id)))
+}
+
+/// Area covered by `notes`, grown by [`CONTENT_MARGIN`]
+fn framed<'a>(notes: impl Iterator<Item = &'a NoteData>) -> Option<Rect> {
+    let bounds = notes
+        .map(|n| Rect::from_min_size(n.pos, n.size))
+        .reduce(Rect::union)?;
+    Some(bounds.expand(bounds.size().max_elem() * CONTENT_MARGIN))
+}
+
+/// Zoom levels the board can be seen at
+pub const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.1..=5.0;
+
+/// How much one click of the zoom buttons zooms in or out
+pub const ZOOM_STEP: f32 = 1.25;
+
+/// `scene_rect` zoomed in by `factor` about its center, or out for a
+/// factor below one, keeping within [`ZOOM_RANGE`] inside `viewport`
+pub fn zoom_scene_rect(viewport: Rect, scene_rect: Rect, factor: f32) -> Rect {
+    let zoom = view_zoom(viewport, scene_rect);
+    let target = (zoom * factor).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+    Rect::from_center_size(scene_rect.center(), scene_rect.size() * zoom / target)
+}
+
+/// Grid step that is visible at the given `zoom`.
+///
+/// The base `grid` is doubled while its lines would be closer than
+/// [`MIN_GRID_SPACING`] on screen, and halved (at most
+/// [`MAX_GRID_SUBDIVISIONS`] times) while a cell spans more than four times
+/// that spacing.
+pub fn adaptive_grid_step(grid: f32, zoom: f32) -> f32 {
+    if grid <= 0.0 || zoom <= 0.0 || !zoom.is_finite() {
+        return grid;
+    }
+    let mut step = grid;
+    while step * zoom < MIN_GRID_SPACING {
+        step *= 2.0;
+    }
+    let mut subdivisions = 0;
+    while subdivisions < MAX_GRID_SUBDIVISIONS && step * zoom >= 4.0 * MIN_GRID_SPACING {
+        step *= 0.5;
+        subdivisions += 1;
+    }
+    step
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,14 +850,16 @@ mod tests {
                 pos: Pos2 { x: 1.0, y: 2.0 },
                 size: Vec2 { x: 10.0, y: 10.0 },
                 color: Color32::BLACK,
+                ..Default::default()
             }],
             scene_rect: Rect::from_min_size(Pos2::ZERO, Vec2::ZERO),
+            ..Default::default()
         };
-        state.board = board;
+        state.boards = vec![board];
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path().to_path_buf();
-        state.save_to_file(&path);
+        state.save_to_file(&path).unwrap();
         let loaded = AppState::load_from_file(&path);
         assert_eq!(state, loaded);
     }
@@ -116,6 +880,10 @@ mod tests {
         fs::write(&path, "not valid json").unwrap();
         let loaded = AppState::load_from_file(&path);
         assert_eq!(loaded, AppState::default());
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "not valid json"
+        );
     }
 
     #[test]
@@ -131,24 +899,290 @@ mod tests {
                 pos: Pos2 { x: 0.0, y: 0.0 },
                 size: Vec2 { x: 10.0, y: 10.0 },
                 color: Color32::BLACK,
+                ..Default::default()
             }],
             scene_rect: Rect::from_min_size(Pos2::ZERO, Vec2::ZERO),
+            ..Default::default()
         };
         board.notes[0].text = "edited".into();
-        state.board = board.clone();
+        state.boards = vec![board.clone()];
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path().to_path_buf();
-        state.save_to_file(&path);
+        state.save_to_file(&path).unwrap();
         let loaded = AppState::load_from_file(&path);
-        assert_eq!(loaded.board.notes[0].text, "edited");
+        assert_eq!(loaded.board().notes[0].text, "edited");
         assert_eq!(loaded, state);
     }
 
+    #[test]
+    fn single_board_files_still_load() {
+        let json = r#"{
+            "board": {
+                "id": 1,
+                "name": "Old",
+                "background": [0, 0, 0, 255],
+                "notes": [],
+                "scene_rect": {"min": {"x": 0.0, "y": 0.0}, "max": {"x": 0.0, "y": 0.0}}
+            },
+            "next_note_id": 7
+        }"#;
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), json).unwrap();
+        let loaded = AppState::try_load_from_file(file.path()).unwrap();
+        assert_eq!(loaded.boards.len(), 1);
+        assert_eq!(loaded.board().name, "Old");
+        assert_eq!(loaded.next_note_id, 7);
+    }
+
+    #[test]
+    fn raising_and_lowering_notes() {
+        let mut board = Board {
+            notes: [(1, 0), (2, 0), (3, 5)]
+                .map(|(id, z)| NoteData {
+                    id,
+                    z,
+                    ..Default::default()
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(board.raise_z(1), Some(6));
+        assert_eq!(board.raise_z(3), None);
+        assert_eq!(board.top_z(), 6);
+        assert_eq!(board.lower_z(3), Some(-1));
+        assert_eq!(board.lower_z(1), Some(-1));
+        board.notes[0].z = -1;
+        assert_eq!(board.lower_z(1), None);
+        let order: Vec<u64> = board.notes_by_z().iter().map(|n| n.id).collect();
+        assert_eq!(order, [1, 2, 3]);
+    }
+
+    #[test]
+    fn boards_can_be_added_and_removed() {
+        let mut state = AppState::default();
+        let second = state.add_board("Second");
+        assert_eq!(state.boards[second].id, 2);
+        state.active = second;
+        assert!(state.remove_board(0).is_some());
+        assert_eq!(state.active, 0);
+        assert_eq!(state.board().name, "Second");
+        // The last board stays
+        assert!(state.remove_board(0).is_none());
+
+        let copy = state.add_board_copy(Board {
+            notes: vec![NoteData::default()],
+            ..Default::default()
+        });
+        assert_eq!(state.boards[copy].id, 3);
+        assert_eq!(state.boards[copy].notes[0].id, 1);
+        assert_eq!(state.next_note_id, 2);
+    }
+
+    #[test]
+    fn copied_boards_keep_their_connectors() {
+        let mut state = AppState::default();
+        let mut board = Board {
+            notes: [10, 20]
+                .map(|id| NoteData {
+                    id,
+                    ..Default::default()
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        for (from_note, to_note) in [(10, 20), (20, 99)] {
+            board.connectors.push(connectors::Connector {
+                from_note,
+                to_note,
+                style: Default::default(),
+                label: String::new(),
+            });
+        }
+        let copy = state.add_board_copy(board);
+        let ids: Vec<u64> = state.boards[copy].notes.iter().map(|n| n.id).collect();
+        let connectors = &state.boards[copy].connectors;
+        assert_eq!(connectors.len(), 1);
+        assert_eq!(
+            (connectors[0].from_note, connectors[0].to_note),
+            (ids[0], ids[1])
+        );
+    }
+
+    #[test]
+    fn board_ops_drive_the_model() {
+        let mut state = AppState::default();
+        let a = state.add_note("Buy milk", Pos2::new(10.0, 10.0));
+        let b = state.add_note("Call Bob", Pos2::ZERO);
+        assert_ne!(a, b);
+        assert!(state.move_note(a, Pos2::new(50.0, 0.0)));
+        assert!(state.edit_text(b, "Call MILKman"));
+        assert_eq!(state.search("milk"), vec![a, b]);
+        assert_eq!(state.delete(a).map(|n| n.pos), Some(Pos2::new(50.0, 0.0)));
+        assert!(!state.move_note(a, Pos2::ZERO));
+        assert_eq!(state.snapshot().notes.len(), 1);
+    }
+
     #[test]
     fn snap_to_grid_rounds_position() {
         let pos = Pos2 { x: 27.0, y: 73.0 };
         let snapped = snap_to_grid(pos, 50.0);
         assert_eq!(snapped, Pos2 { x: 50.0, y: 50.0 });
+        // No grid, no snapping
+        assert_eq!(snap_to_grid(pos, 0.0), pos);
+    }
+
+    #[test]
+    fn boards_can_have_their_own_grid() {
+        let mut board = Board::default();
+        assert_eq!(board.grid(50.0), 50.0);
+        board.grid_size = Some(20.0);
+        assert_eq!(board.grid(50.0), 20.0);
+        // Sizes from hand-edited files stay usable
+        board.grid_size = Some(0.0);
+        assert_eq!(board.grid(50.0), 5.0);
+    }
+
+    #[test]
+    fn view_zoom_uses_smaller_axis() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 400.0));
+        let scene = Rect::from_min_size(Pos2::ZERO, Vec2::new(400.0, 400.0));
+        assert_eq!(view_zoom(viewport, scene), 1.0);
+        let empty = Rect::from_min_size(Pos2::ZERO, Vec2::ZERO);
+        assert_eq!(view_zoom(viewport, empty), 1.0);
+        // The spare width is split evenly on both sides
+        let p = viewport_to_scene(viewport, scene, Pos2::new(400.0, 100.0));
+        assert_eq!(p, Pos2::new(200.0, 100.0));
+    }
+
+    #[test]
+    fn content_view_covers_notes_anywhere() {
+        let fallback = Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0));
+        let mut board = Board::default();
+        assert_eq!(content_view(&board, fallback), fallback);
+        for (id, x, y) in [(1, -20_000.0, 0.0), (2, 90_000.0, 50.0)] {
+            board.notes.push(NoteData {
+                id,
+                pos: Pos2::new(x, y),
+                size: Vec2::splat(100.0),
+                ..Default::default()
+            });
+        }
+        let view = content_view(&board, fallback);
+        assert_eq!(view.min, Pos2::new(-31_010.0, -11_010.0));
+        assert_eq!(view.max, Pos2::new(101_110.0, 11_160.0));
+        let note = notes_view(&board, &[2]).unwrap();
+        assert_eq!(
+            note,
+            Rect::from_min_size(Pos2::new(89_990.0, 40.0), Vec2::splat(120.0))
+        );
+        assert_eq!(notes_view(&board, &[3]), None);
+    }
+
+    #[test]
+    fn zooming_stays_in_range() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::splat(400.0));
+        let scene = Rect::from_center_size(Pos2::ZERO, Vec2::splat(400.0));
+        let closer = zoom_scene_rect(viewport, scene, 2.0);
+        assert_eq!(
+            closer,
+            Rect::from_center_size(Pos2::ZERO, Vec2::splat(200.0))
+        );
+        assert_eq!(
+            view_zoom(viewport, zoom_scene_rect(viewport, scene, 100.0)),
+            5.0
+        );
+        let tiny = Rect::from_center_size(Pos2::ZERO, Vec2::splat(10.0));
+        assert_eq!(
+            view_zoom(viewport, zoom_scene_rect(viewport, tiny, 1.0)),
+            5.0
+        );
+    }
+
+    #[test]
+    fn adaptive_grid_step_follows_zoom() {
+        // At 1:1 the base grid is comfortably visible
+        assert_eq!(adaptive_grid_step(50.0, 1.0), 50.0);
+        // Zoomed out: coarser lines
+        assert_eq!(adaptive_grid_step(50.0, 0.1), 200.0);
+        // Zoomed in: finer subdivisions, bounded
+        assert_eq!(adaptive_grid_step(50.0, 2.0), 25.0);
+        assert_eq!(adaptive_grid_step(50.0, 100.0), 6.25);
+    }
+
+    #[test]
+    fn quarter_turns_rotate_clockwise_around_center() {
+        let center = Pos2::new(10.0, 10.0);
+        let p = Pos2::new(20.0, 10.0);
+        assert_eq!(rotate_quarter_turns(p, center, 1), Pos2::new(10.0, 20.0));
+        assert_eq!(rotate_quarter_turns(p, center, 2), Pos2::new(0.0, 10.0));
+        assert_eq!(rotate_quarter_turns(p, center, 3), Pos2::new(10.0, 0.0));
+        assert_eq!(rotate_quarter_turns(p, center, 4), p);
+        for turns in 0..4 {
+            let there = rotate_quarter_turns(p, center, turns);
+            let back = rotate_quarter_turns(there, center, inverse_quarter_turns(turns));
+            assert_eq!(back, p);
+        }
+    }
+
+    #[test]
+    fn fit_transform_keeps_aspect_and_centers() {
+        let content = Rect::from_min_size(Pos2::new(100.0, 100.0), Vec2::new(200.0, 100.0));
+        let frame = Rect::from_min_size(Pos2::ZERO, Vec2::new(100.0, 100.0));
+        let fitted = fit_transform(content, frame) * content;
+        assert_eq!(fitted.size(), Vec2::new(100.0, 50.0));
+        assert_eq!(fitted.center(), frame.center());
+    }
+
+    #[test]
+    fn visible_rect_covers_the_whole_viewport() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::new(400.0, 200.0));
+        let scene_rect = Rect::from_min_size(Pos2::new(100.0, 100.0), Vec2::splat(100.0));
+        let visible = visible_scene_rect(viewport, scene_rect);
+        assert_eq!(visible.center(), scene_rect.center());
+        assert_eq!(visible.size(), Vec2::new(200.0, 100.0));
+    }
+
+    #[test]
+    fn auto_pan_only_near_edges() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::new(400.0, 300.0));
+        let center = auto_pan_velocity(Pos2::new(200.0, 150.0), viewport, 40.0, 100.0);
+        assert_eq!(center, Vec2::ZERO);
+        let right = auto_pan_velocity(Pos2::new(380.0, 150.0), viewport, 40.0, 100.0);
+        assert_eq!(right, Vec2::new(50.0, 0.0));
+        let top_left = auto_pan_velocity(Pos2::new(-10.0, 0.0), viewport, 40.0, 100.0);
+        assert_eq!(top_left, Vec2::new(-100.0, -100.0));
+    }
+
+    #[test]
+    fn rotated_rect_stays_axis_aligned() {
+        let rect = Rect::from_min_size(Pos2::new(0.0, 0.0), Vec2::new(4.0, 2.0));
+        let rotated = rotate_rect_quarter_turns(rect, Pos2::ZERO, 1);
+        assert_eq!(rotated.size(), Vec2::new(2.0, 4.0));
+        assert_eq!(rotated.min, Pos2::new(-2.0, 0.0));
+    }
+
+    #[test]
+    fn long_text_wraps_before_it_shrinks() {
+        use egui::epaint::text::FontDefinitions;
+        let fonts = Fonts::new(1.0, 2048, FontDefinitions::default());
+        let font = note_font::TextFont::default();
+        let note = Vec2::new(200.0, 150.0);
+        let text = "a sentence that is far too long to fit on a single line of a note";
+        let size = fitted_font_size(&fonts, text, note, &font);
+        let unwrapped = fonts
+            .layout_job(markdown::layout_job(text, &font.id(size), false, None))
+            .size();
+        assert!(size > font.min_size && unwrapped.x > note.x);
+
+        // Past the smallest size the rows that don't fit give way to an ellipsis
+        let long = text.repeat(20);
+        let size = fitted_font_size(&fonts, &long, note, &font);
+        assert_eq!(size, font.min_size);
+        let mut job = markdown::layout_job(&long, &font.id(size), false, None);
+        wrap_to_fit(&fonts, &mut job, note);
+        let galley = fonts.layout_job(job);
+        assert!(galley.size().y <= note.y && galley.size().x <= note.x);
+        assert!(galley.elided);
     }
 }
@@ -0,0 +1,146 @@
+//! `file://` links and `#42` references to other notes inside note text
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extract the local paths of all `file://` links in `text`.
+///
+/// Links end at whitespace; trailing punctuation and surrounding brackets
+/// are dropped and `%XX` escapes are decoded.
+pub fn file_links(text: &str) -> Vec<PathBuf> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            let start = word.find("file://")?;
+            let url =
+                word[start..].trim_end_matches(['.', ',', ';', ':', ')', ']', '>', '"', '\'']);
+            link_path(url)
+        })
+        .collect()
+}
+
+/// Convert a single `file://` url to a path
+pub fn link_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    let decoded = percent_decode(rest);
+    if decoded.is_empty() {
+        return None;
+    }
+    // `file:///C:/dir` names a drive on Windows
+    let bytes = decoded.as_bytes();
+    if cfg!(windows) && bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+        return Some(PathBuf::from(&decoded[1..]));
+    }
+    Some(PathBuf::from(decoded))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = s.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Ids of the notes referenced in `text` as `#<id>`, in order, without
+/// repeats.
+///
+/// The reference must stand alone, so `abc#1`, `#1a` and `##1` are text.
+pub fn note_links(text: &str) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for (start, _) in text.match_indices('#') {
+        let before = text[..start].chars().next_back();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '#') {
+            continue;
+        }
+        let rest = &text[start + 1..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let after = rest[digits..].chars().next();
+        if digits == 0 || after.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        if let Ok(id) = rest[..digits].parse()
+            && !ids.contains(&id)
+        {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// `text` with a reference to note `id` added at the end
+pub fn append_note_link(text: &str, id: u64) -> String {
+    let text = text.trim_end();
+    if text.is_empty() {
+        format!("#{id}")
+    } else {
+        format!("{text} #{id}")
+    }
+}
+
+/// Open `path` with the system file manager or default application
+pub fn open_path(path: &Path) -> io::Result<()> {
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener).arg(path).spawn().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_links_between_words() {
+        let links = file_links("see file:///tmp/plan.md, and (file:///home/me/My%20Docs)");
+        assert_eq!(
+            links,
+            vec![
+                PathBuf::from("/tmp/plan.md"),
+                PathBuf::from("/home/me/My Docs")
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_text_without_links() {
+        assert!(file_links("just a note https://example.com").is_empty());
+        assert_eq!(link_path("file://"), None);
+    }
+
+    #[test]
+    fn finds_references_to_notes() {
+        assert_eq!(
+            note_links("see #42, (#7) and #42 again; not abc#1, #1a, ##3 or #"),
+            vec![42, 7]
+        );
+        assert_eq!(note_links(&append_note_link("Plan\n", 5)), vec![5]);
+        assert_eq!(append_note_link("", 5), "#5");
+    }
+
+    #[test]
+    fn localhost_host_is_dropped() {
+        assert_eq!(
+            link_path("file://localhost/etc/hosts"),
+            Some(PathBuf::from("/etc/hosts"))
+        );
+    }
+}
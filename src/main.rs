@@ -1,13 +1,157 @@
+#![allow(clippy::too_many_arguments)]
+
 use bevy::app::AppExit;
 use bevy::audio::{PlaybackSettings, Volume};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowCloseRequested, WindowMode};
 use bevy_egui::EguiContexts;
 use bevy_prng::WyRand;
 use bevy_rand::prelude::*;
+use egui::emath::TSTransform;
 use egui::{Color32, Pos2, Rect, Shape, Stroke, Vec2, containers::Scene};
-use plop::{AppState, Board, NoteData, snap_to_grid};
+#[cfg(not(target_arch = "wasm32"))]
+use global_hotkey::hotkey::HotKey;
+#[cfg(not(target_arch = "wasm32"))]
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use plop::accessibility::{high_contrast_visuals, note_label, readable_text_styles};
+use plop::attachments::Attachment;
+use plop::autosave::{Autosaver, INTERVAL_CHOICES, interval_label};
+use plop::background::{BackgroundStyle, cork_speckles};
+use plop::backups::{SAVE_BACKUPS, rewrite_backups, write_with_backups};
+use plop::board_templates::{
+    BOARD_TEMPLATES_DIR, BoardTemplate, board_templates, write_board_template,
+};
+use plop::bulk_edit::BulkEdit;
+use plop::categories::Category;
+use plop::checklist;
+use plop::clipboard::{NoteClipboard, PASTE_OFFSET};
+use plop::config::{
+    KEYMAP_FILE, RECENT_FILE, SESSION_FILE, SETTINGS_FILE, config_path, load_json, old_config_path,
+    save_json,
+};
+use plop::connectors::{
+    ARROW_SIZE, CONNECTOR_COLOR, Connector, ConnectorStyle, arrow_head, border_point,
+    connector_ends, connector_shapes,
+};
+use plop::due::{DueStatus, due_status};
+use plop::encryption::reseal;
+use plop::expiry::{
+    DAY, archive_notes, days_in_month, expired_notes, first_weekday, format_date,
+    format_local_datetime, from_local_parts, is_expired, local_parts, now_secs, parse_date,
+    parse_local_datetime,
+};
+use plop::export::{
+    ExportFormat, OutlineGrouping, PNG_SCALES, SELECTION_EXTENSION, board_file_name,
+    board_to_markdown_grouped, crop_to_rect, export_board, export_files, export_selection,
+    read_selection,
+};
+use plop::frames::{DEFAULT_FRAME_COLOR, DEFAULT_FRAME_SIZE, Frame, MIN_FRAME_SIZE, title_bar};
+use plop::gamepad::{nearest_note, note_towards, pan_scene_rect, stick_input, zoom_factor};
+use plop::geometry::{
+    MAX_NEW_NOTE_TILT, MIN_NOTE_SIZE, RESIZE_HANDLE_SIZE, ROTATE_HANDLE_DISTANCE, ROTATION_SNAP,
+    ResizeHandle, SKEW_EASE_DRAGGING, SKEW_EASE_RELEASED, drag_skew_target, ease_skew, quad_bounds,
+    resize_rect, rotate_about, rotation_towards, snap_size_to_grid, view_corners, wiggle_offset,
+};
+use plop::guides::GUIDE_SNAP_DISTANCE;
+use plop::history::{Command, UndoStack};
+use plop::hotseat::{MEMBER_COLORS, Member, author_color, initials};
+use plop::icons::search_icons;
+use plop::images::{
+    IMAGE_EXTENSIONS, IMAGE_TEXT_SHARE, image_rect, is_image_path, load_image,
+    text_center_below_image,
+};
+use plop::import::{markdown_to_notes, paragraphs, texts_to_notes, trello_to_notes};
+use plop::inbox::INBOX_TITLE;
+use plop::ink::InkStroke;
+use plop::integrity::reconcile;
+use plop::keymap::{Action, Keymap};
+use plop::lanes::{LANE_HEADER_HEIGHT, Lane, default_lanes, lane_span};
+use plop::links::{append_note_link, file_links, note_links, open_path};
+use plop::markdown;
+use plop::merge::{merge_states, read_base, write_base};
+use plop::mind_map::{MIND_MAP_EXTENSIONS, mind_map_to_notes};
+use plop::note_font::{FONT_SIZE_RANGE, FontChoice, FontDefaults, TextFont};
+use plop::note_list::NoteOrder;
+use plop::palette::{NamedColor, sticky_colors};
+use plop::presentation::{FLIGHT_SECONDS, FOCUS_SECONDS, fly, slide_rect};
+use plop::priority::Priority;
+use plop::recent::RecentFiles;
+use plop::remote::{
+    Pulled, Pushed, RemoteConfig, RemoteJob, RemoteKind, RemoteReply, SYNC_INTERVAL,
+};
+use plop::replace::replace_preview;
+use plop::sanitize::{MAX_NOTE_CHARS, sanitize_state, sanitize_text};
+use plop::scripting::{SCRIPT_EXTENSION, SCRIPTS_DIR, list_scripts, run_script};
+use plop::search::{SearchQuery, next_board_with_matches};
+use plop::selection::{Selection, cycle_note, notes_in_rect};
+use plop::session::Session;
+use plop::settings::{DEFAULT_QUICK_CAPTURE_SHORTCUT, Settings};
+use plop::shapes::{NoteShape, unit_to_quad};
+use plop::snapshots::Timeline;
+use plop::sounds::{SOUND_PACKS_DIR, SoundEvent, list_packs, sound_asset};
+use plop::spatial::SpatialGrid;
+use plop::spreadsheet::csv_to_notes;
+use plop::stats::{BoardStats, stamp};
+use plop::sync::{
+    DEFAULT_PORT, RemoteChange, SyncConnection, SyncEvent, SyncMessage, SyncState, now_millis,
+};
+use plop::tags::{FilterMode, TagFilter, format_tags, parse_tags};
+use plop::templates::NoteTemplate;
+use plop::theme::{Theme, ThemeLibrary, theme_file_name, write_theme};
+use plop::tidy::{TIDY_SECONDS, TidyGrouping, tidy_positions};
+use plop::timers::{self, NoteTimer, POMODORO, format_countdown, parse_duration};
+use plop::toss::{MIN_TOSS_SPEED, is_resting, slide};
+use plop::triage::{assign_ranks, move_item, sort_by_rank};
+use plop::vault::sync_vault;
+use plop::voting::Ballot;
+use plop::watch::{FileWatch, own_copy_path};
+use plop::{
+    AppState, Board, BoardOps, GRID_SIZE_RANGE, NoteData, ZOOM_RANGE, ZOOM_STEP,
+    adaptive_grid_step, auto_pan_velocity, backup_path, content_view, default_save_path,
+    fit_transform, fitted_font_size, inverse_quarter_turns, notes_view, rotate_quarter_turns,
+    rotate_rect_quarter_turns, rotate_vec_quarter_turns, snap_to_grid, view_zoom,
+    viewport_to_scene, visible_scene_rect, wrap_to_fit, zoom_scene_rect,
+};
 use rand::Rng;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The browser has no file dialogs the app could wait on, so on the web
+/// every pick comes back empty
+#[cfg(target_arch = "wasm32")]
+mod rfd {
+    use std::path::PathBuf;
+
+    pub struct FileDialog;
+
+    impl FileDialog {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn add_filter(self, _name: impl Into<String>, _extensions: &[impl ToString]) -> Self {
+            self
+        }
+
+        pub fn set_file_name(self, _name: impl Into<String>) -> Self {
+            self
+        }
+
+        pub fn pick_file(self) -> Option<PathBuf> {
+            None
+        }
+
+        pub fn pick_folder(self) -> Option<PathBuf> {
+            None
+        }
+
+        pub fn save_file(self) -> Option<PathBuf> {
+            None
+        }
+    }
+}
 
 /// Runtime UI state for a note
 #[derive(Component)]
@@ -15,6 +159,41 @@ struct NoteUi {
     is_editing: bool,
     /// Current skew applied while dragging for a leaning effect
     skew: Vec2,
+    /// `file://` links of the note and whether their targets exist
+    file_links: Vec<(PathBuf, bool)>,
+    /// Text and time the links were last checked for
+    links_checked: Option<(String, f64)>,
+    /// Expiry date being typed in the edit window
+    expiry_input: String,
+    /// Due date being typed in the edit window
+    due_input: String,
+    /// Tags being typed in the edit window
+    tags_input: String,
+    /// Link or path being typed in the edit window to attach
+    attachment_input: String,
+    /// Timer length being typed in the edit window
+    timer_input: String,
+    /// Comment being typed in the edit window
+    comment_input: String,
+    /// Words being typed in the edit window to find an icon
+    icon_query: String,
+    /// What was cleaned up in the last pasted/typed text
+    text_warning: Option<String>,
+    /// Position the current drag started at, for undo
+    drag_origin: Option<Pos2>,
+    /// Velocity of the note sliding on after it was tossed, in board units
+    /// per second
+    toss: Option<Vec2>,
+    /// The note as it was when editing started, for undo
+    edit_origin: Option<NoteData>,
+    /// Put the keyboard focus in the text field once the edit window shows
+    focus_text: bool,
+    /// Close the edit window as if Done was clicked, e.g. from a controller
+    finish_edit: bool,
+    /// The note as it was when a resize started, for undo
+    resize_origin: Option<NoteData>,
+    /// The note as it was when turning it by its handle started, for undo
+    rotate_origin: Option<NoteData>,
 }
 
 impl Default for NoteUi {
@@ -22,309 +201,7397 @@ impl Default for NoteUi {
         Self {
             is_editing: false,
             skew: Vec2::ZERO,
+            file_links: Vec::new(),
+            links_checked: None,
+            expiry_input: String::new(),
+            due_input: String::new(),
+            tags_input: String::new(),
+            attachment_input: String::new(),
+            timer_input: String::new(),
+            comment_input: String::new(),
+            icon_query: String::new(),
+            text_warning: None,
+            drag_origin: None,
+            toss: None,
+            edit_origin: None,
+            focus_text: false,
+            finish_edit: false,
+            resize_origin: None,
+            rotate_origin: None,
+        }
+    }
+}
+
+/// How often (in seconds) link targets are checked for existence
+const LINK_CHECK_INTERVAL: f64 = 2.0;
+
+/// How often (in seconds) the save file is checked for outside changes
+const SAVE_WATCH_INTERVAL: f64 = 2.0;
+
+/// Seconds to wait after the sync settings change before syncing with them
+const REMOTE_SETTLE: f64 = 3.0;
+
+/// How often (in seconds) the board is checked for expired notes
+const EXPIRY_CHECK_INTERVAL: f64 = 60.0;
+/// How long (in seconds) "Later" postpones the expired notes prompt
+const EXPIRY_SNOOZE: f64 = 60.0 * 60.0;
+
+/// Periodic prompt offering to archive expired notes
+#[derive(Resource, Default)]
+struct ExpiryCleanup {
+    /// App time of the next check
+    next_check: f64,
+    /// Expired notes waiting for a decision; the prompt shows while non-empty
+    pending: Vec<u64>,
+}
+
+/// Watch on the save file for changes made outside plop
+#[derive(Resource)]
+struct SaveWatch {
+    watch: FileWatch,
+    /// App time of the next check
+    next_check: f64,
+    /// Set when the file changed outside plop; nothing is saved over it
+    /// until the user decides what to do
+    pending: bool,
+}
+
+impl SaveWatch {
+    /// Watch `path` as it is now, e.g. after opening or saving it
+    fn reset(&mut self, path: &Path) {
+        self.watch = FileWatch::new(path);
+        self.pending = false;
+    }
+}
+
+impl FromWorld for SaveWatch {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            watch: FileWatch::new(&world.resource::<PostItData>().save_path),
+            next_check: 0.0,
+            pending: false,
         }
     }
 }
 
+/// Keeping the save file in step with its copy on a server
+#[derive(Resource, Default)]
+struct RemoteSync {
+    /// Pull or push under way
+    job: Option<RemoteJob>,
+    /// Contents being pushed, to remember once they are written
+    pushing: Option<String>,
+    /// App time of the next check for changes on either side
+    next_check: f64,
+    /// Contents as last pulled or pushed; boards that differ changed here
+    synced: Option<String>,
+    /// Copy changed on the server while the boards here changed too, with
+    /// its ETag, waiting for a decision
+    conflict: Option<(AppState, Option<String>)>,
+    /// Settings synced with, apart from the ETag; changing them syncs anew
+    config: Option<RemoteConfig>,
+}
 
-// Audio resource to play the plop sound
+/// Loaded sounds of the chosen sound pack
 #[derive(Resource)]
-struct AudioAssets {
-    plop: Handle<AudioSource>,
+struct SoundPack {
+    /// Pack the sounds were loaded from, `None` for the built-in one
+    name: Option<String>,
+    sounds: HashMap<SoundEvent, Handle<AudioSource>>,
+}
+
+impl SoundPack {
+    fn load(asset_server: &AssetServer, name: Option<&str>) -> Self {
+        let dir = assets_dir();
+        let sounds = SoundEvent::ALL
+            .into_iter()
+            .map(|event| (event, asset_server.load(sound_asset(&dir, name, event))))
+            .collect();
+        Self {
+            name: name.map(str::to_owned),
+            sounds,
+        }
+    }
+}
+
+/// Folder Bevy loads assets from, found the way its file reader finds it
+fn assets_dir() -> PathBuf {
+    let root = std::env::var_os("BEVY_ASSET_ROOT")
+        .or_else(|| std::env::var_os("CARGO_MANIFEST_DIR"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        })
+        .unwrap_or_default();
+    root.join("assets")
+}
+
+/// Point the board view rotates around. The board has no edges; this was
+/// the middle of its old fixed-size scene, kept so rotated boards stay put.
+fn scene_center() -> Pos2 {
+    Pos2::new(2500.0, 2500.0)
 }
 
-/// Grid size controlling note alignment
+/// Grid size controlling note alignment, unless a board has its own
 #[derive(Resource)]
 struct GridSize(f32);
 
-impl Default for GridSize {
+impl FromWorld for GridSize {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<Themes>().settings.grid_size)
+    }
+}
+
+/// Auto-pan state for the note currently being dragged
+#[derive(Resource, Default)]
+struct DragPan {
+    /// Set once the view has auto-panned during this drag; shows the overview
+    engaged: bool,
+}
+
+/// Whether the minimap of the board is shown
+#[derive(Resource)]
+struct Minimap {
+    visible: bool,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self { visible: true }
+    }
+}
+
+/// Full-screen stepping through the notes of the active board
+#[derive(Resource, Default)]
+struct Presentation {
+    active: bool,
+    /// Ids of the notes in the order they are shown
+    order: Vec<u64>,
+    index: usize,
+    /// View the camera is flying from and when it set off
+    flight: Option<(Rect, f64)>,
+    /// View to go back to afterwards
+    return_to: Rect,
+}
+
+impl Presentation {
+    /// Start presenting `board` from its first note, or return `false` if
+    /// it has none
+    fn start(&mut self, board: &Board, now: f64) -> bool {
+        self.order = board.presentation_order();
+        if self.order.is_empty() {
+            return false;
+        }
+        self.active = true;
+        self.index = 0;
+        self.flight = Some((board.scene_rect, now));
+        self.return_to = board.scene_rect;
+        true
+    }
+}
+
+/// Whether the side list of notes by due date is open
+#[derive(Resource, Default)]
+struct DueList {
+    open: bool,
+}
+
+/// Whether the side panel of board statistics is open
+#[derive(Resource, Default)]
+struct StatsPanel {
+    open: bool,
+}
+
+/// Whether the side list of all notes is open, and its order
+#[derive(Resource, Default)]
+struct NoteList {
+    open: bool,
+    order: NoteOrder,
+}
+
+/// Whether the trash panel is open, and whether it shows the archive
+#[derive(Resource, Default)]
+struct TrashPanel {
+    open: bool,
+    archived: bool,
+}
+
+/// Dot voting: while the panel is open, clicking a note puts a dot on it
+#[derive(Resource, Default)]
+struct VotePanel {
+    open: bool,
+    ballot: Ballot,
+}
+
+/// Side panels that can be opened from the toolbar
+#[derive(SystemParam)]
+struct SidePanels<'w> {
+    due_list: ResMut<'w, DueList>,
+    stats_panel: ResMut<'w, StatsPanel>,
+    note_list: ResMut<'w, NoteList>,
+    trash_panel: ResMut<'w, TrashPanel>,
+    vote_panel: ResMut<'w, VotePanel>,
+}
+
+/// Whether dotted and lined boards show the snap grid
+#[derive(Resource)]
+struct ShowGrid {
+    visible: bool,
+}
+
+impl Default for ShowGrid {
     fn default() -> Self {
-        Self(50.0)
+        Self { visible: true }
     }
 }
 
+/// How far outside the visible area (in board units) notes are still drawn,
+/// so their shadows, link chips and skew never pop in at the edge
+const CULL_MARGIN: f32 = 200.0;
+
+/// Distance from the board edge (in points) where dragging starts to pan
+const AUTO_PAN_MARGIN: f32 = 40.0;
+/// Pan speed at the very edge of the board, in points per second
+const AUTO_PAN_SPEED: f32 = 600.0;
+/// Size of the overview inset shown while auto-panning
+const OVERVIEW_SIZE: Vec2 = Vec2::new(200.0, 150.0);
+
 // Bevy resource to hold our app state
 #[derive(Resource)]
 struct PostItData {
     state: AppState,
     save_path: PathBuf,
+    /// Password the save file is sealed with; plain JSON without one
+    password: Option<String>,
+    /// Sealed file waiting for its password, never written over meanwhile
+    locked: Option<PathBuf>,
+}
+
+impl PostItData {
+    /// Whether saving to `path` would overwrite a file not yet unlocked
+    fn is_locked(&self, path: &Path) -> bool {
+        self.locked.as_deref() == Some(path)
+    }
 }
 
 impl Default for PostItData {
     fn default() -> Self {
         // Where to persist JSON
-        let mut save_path = dirs::home_dir().unwrap_or_default();
-        save_path.push("egui_postit_state.json");
+        let save_path = default_save_path();
 
         // Load existing state or start fresh
-        let state = AppState::load_from_file(&save_path);
+        let mut locked = None;
+        let mut state = match AppState::try_load_from_file(&save_path) {
+            Ok(state) => state,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => AppState::default(),
+            // Asked for in the password window
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                locked = Some(save_path.clone());
+                AppState::default()
+            }
+            Err(e) => {
+                warn!(
+                    "Could not load {}: {e}; keeping a copy at {}",
+                    save_path.display(),
+                    backup_path(&save_path).display()
+                );
+                // Makes the backup
+                AppState::load_from_file(&save_path)
+            }
+        };
+        for warning in sanitize_state(&mut state) {
+            warn!("Loaded {warning}");
+        }
 
-        Self { state, save_path }
+        Self {
+            state,
+            save_path,
+            password: None,
+            locked,
+        }
     }
 }
 
-// Store which board needs sound played in events
+/// Request to play the sound of an event on the board
+#[derive(Event)]
+struct PlaySound(SoundEvent);
+
+/// Request to reconcile note entities with the board, e.g. after loading
 #[derive(Event, Default)]
-struct PlayPlopEvent;
+struct CheckIntegrity;
+
+/// Screen area (in points) the board is shown in, for viewport screenshots
+#[derive(Resource)]
+struct BoardViewport(Rect);
+
+impl Default for BoardViewport {
+    fn default() -> Self {
+        Self(Rect::NOTHING)
+    }
+}
 
+/// Keeps the clipboard alive; on X11 copied data vanishes with its owner
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Resource, Default)]
-struct SearchState {
-    query: String,
-    matches: Vec<u64>, // note_id
-    current: usize,
+struct ClipboardHolder(Option<arboard::Clipboard>);
+
+/// The browser lets pages copy text only, through egui
+#[cfg(target_arch = "wasm32")]
+#[derive(Resource, Default)]
+struct ClipboardHolder;
+
+/// Theme library and the path typed for importing/exporting theme files
+#[derive(Resource)]
+struct Themes {
+    library: ThemeLibrary,
+    /// Folder holding the user's themes
+    dir: PathBuf,
+    path_input: String,
+    settings: Settings,
+    settings_path: PathBuf,
+    /// Whether the Preferences window is shown
+    preferences_open: bool,
 }
 
-fn update_search(app: &PostItData, search: &mut SearchState) {
-    search.matches.clear();
-    if search.query.is_empty() {
-        return;
+impl FromWorld for Themes {
+    fn from_world(world: &mut World) -> Self {
+        let save_path = &world.resource::<PostItData>().save_path;
+        let dir = save_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("plop-themes");
+        let settings_path = config_path(save_path, SETTINGS_FILE);
+        let old = old_config_path(save_path, SETTINGS_FILE);
+        let settings: Settings = load_json(&settings_path, &old).unwrap_or_default();
+        let mut library = ThemeLibrary::load(&dir);
+        library.app_theme = settings.theme.clone();
+        Self {
+            library,
+            dir,
+            path_input: String::new(),
+            settings,
+            settings_path,
+            preferences_open: false,
+        }
+    }
+}
+
+impl Themes {
+    /// Use the theme called `name`, or the default one, wherever a board
+    /// doesn't pick its own
+    fn set_app_theme(&mut self, name: Option<String>) -> std::io::Result<()> {
+        self.library.app_theme = name.clone();
+        self.settings.theme = name;
+        save_json(&self.settings, &self.settings_path)
     }
-    let q = search.query.to_lowercase();
-    for note in &app.state.board.notes {
-        if note.text.to_lowercase().contains(&q) {
-            search.matches.push(note.id);
+
+    /// Remember `etag` as that of the copy on the server as last synced
+    fn set_remote_etag(&mut self, etag: Option<String>) -> std::io::Result<()> {
+        if let Some(remote) = &mut self.settings.remote {
+            remote.etag = etag;
         }
+        save_json(&self.settings, &self.settings_path)
     }
-    search.current = 0;
 }
 
-fn focus_on_match(app: &mut PostItData, search: &SearchState) {
-    if let Some(&nid) = search.matches.get(search.current) {
-        if let Some(note) = app.state.board.notes.iter().find(|n| n.id == nid) {
-            let center = Pos2::new(
-                note.pos.x + note.size.x / 2.0,
-                note.pos.y + note.size.y / 2.0,
-            );
-            app.state.board.scene_rect =
-                Rect::from_center_size(center, app.state.board.scene_rect.size());
+/// Templates offered for new boards
+#[derive(Resource)]
+struct BoardTemplates {
+    templates: Vec<BoardTemplate>,
+    /// Folder holding the user's templates
+    dir: PathBuf,
+}
+
+impl FromWorld for BoardTemplates {
+    fn from_world(world: &mut World) -> Self {
+        let dir = world
+            .resource::<PostItData>()
+            .save_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(BOARD_TEMPLATES_DIR);
+        Self {
+            templates: board_templates(&dir),
+            dir,
         }
     }
 }
 
-// System to handle plop sound events
-fn play_plop_sound(
-    audio_assets: Res<AudioAssets>,
-    mut commands: Commands,
-    mut events: EventReader<PlayPlopEvent>,
-    mut rng: GlobalEntropy<WyRand>,
-) {
-    for _ in events.read() {
-        // Randomize speed and volume slightly for variety
-        let speed = rng.gen_range(0.9..=1.1);
-        let volume = rng.gen_range(0.8..=1.2);
-        commands.spawn((
-            AudioPlayer::new(audio_assets.plop.clone()),
-            PlaybackSettings::DESPAWN
-                .with_speed(speed)
-                .with_volume(Volume::Linear(volume)),
-        ));
+/// Save files used recently, for the Open Recent menu
+#[derive(Resource)]
+struct Recent {
+    files: RecentFiles,
+    path: PathBuf,
+}
+
+impl FromWorld for Recent {
+    fn from_world(world: &mut World) -> Self {
+        let save_path = &world.resource::<PostItData>().save_path;
+        let path = config_path(save_path, RECENT_FILE);
+        let old = old_config_path(save_path, RECENT_FILE);
+        Self {
+            files: load_json(&path, &old).unwrap_or_default(),
+            path,
+        }
     }
 }
 
-/// Calculate a font size so the text fits inside the note rectangle
-fn fitted_font_size(ctx: &egui::Context, text: &str, max: Vec2, start: f32) -> f32 {
-    let mut size = start;
-    let margin = 8.0;
-    while size > 6.0 {
-        let font_id = egui::FontId::proportional(size);
-        let galley = ctx.fonts(|f| f.layout_no_wrap(text.to_owned(), font_id, Color32::BLACK));
-        let text_size = galley.size();
-        if text_size.x <= max.x - margin && text_size.y <= max.y - margin {
-            break;
+impl Recent {
+    /// Remember `path` as used just now
+    fn add(&mut self, path: &Path) {
+        self.files.add(path);
+        self.save();
+    }
+
+    /// Forget `path`, e.g. because it no longer exists
+    fn remove(&mut self, path: &Path) {
+        self.files.remove(path);
+        self.save();
+    }
+
+    fn save(&self) {
+        if let Err(e) = save_json(&self.files, &self.path) {
+            warn!("Could not save the recent files list: {e}");
         }
-        size -= 1.0;
     }
-    size.max(6.0)
 }
 
-fn highlighted_layout(text: &str, query: &str, font_size: f32) -> egui::text::LayoutJob {
-    use egui::text::{LayoutJob, TextFormat};
-    let mut job = LayoutJob::default();
-    let normal = TextFormat::simple(egui::FontId::proportional(font_size), Color32::BLACK);
-    let mut highlight = normal.clone();
-    highlight.background = Color32::LIGHT_RED;
-    let text_lower = text.to_lowercase();
-    let query_lower = query.to_lowercase();
-    let mut i = 0;
-    while let Some(pos) = text_lower[i..].find(&query_lower) {
-        let start = i + pos;
-        if start > i {
-            job.append(&text[i..start], 0.0, normal.clone());
+/// Where the user left off last time, restored at startup and saved on exit
+#[derive(Resource)]
+struct LastSession {
+    session: Session,
+    path: PathBuf,
+}
+
+impl FromWorld for LastSession {
+    fn from_world(world: &mut World) -> Self {
+        let save_path = &world.resource::<PostItData>().save_path;
+        let path = config_path(save_path, SESSION_FILE);
+        let old = old_config_path(save_path, SESSION_FILE);
+        Self {
+            session: load_json(&path, &old).unwrap_or_default(),
+            path,
         }
-        let end = start + query.len();
-        job.append(&text[start..end], 0.0, highlight.clone());
-        i = end;
     }
-    if i < text.len() {
-        job.append(&text[i..], 0.0, normal);
+}
+
+/// Filter for save files in the file dialogs
+const SAVE_FILE_FILTER: (&str, &[&str]) = ("plop board", &["json"]);
+
+/// Hot-seat session: who is adding notes right now
+#[derive(Resource, Default)]
+struct HotSeat {
+    enabled: bool,
+    /// Index into the board's members
+    current: usize,
+    /// Name typed for the next member
+    new_member: String,
+}
+
+impl HotSeat {
+    /// Member new notes are attributed to, while the session runs
+    fn author(&self, board: &Board) -> Option<Member> {
+        if !self.enabled {
+            return None;
+        }
+        board.members.get(self.current).cloned()
     }
-    job
 }
 
-fn ui_system(
-    mut commands: Commands,
-    mut app: ResMut<PostItData>,
-    mut contexts: EguiContexts,
-    mut ev_plop: EventWriter<PlayPlopEvent>,
-    grid: Res<GridSize>,
-    mut notes: Query<(Entity, &mut NoteData, &mut NoteUi)>,
-    mut search: ResMut<SearchState>,
-) {
-    let ctx = contexts.ctx_mut();
+/// Keys selecting the first nine hot-seat members
+const MEMBER_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
 
-    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-        ui.horizontal(|ui| {
-            // Save/Load controls
-            if ui.button("Save").clicked() {
-                // Sync notes from ECS into the app state before saving
-                for (_, note, _) in notes.iter_mut() {
-                    if let Some(n) = app.state.board.notes.iter_mut().find(|n| n.id == note.id) {
-                        *n = note.clone();
-                    }
-                }
-                app.state.save_to_file(&app.save_path);
-            }
-            if ui.button("Load").clicked() {
-                app.state = AppState::load_from_file(&app.save_path);
-                // Remove existing note entities
-                for (e, _, _) in notes.iter_mut() {
-                    commands.entity(e).despawn();
-                }
-                // Spawn notes from loaded state
-                for note in &app.state.board.notes {
-                    commands.spawn((note.clone(), NoteUi::default()));
-                }
-                update_search(&app, &mut search);
-            }
+/// How often (in seconds) a history snapshot of the board is taken
+const SNAPSHOT_INTERVAL: f64 = 120.0;
 
-            ui.separator();
-            ui.label("Search:");
-            let changed = ui.text_edit_singleline(&mut search.query).changed();
-            if changed {
-                update_search(&app, &mut search);
-                focus_on_match(&mut app, &search);
-            }
-            if ui.button("Prev").clicked() && !search.matches.is_empty() {
-                if search.current == 0 {
-                    search.current = search.matches.len() - 1;
-                } else {
-                    search.current -= 1;
-                }
-                focus_on_match(&mut app, &search);
-            }
-            if ui.button("Next").clicked() && !search.matches.is_empty() {
-                search.current = (search.current + 1) % search.matches.len();
-                focus_on_match(&mut app, &search);
-            }
-        });
-    });
+/// Sharing the active board with other instances on the network
+#[derive(Resource)]
+struct LanSync {
+    window_open: bool,
+    /// Address typed for hosting or joining
+    address: String,
+    /// Shared secret everyone in the session types
+    key: String,
+    session: Option<LanSession>,
+    status: String,
+}
 
-    egui::CentralPanel::default().show(ctx, |ui| {
-        let mut next_id = app.state.next_note_id;
-        let highlight = search.matches.get(search.current).copied();
-        board_ui_system(
-            ui,
-            &mut app.state.board,
-            &mut next_id,
-            &mut notes,
-            &mut commands,
-            &grid,
-            &mut ev_plop,
-            &search.query,
-            highlight,
-        );
-        app.state.next_note_id = next_id;
-    });
+impl Default for LanSync {
+    fn default() -> Self {
+        Self {
+            window_open: false,
+            address: format!("0.0.0.0:{DEFAULT_PORT}"),
+            key: String::new(),
+            session: None,
+            status: String::new(),
+        }
+    }
 }
 
-/// Render a single board: background + draggable notes
-fn board_ui_system(
-    ui: &mut egui::Ui,
-    board: &mut Board,
-    next_note_id: &mut u64,
-    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
-    commands: &mut Commands,
-    grid: &GridSize,
-    ev_plop: &mut EventWriter<PlayPlopEvent>,
-    query: &str,
-    highlight_note: Option<u64>,
-) {
-    // Zoomable + draggable scene
-    let scene = Scene::new()
-        .zoom_range(0.1..=5.0)
-        .max_inner_size(Vec2::splat(5000.0));
-    let mut scene_rect = board.scene_rect;
-    let response = scene
-        .show(ui, &mut scene_rect, |ui| {
-            ui.painter()
-                .rect_filled(ui.max_rect(), 0.0, board.background);
+/// A board being shared
+struct LanSession {
+    connection: SyncConnection,
+    state: SyncState,
+    board_id: u64,
+    /// App time local changes are next sent at
+    next_send: f64,
+}
 
-            // Render existing notes from ECS
-            for (_, mut note, mut ui_state) in notes.iter_mut() {
-                let highlight = highlight_note == Some(note.id);
-                let has_query =
-                    !query.is_empty() && note.text.to_lowercase().contains(&query.to_lowercase());
-                add_note_ui(
-                    ui,
-                    &mut note,
-                    &mut ui_state,
-                    board,
-                    grid.0,
-                    ev_plop,
-                    query,
-                    has_query,
-                    highlight,
-                );
-            }
-        })
-        .response;
-    board.scene_rect = scene_rect;
+/// How often local note changes are sent to the other instances, in seconds
+const LAN_SEND_INTERVAL: f64 = 0.1;
 
-    // If user right-clicks on the board, add new note
-    if response.hovered()
-        && ui
-            .ctx()
-            .input(|i| i.pointer.button_released(egui::PointerButton::Secondary))
-    {
-        let id = *next_note_id;
-        *next_note_id += 1;
-        let pointer_pos = ui
-            .ctx()
-            .pointer_hover_pos()
-            .unwrap_or(Pos2 { x: 0.0, y: 0.0 });
-        let data = NoteData {
-            id,
-            text: "New note".into(),
-            pos: snap_to_grid(pointer_pos, grid.0),
-            size: Vec2 { x: 120.0, y: 80.0 },
-            color: Color32::YELLOW,
-        };
-        commands.spawn((data.clone(), NoteUi::default()));
-        board.notes.push(data);
+/// Board history and the state of the history browser
+#[derive(Resource)]
+struct TimeTravel {
+    history: Timeline,
+    path: PathBuf,
+    /// Whether `history` holds what the file does; nothing is written over
+    /// the file until then, e.g. while it is sealed and locked
+    loaded: bool,
+    /// App time of the next snapshot
+    next_snapshot: f64,
+    open: bool,
+    /// Snapshot of the active board shown in the browser; clamped to the
+    /// latest one when out of range
+    index: usize,
+}
 
-        // Send event to play sound
-        ev_plop.write_default();
+impl FromWorld for TimeTravel {
+    fn from_world(world: &mut World) -> Self {
+        let app = world.resource::<PostItData>();
+        let path = Timeline::path_for(&app.save_path);
+        let history = Timeline::load_from_file(&path, app.password.as_deref());
+        Self {
+            index: usize::MAX,
+            loaded: history.is_ok(),
+            history: history.unwrap_or_default(),
+            path,
+            next_snapshot: 0.0,
+            open: false,
+        }
     }
 }
 
+/// Periodic saving of the app state when it changed
+#[derive(Resource)]
+struct Autosave(Autosaver);
+
+impl FromWorld for Autosave {
+    fn from_world(world: &mut World) -> Self {
+        let mut saver = Autosaver::new(world.resource::<Themes>().settings.autosave_interval);
+        // What was just loaded doesn't need saving
+        if let Ok(json) = world.resource::<PostItData>().state.to_json() {
+            saver.mark_saved(&json);
+        }
+        Self(saver)
+    }
+}
+
+/// Undo/redo of note changes
+#[derive(Resource, Default)]
+struct UndoHistory(UndoStack);
+
+/// Keyboard shortcuts, read from `keys.json` in the config folder
+#[derive(Resource)]
+struct Keys(Keymap);
+
+impl FromWorld for Keys {
+    fn from_world(world: &mut World) -> Self {
+        let save_path = &world.resource::<PostItData>().save_path;
+        let path = config_path(save_path, KEYMAP_FILE);
+        let old = old_config_path(save_path, KEYMAP_FILE);
+        let keymap = match load_json::<Keymap>(&path, &old) {
+            Ok(keymap) => keymap.over_defaults(),
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    // Write the defaults so there is a file to remap keys in
+                    let _ = save_json(&Keymap::default(), &path);
+                } else {
+                    warn!(
+                        "Could not read {}: {e}; using the default keys",
+                        path.display()
+                    );
+                }
+                Keymap::default()
+            }
+        };
+        Self(keymap)
+    }
+}
+
+/// Resources the toolbar and side panels work with
+#[derive(SystemParam)]
+struct Panels<'w> {
+    search: ResMut<'w, SearchState>,
+    drag_pan: ResMut<'w, DragPan>,
+    status: ResMut<'w, StatusMessage>,
+    board_viewport: ResMut<'w, BoardViewport>,
+    themes: ResMut<'w, Themes>,
+    hot_seat: ResMut<'w, HotSeat>,
+    time_travel: ResMut<'w, TimeTravel>,
+    undo: ResMut<'w, UndoHistory>,
+    selected: ResMut<'w, SelectedNotes>,
+    connect: ResMut<'w, ConnectTool>,
+    autosave: ResMut<'w, Autosave>,
+    copied: ResMut<'w, CopiedNotes>,
+    minimap: ResMut<'w, Minimap>,
+    recent: ResMut<'w, Recent>,
+    keys: Res<'w, Keys>,
+    time: Res<'w, Time>,
+}
+
+/// Short feedback shown at the end of the top panel
+#[derive(Resource, Default)]
+struct StatusMessage(String);
+
+/// Connection mode: dragging from a note to another connects them
+#[derive(Resource, Default)]
+struct ConnectTool {
+    enabled: bool,
+    /// Style and label given to new connectors
+    style: ConnectorStyle,
+    label: String,
+}
+
+/// Pen mode: dragging on the empty board draws ink, or erases it
+#[derive(Resource)]
+struct PenTool {
+    enabled: bool,
+    erase: bool,
+    /// Color and width of new strokes
+    color: Color32,
+    width: f32,
+}
+
+impl Default for PenTool {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            erase: false,
+            color: Color32::from_rgb(40, 40, 40),
+            width: 3.0,
+        }
+    }
+}
+
+/// How close to a stroke the eraser has to pass, in screen points
+const ERASER_RADIUS: f32 = 6.0;
+
+/// Color of the guides showing what dragged notes line up with
+const GUIDE_COLOR: Color32 = Color32::from_rgb(230, 60, 160);
+
+/// Color of dot-voting dots
+const VOTE_COLOR: Color32 = Color32::from_rgb(40, 110, 230);
+
+/// Most dots drawn on a note; more are shown as a count
+const MAX_VOTE_DOTS: u32 = 8;
+
+/// Color of timer rings, and of the flashing of timers that ran out
+const TIMER_COLOR: Color32 = Color32::from_rgb(220, 70, 50);
+
+/// Name votes are cast under when no hot-seat member is taking a turn
+const DEFAULT_VOTER: &str = "You";
+
+/// Diameter of the count badge on piles, in board units
+const PILE_BADGE_SIZE: f32 = 22.0;
+
+/// Notes picked by clicking or lassoing, which the Delete key removes
+#[derive(Resource, Default)]
+struct SelectedNotes(Selection);
+
+/// Notes copied with Ctrl+C, pasted with Ctrl+V
+#[derive(Resource, Default)]
+struct CopiedNotes(NoteClipboard);
+
+/// Something done to a note that affects other notes or entities
+enum NoteAction {
+    /// Delete every selected note
+    DeleteSelected,
+    /// Move every selected note into the board's archive
+    ArchiveSelected,
+    Duplicate(u64),
+    /// Add copies of these notes with their top-left corner at `at`
+    Paste {
+        notes: NoteClipboard,
+        at: Pos2,
+    },
+    /// Add pasted text as a note at `at`, or a grid of notes, one per
+    /// paragraph, if `split`
+    PasteText {
+        text: String,
+        at: Pos2,
+        split: bool,
+    },
+    /// Move every selected note by `delta` (board coordinates)
+    Nudge(Vec2),
+    /// Open the edit window of the note
+    Edit(u64),
+    /// Add a template making notes like this one
+    SaveTemplate(u64),
+    /// Add a reference to note `to` to the text of note `from`
+    LinkTo {
+        from: u64,
+        to: u64,
+    },
+    /// Select the note and bring it into view
+    JumpTo(u64),
+    /// Show this image on the note, or none
+    AttachImage {
+        id: u64,
+        image: Option<PathBuf>,
+    },
+    /// Add a note showing the image, centered at `at`
+    ImageNote {
+        image: PathBuf,
+        at: Pos2,
+    },
+    /// Give every selected note this color
+    Recolor(Color32),
+    /// Put every selected note in the board category of this name, or in none
+    Categorize(Option<String>),
+    /// Give every selected note this priority
+    Prioritize(Priority),
+    /// Raise every selected note's priority a level, or lower it if false
+    StepPriority(bool),
+    /// Note `from` started being dragged, taking the selection with it
+    StartMove {
+        from: u64,
+    },
+    /// Note `from` was dragged by `delta` (board coordinates)
+    Move {
+        from: u64,
+        delta: Vec2,
+    },
+    /// The dragged notes were dropped
+    Drop,
+    /// A frame started being dragged, taking these notes with it
+    StartFrameMove {
+        notes: Vec<u64>,
+    },
+    /// The dragged frame moved by this much (board coordinates)
+    MoveFrame(Vec2),
+    /// The dragged frame and its notes were dropped
+    DropFrame {
+        before: Frame,
+        after: Frame,
+    },
+    RemoveFrame(u64),
+    /// A connector was dragged out of note `from` and released
+    Connect {
+        from: u64,
+    },
+    /// Remove the connectors attached to the note
+    Disconnect(u64),
+    /// Put the note on top (or at the bottom) of the stack; only recorded for
+    /// undo when `record` is set
+    Restack {
+        id: u64,
+        to_front: bool,
+        record: bool,
+    },
+    /// Put a dot on the note, or take one back, in dot voting
+    Vote {
+        id: u64,
+        add: bool,
+    },
+}
+
+#[derive(Resource, Default)]
+struct SearchState {
+    query: String,
+    /// `query` as understood, empty while it is invalid
+    parsed: SearchQuery,
+    /// Why `query` is invalid
+    error: Option<String>,
+    matches: Vec<u64>, // note_id
+    current: usize,
+    /// Show the matches as a list that can be put in priority order
+    triage: bool,
+    /// Only show notes carrying a tag
+    tag_filter: TagFilter,
+    /// Search every board rather than the active one only
+    all_boards: bool,
+    /// Matches on the other boards while searching all of them, by board
+    /// index
+    other_boards: Vec<(usize, Vec<u64>)>,
+    /// Show the field replacing what is searched for
+    replacing: bool,
+    /// Text put in place of what is searched for
+    replacement: String,
+    /// The notes "Replace all" would change, while it waits for a yes
+    replace_preview: Option<Vec<NoteData>>,
+}
+
+fn update_search(app: &PostItData, search: &mut SearchState) {
+    search.other_boards.clear();
+    match SearchQuery::parse(&search.query) {
+        Ok(parsed) => {
+            search.matches = parsed.search(app.state.board());
+            if search.all_boards {
+                search.other_boards = parsed.search_boards(&app.state);
+                search.other_boards.retain(|(i, _)| *i != app.state.active);
+            }
+            search.parsed = parsed;
+            search.error = None;
+        }
+        Err(e) => {
+            search.matches.clear();
+            search.parsed = SearchQuery::default();
+            search.error = Some(e);
+        }
+    }
+    if search.triage {
+        sort_by_rank(app.state.board(), &mut search.matches);
+    }
+    search.current = 0;
+}
+
+fn focus_on_match(ctx: &egui::Context, app: &PostItData, search: &SearchState) {
+    if let Some(&nid) = search.matches.get(search.current) {
+        center_on_note(ctx, app.state.board(), nid);
+    }
+}
+
+/// Go to the next or previous match, on to the next board with matches
+/// past the last one when searching all boards
+fn step_match(
+    ctx: &egui::Context,
+    app: &mut PostItData,
+    commands: &mut Commands,
+    notes: &Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    search: &mut SearchState,
+    forward: bool,
+) {
+    let count = search.matches.len();
+    let at_end = if forward {
+        search.current + 1 >= count
+    } else {
+        search.current == 0
+    };
+    if at_end
+        && let Some(index) =
+            next_board_with_matches(&search.other_boards, app.state.active, forward)
+    {
+        show_board(app, commands, notes, search, index);
+        if !forward {
+            search.current = search.matches.len().saturating_sub(1);
+        }
+    } else if count == 0 {
+        return;
+    } else if forward {
+        search.current = (search.current + 1) % count;
+    } else {
+        search.current = search.current.checked_sub(1).unwrap_or(count - 1);
+    }
+    focus_on_match(ctx, app, search);
+}
+
+/// Make the board at `index` the active one, keeping the latest state of
+/// the notes shown until now
+fn show_board(
+    app: &mut PostItData,
+    commands: &mut Commands,
+    notes: &Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    search: &mut SearchState,
+    index: usize,
+) {
+    let board = app.state.board_mut();
+    let positions = board.note_index();
+    for (_, note, _) in notes.iter() {
+        if let Some(&i) = positions.get(&note.id) {
+            board.notes[i] = note.clone();
+        }
+    }
+    app.state.active = index;
+    respawn_notes(commands, app.state.board(), notes.iter().map(|(e, ..)| e));
+    update_search(app, search);
+}
+
+/// Side list of the matches on every board, under the board names;
+/// returns the board index and id of the match clicked
+fn search_results_panel(
+    ctx: &egui::Context,
+    app: &PostItData,
+    search: &SearchState,
+) -> Option<(usize, u64)> {
+    let mut picked = None;
+    let active = app.state.active;
+    let mut groups = search.other_boards.clone();
+    if !search.matches.is_empty() {
+        groups.push((active, search.matches.clone()));
+        groups.sort_by_key(|(index, _)| *index);
+    }
+    egui::SidePanel::right("search_results").show(ctx, |ui| {
+        ui.heading("Matches");
+        if groups.is_empty() {
+            ui.label("No board has matches");
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, ids) in groups {
+                let board = &app.state.boards[index];
+                ui.strong(format!("{} ({})", board.name, ids.len()));
+                for (position, id) in ids.into_iter().enumerate() {
+                    let Some(note) = board.notes.iter().find(|n| n.id == id) else {
+                        continue;
+                    };
+                    let current = index == active && position == search.current;
+                    if ui.selectable_label(current, link_title(note)).clicked() {
+                        picked = Some((index, id));
+                    }
+                }
+            }
+        });
+    });
+    picked
+}
+
+/// Glide the view so the note `id` is in the middle, keeping the zoom
+fn center_on_note(ctx: &egui::Context, board: &Board, id: u64) {
+    if let Some(note) = board.notes.iter().find(|n| n.id == id) {
+        let center = Pos2::new(
+            note.pos.x + note.size.x / 2.0,
+            note.pos.y + note.size.y / 2.0,
+        );
+        let turns = board.view_quarter_turns;
+        let center = rotate_quarter_turns(center, scene_center(), turns);
+        fly_view(
+            ctx,
+            board,
+            Rect::from_center_size(center, board.scene_rect.size()),
+        );
+    }
+}
+
+/// Dropdown picking the tag notes are filtered by, and whether the other
+/// notes are dimmed or hidden
+fn tag_filter_picker(ui: &mut egui::Ui, board: &Board, filter: &mut TagFilter) {
+    let selected = match &filter.tag {
+        Some(tag) => format!("#{tag}"),
+        None => "All tags".to_owned(),
+    };
+    egui::ComboBox::from_id_salt("tag_filter")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut filter.tag, None, "All tags");
+            for tag in board.tags() {
+                let label = format!("#{tag}");
+                ui.selectable_value(&mut filter.tag, Some(tag), label);
+            }
+        });
+    if filter.tag.is_some() {
+        ui.selectable_value(&mut filter.mode, FilterMode::Dim, "Dim");
+        ui.selectable_value(&mut filter.mode, FilterMode::Hide, "Hide");
+    }
+}
+
+/// Play the sounds of board events from the chosen pack, at the chosen
+/// volume
+fn play_sounds(
+    pack: Option<Res<SoundPack>>,
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+    mut commands: Commands,
+    mut events: EventReader<PlaySound>,
+    mut rng: GlobalEntropy<WyRand>,
+) {
+    let settings = &themes.settings.sounds;
+    let chosen = settings.pack.as_deref();
+    let Some(pack) = pack.filter(|pack| pack.name.as_deref() == chosen) else {
+        // In the browser sounds load with the first one played, which
+        // always follows a click; a newly picked pack loads the same way
+        if !events.is_empty() {
+            events.clear();
+            commands.insert_resource(SoundPack::load(&asset_server, chosen));
+        }
+        return;
+    };
+    let gain = settings.gain();
+    for PlaySound(event) in events.read() {
+        let Some(sound) = pack.sounds.get(event).filter(|_| gain > 0.0) else {
+            continue;
+        };
+        // Randomize speed and volume slightly for variety
+        let speed = rng.gen_range(0.9..=1.1);
+        let volume = rng.gen_range(0.8..=1.2) * gain;
+        commands.spawn((
+            AudioPlayer::new(sound.clone()),
+            PlaybackSettings::DESPAWN
+                .with_speed(speed)
+                .with_volume(Volume::Linear(volume)),
+        ));
+    }
+}
+
+fn ui_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut ev_sound: EventWriter<PlaySound>,
+    mut ev_integrity: EventWriter<CheckIntegrity>,
+    mut grid: ResMut<GridSize>,
+    mut notes: Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    mut pen: ResMut<PenTool>,
+    mut lan: ResMut<LanSync>,
+    mut show_grid: ResMut<ShowGrid>,
+    mut presentation: ResMut<Presentation>,
+    mut save_watch: ResMut<SaveWatch>,
+    side_panels: SidePanels,
+    panels: Panels,
+) {
+    let SidePanels {
+        mut due_list,
+        mut stats_panel,
+        mut note_list,
+        mut trash_panel,
+        mut vote_panel,
+    } = side_panels;
+    let Panels {
+        mut search,
+        mut drag_pan,
+        mut status,
+        mut board_viewport,
+        mut themes,
+        mut hot_seat,
+        mut time_travel,
+        mut undo,
+        mut selected,
+        mut connect,
+        mut autosave,
+        mut copied,
+        mut minimap,
+        mut recent,
+        keys,
+        time,
+    } = panels;
+    let ctx = contexts.ctx_mut();
+    let theme = themes
+        .settings
+        .apply_to(themes.library.for_board(app.state.board()));
+    let visuals = if theme.high_contrast {
+        high_contrast_visuals()
+    } else if theme.dark_ui {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    };
+    let text_styles = readable_text_styles(themes.settings.fonts.min_readable);
+    let style = ctx.style();
+    if style.visuals != visuals || style.text_styles != text_styles {
+        ctx.style_mut(|s| {
+            s.visuals = visuals;
+            s.text_styles = text_styles;
+        });
+    }
+    // Lets screen readers walk the panels and the notes
+    ctx.enable_accesskit();
+
+    // Text fields keep their own undo
+    let mut undo_requested = None;
+    if !ctx.wants_keyboard_input() {
+        if ctx.input_mut(|i| keys.0.take(&mut i.events, Action::Redo)) {
+            undo_requested = Some(true);
+        } else if ctx.input_mut(|i| keys.0.take(&mut i.events, Action::Undo)) {
+            undo_requested = Some(false);
+        }
+    }
+    let find = ctx.input_mut(|i| keys.0.take(&mut i.events, Action::Find));
+
+    // Presenting hides everything but the board
+    let presenting = presentation.active;
+    egui::TopBottomPanel::top("top_panel").show_animated(ctx, !presenting, |ui| {
+        ui.horizontal(|ui| {
+            // Save/Load controls
+            let mut save_to = None;
+            let mut load_from = None;
+            let save_locked = app.is_locked(&app.save_path);
+            if ui
+                .add_enabled(!save_locked, egui::Button::new("Save"))
+                .on_hover_text(app.save_path.display().to_string())
+                .clicked()
+            {
+                save_to = Some(app.save_path.clone());
+            }
+            if ui.button("Save As…").clicked() {
+                save_to = rfd::FileDialog::new()
+                    .add_filter(SAVE_FILE_FILTER.0, SAVE_FILE_FILTER.1)
+                    .set_file_name("board.json")
+                    .save_file();
+            }
+            if ui.button("Load").clicked() {
+                load_from = Some(app.save_path.clone());
+            }
+            if ui.button("Open…").clicked() {
+                load_from = rfd::FileDialog::new()
+                    .add_filter(SAVE_FILE_FILTER.0, SAVE_FILE_FILTER.1)
+                    .pick_file();
+            }
+            ui.add_enabled_ui(!recent.files.paths.is_empty(), |ui| {
+                ui.menu_button("Recent", |ui| {
+                    for path in &recent.files.paths {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        if ui
+                            .button(name)
+                            .on_hover_text(path.display().to_string())
+                            .clicked()
+                        {
+                            load_from = Some(path.clone());
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+            if save_to.as_ref() == Some(&app.save_path)
+                && (save_watch.pending || save_watch.watch.changed())
+            {
+                // Left to the choice save_watch_system asks for
+                save_watch.pending = true;
+                save_to = None;
+                status.0 = "The save file changed outside plop; choose what to keep".into();
+            }
+            if let Some(path) = save_to.filter(|path| !app.is_locked(path)) {
+                // Sync notes from ECS into the app state before saving
+                for (_, note, _) in notes.iter_mut() {
+                    if let Some(n) = app
+                        .state
+                        .board_mut()
+                        .notes
+                        .iter_mut()
+                        .find(|n| n.id == note.id)
+                    {
+                        *n = note.clone();
+                    }
+                }
+                let written = app
+                    .state
+                    .to_json()
+                    .map_err(std::io::Error::other)
+                    .and_then(|json| {
+                        let saved = app.state.to_saved(app.password.as_deref())?;
+                        write_with_backups(&path, &saved, SAVE_BACKUPS)?;
+                        remember_merge_base(&path, &saved);
+                        Ok(json)
+                    });
+                match written {
+                    Ok(json) => {
+                        autosave.0.mark_saved(&json);
+                        recent.add(&path);
+                        save_watch.reset(&path);
+                        app.save_path = path;
+                    }
+                    Err(e) => status.0 = format!("Save failed: {e}"),
+                }
+            }
+            let mut opened = None;
+            if let Some(path) = load_from {
+                match AppState::try_load_from_file(&path) {
+                    Ok(state) => opened = Some((path, state, None)),
+                    Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                        status.0 = format!("{} is locked with a password", path.display());
+                        app.locked = Some(path);
+                    }
+                    // Keep the notes on screen rather than replacing them with nothing
+                    Err(e) => {
+                        if e.kind() == std::io::ErrorKind::NotFound {
+                            recent.remove(&path);
+                        }
+                        status.0 = format!("Load failed: {e}");
+                    }
+                }
+            }
+            if let Some(path) = app.locked.clone() {
+                let can_cancel = path != app.save_path;
+                match unlock_window(ui.ctx(), &path, can_cancel) {
+                    Some(Unlock::Opened(state, password)) => {
+                        opened = Some((path, state, Some(password)))
+                    }
+                    Some(Unlock::Failed(e)) => status.0 = format!("Could not unlock: {e}"),
+                    Some(Unlock::Cancelled) => app.locked = None,
+                    None => {}
+                }
+            }
+            if let Some((path, state, password)) = opened {
+                app.state = state;
+                app.password = password;
+                recent.add(&path);
+                if app.is_locked(&path) {
+                    app.locked = None;
+                }
+                save_watch.reset(&path);
+                app.save_path = path;
+                let warnings = sanitize_state(&mut app.state);
+                if !warnings.is_empty() {
+                    status.0 = format!("Cleaned up {} note(s) while loading", warnings.len());
+                }
+                respawn_notes(
+                    &mut commands,
+                    app.state.board(),
+                    notes.iter().map(|(e, ..)| e),
+                );
+                undo.0 = UndoStack::default();
+                if let Ok(json) = app.state.to_json() {
+                    autosave.0.mark_saved(&json);
+                }
+                ev_integrity.write_default();
+                update_search(&app, &mut search);
+            }
+            ui.menu_button(
+                if app.password.is_some() {
+                    "🔒"
+                } else {
+                    "🔓"
+                },
+                |ui| {
+                    let old_password = app.password.clone();
+                    if password_menu(ui, &mut app.password) {
+                        if app.is_locked(&app.save_path) || save_watch.pending {
+                            // Written again in the new form once it can be
+                            autosave.0.mark_saved("");
+                        } else {
+                            let board = app.state.board_mut();
+                            let index = board.note_index();
+                            for (_, note, _) in notes.iter() {
+                                if let Some(&i) = index.get(&note.id) {
+                                    board.notes[i] = note.clone();
+                                }
+                            }
+                            match reseal_save_files(&app, old_password.as_deref()) {
+                                Ok(json) => {
+                                    autosave.0.mark_saved(&json);
+                                    save_watch.watch.acknowledge();
+                                }
+                                Err(e) => {
+                                    autosave.0.mark_saved("");
+                                    status.0 = format!("Sealing the save file failed: {e}");
+                                }
+                            }
+                        }
+                    }
+                },
+            )
+            .response
+            .on_hover_text("Password the save file is sealed with");
+            if ui
+                .button("Export all")
+                .on_hover_text(
+                    "Export every board of this and the recent files to PNG and Markdown",
+                )
+                .clicked()
+            {
+                status.0 = export_all_boards(&app.save_path, &recent.files);
+            }
+            ui.menu_button("Export as PNG", |ui| {
+                for scale in PNG_SCALES {
+                    if ui.button(format!("{scale}×")).clicked() {
+                        status.0 = export_active_board(&app, ExportFormat::Png, scale);
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Render the whole board to an image");
+            if ui
+                .button("Export as SVG")
+                .on_hover_text("Save the board as a drawing vector tools can edit")
+                .clicked()
+            {
+                status.0 = export_active_board(&app, ExportFormat::Svg, 1.0);
+            }
+            if ui
+                .button("Export as HTML")
+                .on_hover_text("Save the board as a web page anyone can pan and zoom")
+                .clicked()
+            {
+                status.0 = export_active_board(&app, ExportFormat::Html, 1.0);
+            }
+            if ui
+                .button("Export as CSV")
+                .on_hover_text("Save the notes as a table for spreadsheets, a row per note")
+                .clicked()
+            {
+                status.0 = export_active_board(&app, ExportFormat::Csv, 1.0);
+            }
+            ui.menu_button("Selection", |ui| {
+                if ui
+                    .add_enabled(!selected.0.is_empty(), egui::Button::new("Export…"))
+                    .on_hover_text("Save just the selected notes, from their top-left corner")
+                    .clicked()
+                {
+                    ui.close_menu();
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("Notes to import", &[SELECTION_EXTENSION])
+                        .add_filter("Markdown", &["md"])
+                        .add_filter("PNG image", &["png"])
+                        .set_file_name(format!("selection.{SELECTION_EXTENSION}"))
+                        .save_file();
+                    if let Some(path) = picked {
+                        let board = app.state.board();
+                        status.0 = match export_selection(board, selected.0.ids(), &path, 1.0) {
+                            Ok(()) => format!("Exported {}", path.display()),
+                            Err(e) => format!("Export failed: {e}"),
+                        };
+                    }
+                }
+                if ui
+                    .button("Import…")
+                    .on_hover_text("Add notes exported from a selection to this board")
+                    .clicked()
+                {
+                    ui.close_menu();
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("Exported notes", &[SELECTION_EXTENSION])
+                        .pick_file();
+                    if let Some(path) = picked {
+                        status.0 = match read_selection(&path) {
+                            Ok(exported) => {
+                                let board = app.state.board();
+                                let at = import_origin(board, board.grid(grid.0));
+                                let mut next_id = app.state.next_note_id;
+                                let (notes, connectors) = exported.paste(board, &mut next_id, at);
+                                app.state.next_note_id = next_id;
+                                selected.0.clear();
+                                selected.0.extend(notes.iter().map(|n| n.id));
+                                let count = notes.len();
+                                with_warning(
+                                    format!("Imported {count} note(s)"),
+                                    add_imported(
+                                        &mut app,
+                                        &mut commands,
+                                        &mut undo.0,
+                                        notes,
+                                        vec![],
+                                        connectors,
+                                    ),
+                                )
+                            }
+                            Err(e) => format!("Import failed: {e}"),
+                        };
+                    }
+                }
+            });
+            ui.menu_button("Markdown", |ui| {
+                ui.label("Export outline");
+                for grouping in OutlineGrouping::ALL {
+                    if ui.button(grouping.name()).clicked() {
+                        status.0 = export_board_markdown(&app, grouping);
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                let folder = app.state.board().vault.clone();
+                if ui
+                    .button("Sync notes folder…")
+                    .on_hover_text(
+                        "Keep the notes as Markdown files in a folder, e.g. an Obsidian vault, \
+                         and bring edits made there back",
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                        app.state.board_mut().vault = Some(dir.clone());
+                        status.0 = sync_notes_folder(
+                            &mut app,
+                            &mut commands,
+                            &mut notes,
+                            &mut undo.0,
+                            &dir,
+                            grid.0,
+                        );
+                    }
+                }
+                if let Some(dir) = folder
+                    && ui
+                        .button("Sync again")
+                        .on_hover_text(dir.display().to_string())
+                        .clicked()
+                {
+                    ui.close_menu();
+                    status.0 = sync_notes_folder(
+                        &mut app,
+                        &mut commands,
+                        &mut notes,
+                        &mut undo.0,
+                        &dir,
+                        grid.0,
+                    );
+                }
+                ui.separator();
+                if ui.button("Import list…").clicked() {
+                    ui.close_menu();
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("Markdown", &["md", "markdown", "txt"])
+                        .pick_file();
+                    if let Some(path) = picked {
+                        status.0 = match std::fs::read_to_string(&path) {
+                            Ok(markdown) => {
+                                let mut next_id = app.state.next_note_id;
+                                let imported = markdown_to_notes(
+                                    &markdown,
+                                    import_origin(
+                                        app.state.board(),
+                                        app.state.board().grid(grid.0),
+                                    ),
+                                    theme.note_size,
+                                    app.state.board().new_note_color(&theme),
+                                    &mut next_id,
+                                );
+                                app.state.next_note_id = next_id;
+                                let count = imported.len();
+                                with_warning(
+                                    format!("Imported {count} note(s)"),
+                                    add_imported(
+                                        &mut app,
+                                        &mut commands,
+                                        &mut undo.0,
+                                        imported,
+                                        vec![],
+                                        vec![],
+                                    ),
+                                )
+                            }
+                            Err(e) => format!("Import failed: {e}"),
+                        };
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Export the board as an outline or turn a list into notes");
+            if ui
+                .button("Trello")
+                .on_hover_text("Import the lists and cards of a Trello board export")
+                .clicked()
+                && let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Trello export", &["json"])
+                    .pick_file()
+            {
+                let imported = std::fs::read_to_string(&path).map_err(|e| e.to_string());
+                let board = app.state.board();
+                let mut next_id = app.state.next_note_id;
+                let imported = imported.and_then(|json| {
+                    trello_to_notes(
+                        &json,
+                        import_origin(board, board.grid(grid.0)),
+                        theme.note_size,
+                        board.new_note_color(&theme),
+                        &mut next_id,
+                        board.next_frame_id(),
+                    )
+                    .map_err(|e| e.to_string())
+                });
+                status.0 = match imported {
+                    Ok((notes, frames)) => {
+                        app.state.next_note_id = next_id;
+                        let message = format!(
+                            "Imported {} card(s) in {} list(s)",
+                            notes.len(),
+                            frames.len()
+                        );
+                        with_warning(
+                            message,
+                            add_imported(
+                                &mut app,
+                                &mut commands,
+                                &mut undo.0,
+                                notes,
+                                frames,
+                                vec![],
+                            ),
+                        )
+                    }
+                    Err(e) => format!("Trello import failed: {e}"),
+                };
+            }
+            if ui
+                .button("CSV")
+                .on_hover_text("Import a table with a \"text\" column, a row per note")
+                .clicked()
+                && let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .pick_file()
+            {
+                let imported = std::fs::read_to_string(&path).map_err(|e| e.to_string());
+                let board = app.state.board();
+                let mut next_id = app.state.next_note_id;
+                let imported = imported.and_then(|csv| {
+                    csv_to_notes(
+                        &csv,
+                        import_origin(board, board.grid(grid.0)),
+                        theme.note_size,
+                        board.new_note_color(&theme),
+                        &mut next_id,
+                    )
+                });
+                status.0 = match imported {
+                    Ok(notes) => {
+                        app.state.next_note_id = next_id;
+                        let message = format!("Imported {} note(s)", notes.len());
+                        with_warning(
+                            message,
+                            add_imported(
+                                &mut app,
+                                &mut commands,
+                                &mut undo.0,
+                                notes,
+                                vec![],
+                                vec![],
+                            ),
+                        )
+                    }
+                    Err(e) => format!("CSV import failed: {e}"),
+                };
+            }
+            if ui
+                .button("Mind map")
+                .on_hover_text("Import an OPML outline or a FreeMind mind map")
+                .clicked()
+                && let Some(path) = rfd::FileDialog::new()
+                    .add_filter("OPML or FreeMind", &MIND_MAP_EXTENSIONS)
+                    .pick_file()
+            {
+                let imported = std::fs::read_to_string(&path).map_err(|e| e.to_string());
+                let board = app.state.board();
+                let mut next_id = app.state.next_note_id;
+                let imported = imported.and_then(|xml| {
+                    mind_map_to_notes(
+                        &xml,
+                        import_origin(board, board.grid(grid.0)),
+                        theme.note_size,
+                        board.new_note_color(&theme),
+                        &mut next_id,
+                    )
+                });
+                status.0 = match imported {
+                    Ok((notes, connectors)) => {
+                        app.state.next_note_id = next_id;
+                        let message = format!("Imported {} topic(s)", notes.len());
+                        with_warning(
+                            message,
+                            add_imported(
+                                &mut app,
+                                &mut commands,
+                                &mut undo.0,
+                                notes,
+                                vec![],
+                                connectors,
+                            ),
+                        )
+                    }
+                    Err(e) => format!("Mind map import failed: {e}"),
+                };
+            }
+            if ui
+                .button("History")
+                .on_hover_text("Browse and restore earlier states of the board")
+                .clicked()
+            {
+                time_travel.open = true;
+                time_travel.index = usize::MAX;
+            }
+
+            ui.separator();
+            if ui
+                .add_enabled(undo.0.can_undo(), egui::Button::new("⟲"))
+                .on_hover_text(format!("Undo ({})", keys.0.label(Action::Undo)))
+                .clicked()
+            {
+                undo_requested = Some(false);
+            }
+            if ui
+                .add_enabled(undo.0.can_redo(), egui::Button::new("⟳"))
+                .on_hover_text(format!("Redo ({})", keys.0.label(Action::Redo)))
+                .clicked()
+            {
+                undo_requested = Some(true);
+            }
+
+            ui.separator();
+            ui.label("Search:");
+            let search_box = ui
+                .text_edit_singleline(&mut search.query)
+                .on_hover_text(format!(
+                    "Find ({})\nNarrow down with color:yellow, tag:urgent, prio:high or /regex/",
+                    keys.0.label(Action::Find)
+                ));
+            if find {
+                search_box.request_focus();
+            }
+            if search_box.changed() {
+                update_search(&app, &mut search);
+                focus_on_match(ctx, &app, &search);
+            }
+            if let Some(error) = &search.error {
+                ui.colored_label(Color32::RED, "⚠ Invalid")
+                    .on_hover_text(error);
+            } else if !search.parsed.is_empty() {
+                let elsewhere: usize = search.other_boards.iter().map(|(_, ids)| ids.len()).sum();
+                let here = match search.matches.len() {
+                    0 => "No matches".to_owned(),
+                    count => format!("{} of {count}", search.current + 1),
+                };
+                ui.label(if elsewhere > 0 {
+                    format!("{here}, {elsewhere} on other boards")
+                } else {
+                    here
+                });
+            }
+            if ui.button("Prev").clicked() {
+                step_match(ctx, &mut app, &mut commands, &notes, &mut search, false);
+            }
+            if ui.button("Next").clicked() {
+                step_match(ctx, &mut app, &mut commands, &notes, &mut search, true);
+            }
+            ui.toggle_value(&mut search.replacing, "Replace")
+                .on_hover_text("Replace what is searched for in the matching notes");
+            if search.replacing {
+                ui.add(
+                    egui::TextEdit::singleline(&mut search.replacement)
+                        .hint_text("with…")
+                        .desired_width(100.0),
+                );
+                let current = search.matches.get(search.current).copied();
+                if ui
+                    .add_enabled(current.is_some(), egui::Button::new("Replace"))
+                    .on_hover_text("In the current match, then go to the next")
+                    .clicked()
+                    && let Some(id) = current
+                {
+                    replace_in_notes(&mut app, &mut notes, &mut undo, &mut search, &[id]);
+                    focus_on_match(ctx, &app, &search);
+                }
+                if ui
+                    .add_enabled(!search.matches.is_empty(), egui::Button::new("Replace all"))
+                    .on_hover_text("In every match on this board, after a look at the changes")
+                    .clicked()
+                {
+                    search.replace_preview = Some(replace_preview(
+                        app.state.board(),
+                        &search.matches,
+                        &search.parsed,
+                        &search.replacement,
+                    ));
+                }
+            }
+            if ui
+                .toggle_value(&mut search.all_boards, "All boards")
+                .on_hover_text("Search every board, listing the matches by board")
+                .changed()
+            {
+                update_search(&app, &mut search);
+            }
+            if ui
+                .toggle_value(&mut search.triage, "Triage")
+                .on_hover_text("Put the matches in priority order")
+                .changed()
+            {
+                update_search(&app, &mut search);
+            }
+            ui.toggle_value(&mut due_list.open, "Due")
+                .on_hover_text("List the notes by due date");
+            ui.toggle_value(&mut note_list.open, "Notes")
+                .on_hover_text("List the notes by when they changed or by name");
+            ui.toggle_value(&mut stats_panel.open, "Stats")
+                .on_hover_text("Show figures about the notes of the board");
+            ui.toggle_value(&mut trash_panel.open, "🗑")
+                .on_hover_text("Restore deleted and archived notes");
+            ui.toggle_value(&mut vote_panel.open, "Vote")
+                .on_hover_text("Dot voting: click notes to put your dots on them");
+            tag_filter_picker(ui, app.state.board(), &mut search.tag_filter);
+
+            ui.separator();
+            ui.label("View:");
+            if ui.button("↺").on_hover_text("Rotate left").clicked() {
+                rotate_view(app.state.board_mut(), 3);
+            }
+            if ui.button("↻").on_hover_text("Rotate right").clicked() {
+                rotate_view(app.state.board_mut(), 1);
+            }
+            let turns = app.state.board().view_quarter_turns;
+            if ui
+                .add_enabled(
+                    turns != 0,
+                    egui::Button::new(format!("N {}°", turns as u32 * 90)),
+                )
+                .on_hover_text("Reset rotation")
+                .clicked()
+            {
+                rotate_view(app.state.board_mut(), inverse_quarter_turns(turns));
+            }
+            let board = app.state.board();
+            let viewport = board_viewport.0;
+            let view = view_target(ctx, board);
+            if ui.button("−").on_hover_text("Zoom out").clicked() {
+                fly_view(ctx, board, zoom_scene_rect(viewport, view, 1.0 / ZOOM_STEP));
+            }
+            let zoom = view_zoom(viewport, view);
+            if ui
+                .button(format!("{:.0}%", zoom * 100.0))
+                .on_hover_text("Zoom to 100%")
+                .clicked()
+            {
+                fly_view(ctx, board, zoom_scene_rect(viewport, view, 1.0 / zoom));
+            }
+            if ui.button("+").on_hover_text("Zoom in").clicked() {
+                fly_view(ctx, board, zoom_scene_rect(viewport, view, ZOOM_STEP));
+            }
+            let rotated = |r: Rect| {
+                let r = rotate_rect_quarter_turns(r, scene_center(), turns);
+                zoom_scene_rect(viewport, r, 1.0)
+            };
+            if ui
+                .add_enabled(!board.notes.is_empty(), egui::Button::new("Fit"))
+                .on_hover_text("Zoom to fit all notes")
+                .clicked()
+            {
+                fly_view(ctx, board, rotated(content_view(board, view)));
+            }
+            if ui
+                .add_enabled(!selected.0.is_empty(), egui::Button::new("Fit selection"))
+                .on_hover_text("Zoom to the selected notes")
+                .clicked()
+                && let Some(target) = notes_view(board, selected.0.ids())
+            {
+                fly_view(ctx, board, rotated(target));
+            }
+            ui.toggle_value(&mut minimap.visible, "Minimap")
+                .on_hover_text("Show all notes in a corner; click it to jump there");
+            ui.toggle_value(&mut show_grid.visible, "Grid")
+                .on_hover_text("Show the snap grid on dotted and lined boards");
+            let board = app.state.board_mut();
+            let size = board.grid_size.as_mut().unwrap_or(&mut grid.0);
+            let spacing = ui
+                .add(
+                    egui::DragValue::new(size)
+                        .range(GRID_SIZE_RANGE)
+                        .suffix(" px"),
+                )
+                .on_hover_text("Grid spacing notes snap to; hold Alt to place them freely");
+            // The spacing of boards without their own is a preference
+            if (spacing.drag_stopped() || spacing.lost_focus())
+                && board.grid_size.is_none()
+                && themes.settings.grid_size != grid.0
+            {
+                themes.settings.grid_size = grid.0;
+                if let Err(e) = save_json(&themes.settings, &themes.settings_path) {
+                    status.0 = format!("Could not save the settings: {e}");
+                }
+            }
+            let mut own_grid = board.grid_size.is_some();
+            if ui
+                .checkbox(&mut own_grid, "This board")
+                .on_hover_text("Keep this grid spacing for this board only")
+                .changed()
+            {
+                board.grid_size = own_grid.then_some(grid.0);
+            }
+            let style = &mut app.state.board_mut().background_style;
+            egui::ComboBox::from_id_salt("background_style")
+                .selected_text(style.name())
+                .show_ui(ui, |ui| {
+                    for option in BackgroundStyle::ALL {
+                        ui.selectable_value(style, option, option.name());
+                    }
+                });
+            ui.checkbox(&mut app.state.board_mut().tilt_new_notes, "Tilt")
+                .on_hover_text("Stick new notes on slightly askew");
+            if lanes_menu(ui, app.state.board_mut()) {
+                // Renamed or removed lanes relabel their notes
+                let board = app.state.board();
+                let index = board.note_index();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if let Some(&i) = index.get(&note.id) {
+                        note.lane = board.notes[i].lane.clone();
+                    }
+                }
+            }
+            ui.menu_button("Present", |ui| {
+                if ui.button("▶ Start").clicked() {
+                    let now = ui.input(|i| i.time);
+                    if presentation.start(app.state.board(), now) {
+                        selected.0.clear();
+                    } else {
+                        status.0 = "There are no notes to present".into();
+                    }
+                    ui.close_menu();
+                }
+                ui.separator();
+                let board = app.state.board_mut();
+                ui.label(if board.presentation.is_empty() {
+                    "Notes are shown row by row".to_owned()
+                } else {
+                    format!("{} notes are shown in your order", board.presentation.len())
+                });
+                if ui
+                    .add_enabled(
+                        !selected.0.is_empty(),
+                        egui::Button::new("Add selected notes to the order"),
+                    )
+                    .clicked()
+                {
+                    for &id in selected.0.ids() {
+                        if !board.presentation.contains(&id) {
+                            board.presentation.push(id);
+                        }
+                    }
+                }
+                if !board.presentation.is_empty() && ui.button("Show row by row").clicked() {
+                    board.presentation.clear();
+                }
+            });
+
+            ui.separator();
+            ui.toggle_value(&mut connect.enabled, "Connect")
+                .on_hover_text("Drag from one note to another to connect them");
+            if connect.enabled {
+                egui::ComboBox::from_id_salt("connector_style")
+                    .selected_text(connect.style.name())
+                    .show_ui(ui, |ui| {
+                        for style in ConnectorStyle::ALL {
+                            ui.selectable_value(&mut connect.style, style, style.name());
+                        }
+                    });
+                ui.add(
+                    egui::TextEdit::singleline(&mut connect.label)
+                        .hint_text("label")
+                        .desired_width(80.0),
+                );
+            }
+
+            ui.separator();
+            if ui
+                .button("Frame")
+                .on_hover_text("Frame the selected notes, or add an empty frame in the middle")
+                .clicked()
+            {
+                let board = app.state.board();
+                let ids: Vec<u64> = board
+                    .notes
+                    .iter()
+                    .filter(|n| selected.0.contains(n.id))
+                    .map(|n| n.id)
+                    .collect();
+                let frame = board.frame_around(&ids, "Frame").unwrap_or_else(|| {
+                    let center = rotate_quarter_turns(
+                        board.scene_rect.center(),
+                        scene_center(),
+                        inverse_quarter_turns(board.view_quarter_turns),
+                    );
+                    Frame {
+                        id: board.next_frame_id(),
+                        title: "Frame".into(),
+                        rect: Rect::from_center_size(center, DEFAULT_FRAME_SIZE),
+                        color: DEFAULT_FRAME_COLOR,
+                    }
+                });
+                undo.0.execute(&mut app.state, Command::AddFrame { frame });
+            }
+            ui.menu_button("Tidy", |ui| {
+                for grouping in TidyGrouping::ALL {
+                    if ui.button(grouping.name()).clicked() {
+                        let now = ui.input(|i| i.time);
+                        let grid_step = app.state.board().grid(grid.0);
+                        let board = app.state.board();
+                        if let Some(command) =
+                            tidy_notes(ui.ctx(), board, &selected.0, grouping, grid_step, now)
+                        {
+                            undo.0.record(board.id, command);
+                        }
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Arrange the selected notes, or all of them, in a grid");
+            let mut script = None;
+            ui.menu_button("Scripts", |ui| {
+                let dir = scripts_dir(&app.save_path);
+                let scripts = list_scripts(&dir);
+                if scripts.is_empty() {
+                    ui.label(format!(
+                        "Put .{SCRIPT_EXTENSION} scripts in {}",
+                        dir.display()
+                    ));
+                }
+                for path in scripts {
+                    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                    if ui.button(name.as_ref()).clicked() {
+                        script = Some(path.clone());
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                if ui.button("Open scripts folder").clicked() {
+                    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|()| open_path(&dir)) {
+                        status.0 = format!("Could not open {}: {e}", dir.display());
+                    }
+                    ui.close_menu();
+                }
+            })
+            .response
+            .on_hover_text("Run a script changing the notes of the board");
+            if let Some(path) = script {
+                let (change, message) = run_script_file(&path, &mut app.state);
+                status.0 = message;
+                if let Some(mut change) = change {
+                    let board = app.state.board_mut();
+                    stamp_notes(board, &mut notes, &mut change, now_secs());
+                    respawn_notes(&mut commands, board, notes.iter().map(|(e, ..)| e));
+                    undo.0.record(board.id, change);
+                    ev_integrity.write_default();
+                    update_search(&app, &mut search);
+                }
+            }
+            ui.toggle_value(&mut pen.enabled, "Pen")
+                .on_hover_text("Drag on the empty board to draw under the notes");
+            if pen.enabled {
+                ui.toggle_value(&mut pen.erase, "Eraser")
+                    .on_hover_text("Drag over ink to remove it");
+                ui.color_edit_button_srgba(&mut pen.color);
+                ui.add(egui::Slider::new(&mut pen.width, 1.0..=20.0).text("width"));
+            }
+
+            ui.separator();
+            ui.toggle_value(&mut lan.window_open, "LAN")
+                .on_hover_text("Edit the board together with plop on other computers");
+            ui.toggle_value(&mut hot_seat.enabled, "Hot-seat")
+                .on_hover_text("Attribute new notes to workshop members; keys 1-9 switch");
+
+            ui.separator();
+            ui.toggle_value(&mut themes.preferences_open, "Preferences")
+                .on_hover_text("Theme, new notes, grid, fonts, sounds and autosave");
+            theme_switcher(ui, app.state.board_mut(), &theme, &mut themes, &mut status);
+            palette_menu(ui, app.state.board_mut(), &theme);
+            if categories_menu(ui, app.state.board_mut()) {
+                // Renamed, recolored or removed categories change their notes
+                let board = app.state.board();
+                let index = board.note_index();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if let Some(&i) = index.get(&note.id) {
+                        note.category = board.notes[i].category.clone();
+                        note.color = board.notes[i].color;
+                    }
+                }
+            }
+            templates_menu(ui, &mut app.state.templates);
+
+            if !status.0.is_empty() {
+                ui.separator();
+                ui.label(&status.0);
+            }
+        });
+    });
+
+    if themes.preferences_open {
+        preferences_window(
+            ctx,
+            &mut themes,
+            &mut grid,
+            &mut autosave.0,
+            time.elapsed_secs_f64(),
+            &mut status,
+        );
+    }
+
+    if let Some(redo) = undo_requested {
+        let done = if redo {
+            undo.0
+                .redo(&mut app.state)
+                .map(|label| format!("Redo {label}"))
+        } else {
+            undo.0
+                .undo(&mut app.state)
+                .map(|label| format!("Undo {label}"))
+        };
+        if let Some(done) = done {
+            status.0 = done;
+            ctx.data_mut(|d| d.remove::<Tidying>(tidy_id()));
+            respawn_notes(
+                &mut commands,
+                app.state.board(),
+                notes.iter().map(|(e, ..)| e),
+            );
+            update_search(&app, &mut search);
+        }
+    }
+
+    if presenting {
+        presentation_step(ctx, app.state.board_mut(), &mut presentation);
+    }
+    tidy_step(ctx, app.state.board_mut(), &mut notes);
+    camera_step(ctx, app.state.board_mut());
+    if search.triage && !presenting {
+        triage_panel(ctx, &mut app, &mut search, &mut notes, &mut undo.0);
+    }
+    if search.all_boards && !search.parsed.is_empty() && !presenting {
+        if let Some((index, id)) = search_results_panel(ctx, &app, &search) {
+            if index != app.state.active {
+                show_board(&mut app, &mut commands, &notes, &mut search, index);
+            }
+            if let Some(position) = search.matches.iter().position(|&m| m == id) {
+                search.current = position;
+            }
+            focus_on_match(ctx, &app, &search);
+        }
+    }
+    if due_list.open && !presenting {
+        due_panel(ctx, app.state.board_mut());
+    }
+    if note_list.open && !presenting {
+        note_list_panel(ctx, app.state.board_mut(), &mut note_list.order);
+    }
+    if stats_panel.open && !presenting {
+        stats_panel_ui(ctx, app.state.board());
+    }
+    if trash_panel.open && !presenting {
+        let archived = trash_panel.archived;
+        let days = themes.settings.trash_days;
+        let picked = trash_panel_ui(ctx, app.state.board(), &mut trash_panel.archived, days);
+        let board = app.state.board_mut();
+        match picked {
+            Some(BinAction::Restore(id)) => {
+                if let Some(note) = board.restore_note(id, archived) {
+                    commands.spawn((note, NoteUi::default()));
+                    update_search(&app, &mut search);
+                }
+            }
+            Some(BinAction::Discard(id)) if archived => {
+                if let Some(index) = board.archived.iter().position(|n| n.id == id) {
+                    let note = board.archived.remove(index);
+                    board.trash_note(note, now_secs());
+                }
+            }
+            Some(BinAction::Discard(id)) => {
+                board.untrash(id);
+            }
+            Some(BinAction::Empty) => board.trash.clear(),
+            None => {}
+        }
+    }
+    if vote_panel.open && !presenting {
+        let voter = hot_seat
+            .author(app.state.board())
+            .map_or_else(|| DEFAULT_VOTER.to_owned(), |m| m.name);
+        let picked = vote_panel_ui(ctx, app.state.board(), &mut vote_panel.ballot, &voter);
+        match picked {
+            Some(VoteAction::Show(id)) => {
+                selected.0.select_only(id);
+                center_on_note(ctx, app.state.board(), id);
+            }
+            Some(VoteAction::NewRound) => {
+                vote_panel.ballot.reset();
+                for (_, mut note, _) in notes.iter_mut() {
+                    note.votes = 0;
+                }
+                for note in &mut app.state.board_mut().notes {
+                    note.votes = 0;
+                }
+            }
+            None => {}
+        }
+    }
+    if let Some(preview) = search.replace_preview.take() {
+        match replace_all_window(ctx, app.state.board(), &preview) {
+            Some(true) => {
+                let ids: Vec<u64> = preview.iter().map(|n| n.id).collect();
+                replace_in_notes(&mut app, &mut notes, &mut undo, &mut search, &ids);
+            }
+            Some(false) => {}
+            None => search.replace_preview = Some(preview),
+        }
+    }
+    if selected.0.ids().len() > 1 && !presenting {
+        let palette = app.state.board().note_palette(&theme);
+        if let Some(edit) = bulk_edit_window(ctx, app.state.board(), &selected.0, &palette) {
+            let board = app.state.board_mut();
+            if let Some(mut change) = board.bulk_edit(selected.0.ids(), &edit) {
+                let index = board.note_index();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if let Some(&i) = index.get(&note.id) {
+                        *note = board.notes[i].clone();
+                    }
+                }
+                stamp_notes(board, &mut notes, &mut change, now_secs());
+                undo.0.record(board.id, change);
+            }
+        }
+    }
+    if hot_seat.enabled && !presenting {
+        hot_seat_panel(ctx, app.state.board_mut(), &mut hot_seat);
+    }
+    let author = hot_seat.author(app.state.board());
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        board_viewport.0 = ui.max_rect();
+        let mut next_id = app.state.next_note_id;
+        let highlight = search.matches.get(search.current).copied();
+        let mut changes = Vec::new();
+        let state = &mut app.state;
+        let active = state.active;
+        board_ui_system(
+            ui,
+            &mut state.boards[active],
+            &mut next_id,
+            &mut notes,
+            &mut commands,
+            &grid,
+            show_grid.visible,
+            &mut ev_sound,
+            &search.parsed,
+            &search.tag_filter,
+            highlight,
+            &mut drag_pan,
+            &theme,
+            &themes.settings.fonts,
+            themes.settings.tossing,
+            author,
+            vote_panel.open.then_some(&mut vote_panel.ballot),
+            &mut selected.0,
+            &connect,
+            &pen,
+            &mut copied.0,
+            &keys.0,
+            &mut state.templates,
+            &mut changes,
+        );
+        app.state.next_note_id = next_id;
+        let board_id = app.state.board().id;
+        let now = now_secs();
+        for mut change in changes {
+            stamp_notes(app.state.board_mut(), &mut notes, &mut change, now);
+            undo.0.record(board_id, change);
+        }
+    });
+
+    // The drag overview takes the same corner while it is shown
+    if minimap.visible && !drag_pan.engaged && !presenting {
+        show_minimap(ctx, app.state.board_mut());
+    }
+    if !app.state.board().categories.is_empty() {
+        show_category_legend(ctx, app.state.board());
+    }
+    if presentation.active {
+        presentation_overlay(ctx, &presentation);
+    }
+}
+
+/// Notes gliding to their tidy places
+#[derive(Clone)]
+struct Tidying {
+    /// Each note's id, start and end
+    moves: Vec<(u64, Pos2, Pos2)>,
+    start: f64,
+}
+
+fn tidy_id() -> egui::Id {
+    egui::Id::new("tidying")
+}
+
+/// View of a board gliding to a note picked elsewhere
+#[derive(Clone, Copy)]
+struct CameraFlight {
+    board: u64,
+    from: Rect,
+    to: Rect,
+    start: f64,
+}
+
+fn camera_flight_id() -> egui::Id {
+    egui::Id::new("camera_flight")
+}
+
+/// Glide the view of `board` to `target` rather than jump there
+fn fly_view(ctx: &egui::Context, board: &Board, target: Rect) {
+    let flight = CameraFlight {
+        board: board.id,
+        from: board.scene_rect,
+        to: target,
+        start: ctx.input(|i| i.time),
+    };
+    ctx.data_mut(|d| d.insert_temp(camera_flight_id(), flight));
+}
+
+/// Where the view of `board` is, or is gliding to
+fn view_target(ctx: &egui::Context, board: &Board) -> Rect {
+    ctx.data(|d| d.get_temp::<CameraFlight>(camera_flight_id()))
+        .filter(|flight| flight.board == board.id)
+        .map_or(board.scene_rect, |flight| flight.to)
+}
+
+/// Stop a glide of the view, e.g. when it is dragged meanwhile
+fn stop_flight(ctx: &egui::Context) {
+    ctx.data_mut(|d| d.remove::<CameraFlight>(camera_flight_id()));
+}
+
+/// Move the view of `board` along while it glides; switching boards ends
+/// the glide
+fn camera_step(ctx: &egui::Context, board: &mut Board) {
+    let Some(flight) = ctx.data(|d| d.get_temp::<CameraFlight>(camera_flight_id())) else {
+        return;
+    };
+    if flight.board != board.id {
+        stop_flight(ctx);
+        return;
+    }
+    let t = ((ctx.input(|i| i.time) - flight.start) / FOCUS_SECONDS) as f32;
+    board.scene_rect = fly(flight.from, flight.to, t);
+    if t >= 1.0 {
+        stop_flight(ctx);
+    } else {
+        ctx.request_repaint();
+    }
+}
+
+/// Start moving the selected notes of `board`, or all of them, to tidy
+/// places on the grid; locked notes stay put. Returns the move for undo.
+fn tidy_notes(
+    ctx: &egui::Context,
+    board: &Board,
+    selection: &Selection,
+    grouping: TidyGrouping,
+    grid_step: f32,
+    now: f64,
+) -> Option<Command> {
+    let tidied: Vec<&NoteData> = board
+        .notes
+        .iter()
+        .filter(|n| !n.locked && (selection.is_empty() || selection.contains(n.id)))
+        .collect();
+    let mut moves = Vec::new();
+    let mut moved = Vec::new();
+    for (id, to) in tidy_positions(&tidied, grouping, grid_step) {
+        let Some(note) = tidied.iter().find(|n| n.id == id) else {
+            continue;
+        };
+        if note.pos != to {
+            moves.push((id, note.pos, to));
+            moved.push(Command::Update {
+                before: (*note).clone(),
+                after: NoteData {
+                    pos: to,
+                    ..(*note).clone()
+                },
+            });
+        }
+    }
+    ctx.data_mut(|d| d.insert_temp(tidy_id(), Tidying { moves, start: now }));
+    Command::batch(moved)
+}
+
+/// Move tidied notes along while they glide to their places
+fn tidy_step(
+    ctx: &egui::Context,
+    board: &mut Board,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+) {
+    let Some(tidying) = ctx.data(|d| d.get_temp::<Tidying>(tidy_id())) else {
+        return;
+    };
+    let t = ((ctx.input(|i| i.time) - tidying.start) / TIDY_SECONDS).clamp(0.0, 1.0) as f32;
+    let eased = t * t * (3.0 - 2.0 * t);
+    let index = board.note_index();
+    for (_, mut note, _) in notes.iter_mut() {
+        if let Some(&(_, from, to)) = tidying.moves.iter().find(|m| m.0 == note.id) {
+            note.pos = from.lerp(to, eased);
+            if let Some(&i) = index.get(&note.id) {
+                board.notes[i].pos = note.pos;
+            }
+        }
+    }
+    if t >= 1.0 {
+        ctx.data_mut(|d| d.remove::<Tidying>(tidy_id()));
+    } else {
+        ctx.request_repaint();
+    }
+}
+
+/// While presenting, step through the notes with the arrow keys (Esc
+/// stops) and fly the view to the current one
+fn presentation_step(ctx: &egui::Context, board: &mut Board, presentation: &mut Presentation) {
+    use egui::{Key, Modifiers};
+    let now = ctx.input(|i| i.time);
+    let (next, previous, stop) = ctx.input_mut(|i| {
+        let mut pressed = |keys: &[Key]| keys.iter().any(|&k| i.consume_key(Modifiers::NONE, k));
+        (
+            pressed(&[Key::ArrowRight, Key::Space, Key::PageDown]),
+            pressed(&[Key::ArrowLeft, Key::PageUp]),
+            pressed(&[Key::Escape]),
+        )
+    });
+    if stop {
+        presentation.active = false;
+        board.scene_rect = presentation.return_to;
+        return;
+    }
+    let last = presentation.order.len().saturating_sub(1);
+    let index = if next {
+        (presentation.index + 1).min(last)
+    } else if previous {
+        presentation.index.saturating_sub(1)
+    } else {
+        presentation.index
+    };
+    if index != presentation.index {
+        presentation.index = index;
+        presentation.flight = Some((board.scene_rect, now));
+    }
+    // A note deleted meanwhile leaves the view where it is
+    let Some(note) = presentation
+        .order
+        .get(index)
+        .and_then(|&id| board.notes.iter().find(|n| n.id == id))
+    else {
+        return;
+    };
+    let target = slide_rect(rotate_rect_quarter_turns(
+        Rect::from_min_size(note.pos, note.size),
+        scene_center(),
+        board.view_quarter_turns,
+    ));
+    board.scene_rect = match presentation.flight {
+        Some((from, start)) => {
+            let t = ((now - start) / FLIGHT_SECONDS) as f32;
+            if t >= 1.0 {
+                presentation.flight = None;
+            }
+            fly(from, target, t)
+        }
+        None => target,
+    };
+}
+
+/// Position in the presentation and how to move on, along the bottom edge
+fn presentation_overlay(ctx: &egui::Context, presentation: &Presentation) {
+    egui::Area::new(egui::Id::new("presentation"))
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -12.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} / {}   ← → to move, Esc to stop",
+                    presentation.index + 1,
+                    presentation.order.len()
+                ))
+                .color(Color32::from_white_alpha(200))
+                .background_color(Color32::from_black_alpha(120)),
+            );
+        });
+}
+
+/// The system-wide quick capture shortcut, registered with the OS from the
+/// main thread
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct GlobalShortcut {
+    manager: Option<GlobalHotKeyManager>,
+    /// Setting the shortcut was last registered for
+    setting: Option<String>,
+    hotkey: Option<HotKey>,
+}
+
+/// Window jotting down a note into the inbox, open after the quick capture
+/// shortcut was pressed
+#[derive(Resource, Default)]
+struct QuickCapture {
+    open: bool,
+    text: String,
+    /// Hide the window again once done, as it was in the background
+    hide_after: bool,
+}
+
+/// Pages can't have system-wide shortcuts
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+struct GlobalShortcut;
+
+#[cfg(target_arch = "wasm32")]
+fn global_shortcut_system() {}
+
+/// Keep the quick capture shortcut registered as the settings say, and pop
+/// up quick capture when it is pressed, bringing the window back if it was
+/// hidden or minimized
+#[cfg(not(target_arch = "wasm32"))]
+fn global_shortcut_system(
+    mut shortcut: NonSendMut<GlobalShortcut>,
+    themes: Res<Themes>,
+    mut capture: ResMut<QuickCapture>,
+    mut status: ResMut<StatusMessage>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let wanted = &themes.settings.quick_capture_shortcut;
+    if shortcut.setting != *wanted {
+        let GlobalShortcut {
+            manager,
+            setting,
+            hotkey,
+        } = &mut *shortcut;
+        setting.clone_from(wanted);
+        if let (Some(manager), Some(old)) = (manager.as_ref(), hotkey.take()) {
+            let _ = manager.unregister(old);
+        }
+        if let Some(keys) = wanted {
+            match register_shortcut(manager, keys) {
+                Ok(registered) => *hotkey = Some(registered),
+                Err(e) => status.0 = format!("Could not set the quick capture shortcut: {e}"),
+            }
+        }
+    }
+    let Some(hotkey) = shortcut.hotkey else {
+        return;
+    };
+    while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+        if event.id != hotkey.id() || event.state != HotKeyState::Pressed {
+            continue;
+        }
+        for mut window in &mut windows {
+            if !capture.open {
+                capture.hide_after = !window.visible;
+            }
+            window.visible = true;
+            window.set_minimized(false);
+            window.focused = true;
+        }
+        capture.open = true;
+    }
+}
+
+/// Register the shortcut written as `keys`, making the manager on first use
+#[cfg(not(target_arch = "wasm32"))]
+fn register_shortcut(
+    manager: &mut Option<GlobalHotKeyManager>,
+    keys: &str,
+) -> Result<HotKey, String> {
+    let hotkey: HotKey = keys.parse().map_err(|e| format!("{e}"))?;
+    if manager.is_none() {
+        *manager = Some(GlobalHotKeyManager::new().map_err(|e| e.to_string())?);
+    }
+    let manager = manager.as_ref().expect("made above");
+    manager.register(hotkey).map_err(|e| e.to_string())?;
+    Ok(hotkey)
+}
+
+/// The quick capture window: Enter puts the text as a new note into the
+/// inbox of the active board, Shift+Enter starts a new line
+fn quick_capture_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut capture: ResMut<QuickCapture>,
+    mut undo: ResMut<UndoHistory>,
+    mut status: ResMut<StatusMessage>,
+    themes: Res<Themes>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !capture.open {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    let mut done = None;
+    egui::Window::new("Quick capture")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(ctx, |ui| {
+            // Taken before the text field can type it
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)) {
+                done = Some(true);
+            }
+            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                done = Some(false);
+            }
+            ui.add(
+                egui::TextEdit::multiline(&mut capture.text)
+                    .hint_text("Jot it down…")
+                    .desired_rows(3)
+                    .desired_width(240.0),
+            )
+            .request_focus();
+            ui.horizontal(|ui| {
+                if ui.button(format!("Add to {INBOX_TITLE}")).clicked() {
+                    done = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    done = Some(false);
+                }
+            });
+        });
+    let Some(add) = done else {
+        return;
+    };
+    let text = sanitize_text(capture.text.trim(), MAX_NOTE_CHARS).text;
+    if add && !text.is_empty() {
+        let theme = themes
+            .settings
+            .apply_to(themes.library.for_board(app.state.board()));
+        let id = app.state.next_note_id;
+        app.state.next_note_id += 1;
+        let now = now_secs();
+        let board = app.state.board_mut();
+        let note = NoteData {
+            id,
+            text,
+            size: theme.note_size,
+            color: board.new_note_color(&theme),
+            created_at: Some(now),
+            modified_at: Some(now),
+            ..Default::default()
+        };
+        let change = board.capture(note);
+        if let Some(note) = board.notes.last() {
+            commands.spawn((note.clone(), NoteUi::default()));
+        }
+        undo.0.record(board.id, change);
+        status.0 = format!("Captured into {INBOX_TITLE} on \"{}\"", board.name);
+    }
+    if capture.hide_after {
+        for mut window in &mut windows {
+            window.visible = false;
+        }
+    }
+    *capture = QuickCapture::default();
+}
+
+/// Hide the window when it is closed while running in the background, so
+/// the quick capture shortcut keeps working; close it otherwise, which
+/// quits
+fn close_window_system(
+    mut commands: Commands,
+    mut closed: EventReader<WindowCloseRequested>,
+    themes: Res<Themes>,
+    mut windows: Query<&mut Window>,
+) {
+    let settings = &themes.settings;
+    let background = settings.run_in_background && settings.quick_capture_shortcut.is_some();
+    for event in closed.read() {
+        match windows.get_mut(event.window) {
+            Ok(mut window) if background => window.visible = false,
+            _ => commands.entity(event.window).despawn(),
+        }
+    }
+}
+
+/// Put the window in full screen while presenting
+fn presentation_window_system(
+    presentation: Res<Presentation>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let mode = if presentation.active {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    };
+    for mut window in &mut windows {
+        if window.mode != mode {
+            window.mode = mode;
+        }
+    }
+}
+
+/// Every few seconds, check whether the save file was changed outside plop,
+/// and if so offer to reload it, merge it in or keep the version here
+fn save_watch_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut save_watch: ResMut<SaveWatch>,
+    mut autosave: ResMut<Autosave>,
+    mut undo: ResMut<UndoHistory>,
+    mut status: ResMut<StatusMessage>,
+    mut search: ResMut<SearchState>,
+    mut ev_integrity: EventWriter<CheckIntegrity>,
+    notes: Query<(Entity, &NoteData)>,
+    time: Res<Time>,
+) {
+    let elapsed = time.elapsed_secs_f64();
+    if !save_watch.pending && elapsed >= save_watch.next_check {
+        save_watch.next_check = elapsed + SAVE_WATCH_INTERVAL;
+        save_watch.pending = save_watch.watch.path() == app.save_path && save_watch.watch.changed();
+    }
+    if !save_watch.pending {
+        return;
+    }
+
+    let (mut reload, mut merge, mut keep) = (false, false, false);
+    egui::Window::new("Save file changed")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{} was changed outside plop, e.g. by a sync service.",
+                app.save_path.display()
+            ));
+            ui.label("Nothing is saved over it until you choose.");
+            ui.horizontal(|ui| {
+                reload = ui
+                    .button("Reload")
+                    .on_hover_text("Show the changed file, dropping changes made here")
+                    .clicked();
+                merge = ui
+                    .button("Merge")
+                    .on_hover_text("Add what changed there to the boards here")
+                    .clicked();
+                keep = ui
+                    .button("Keep mine")
+                    .on_hover_text("Save the boards here over the changed file")
+                    .clicked();
+            });
+        });
+
+    if keep {
+        save_watch.watch.acknowledge();
+        save_watch.pending = false;
+        // Written over the changed file at the next autosave
+        autosave.0.mark_saved("");
+        return;
+    }
+    if !reload && !merge {
+        return;
+    }
+    let app = &mut *app;
+    let theirs = match AppState::try_load_sealed_file(&app.save_path, app.password.as_deref()) {
+        Ok(state) => state,
+        Err(e) => {
+            status.0 = format!("Could not read the changed file: {e}");
+            return;
+        }
+    };
+    save_watch.watch.acknowledge();
+    save_watch.pending = false;
+    let base = read_base(&app.save_path, app.password.as_deref());
+    // Both sides go on from what is in the file now
+    if let Ok(saved) = theirs.to_saved(app.password.as_deref()) {
+        remember_merge_base(&app.save_path, &saved);
+    }
+    if reload {
+        app.state = theirs;
+        if let Ok(json) = app.state.to_json() {
+            autosave.0.mark_saved(&json);
+        }
+        status.0 = "Reloaded the changed save file".into();
+    } else {
+        sync_board_from_entities(app.state.board_mut(), &notes);
+        let report = merge_states(base.as_ref(), &mut app.state, &theirs);
+        autosave.0.mark_saved("");
+        status.0 = format!("Merged the changed save file: {}", report.summary());
+    }
+    sanitize_state(&mut app.state);
+    respawn_notes(
+        &mut commands,
+        app.state.board(),
+        notes.iter().map(|(e, _)| e),
+    );
+    undo.0 = UndoStack::default();
+    ev_integrity.write_default();
+    update_search(app, &mut search);
+}
+
+/// Every minute, push the save file to its copy on the server if the boards
+/// changed here, or else pull the copy if it changed there; when both
+/// changed, offer to take the copy, merge it in or keep the boards here
+fn remote_sync_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut remote: ResMut<RemoteSync>,
+    mut themes: ResMut<Themes>,
+    mut undo: ResMut<UndoHistory>,
+    mut status: ResMut<StatusMessage>,
+    mut search: ResMut<SearchState>,
+    mut ev_integrity: EventWriter<CheckIntegrity>,
+    notes: Query<(Entity, &NoteData)>,
+    time: Res<Time>,
+) {
+    let Some(config) = themes.settings.remote.clone() else {
+        *remote = RemoteSync::default();
+        return;
+    };
+    let elapsed = time.elapsed_secs_f64();
+    let settings = RemoteConfig {
+        etag: None,
+        ..config.clone()
+    };
+    if remote.config.as_ref() != Some(&settings) {
+        remote.config = Some(settings);
+        remote.synced = None;
+        remote.next_check = elapsed + REMOTE_SETTLE;
+    }
+
+    let app = &mut *app;
+    let mut theirs = None;
+    if let Some(reply) = remote.job.as_ref().and_then(RemoteJob::poll) {
+        remote.job = None;
+        match reply {
+            RemoteReply::Pushed(Ok(Pushed::Written { etag })) => {
+                remote.synced = remote.pushing.take();
+                if let Err(e) = themes.set_remote_etag(etag) {
+                    status.0 = format!("Could not save the settings: {e}");
+                } else {
+                    status.0 = format!("Synced at {}", format_local_datetime(now_secs()));
+                }
+            }
+            // Changed on the server since; see what changed there
+            RemoteReply::Pushed(Ok(Pushed::Conflict)) => {
+                remote.job = Some(RemoteJob::pull(config.clone()));
+            }
+            RemoteReply::Pulled(Ok(Pulled::Unchanged)) => {}
+            // Nothing to pull; push at the next check
+            RemoteReply::Pulled(Ok(Pulled::Missing)) => {
+                remote.synced = None;
+                if let Err(e) = themes.set_remote_etag(None) {
+                    status.0 = format!("Could not save the settings: {e}");
+                }
+            }
+            RemoteReply::Pulled(Ok(Pulled::Changed { body, etag })) => {
+                match AppState::from_saved(body, app.password.as_deref()) {
+                    Ok(state) => theirs = Some((state, etag)),
+                    Err(e) => status.0 = format!("Could not read the synced copy: {e}"),
+                }
+            }
+            RemoteReply::Pushed(Err(e)) | RemoteReply::Pulled(Err(e)) => {
+                remote.pushing = None;
+                status.0 = format!("Syncing failed: {e}");
+            }
+        }
+    }
+    // The copy to show or merge in place of the boards here
+    let mut taken = None;
+    if let Some(copy) = theirs {
+        sync_board_from_entities(app.state.board_mut(), &notes);
+        let ours = app.state.to_json().ok();
+        if ours == remote.synced || ours == copy.0.to_json().ok() {
+            // Nothing to lose here, so take the copy as it is
+            taken = Some((copy, false));
+        } else {
+            remote.conflict = Some(copy);
+        }
+    }
+
+    if remote.conflict.is_some() {
+        let (mut take, mut merge, mut keep) = (false, false, false);
+        egui::Window::new("Boards changed on the server")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.label("The synced copy changed elsewhere while the boards here changed too.");
+                ui.horizontal(|ui| {
+                    take = ui
+                        .button("Use the server's")
+                        .on_hover_text("Show the synced copy, dropping changes made here")
+                        .clicked();
+                    merge = ui
+                        .button("Merge")
+                        .on_hover_text("Add what changed there to the boards here")
+                        .clicked();
+                    keep = ui
+                        .button("Keep mine")
+                        .on_hover_text("Push the boards here over the synced copy")
+                        .clicked();
+                });
+            });
+        if keep {
+            // Pushing over the copy just pulled is no longer a conflict
+            let etag = remote.conflict.take().and_then(|(_, etag)| etag);
+            if let Err(e) = themes.set_remote_etag(etag) {
+                status.0 = format!("Could not save the settings: {e}");
+            }
+            remote.synced = None;
+            remote.next_check = elapsed;
+        } else if (take || merge)
+            && let Some(copy) = remote.conflict.take()
+        {
+            taken = Some((copy, merge));
+            // Push what was merged
+            remote.next_check = elapsed;
+        }
+    }
+    if let Some(((theirs, etag), merge)) = taken {
+        if merge {
+            sync_board_from_entities(app.state.board_mut(), &notes);
+            // Both sides started from the copy as last synced
+            let base = remote
+                .synced
+                .take()
+                .and_then(|json| AppState::from_saved(json.into_bytes(), None).ok());
+            let report = merge_states(base.as_ref(), &mut app.state, &theirs);
+            status.0 = format!("Merged the synced copy: {}", report.summary());
+        } else {
+            app.state = theirs;
+            remote.synced = app.state.to_json().ok();
+            status.0 = "Pulled the boards from the server".into();
+        }
+        if let Err(e) = themes.set_remote_etag(etag) {
+            status.0 = format!("Could not save the settings: {e}");
+        }
+        sanitize_state(&mut app.state);
+        respawn_notes(
+            &mut commands,
+            app.state.board(),
+            notes.iter().map(|(e, _)| e),
+        );
+        undo.0 = UndoStack::default();
+        ev_integrity.write_default();
+        update_search(app, &mut search);
+        return;
+    }
+    if remote.conflict.is_some() {
+        return;
+    }
+
+    if remote.job.is_some() || elapsed < remote.next_check || app.is_locked(&app.save_path) {
+        return;
+    }
+    remote.next_check = elapsed + SYNC_INTERVAL;
+    sync_board_from_entities(app.state.board_mut(), &notes);
+    let Ok(json) = app.state.to_json() else {
+        return;
+    };
+    if remote.synced.as_ref() == Some(&json) {
+        remote.job = Some(RemoteJob::pull(config));
+        return;
+    }
+    match app.state.to_saved(app.password.as_deref()) {
+        Ok(saved) => {
+            remote.job = Some(RemoteJob::push(config, saved.into_bytes()));
+            remote.pushing = Some(json);
+        }
+        Err(e) => status.0 = format!("Syncing failed: {e}"),
+    }
+}
+
+/// Ring the timers of notes that ran out, on every board
+fn note_timer_system(
+    mut app: ResMut<PostItData>,
+    mut notes: Query<&mut NoteData>,
+    mut status: ResMut<StatusMessage>,
+    mut ev_sound: EventWriter<PlaySound>,
+) {
+    let now = timers::now();
+    let mut rung = Vec::new();
+    let mut rung_here = Vec::new();
+    for mut note in &mut notes {
+        // Only running timers are touched, so other notes aren't marked changed
+        let Some(mut timer) = note.timer.filter(NoteTimer::is_running) else {
+            continue;
+        };
+        if timer.tick(now) {
+            note.timer = Some(timer);
+            rung.push(link_title(&note));
+            rung_here.push((note.id, timer));
+        }
+    }
+    let active = app.state.active;
+    for (i, board) in app.state.boards.iter_mut().enumerate() {
+        if i == active {
+            let index = board.note_index();
+            for &(id, timer) in &rung_here {
+                if let Some(&at) = index.get(&id) {
+                    board.notes[at].timer = Some(timer);
+                }
+            }
+            continue;
+        }
+        for note in &mut board.notes {
+            if let Some(timer) = &mut note.timer
+                && timer.tick(now)
+            {
+                rung.push(format!("{} on {}", link_title(note), board.name));
+            }
+        }
+    }
+    for title in rung {
+        ev_sound.write(PlaySound(SoundEvent::TimerDone));
+        status.0 = format!("Time's up: {title}");
+    }
+}
+
+/// Every few minutes, offer to archive notes whose expiry date has passed,
+/// and empty the trash of notes deleted long enough ago
+fn expiry_cleanup_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut cleanup: ResMut<ExpiryCleanup>,
+    mut status: ResMut<StatusMessage>,
+    notes: Query<(Entity, &NoteData)>,
+    themes: Res<Themes>,
+    time: Res<Time>,
+) {
+    let elapsed = time.elapsed_secs_f64();
+    if elapsed >= cleanup.next_check {
+        cleanup.next_check = elapsed + EXPIRY_CHECK_INTERVAL;
+        let now = now_secs();
+        cleanup.pending = expired_notes(app.state.board(), now);
+        // Notes long in the trash go for good
+        for board in &mut app.state.boards {
+            board.purge_trash(now, themes.settings.trash_days);
+        }
+    }
+    if cleanup.pending.is_empty() {
+        return;
+    }
+
+    let mut archive = false;
+    let mut later = false;
+    egui::Window::new("Expired notes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{} note(s) have expired. Archive them to keep the board tidy?",
+                cleanup.pending.len()
+            ));
+            for note in app
+                .state
+                .board()
+                .notes
+                .iter()
+                .filter(|n| cleanup.pending.contains(&n.id))
+            {
+                let first_line = note.text.lines().next().unwrap_or_default();
+                let date = note.expires_at.map(format_date).unwrap_or_default();
+                ui.label(format!("⌛ {first_line} ({date})"));
+            }
+            ui.horizontal(|ui| {
+                archive = ui.button("Archive").clicked();
+                later = ui.button("Later").clicked();
+            });
+        });
+
+    if archive {
+        // Keep the latest ECS state of the notes being archived
+        for (entity, note) in notes.iter() {
+            if !cleanup.pending.contains(&note.id) {
+                continue;
+            }
+            if let Some(n) = app
+                .state
+                .board_mut()
+                .notes
+                .iter_mut()
+                .find(|n| n.id == note.id)
+            {
+                *n = note.clone();
+            }
+            commands.entity(entity).despawn();
+        }
+        let count = archive_notes(app.state.board_mut(), &cleanup.pending);
+        status.0 = format!("Archived {count} expired note(s)");
+        cleanup.pending.clear();
+    } else if later {
+        cleanup.pending.clear();
+        cleanup.next_check = elapsed + EXPIRY_SNOOZE;
+    }
+}
+
+/// Side list of the search matches; dragging them into order ranks the notes
+fn triage_panel(
+    ctx: &egui::Context,
+    app: &mut PostItData,
+    search: &mut SearchState,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    undo: &mut UndoStack,
+) {
+    egui::SidePanel::right("triage").show(ctx, |ui| {
+        ui.heading("Triage");
+        if search.matches.is_empty() {
+            ui.label("Search for the notes to rank");
+            return;
+        }
+        ui.label("Drag or use the arrows to order by priority");
+
+        let mut moved = None;
+        let mut focus = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, id) in search.matches.iter().enumerate() {
+                let Some(note) = app.state.board().notes.iter().find(|n| n.id == *id) else {
+                    continue;
+                };
+                let title = note.text.lines().next().unwrap_or_default();
+                let row = ui
+                    .horizontal(|ui| {
+                        ui.dnd_drag_source(egui::Id::new(("triage", *id)), index, |ui| {
+                            ui.label("☰");
+                        });
+                        if ui.small_button("⏶").clicked() && index > 0 {
+                            moved = Some((index, index - 1));
+                        }
+                        if ui.small_button("⏷").clicked() {
+                            moved = Some((index, index + 1));
+                        }
+                        let label = format!("{}. {title}", index + 1);
+                        if ui
+                            .selectable_label(index == search.current, label)
+                            .clicked()
+                        {
+                            focus = Some(index);
+                        }
+                    })
+                    .response;
+                if let Some(from) = row.dnd_release_payload::<usize>() {
+                    moved = Some((*from, index));
+                }
+            }
+        });
+        let before = app.state.board().notes.clone();
+        if ui.button("Clear ranks").clicked() {
+            for note in app.state.board_mut().notes.iter_mut() {
+                if search.matches.contains(&note.id) {
+                    note.rank = None;
+                }
+            }
+        }
+        if let Some((from, to)) = moved {
+            move_item(&mut search.matches, from, to);
+            search.current = to.min(search.matches.len() - 1);
+            assign_ranks(app.state.board_mut(), &search.matches);
+        }
+        let change = Command::diff(&before, &app.state.board().notes);
+        if !change.is_noop() {
+            sync_ranks(app, notes);
+            undo.record(app.state.board().id, change);
+        }
+        if let Some(index) = focus {
+            search.current = index;
+            focus_on_match(ctx, app, search);
+        }
+    });
+}
+
+/// Button opening a calendar for `due`, in local time, with the time of day
+/// below it; returns whether `due` was changed
+fn due_picker(ui: &mut egui::Ui, due: &mut Option<u64>) -> bool {
+    let mut changed = false;
+    ui.menu_button("📅", |ui| {
+        let (mut y, mut m, mut d, mut h, mut min) = local_parts(due.unwrap_or_else(now_secs));
+        let mut picked = false;
+        ui.horizontal(|ui| {
+            if ui.small_button("◀").on_hover_text("Month before").clicked() {
+                (y, m) = if m == 1 { (y - 1, 12) } else { (y, m - 1) };
+                picked = true;
+            }
+            ui.label(format!("{y:04}-{m:02}"));
+            if ui.small_button("▶").on_hover_text("Month after").clicked() {
+                (y, m) = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+                picked = true;
+            }
+        });
+        egui::Grid::new("due_calendar").show(ui, |ui| {
+            for name in ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"] {
+                ui.label(egui::RichText::new(name).weak());
+            }
+            ui.end_row();
+            let first = first_weekday(y, m);
+            for _ in 0..first {
+                ui.label("");
+            }
+            for day in 1..=days_in_month(y, m) {
+                if ui.selectable_label(day == d, day.to_string()).clicked() {
+                    d = day;
+                    picked = true;
+                }
+                if (first + day) % 7 == 0 {
+                    ui.end_row();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Time");
+            picked |= ui.add(egui::DragValue::new(&mut h).range(0..=23)).changed();
+            ui.label(":");
+            picked |= ui
+                .add(egui::DragValue::new(&mut min).range(0..=59))
+                .changed();
+        });
+        if picked && let Some(at) = from_local_parts(y, m, d, h, min) {
+            *due = Some(at);
+            changed = true;
+        }
+    })
+    .response
+    .on_hover_text("Pick the due date");
+    changed
+}
+
+/// Side list of the notes with a due date, the earliest first
+fn due_panel(ctx: &egui::Context, board: &mut Board) {
+    let now = now_secs();
+    let mut focus = None;
+    egui::SidePanel::right("due").show(ctx, |ui| {
+        ui.heading("Due");
+        let notes = board.notes_by_due();
+        if notes.is_empty() {
+            ui.label("No note has a due date");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for note in notes {
+                let when = note.due.map(format_local_datetime).unwrap_or_default();
+                let mut text = egui::RichText::new(format!("{when}  {}", link_title(note)));
+                if let Some(status) = due_status(note, now) {
+                    text = text.color(due_color(status));
+                }
+                if ui.selectable_label(false, text).clicked() {
+                    focus = Some(note.id);
+                }
+            }
+        });
+    });
+    if let Some(id) = focus {
+        center_on_note(ctx, board, id);
+    }
+}
+
+/// Side list of every note in `order`; clicking one centers the view on it
+fn note_list_panel(ctx: &egui::Context, board: &mut Board, order: &mut NoteOrder) {
+    let mut focus = None;
+    egui::SidePanel::right("note_list").show(ctx, |ui| {
+        ui.heading("Notes");
+        egui::ComboBox::from_id_salt("note_order")
+            .selected_text(order.name())
+            .show_ui(ui, |ui| {
+                for choice in NoteOrder::ALL {
+                    ui.selectable_value(order, choice, choice.name());
+                }
+            });
+        let notes = board.notes_in_order(*order);
+        if notes.is_empty() {
+            ui.label("The board has no notes");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for note in notes {
+                let when = match order {
+                    NoteOrder::Modified => note.modified_at,
+                    NoteOrder::Created => note.created_at,
+                    NoteOrder::Alphabetical => None,
+                    NoteOrder::Priority => note.modified_at,
+                };
+                let mut label = match when {
+                    Some(at) => format!("{}  {}", format_local_datetime(at), link_title(note)),
+                    None => link_title(note),
+                };
+                if *order == NoteOrder::Priority
+                    && let Some(badge) = note.priority.badge()
+                {
+                    label = format!("{badge} {label}");
+                }
+                if ui.selectable_label(false, label).clicked() {
+                    focus = Some(note.id);
+                }
+            }
+        });
+    });
+    if let Some(id) = focus {
+        center_on_note(ctx, board, id);
+    }
+}
+
+/// What is typed into the bulk edit window, kept between frames
+#[derive(Clone, Default)]
+struct BulkEditForm {
+    tags: String,
+    text: String,
+    size: Option<Vec2>,
+}
+
+/// Replace what is searched for in the notes `ids` of the active board, as
+/// one undo step, and search again
+fn replace_in_notes(
+    app: &mut PostItData,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    undo: &mut UndoHistory,
+    search: &mut SearchState,
+    ids: &[u64],
+) {
+    let board = app.state.board_mut();
+    if let Some(mut change) = board.replace_text(ids, &search.parsed, &search.replacement) {
+        let index = board.note_index();
+        for (_, mut note, _) in notes.iter_mut() {
+            if let Some(&i) = index.get(&note.id)
+                && ids.contains(&note.id)
+            {
+                *note = board.notes[i].clone();
+            }
+        }
+        stamp_notes(board, notes, &mut change, now_secs());
+        undo.0.record(board.id, change);
+    }
+    let current = search.current;
+    update_search(app, search);
+    search.current = current.min(search.matches.len().saturating_sub(1));
+}
+
+/// Window listing the notes "Replace all" would change, each before and
+/// after; returns whether they are to be changed once a button is clicked
+fn replace_all_window(ctx: &egui::Context, board: &Board, preview: &[NoteData]) -> Option<bool> {
+    let mut answer = None;
+    egui::Window::new(format!("Replace in {} notes?", preview.len()))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            if preview.is_empty() {
+                ui.label("The replacement changes no note");
+            }
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for after in preview {
+                        let Some(before) = board.notes.iter().find(|n| n.id == after.id) else {
+                            continue;
+                        };
+                        ui.label(egui::RichText::new(&before.text).strikethrough().weak());
+                        ui.label(&after.text);
+                        ui.separator();
+                    }
+                });
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!preview.is_empty(), egui::Button::new("Replace"))
+                    .clicked()
+                {
+                    answer = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    answer = Some(false);
+                }
+            });
+        });
+    answer
+}
+
+/// Window changing every selected note at once; returns the change picked
+fn bulk_edit_window(
+    ctx: &egui::Context,
+    board: &Board,
+    selection: &Selection,
+    palette: &[NamedColor],
+) -> Option<BulkEdit> {
+    let id = egui::Id::new("bulk_edit");
+    let form_id = id.with("form");
+    let mut form: BulkEditForm = ctx.data_mut(|d| d.get_temp(form_id)).unwrap_or_default();
+    let first = board.notes.iter().find(|n| selection.contains(n.id))?;
+    let size = form.size.get_or_insert(first.size);
+    let mut edit = None;
+    egui::Window::new(format!("Edit {} notes", selection.ids().len()))
+        .id(id)
+        .default_pos(ctx.screen_rect().right_top() + egui::vec2(-260.0, 40.0))
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Color");
+                let mut color = first.color;
+                if palette_swatches(ui, &mut color, palette) {
+                    edit = Some(BulkEdit::Recolor(color));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut form.tags)
+                        .hint_text("tags")
+                        .desired_width(120.0),
+                );
+                if ui.button("Add").clicked() {
+                    edit = Some(BulkEdit::AddTags(form.tags.clone()));
+                }
+                if ui.button("Remove").clicked() {
+                    edit = Some(BulkEdit::RemoveTags(form.tags.clone()));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut form.text)
+                        .hint_text("text")
+                        .desired_rows(1)
+                        .desired_width(120.0),
+                );
+                if ui.button("Prepend").clicked() {
+                    edit = Some(BulkEdit::Prepend(form.text.clone()));
+                }
+                if ui.button("Append").clicked() {
+                    edit = Some(BulkEdit::Append(form.text.clone()));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut size.x).range(MIN_NOTE_SIZE.x..=2000.0));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut size.y).range(MIN_NOTE_SIZE.y..=2000.0));
+                if ui
+                    .button("Resize")
+                    .on_hover_text("Locked notes keep their size")
+                    .clicked()
+                {
+                    edit = Some(BulkEdit::Resize(*size));
+                }
+            });
+        });
+    ctx.data_mut(|d| d.insert_temp(form_id, form));
+    edit
+}
+
+/// What was picked in the trash panel
+enum BinAction {
+    Restore(u64),
+    /// Delete a note in the trash for good, or move an archived one to
+    /// the trash
+    Discard(u64),
+    /// Delete every note in the trash for good
+    Empty,
+}
+
+/// Side panel listing the deleted notes of the board, or its archived
+/// ones if `archived`, to restore or get rid of
+fn trash_panel_ui(
+    ctx: &egui::Context,
+    board: &Board,
+    archived: &mut bool,
+    days: u64,
+) -> Option<BinAction> {
+    let mut picked = None;
+    egui::SidePanel::right("trash").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.selectable_value(archived, false, "Trash");
+            ui.selectable_value(archived, true, "Archive");
+        });
+        let entries: Vec<(&NoteData, Option<u64>)> = if *archived {
+            board.archived.iter().map(|n| (n, None)).collect()
+        } else {
+            ui.label(format!("Deleted notes are kept for {days} days"));
+            board
+                .trash
+                .iter()
+                .rev()
+                .map(|t| (&t.note, Some(t.deleted_at)))
+                .collect()
+        };
+        if entries.is_empty() {
+            ui.label(if *archived {
+                "No archived notes"
+            } else {
+                "The trash is empty"
+            });
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (note, deleted_at) in entries {
+                ui.horizontal(|ui| {
+                    if ui.small_button("↩").on_hover_text("Restore").clicked() {
+                        picked = Some(BinAction::Restore(note.id));
+                    }
+                    let discard = if *archived {
+                        "Move to the trash"
+                    } else {
+                        "Delete for good"
+                    };
+                    if ui.small_button("✖").on_hover_text(discard).clicked() {
+                        picked = Some(BinAction::Discard(note.id));
+                    }
+                    match deleted_at {
+                        Some(at) => ui.label(format!("{}  {}", format_date(at), link_title(note))),
+                        None => ui.label(link_title(note)),
+                    };
+                });
+            }
+        });
+        if !*archived && ui.button("Empty trash").clicked() {
+            picked = Some(BinAction::Empty);
+        }
+    });
+    picked
+}
+
+/// What was picked in the vote panel
+enum VoteAction {
+    /// Select the note and bring it into view
+    Show(u64),
+    /// Clear every vote and hand out the dots again
+    NewRound,
+}
+
+/// Side panel of dot voting: the dots `voter` has left and the notes
+/// ranked by their votes
+fn vote_panel_ui(
+    ctx: &egui::Context,
+    board: &Board,
+    ballot: &mut Ballot,
+    voter: &str,
+) -> Option<VoteAction> {
+    let mut picked = None;
+    egui::SidePanel::right("votes").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Dots each:");
+            ui.add(egui::DragValue::new(&mut ballot.dots).range(1..=20));
+        });
+        ui.label(format!("{voter}: {} dot(s) left", ballot.remaining(voter)));
+        ui.label(
+            egui::RichText::new("Click a note to put a dot on it, right-click to take it back")
+                .weak(),
+        );
+        ui.separator();
+        let ranked = board.ranked_by_votes();
+        if ranked.is_empty() {
+            ui.label("No votes yet");
+        }
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 40.0)
+            .show(ui, |ui| {
+                for (place, note) in ranked.into_iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", place + 1));
+                        ui.colored_label(VOTE_COLOR, format!("● {}", note.votes));
+                        if ui.link(link_title(note)).clicked() {
+                            picked = Some(VoteAction::Show(note.id));
+                        }
+                    });
+                }
+            });
+        ui.separator();
+        if ui
+            .button("New round")
+            .on_hover_text("Clear every vote and hand out the dots again")
+            .clicked()
+        {
+            picked = Some(VoteAction::NewRound);
+        }
+    });
+    picked
+}
+
+/// Side panel of figures about the board's notes
+fn stats_panel_ui(ctx: &egui::Context, board: &Board) {
+    let stats = BoardStats::of(board);
+    egui::SidePanel::right("stats").show(ctx, |ui| {
+        ui.heading("Stats");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.label(format!("{} notes, {} words", stats.notes, stats.words));
+            if let Some(percent) = stats.checklist_percent() {
+                let (checked, total) = stats.checklist;
+                ui.add(
+                    egui::ProgressBar::new(percent / 100.0)
+                        .text(format!("{checked} of {total} checklist items done")),
+                );
+            }
+            egui::CollapsingHeader::new("By color")
+                .default_open(true)
+                .show(ui, |ui| {
+                    for (color, count) in &stats.colors {
+                        ui.horizontal(|ui| {
+                            let (swatch, _) =
+                                ui.allocate_exact_size(Vec2::splat(12.0), egui::Sense::hover());
+                            ui.painter().rect_filled(swatch, 2.0, *color);
+                            ui.label(count.to_string());
+                        });
+                    }
+                });
+            egui::CollapsingHeader::new("By tag").show(ui, |ui| {
+                if stats.tags.is_empty() {
+                    ui.label("No tags");
+                }
+                for (tag, count) in &stats.tags {
+                    ui.label(format!("#{tag}: {count}"));
+                }
+            });
+            egui::CollapsingHeader::new("Made per day").show(ui, |ui| {
+                let most = stats.timeline.iter().map(|(_, n)| *n).max().unwrap_or(1);
+                for (day, count) in &stats.timeline {
+                    ui.horizontal(|ui| {
+                        ui.label(format_date(*day));
+                        ui.add(
+                            egui::ProgressBar::new(*count as f32 / most as f32)
+                                .desired_width(80.0)
+                                .text(count.to_string()),
+                        );
+                    });
+                }
+                if stats.undated > 0 {
+                    ui.label(format!("{} from before dates were kept", stats.undated));
+                }
+            });
+        });
+    });
+}
+
+/// Stamp the notes `change` makes or changes with `now`, on the board and
+/// the note entities alike
+fn stamp_notes(
+    board: &mut Board,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    change: &mut Command,
+    now: u64,
+) {
+    let stamped = stamp(change, now);
+    if stamped.is_empty() {
+        return;
+    }
+    let times = |id: u64| stamped.iter().find(|n| n.id == id);
+    for note in &mut board.notes {
+        if let Some(s) = times(note.id) {
+            (note.created_at, note.modified_at) = (s.created_at, s.modified_at);
+        }
+    }
+    for (_, mut note, _) in notes.iter_mut() {
+        if let Some(s) = times(note.id) {
+            (note.created_at, note.modified_at) = (s.created_at, s.modified_at);
+        }
+    }
+}
+
+/// Color marking notes that are overdue or due soon
+fn due_color(status: DueStatus) -> Color32 {
+    match status {
+        DueStatus::Overdue => Color32::RED,
+        DueStatus::Soon => Color32::ORANGE,
+    }
+}
+
+/// Bottom bar listing the hot-seat members; number keys switch the author
+fn hot_seat_panel(ctx: &egui::Context, board: &mut Board, hot_seat: &mut HotSeat) {
+    if !ctx.wants_keyboard_input() {
+        let pressed = ctx.input(|i| MEMBER_KEYS.iter().position(|k| i.key_pressed(*k)));
+        if let Some(index) = pressed.filter(|i| *i < board.members.len()) {
+            hot_seat.current = index;
+        }
+    }
+
+    egui::TopBottomPanel::bottom("hot_seat").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Author:");
+            let mut remove = None;
+            for (index, member) in board.members.iter_mut().enumerate() {
+                ui.color_edit_button_srgba(&mut member.color);
+                let label = egui::RichText::new(format!("{} {}", index + 1, member.name))
+                    .color(member.color);
+                let response = ui.selectable_label(index == hot_seat.current, label);
+                if response.clicked() {
+                    hot_seat.current = index;
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Remove member").clicked() {
+                        remove = Some(index);
+                        ui.close_menu();
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                board.members.remove(index);
+                hot_seat.current = hot_seat.current.min(board.members.len().saturating_sub(1));
+            }
+
+            ui.separator();
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut hot_seat.new_member)
+                    .hint_text("New member")
+                    .desired_width(100.0),
+            );
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let name = hot_seat.new_member.trim().to_owned();
+            if (ui.button("Add").clicked() || submitted) && !name.is_empty() {
+                let color = MEMBER_COLORS[board.members.len() % MEMBER_COLORS.len()];
+                board.members.push(Member { name, color });
+                hot_seat.current = board.members.len() - 1;
+                hot_seat.new_member.clear();
+            }
+        });
+    });
+}
+
+/// Copy note ranks from the board onto the note entities
+fn sync_ranks(app: &PostItData, notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>) {
+    let board = app.state.board();
+    let index = board.note_index();
+    for (_, mut note, _) in notes.iter_mut() {
+        if let Some(&i) = index.get(&note.id) {
+            note.rank = board.notes[i].rank;
+        }
+    }
+}
+
+/// Setting or removing the password the save file is sealed with; true if
+/// it changed
+fn password_menu(ui: &mut egui::Ui, password: &mut Option<String>) -> bool {
+    let id = egui::Id::new("new_password");
+    let mut typed: String = ui.data_mut(|d| d.get_temp(id)).unwrap_or_default();
+    let mut changed = false;
+    ui.label(if password.is_some() {
+        "Saves are sealed with a password"
+    } else {
+        "Saves are plain JSON"
+    });
+    ui.add(
+        egui::TextEdit::singleline(&mut typed)
+            .password(true)
+            .hint_text("New password"),
+    );
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(!typed.is_empty(), egui::Button::new("Set password"))
+            .clicked()
+        {
+            *password = Some(std::mem::take(&mut typed));
+            changed = true;
+            ui.close_menu();
+        }
+        if ui
+            .add_enabled(password.is_some(), egui::Button::new("Remove"))
+            .clicked()
+        {
+            *password = None;
+            changed = true;
+            ui.close_menu();
+        }
+    });
+    ui.data_mut(|d| d.insert_temp(id, typed));
+    changed
+}
+
+/// What was done in the [`unlock_window`]
+enum Unlock {
+    Opened(AppState, String),
+    Failed(std::io::Error),
+    Cancelled,
+}
+
+/// Window asking for the password of the sealed save file at `path`,
+/// which can be cancelled unless the app would save over it
+fn unlock_window(ctx: &egui::Context, path: &Path, can_cancel: bool) -> Option<Unlock> {
+    let id = egui::Id::new("unlock_password");
+    let mut typed: String = ctx.data_mut(|d| d.get_temp(id)).unwrap_or_default();
+    let mut result = None;
+    egui::Window::new("🔒 Locked board")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!("{} is sealed with a password.", path.display()));
+            let field = ui.add(
+                egui::TextEdit::singleline(&mut typed)
+                    .password(true)
+                    .hint_text("Password"),
+            );
+            let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.horizontal(|ui| {
+                if (ui.button("Open").clicked() || entered) && !typed.is_empty() {
+                    result = Some(match AppState::try_load_sealed_file(path, Some(&typed)) {
+                        Ok(state) => Unlock::Opened(state, typed.clone()),
+                        Err(e) => Unlock::Failed(e),
+                    });
+                }
+                if can_cancel && ui.button("Cancel").clicked() {
+                    result = Some(Unlock::Cancelled);
+                }
+            });
+        });
+    if matches!(result, Some(Unlock::Opened(..) | Unlock::Cancelled)) {
+        typed.clear();
+    }
+    ctx.data_mut(|d| d.insert_temp(id, typed));
+    result
+}
+
+/// Window of app-wide preferences: the theme of the panels and of boards
+/// without one of their own, new notes, the grid, fonts, sounds, autosave
+/// and the trash
+fn preferences_window(
+    ctx: &egui::Context,
+    themes: &mut Themes,
+    grid: &mut GridSize,
+    autosave: &mut Autosaver,
+    now: f64,
+    status: &mut StatusMessage,
+) {
+    let mut open = themes.preferences_open;
+    egui::Window::new("Preferences")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("App theme");
+            let mut choice = None;
+            let current = themes.library.app_theme.clone();
+            if ui.radio(current.is_none(), "Default").clicked() {
+                choice = Some(None);
+            }
+            for theme in &themes.library.themes {
+                let picked = current.as_deref() == Some(theme.name.as_str());
+                if ui.radio(picked, &theme.name).clicked() {
+                    choice = Some(Some(theme.name.clone()));
+                }
+            }
+            if let Some(name) = choice
+                && let Err(e) = themes.set_app_theme(name)
+            {
+                status.0 = format!("Could not save the settings: {e}");
+            }
+            let settings = &mut themes.settings;
+            let mut changed = false;
+            ui.separator();
+            ui.label("New notes");
+            ui.horizontal(|ui| {
+                let mut own_size = settings.note_size.is_some();
+                if ui
+                    .checkbox(&mut own_size, "Size")
+                    .on_hover_text("Instead of the theme's")
+                    .changed()
+                {
+                    settings.note_size = own_size.then_some(Theme::default().note_size);
+                    changed = true;
+                }
+                if let Some(size) = &mut settings.note_size {
+                    for side in [&mut size.x, &mut size.y] {
+                        changed |= ui
+                            .add(egui::DragValue::new(side).range(MIN_NOTE_SIZE.x..=1000.0))
+                            .changed();
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                let mut own_color = settings.note_color.is_some();
+                if ui
+                    .checkbox(&mut own_color, "Color")
+                    .on_hover_text("Instead of the theme's, on boards without their own")
+                    .changed()
+                {
+                    settings.note_color = own_color.then_some(Theme::default().note_color);
+                    changed = true;
+                }
+                if let Some(color) = &mut settings.note_color {
+                    changed |= ui.color_edit_button_srgba(color).changed();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Grid:");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut settings.grid_size)
+                            .range(GRID_SIZE_RANGE)
+                            .suffix(" px"),
+                    )
+                    .on_hover_text("Grid spacing of boards without their own")
+                    .changed()
+                {
+                    grid.0 = settings.grid_size;
+                    changed = true;
+                }
+            });
+            changed |= ui
+                .checkbox(&mut settings.tossing, "Toss notes")
+                .on_hover_text("Notes let go while moving slide on and bounce off the edges")
+                .changed();
+            ui.separator();
+            ui.label("Note font");
+            let fonts = &mut settings.fonts;
+            for choice in FontChoice::ALL {
+                changed |= ui
+                    .radio_value(&mut fonts.family, choice, choice.name())
+                    .changed();
+            }
+            ui.horizontal(|ui| {
+                ui.label("Sizes:");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut fonts.min_size).range(FONT_SIZE_RANGE))
+                    .on_hover_text("Smallest size text shrinks to so it fits its note")
+                    .changed();
+                ui.label("to");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut fonts.max_size).range(FONT_SIZE_RANGE))
+                    .on_hover_text("Size of text with room to spare")
+                    .changed();
+            });
+            ui.separator();
+            ui.label("Accessibility");
+            changed |= ui
+                .checkbox(&mut settings.high_contrast, "High contrast")
+                .on_hover_text("Show every board and panel in strong contrast")
+                .changed();
+            ui.horizontal(|ui| {
+                let mut floor = fonts.min_readable.is_some();
+                if ui
+                    .checkbox(&mut floor, "Text no smaller than")
+                    .on_hover_text("For notes, even those with a size of their own, and panels")
+                    .changed()
+                {
+                    fonts.min_readable = floor.then_some(fonts.max_size);
+                    changed = true;
+                }
+                if let Some(size) = &mut fonts.min_readable {
+                    changed |= ui
+                        .add(egui::DragValue::new(size).range(FONT_SIZE_RANGE))
+                        .changed();
+                }
+            });
+            ui.separator();
+            ui.label("Quick capture");
+            ui.horizontal(|ui| {
+                let mut on = settings.quick_capture_shortcut.is_some();
+                if ui
+                    .checkbox(&mut on, "Shortcut")
+                    .on_hover_text("Jot a note into the board's inbox from any app")
+                    .changed()
+                {
+                    settings.quick_capture_shortcut =
+                        on.then(|| DEFAULT_QUICK_CAPTURE_SHORTCUT.to_owned());
+                    changed = true;
+                }
+                if let Some(keys) = &mut settings.quick_capture_shortcut {
+                    // Typed into a draft and applied on Enter or leaving the
+                    // field, so half-typed keys are never registered
+                    let id = egui::Id::new("quick_capture_keys");
+                    let mut typed: String = ui
+                        .data_mut(|d| d.get_temp(id))
+                        .unwrap_or_else(|| keys.clone());
+                    let field = ui
+                        .add(egui::TextEdit::singleline(&mut typed).desired_width(120.0))
+                        .on_hover_text("Like ctrl+shift+N or alt+Space");
+                    if field.lost_focus() {
+                        ui.data_mut(|d| d.remove::<String>(id));
+                        if typed != *keys {
+                            *keys = typed;
+                            changed = true;
+                        }
+                    } else if field.has_focus() {
+                        ui.data_mut(|d| d.insert_temp(id, typed));
+                    }
+                }
+            });
+            changed |= ui
+                .add_enabled(
+                    settings.quick_capture_shortcut.is_some(),
+                    egui::Checkbox::new(&mut settings.run_in_background, "Keep running when closed"),
+                )
+                .on_hover_text("Closing the window hides it; quit with the button by the boards")
+                .changed();
+            ui.separator();
+            ui.label("Sounds");
+            let sounds = &mut settings.sounds;
+            changed |= ui.checkbox(&mut sounds.muted, "Mute").changed();
+            changed |= ui
+                .add_enabled(
+                    !sounds.muted,
+                    egui::Slider::new(&mut sounds.volume, 0.0..=1.0).text("Volume"),
+                )
+                .changed();
+            let current = sounds.pack.clone();
+            egui::ComboBox::from_label("Pack")
+                .selected_text(current.as_deref().unwrap_or("Plop"))
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut sounds.pack, None, "Plop")
+                        .changed();
+                    for pack in list_packs(&assets_dir()) {
+                        changed |= ui
+                            .selectable_value(&mut sounds.pack, Some(pack.clone()), pack)
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(format!(
+                    "Packs are folders in {} with create, delete, drag_stop, snap and timer_done sounds",
+                    assets_dir().join(SOUND_PACKS_DIR).display()
+                ));
+            ui.separator();
+            egui::ComboBox::from_label("Autosave")
+                .selected_text(interval_label(settings.autosave_interval))
+                .show_ui(ui, |ui| {
+                    for interval in INTERVAL_CHOICES {
+                        if ui
+                            .selectable_value(
+                                &mut settings.autosave_interval,
+                                interval,
+                                interval_label(interval),
+                            )
+                            .changed()
+                        {
+                            autosave.set_interval(interval, now);
+                            changed = true;
+                        }
+                    }
+                });
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut settings.trash_days)
+                        .range(1..=365)
+                        .prefix("Empty the trash after ")
+                        .suffix(" days"),
+                )
+                .changed();
+            ui.separator();
+            let mut syncing = settings.remote.is_some();
+            if ui
+                .checkbox(&mut syncing, "Sync with a server")
+                .on_hover_text(
+                    "Keep a copy of the save file on a WebDAV server or in S3-compatible storage",
+                )
+                .changed()
+            {
+                settings.remote = syncing.then(RemoteConfig::default);
+                changed = true;
+            }
+            if let Some(remote) = &mut settings.remote {
+                let before = remote.clone();
+                ui.horizontal(|ui| {
+                    for kind in RemoteKind::ALL {
+                        ui.radio_value(&mut remote.kind, kind, kind.name());
+                    }
+                });
+                let s3 = remote.kind == RemoteKind::S3;
+                egui::Grid::new("remote_sync")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Address:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut remote.url).hint_text(if s3 {
+                                "https://s3.us-east-1.amazonaws.com/bucket/plop.json"
+                            } else {
+                                "https://dav.example.com/plop.json"
+                            }),
+                        );
+                        ui.end_row();
+                        if s3 {
+                            ui.label("Region:");
+                            ui.text_edit_singleline(&mut remote.region);
+                            ui.end_row();
+                        }
+                        ui.label(if s3 { "Access key:" } else { "User:" });
+                        ui.text_edit_singleline(&mut remote.user);
+                        ui.end_row();
+                        ui.label(if s3 { "Secret key:" } else { "Password:" });
+                        ui.add(egui::TextEdit::singleline(&mut remote.secret).password(true))
+                            .on_hover_text("Kept in the settings file");
+                        ui.end_row();
+                    });
+                if *remote != before {
+                    // A different copy has its own history
+                    if remote.url != before.url || remote.kind != before.kind {
+                        remote.etag = None;
+                    }
+                    changed = true;
+                }
+            }
+            if changed && let Err(e) = save_json(&*settings, &themes.settings_path) {
+                status.0 = format!("Could not save the settings: {e}");
+            }
+        });
+    themes.preferences_open = open;
+}
+
+/// Dropdown switching the board's theme, plus import/export of theme files
+fn theme_switcher(
+    ui: &mut egui::Ui,
+    board: &mut Board,
+    current: &Theme,
+    themes: &mut Themes,
+    status: &mut StatusMessage,
+) {
+    egui::ComboBox::from_id_salt("theme")
+        .selected_text(&current.name)
+        .show_ui(ui, |ui| {
+            for theme in &themes.library.themes {
+                if ui
+                    .selectable_label(theme.name == current.name, &theme.name)
+                    .clicked()
+                {
+                    board.apply_theme(theme);
+                }
+            }
+        });
+    ui.menu_button("Theme files", |ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut themes.path_input)
+                .hint_text("path/to/theme.json")
+                .desired_width(200.0),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Import").clicked() {
+                let path = PathBuf::from(themes.path_input.trim());
+                let dir = themes.dir.clone();
+                status.0 = match themes.library.import(&path, &dir) {
+                    Ok(name) => {
+                        if let Some(theme) = themes.library.get(&name) {
+                            board.apply_theme(theme);
+                        }
+                        format!("Imported theme {name}")
+                    }
+                    Err(e) => format!("Theme import failed: {e}"),
+                };
+                ui.close_menu();
+            }
+            if ui.button("Export current").clicked() {
+                let mut path = PathBuf::from(themes.path_input.trim());
+                if themes.path_input.trim().is_empty() || path.is_dir() {
+                    path = path.join(theme_file_name(&current.name));
+                }
+                status.0 = match write_theme(current, &path) {
+                    Ok(()) => format!("Theme written to {}", path.display()),
+                    Err(e) => format!("Theme export failed: {e}"),
+                };
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+/// Menu showing, naming and coloring the board's kanban lanes. Returns
+/// whether notes changed lanes.
+fn lanes_menu(ui: &mut egui::Ui, board: &mut Board) -> bool {
+    let mut relabeled = false;
+    ui.menu_button("Lanes", |ui| {
+        if ui
+            .checkbox(&mut board.kanban, "Show lanes")
+            .on_hover_text("Notes dropped into a lane snap into its column")
+            .changed()
+            && board.kanban
+            && board.lanes.is_empty()
+        {
+            board.lanes = default_lanes();
+        }
+        let mut rename = None;
+        let mut remove = None;
+        for (i, lane) in board.lanes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut lane.color);
+                let mut name = lane.name.clone();
+                if ui
+                    .add(egui::TextEdit::singleline(&mut name).desired_width(100.0))
+                    .changed()
+                {
+                    rename = Some((i, name));
+                }
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some((i, name)) = rename {
+            board.rename_lane(i, &name);
+            relabeled = true;
+        }
+        if let Some(i) = remove {
+            board.remove_lane(i);
+            relabeled = true;
+        }
+        if ui.button("Add lane").clicked() {
+            let color = Color32::from_rgb(150, 110, 200);
+            board.lanes.push(Lane::new("New lane", color));
+        }
+    });
+    relabeled
+}
+
+/// Menu naming and coloring the board's note categories. Returns whether
+/// notes changed with their category.
+fn categories_menu(ui: &mut egui::Ui, board: &mut Board) -> bool {
+    let mut changed = false;
+    ui.menu_button("Categories", |ui| {
+        let mut rename = None;
+        let mut recolor = None;
+        let mut remove = None;
+        for (i, category) in board.categories.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let mut color = category.color;
+                if ui.color_edit_button_srgba(&mut color).changed() {
+                    recolor = Some((i, color));
+                }
+                let mut name = category.name.clone();
+                if ui
+                    .add(egui::TextEdit::singleline(&mut name).desired_width(100.0))
+                    .changed()
+                {
+                    rename = Some((i, name));
+                }
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some((i, color)) = recolor {
+            board.recolor_category(i, color);
+            changed = true;
+        }
+        if let Some((i, name)) = rename {
+            board.rename_category(i, &name);
+            changed = true;
+        }
+        if let Some(i) = remove {
+            board.remove_category(i);
+            changed = true;
+        }
+        if ui.button("Add category").clicked() {
+            // Start from a sticky color no category has yet, if any
+            let color = sticky_colors()
+                .into_iter()
+                .map(|c| c.color)
+                .find(|&c| !board.categories.iter().any(|k| k.color == c))
+                .unwrap_or(Color32::WHITE);
+            board.categories.push(Category::new("New category", color));
+        }
+        if board.categories.is_empty() {
+            ui.weak("Categories give note colors a meaning, shown in a legend");
+        }
+    });
+    changed
+}
+
+/// Menu editing the board's named note colors and the color of new notes
+fn palette_menu(ui: &mut egui::Ui, board: &mut Board, theme: &Theme) {
+    ui.menu_button("Palette", |ui| {
+        if board.palette.is_empty() {
+            ui.label(format!("Using the colors of the {} theme", theme.name));
+            if ui.button("Customize").clicked() {
+                board.palette = board.note_palette(theme);
+            }
+        } else {
+            let mut remove = None;
+            for (i, named) in board.palette.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(&mut named.color);
+                    ui.add(egui::TextEdit::singleline(&mut named.name).desired_width(100.0));
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                board.palette.remove(i);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add color").clicked() {
+                    // Offer a sticky color the palette lacks, if any
+                    let fresh = sticky_colors()
+                        .into_iter()
+                        .find(|c| !board.palette.iter().any(|p| p.color == c.color))
+                        .unwrap_or_else(|| NamedColor::new("New color", Color32::WHITE));
+                    board.palette.push(fresh);
+                }
+                if ui.button("Use theme colors").clicked() {
+                    board.palette.clear();
+                }
+            });
+        }
+        ui.separator();
+        ui.label("New notes:");
+        ui.horizontal(|ui| {
+            let mut color = board.new_note_color(theme);
+            if palette_swatches(ui, &mut color, &board.note_palette(theme)) {
+                board.default_note_color = Some(color);
+            }
+            if board.default_note_color.is_some()
+                && ui
+                    .small_button("Theme default")
+                    .on_hover_text("Use the theme's color for new notes")
+                    .clicked()
+            {
+                board.default_note_color = None;
+            }
+        });
+    });
+}
+
+/// Share note changes with the other instances of a LAN session and apply
+/// theirs, and show the window for hosting and joining
+fn lan_sync_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut lan: ResMut<LanSync>,
+    mut notes: Query<(Entity, &mut NoteData)>,
+    time: Res<Time>,
+) {
+    let lan = &mut *lan;
+    if lan.window_open {
+        lan_window(contexts.ctx_mut(), lan, &app, &notes);
+    }
+    let Some(session) = &mut lan.session else {
+        return;
+    };
+    let active = app.state.board().id == session.board_id;
+    let Some(index) = app
+        .state
+        .boards
+        .iter()
+        .position(|b| b.id == session.board_id)
+    else {
+        lan.session = None;
+        lan.status = "The shared board was removed".into();
+        return;
+    };
+    // The board holds the notes created, deleted and changed remotely even
+    // before the entities do, so changes are found against it with the
+    // latest edits from the entities copied over
+    if active {
+        let board = &mut app.state.boards[index];
+        let note_index = board.note_index();
+        for (_, note) in notes.iter() {
+            if let Some(&i) = note_index.get(&note.id) {
+                board.notes[i] = note.clone();
+            }
+        }
+    }
+    let mut ended = false;
+    for event in session.connection.poll() {
+        match event {
+            SyncEvent::Joined(peer) => {
+                let current = app.state.boards[index].notes.clone();
+                let snapshot = SyncMessage::Snapshot { notes: current };
+                lan.status = match session.connection.send_to(peer, &snapshot) {
+                    Ok(()) => "Someone joined".into(),
+                    Err(e) => format!("Sending the board failed: {e}"),
+                };
+            }
+            SyncEvent::Left(_) if session.connection.is_host() => {
+                lan.status = "Someone left".into();
+            }
+            SyncEvent::Left(_) => ended = true,
+            SyncEvent::Message(message) => {
+                let snapshot = matches!(message, SyncMessage::Snapshot { .. });
+                let board = &mut app.state.boards[index];
+                for change in session.state.receive(message) {
+                    apply_remote_change(&mut commands, board, active.then_some(&mut notes), change);
+                }
+                if snapshot {
+                    let first_id = session.state.first_note_id();
+                    app.state.next_note_id = app.state.next_note_id.max(first_id);
+                }
+            }
+        }
+    }
+    if ended {
+        lan.session = None;
+        lan.status = "The host stopped sharing".into();
+        return;
+    }
+
+    let elapsed = time.elapsed_secs_f64();
+    if active && elapsed >= session.next_send {
+        session.next_send = elapsed + LAN_SEND_INTERVAL;
+        let current = &app.state.boards[index].notes;
+        for message in session.state.local_changes(current, now_millis()) {
+            if let Err(e) = session.connection.send(&message) {
+                lan.status = format!("Sending failed: {e}");
+            }
+        }
+    }
+}
+
+/// Make a change from another instance to `board`, and to the note
+/// entities when it is the active board
+fn apply_remote_change(
+    commands: &mut Commands,
+    board: &mut Board,
+    entities: Option<&mut Query<(Entity, &mut NoteData)>>,
+    change: RemoteChange,
+) {
+    change.apply(&mut board.notes);
+    match change {
+        RemoteChange::Upsert(note) => {
+            let Some(entities) = entities else {
+                return;
+            };
+            match entities.iter_mut().find(|(_, n)| n.id == note.id) {
+                Some((_, mut n)) => *n = note,
+                None => {
+                    commands.spawn((note, NoteUi::default()));
+                }
+            }
+        }
+        RemoteChange::Remove(id) => {
+            if let Some(entities) = entities
+                && let Some((entity, _)) = entities.iter().find(|(_, n)| n.id == id)
+            {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// Window for hosting, joining and leaving a LAN session
+fn lan_window(
+    ctx: &egui::Context,
+    lan: &mut LanSync,
+    app: &PostItData,
+    notes: &Query<(Entity, &mut NoteData)>,
+) {
+    let mut open = lan.window_open;
+    egui::Window::new("LAN sharing")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if let Some(session) = &lan.session {
+                let name = app
+                    .state
+                    .boards
+                    .iter()
+                    .find(|b| b.id == session.board_id)
+                    .map(|b| b.name.as_str())
+                    .unwrap_or_default();
+                ui.label(format!(
+                    "Sharing \"{name}\" with {} other instance(s)",
+                    session.connection.peer_count()
+                ));
+                if ui.button("Stop sharing").clicked() {
+                    lan.session = None;
+                    lan.status = "Stopped sharing".into();
+                }
+            } else {
+                ui.label("Edit the active board together with plop on other computers");
+                ui.horizontal(|ui| {
+                    ui.label("Address");
+                    ui.text_edit_singleline(&mut lan.address);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Session key");
+                    ui.add(egui::TextEdit::singleline(&mut lan.key).password(true))
+                        .on_hover_text("Only instances given the same key can join");
+                });
+                ui.horizontal(|ui| {
+                    let host = ui
+                        .button("Host")
+                        .on_hover_text("Wait for others to join on this address")
+                        .clicked();
+                    let join = ui
+                        .button("Join")
+                        .on_hover_text("Replace the active board with the one hosted there")
+                        .clicked();
+                    let connection = if host {
+                        Some(SyncConnection::host(&lan.address, &lan.key))
+                    } else if join {
+                        Some(SyncConnection::join(&lan.address, &lan.key))
+                    } else {
+                        None
+                    };
+                    match connection {
+                        Some(Ok(connection)) => {
+                            let current: Vec<NoteData> =
+                                notes.iter().map(|(_, n)| n.clone()).collect();
+                            lan.status = if host {
+                                format!("Hosting on {}", connection.local_addr())
+                            } else {
+                                format!("Joined {}", lan.address)
+                            };
+                            lan.session = Some(LanSession {
+                                connection,
+                                state: SyncState::new(rand::random(), &current),
+                                board_id: app.state.board().id,
+                                next_send: 0.0,
+                            });
+                        }
+                        Some(Err(e)) => lan.status = format!("Connection failed: {e}"),
+                        None => {}
+                    }
+                });
+            }
+            if !lan.status.is_empty() {
+                ui.label(&lan.status);
+            }
+        });
+    lan.window_open = open;
+}
+
+/// Menu editing the templates offered when right-clicking the board
+fn templates_menu(ui: &mut egui::Ui, templates: &mut Vec<NoteTemplate>) {
+    ui.menu_button("Templates", |ui| {
+        let mut remove = None;
+        for (i, template) in templates.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut template.color);
+                ui.add(egui::TextEdit::singleline(&mut template.name).desired_width(80.0))
+                    .on_hover_text("Name");
+                ui.add(egui::TextEdit::singleline(&mut template.text).desired_width(120.0))
+                    .on_hover_text("Text new notes start with");
+                ui.add(egui::DragValue::new(&mut template.size.x).range(MIN_NOTE_SIZE.x..=1000.0))
+                    .on_hover_text("Width");
+                ui.add(egui::DragValue::new(&mut template.size.y).range(MIN_NOTE_SIZE.y..=1000.0))
+                    .on_hover_text("Height");
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            templates.remove(i);
+        }
+        if templates.is_empty() {
+            ui.label("Right-click a note and pick \"Save as template\" to add one");
+        }
+    });
+}
+
+/// Take periodic board snapshots and show the history browser
+fn time_travel_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut travel: ResMut<TimeTravel>,
+    mut status: ResMut<StatusMessage>,
+    notes: Query<(Entity, &NoteData)>,
+    time: Res<Time>,
+) {
+    let elapsed = time.elapsed_secs_f64();
+    if elapsed >= travel.next_snapshot && !app.is_locked(&app.save_path) {
+        travel.next_snapshot = elapsed + SNAPSHOT_INTERVAL;
+        if !travel.loaded {
+            match Timeline::load_from_file(&travel.path, app.password.as_deref()) {
+                Ok(history) => {
+                    travel.history = history;
+                    travel.loaded = true;
+                }
+                Err(e) => warn!("Failed to load board history: {e}"),
+            }
+        }
+        sync_board_from_entities(app.state.board_mut(), &notes);
+        let board = app.state.board();
+        if travel.loaded
+            && travel.history.record(board, now_secs())
+            && let Err(e) = travel
+                .history
+                .save_to_file(&travel.path, app.password.as_deref())
+        {
+            warn!("Failed to save board history: {e}");
+        }
+    }
+    if !travel.open {
+        return;
+    }
+
+    let mut open = true;
+    let mut restore = None;
+    let mut branch = None;
+    egui::Window::new("History")
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let shown = travel.history.snapshots_of(app.state.board().id);
+            let count = shown.len();
+            if count == 0 {
+                ui.label("No snapshots yet");
+                return;
+            }
+            travel.index = travel.index.min(count - 1);
+            ui.add(egui::Slider::new(&mut travel.index, 0..=count - 1).show_value(false));
+            let snapshot = &travel.history.snapshots[shown[travel.index]];
+            ui.label(format!(
+                "{} UTC · {} notes",
+                format_local_datetime(snapshot.taken_at),
+                snapshot.board.notes.len()
+            ));
+            let (frame, _) = ui.allocate_exact_size(egui::vec2(360.0, 240.0), egui::Sense::hover());
+            paint_overview(ui.painter(), frame, &snapshot.board, None);
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Restore")
+                    .on_hover_text("Replace the board with this snapshot")
+                    .clicked()
+                {
+                    restore = Some(snapshot.board.clone());
+                }
+                if ui
+                    .button("Branch")
+                    .on_hover_text("Copy this snapshot into a new board")
+                    .clicked()
+                {
+                    branch = Some(snapshot.clone());
+                }
+            });
+        });
+    travel.open = open;
+
+    if let Some(board) = restore {
+        // Keep the current state so the restore can be undone from here
+        sync_board_from_entities(app.state.board_mut(), &notes);
+        let now = now_secs();
+        travel.history.record(app.state.board(), now);
+        respawn_notes(&mut commands, &board, notes.iter().map(|(e, _)| e));
+        *app.state.board_mut() = board;
+        let max_id = app
+            .state
+            .board()
+            .notes
+            .iter()
+            .map(|n| n.id)
+            .max()
+            .unwrap_or(0);
+        app.state.next_note_id = app.state.next_note_id.max(max_id + 1);
+        travel.history.record(app.state.board(), now);
+        travel.index = usize::MAX;
+        status.0 = "Board restored from history".into();
+    }
+    if let Some(snapshot) = branch {
+        let name = format!("{} (branch)", snapshot.board.name);
+        let index = app.state.add_board_copy(Board {
+            name,
+            ..snapshot.board
+        });
+        status.0 = format!("Branched into board \"{}\"", app.state.boards[index].name);
+    }
+}
+
+/// Tab strip to switch, create, rename (double-click) and delete boards
+fn board_tabs_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut search: ResMut<SearchState>,
+    mut templates: ResMut<BoardTemplates>,
+    mut status: ResMut<StatusMessage>,
+    mut split: ResMut<SplitPane>,
+    mut exit: EventWriter<AppExit>,
+    themes: Res<Themes>,
+    notes: Query<(Entity, &NoteData)>,
+) {
+    let ctx = contexts.ctx_mut();
+    // Board being renamed and the name typed so far
+    let rename_id = egui::Id::new("board_rename");
+    let mut renaming = ctx.data(|d| d.get_temp::<(usize, String)>(rename_id));
+    let mut renamed = None;
+    let mut switch_to = None;
+    let mut delete = None;
+    let mut create = None;
+    let mut save_template = None;
+
+    egui::TopBottomPanel::top("board_tabs").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            let count = app.state.boards.len();
+            for (index, board) in app.state.boards.iter().enumerate() {
+                if let Some((i, name)) = &mut renaming
+                    && *i == index
+                {
+                    let edit = ui.add(egui::TextEdit::singleline(name).desired_width(100.0));
+                    if edit.lost_focus() {
+                        renamed = Some((index, name.trim().to_owned()));
+                    } else if !edit.has_focus() {
+                        edit.request_focus();
+                    }
+                    continue;
+                }
+                let tab = ui.selectable_label(index == app.state.active, &board.name);
+                if tab.clicked() {
+                    switch_to = Some(index);
+                }
+                if tab.double_clicked() {
+                    renaming = Some((index, board.name.clone()));
+                }
+                tab.context_menu(|ui| {
+                    if ui.button("Rename").clicked() {
+                        renaming = Some((index, board.name.clone()));
+                        ui.close_menu();
+                    }
+                    if ui.button("Save as template").clicked() {
+                        save_template = Some(index);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Show beside")
+                        .on_hover_text("Split the view, showing this board on the right")
+                        .clicked()
+                    {
+                        *split = SplitPane {
+                            board: Some(board.id),
+                            ..Default::default()
+                        };
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(count > 1, egui::Button::new("Delete board"))
+                        .clicked()
+                    {
+                        delete = Some(index);
+                        ui.close_menu();
+                    }
+                });
+            }
+            ui.menu_button("+", |ui| {
+                for template in &templates.templates {
+                    if ui
+                        .button(&template.name)
+                        .on_hover_text(&template.description)
+                        .clicked()
+                    {
+                        create = Some(template.clone());
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("New board");
+            // Closing the window only hides it then
+            if themes.settings.run_in_background
+                && themes.settings.quick_capture_shortcut.is_some()
+                && ui.button("Quit").on_hover_text("Quit plop").clicked()
+            {
+                exit.write(AppExit::Success);
+            }
+        });
+    });
+
+    if let Some((index, name)) = renamed {
+        if !name.is_empty() {
+            app.state.boards[index].name = name;
+        }
+        renaming = None;
+    }
+    ctx.data_mut(|d| {
+        if let Some(renaming) = renaming {
+            d.insert_temp(rename_id, renaming);
+        } else {
+            d.remove::<(usize, String)>(rename_id);
+        }
+    });
+
+    if let Some(index) = save_template {
+        // The active board's notes live in the entities until synced
+        if index == app.state.active {
+            sync_board_from_entities(app.state.board_mut(), &notes);
+        }
+        let board = &app.state.boards[index];
+        let template = BoardTemplate::from_board(&board.name, board);
+        let path = templates.dir.join(theme_file_name(&template.name));
+        let written = std::fs::create_dir_all(&templates.dir)
+            .and_then(|()| write_board_template(&template, &path));
+        status.0 = match written {
+            Ok(()) => {
+                templates.templates = board_templates(&templates.dir);
+                format!("Saved template \"{}\"", template.name)
+            }
+            Err(e) => format!("Could not save template: {e}"),
+        };
+    }
+
+    if switch_to.is_none_or(|i| i == app.state.active) && delete.is_none() && create.is_none() {
+        return;
+    }
+    sync_board_from_entities(app.state.board_mut(), &notes);
+    if let Some(index) = switch_to {
+        app.state.active = index;
+    }
+    if let Some(index) = delete {
+        app.state.remove_board(index);
+    }
+    if let Some(template) = create {
+        let name = format!("Board {}", app.state.boards.len() + 1);
+        app.state.active = app.state.add_board_copy(template.to_board(&name));
+    }
+    respawn_notes(
+        &mut commands,
+        app.state.board(),
+        notes.iter().map(|(e, _)| e),
+    );
+    update_search(&app, &mut search);
+}
+
+/// Second view beside the board, open while `board` is set
+#[derive(Resource)]
+struct SplitPane {
+    /// Id of the board it shows
+    board: Option<u64>,
+    /// Part of that board it shows
+    scene_rect: Rect,
+    /// Screen area (in points) it shows the board in, for notes dropped
+    /// on it
+    viewport: Rect,
+    /// Note being dragged in it and how far it has moved so far
+    dragging: Option<(u64, Vec2)>,
+}
+
+impl Default for SplitPane {
+    fn default() -> Self {
+        Self {
+            board: None,
+            scene_rect: Rect::NOTHING,
+            viewport: Rect::NOTHING,
+            dragging: None,
+        }
+    }
+}
+
+/// Show the split view beside the board, pannable and zoomable on its own,
+/// and move the notes dragged from one to the other
+fn split_pane_system(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    mut contexts: EguiContexts,
+    mut split: ResMut<SplitPane>,
+    mut notes: Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    mut undo: ResMut<UndoHistory>,
+    mut search: ResMut<SearchState>,
+    themes: Res<Themes>,
+    board_viewport: Res<BoardViewport>,
+) {
+    let Some(board_id) = split.board else {
+        return;
+    };
+    let Some(index) = app.state.boards.iter().position(|b| b.id == board_id) else {
+        split.board = None;
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let mut close = false;
+    let mut show = None;
+    let mut dropped = None;
+    let SplitPane {
+        scene_rect,
+        viewport,
+        dragging,
+        ..
+    } = &mut *split;
+    egui::SidePanel::right("split_pane")
+        .resizable(true)
+        .default_width(ctx.screen_rect().width() / 2.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("split_board")
+                    .selected_text(&app.state.boards[index].name)
+                    .show_ui(ui, |ui| {
+                        for board in &app.state.boards {
+                            if ui
+                                .selectable_label(board.id == board_id, &board.name)
+                                .clicked()
+                            {
+                                show = Some(board.id);
+                            }
+                        }
+                    });
+                if ui
+                    .button("✖")
+                    .on_hover_text("Close the split view")
+                    .clicked()
+                {
+                    close = true;
+                }
+            });
+            let board = &app.state.boards[index];
+            let theme = themes.settings.apply_to(themes.library.for_board(board));
+            *viewport = ui.available_rect_before_wrap();
+            if !scene_rect.is_positive() {
+                let fallback = Rect::from_center_size(scene_center(), viewport.size());
+                *scene_rect = content_view(board, fallback);
+            }
+            let background = if theme.high_contrast {
+                theme.background
+            } else {
+                board.background
+            };
+            ui.painter().rect_filled(*viewport, 0.0, background);
+            // The board is shown unrotated, its notes plainly
+            egui::Scene::new()
+                .zoom_range(ZOOM_RANGE)
+                .max_inner_size(Vec2::INFINITY)
+                .show(ui, scene_rect, |ui| {
+                    let mut order: Vec<&NoteData> = board.notes.iter().collect();
+                    order.sort_by_key(|n| n.z);
+                    for note in order {
+                        let offset = dragging
+                            .filter(|(id, _)| *id == note.id)
+                            .map_or(Vec2::ZERO, |(_, offset)| offset);
+                        let rect = Rect::from_min_size(note.pos + offset, note.size);
+                        let response = ui.interact(
+                            rect,
+                            ui.id().with(("split_note", note.id)),
+                            egui::Sense::drag(),
+                        );
+                        paint_plain_note(ui.painter(), rect, note, theme.text_color);
+                        if response.drag_stopped() {
+                            dropped = Some((note.id, offset + response.drag_delta()));
+                            *dragging = None;
+                        } else if response.dragged() {
+                            *dragging = Some((note.id, offset + response.drag_delta()));
+                        }
+                    }
+                });
+        });
+    if close || show.is_some() {
+        *split = SplitPane {
+            board: show,
+            ..Default::default()
+        };
+        return;
+    }
+
+    let Some(pointer) = ctx.input(|i| i.pointer.interact_pos()) else {
+        return;
+    };
+    let active = app.state.active;
+    let board = app.state.board();
+    // Where the pointer is on the board, even when it is off to the side
+    let on_board = rotate_quarter_turns(
+        viewport_to_scene(board_viewport.0, board.scene_rect, pointer),
+        scene_center(),
+        inverse_quarter_turns(board.view_quarter_turns),
+    );
+    let in_split = viewport_to_scene(split.viewport, split.scene_rect, pointer);
+    if let Some((id, offset)) = dropped {
+        if split.viewport.contains(pointer) {
+            shift_note(&mut app, &mut notes, &mut undo, index, id, offset);
+        } else if board_viewport.0.contains(pointer)
+            // The note lands centered on the pointer
+            && let Some(offset) = app.state.boards[index]
+                .notes
+                .iter()
+                .find(|n| n.id == id)
+                .map(|note| on_board - note.size / 2.0 - note.pos)
+        {
+            if index == active {
+                shift_note(&mut app, &mut notes, &mut undo, index, id, offset);
+            } else {
+                move_across(
+                    &mut commands,
+                    &mut app,
+                    &notes,
+                    &mut undo,
+                    index,
+                    &[id],
+                    active,
+                    offset,
+                );
+                update_search(&app, &mut search);
+            }
+        }
+    } else if ctx.input(|i| i.pointer.any_released()) && split.viewport.contains(pointer) {
+        // Notes of the board dropped on the split view
+        let dragged: Vec<u64> = notes
+            .iter()
+            .filter(|(_, _, ui_state)| ui_state.drag_origin.is_some() && ui_state.toss.is_none())
+            .map(|(_, note, _)| note.id)
+            .collect();
+        if dragged.is_empty() {
+            return;
+        }
+        let offset = in_split - on_board;
+        if index == active {
+            // The board's own drop records the move for undo
+            let board = app.state.board_mut();
+            let board_index = board.note_index();
+            for (_, mut note, _) in notes.iter_mut() {
+                if dragged.contains(&note.id) {
+                    note.pos += offset;
+                    if let Some(&i) = board_index.get(&note.id) {
+                        board.notes[i].pos = note.pos;
+                    }
+                }
+            }
+        } else {
+            move_across(
+                &mut commands,
+                &mut app,
+                &notes,
+                &mut undo,
+                active,
+                &dragged,
+                index,
+                offset,
+            );
+            update_search(&app, &mut search);
+        }
+    }
+}
+
+/// A note as the split view shows it: its color and text
+fn paint_plain_note(painter: &egui::Painter, rect: Rect, note: &NoteData, text_color: Color32) {
+    painter.rect_filled(rect, 4.0, note.color);
+    let galley = painter.layout(
+        note.text.clone(),
+        egui::FontId::proportional(14.0),
+        text_color,
+        rect.width() - 8.0,
+    );
+    painter
+        .with_clip_rect(rect.intersect(painter.clip_rect()))
+        .galley(rect.min + Vec2::splat(4.0), galley, text_color);
+}
+
+/// Move the note `id` of the board at `index` by `offset` as one undo step,
+/// along with its entity if the board is the active one
+fn shift_note(
+    app: &mut PostItData,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    undo: &mut UndoHistory,
+    index: usize,
+    id: u64,
+    offset: Vec2,
+) {
+    let active = index == app.state.active;
+    let board = &mut app.state.boards[index];
+    let Some(i) = board.notes.iter().position(|n| n.id == id) else {
+        return;
+    };
+    let before = board.notes[i].clone();
+    let mut after = before.clone();
+    after.pos += offset;
+    board.place_in_lane(&mut after);
+    board.notes[i] = after.clone();
+    if active && let Some((_, mut note, _)) = notes.iter_mut().find(|(_, n, _)| n.id == id) {
+        *note = after.clone();
+    }
+    undo.0.record(board.id, Command::Update { before, after });
+}
+
+/// Move the notes `ids` from the board at `from` to the board at `to`,
+/// shifted by `offset`, keeping the entities in step with the active board
+fn move_across(
+    commands: &mut Commands,
+    app: &mut PostItData,
+    notes: &Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    undo: &mut UndoHistory,
+    from: usize,
+    ids: &[u64],
+    to: usize,
+    offset: Vec2,
+) {
+    let Some((removed, added)) = app.state.move_notes_to_board(from, ids, to, offset) else {
+        return;
+    };
+    let active = app.state.active;
+    if from == active {
+        for (entity, note, _) in notes.iter() {
+            if ids.contains(&note.id) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+    if to == active {
+        for note in app.state.boards[to]
+            .notes
+            .iter()
+            .filter(|n| ids.contains(&n.id))
+        {
+            commands.spawn((note.clone(), NoteUi::default()));
+        }
+    }
+    undo.0.record(app.state.boards[to].id, added);
+    undo.0.record(app.state.boards[from].id, removed);
+}
+
+/// Replace the note entities with fresh ones for the notes of `board`
+fn respawn_notes(
+    commands: &mut Commands,
+    board: &Board,
+    entities: impl IntoIterator<Item = Entity>,
+) {
+    for entity in entities {
+        commands.entity(entity).despawn();
+    }
+    for note in &board.notes {
+        commands.spawn((note.clone(), NoteUi::default()));
+    }
+}
+
+/// Copy the latest note data from the entities onto the board
+fn sync_board_from_entities(board: &mut Board, notes: &Query<(Entity, &NoteData)>) {
+    let index = board.note_index();
+    for (_, note) in notes.iter() {
+        if let Some(&i) = index.get(&note.id) {
+            board.notes[i] = note.clone();
+        }
+    }
+}
+
+/// Folder of the user's scripts, next to the save file
+fn scripts_dir(save_path: &Path) -> PathBuf {
+    save_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(SCRIPTS_DIR)
+}
+
+/// Run the script at `path` on the active board, returning its change for
+/// undo, if it made one, and a status message
+fn run_script_file(path: &Path, state: &mut AppState) -> (Option<Command>, String) {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return (None, format!("Could not read {}: {e}", path.display())),
+    };
+    let run = match run_script(&source, state.board(), state.next_note_id) {
+        Ok(run) => run,
+        Err(e) => return (None, format!("Script {name} failed: {e}")),
+    };
+    let board = state.board_mut();
+    let change = Command::diff(&board.notes, &run.notes);
+    board.notes = run.notes;
+    state.next_note_id = run.next_note_id;
+    let message = match run.output.last() {
+        Some(line) => format!("{name}: {line}"),
+        None => format!("Ran {name}"),
+    };
+    ((!change.is_noop()).then_some(change), message)
+}
+
+/// Export every board of the save file at `save_path` and of the recently
+/// used files into a `plop-export` folder next to it
+fn export_all_boards(save_path: &Path, recent: &RecentFiles) -> String {
+    let workspace = save_path.parent().unwrap_or(Path::new("."));
+    let out = workspace.join("plop-export");
+    let formats = [ExportFormat::Png, ExportFormat::Markdown];
+    let mut sources = vec![save_path.to_path_buf()];
+    sources.extend(recent.paths.iter().filter(|p| *p != save_path).cloned());
+    match export_files(&sources, &out, &formats, 1.0) {
+        Ok(written) => format!("Exported {} files to {}", written.len(), out.display()),
+        Err(e) => format!("Export failed: {e}"),
+    }
+}
+
+/// Export the active board in `format` (PNGs with `scale` pixels per board
+/// unit) into the `plop-export` folder next to the save file
+fn export_active_board(app: &PostItData, format: ExportFormat, scale: f32) -> String {
+    let board = app.state.board();
+    let workspace = app.save_path.parent().unwrap_or(Path::new("."));
+    let out = workspace.join("plop-export");
+    let path = out.join(board_file_name(board, format));
+    let result =
+        std::fs::create_dir_all(&out).and_then(|()| export_board(board, &path, format, scale));
+    match result {
+        Ok(()) => format!("Exported {}", path.display()),
+        Err(e) => format!("Export failed: {e}"),
+    }
+}
+
+/// Where imported notes start: the middle of the view, on the grid
+fn import_origin(board: &Board, grid: f32) -> Pos2 {
+    let center = rotate_quarter_turns(
+        board.scene_rect.center(),
+        scene_center(),
+        inverse_quarter_turns(board.view_quarter_turns),
+    );
+    snap_to_grid(center, grid)
+}
+
+/// Add imported notes and frames to the active board as one undoable step,
+/// with their text cleaned up; returns a warning if any text lost characters
+fn add_imported(
+    app: &mut PostItData,
+    commands: &mut Commands,
+    undo: &mut UndoStack,
+    notes: Vec<NoteData>,
+    frames: Vec<Frame>,
+    connectors: Vec<Connector>,
+) -> Option<String> {
+    let board = app.state.board_mut();
+    let mut created = Vec::new();
+    for frame in frames {
+        board.frames.push(frame.clone());
+        created.push(Command::AddFrame { frame });
+    }
+    let now = now_secs();
+    let mut cleaned = Vec::new();
+    for mut note in notes {
+        let clean = sanitize_text(&note.text, MAX_NOTE_CHARS);
+        cleaned.extend(clean.warning());
+        note.text = clean.text;
+        // Times the file kept, e.g. in a CSV export, stay
+        note.created_at.get_or_insert(now);
+        note.modified_at.get_or_insert(now);
+        commands.spawn((note.clone(), NoteUi::default()));
+        board.notes.push(note.clone());
+        created.push(Command::Create { note });
+    }
+    for connector in connectors {
+        board.connectors.push(connector.clone());
+        created.push(Command::Connect { connector });
+    }
+    if let Some(change) = Command::batch(created) {
+        undo.record(board.id, change);
+    }
+    let first = cleaned.first()?;
+    Some(format!("cleaned up {} note(s): {first}", cleaned.len()))
+}
+
+/// `message`, followed by `warning` if there is one
+fn with_warning(message: String, warning: Option<String>) -> String {
+    match warning {
+        Some(warning) => format!("{message}; {warning}"),
+        None => message,
+    }
+}
+
+/// Sync the notes of the active board with the Markdown files in `dir`,
+/// taking in edits made there as one undoable step
+fn sync_notes_folder(
+    app: &mut PostItData,
+    commands: &mut Commands,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    undo: &mut UndoStack,
+    dir: &Path,
+    grid: f32,
+) -> String {
+    // The folder gets the notes as they are on screen
+    let board = app.state.board_mut();
+    let index = board.note_index();
+    for (_, note, _) in notes.iter() {
+        if let Some(&i) = index.get(&note.id) {
+            board.notes[i] = note.clone();
+        }
+    }
+    let board = app.state.board();
+    let origin = import_origin(board, board.grid(grid));
+    let mut next_id = app.state.next_note_id;
+    let sync = match sync_vault(board, dir, &mut next_id, origin) {
+        Ok(sync) => sync,
+        Err(e) => return format!("Sync with {} failed: {e}", dir.display()),
+    };
+    app.state.next_note_id = next_id;
+    let (updated, created) = (sync.updated.len(), sync.created.len());
+    let board = app.state.board_mut();
+    let now = now_secs();
+    let mut changes = Vec::new();
+    for (_, mut note, _) in notes.iter_mut() {
+        if let Some(after) = sync.updated.iter().find(|n| n.id == note.id) {
+            let mut after = after.clone();
+            after.modified_at = Some(now);
+            changes.push(Command::Update {
+                before: note.clone(),
+                after: after.clone(),
+            });
+            if let Some(&i) = index.get(&after.id) {
+                board.notes[i] = after.clone();
+            }
+            *note = after;
+        }
+    }
+    if let Some(change) = Command::batch(changes) {
+        undo.record(board.id, change);
+    }
+    // Texts from the folder come already cleaned up, with the warnings here
+    add_imported(app, commands, undo, sync.created, vec![], vec![]);
+    let cleaned = sync
+        .cleaned
+        .first()
+        .map(|first| format!("cleaned up {} note(s): {first}", sync.cleaned.len()));
+    let message = format!(
+        "Synced with {}: {updated} note(s) changed, {created} added",
+        dir.display()
+    );
+    with_warning(message, cleaned)
+}
+
+/// Export the active board as a Markdown outline into the `plop-export`
+/// folder next to the save file
+fn export_board_markdown(app: &PostItData, grouping: OutlineGrouping) -> String {
+    let board = app.state.board();
+    let workspace = app.save_path.parent().unwrap_or(Path::new("."));
+    let out = workspace.join("plop-export");
+    let path = out.join(board_file_name(board, ExportFormat::Markdown));
+    let result = std::fs::create_dir_all(&out)
+        .and_then(|()| std::fs::write(&path, board_to_markdown_grouped(board, grouping)));
+    match result {
+        Ok(()) => format!("Exported {}", path.display()),
+        Err(e) => format!("Export failed: {e}"),
+    }
+}
+
+/// Rotate the board view by `quarter_turns`, keeping the same spot centered
+fn rotate_view(board: &mut Board, quarter_turns: u8) {
+    let center = rotate_quarter_turns(board.scene_rect.center(), scene_center(), quarter_turns);
+    board.scene_rect = Rect::from_center_size(center, board.scene_rect.size());
+    board.view_quarter_turns = (board.view_quarter_turns + quarter_turns) % 4;
+}
+
+/// Give every selected note the priority `change` makes of its own, as one
+/// undo step
+fn prioritize(
+    board: &mut Board,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    selection: &Selection,
+    change: impl Fn(Priority) -> Priority,
+) -> Option<Command> {
+    let index = board.note_index();
+    let mut prioritized = Vec::new();
+    for (_, mut note, _) in notes.iter_mut() {
+        let priority = change(note.priority);
+        if !selection.contains(note.id) || note.priority == priority {
+            continue;
+        }
+        let before = note.clone();
+        note.priority = priority;
+        if let Some(&i) = index.get(&note.id) {
+            board.notes[i].priority = priority;
+        }
+        prioritized.push(Command::Update {
+            before,
+            after: note.clone(),
+        });
+    }
+    Command::batch(prioritized)
+}
+
+/// Render a single board: background + draggable notes
+fn board_ui_system(
+    ui: &mut egui::Ui,
+    board: &mut Board,
+    next_note_id: &mut u64,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    commands: &mut Commands,
+    grid: &GridSize,
+    show_grid: bool,
+    ev_sound: &mut EventWriter<PlaySound>,
+    query: &SearchQuery,
+    tag_filter: &TagFilter,
+    highlight_note: Option<u64>,
+    drag_pan: &mut DragPan,
+    theme: &Theme,
+    fonts: &FontDefaults,
+    tossing: bool,
+    author: Option<Member>,
+    mut ballot: Option<&mut Ballot>,
+    selection: &mut Selection,
+    connect: &ConnectTool,
+    pen: &PenTool,
+    clipboard: &mut NoteClipboard,
+    keys: &Keymap,
+    templates: &mut Vec<NoteTemplate>,
+    changes: &mut Vec<Command>,
+) {
+    // Zoomable + draggable scene without edges; only the visible part of it
+    // is ever painted
+    let scene = Scene::new()
+        .zoom_range(ZOOM_RANGE)
+        .max_inner_size(Vec2::INFINITY);
+    let viewport = ui.available_rect_before_wrap();
+    let turns = board.view_quarter_turns;
+    if !board.scene_rect.is_positive() {
+        let fallback = Rect::from_center_size(scene_center(), viewport.size());
+        board.scene_rect =
+            rotate_rect_quarter_turns(content_view(board, fallback), scene_center(), turns);
+    }
+    let mut scene_rect = board.scene_rect;
+    // Grid density follows the zoom so snapping matches what is visible
+    let zoom = view_zoom(viewport, scene_rect);
+    let base_grid = board.grid(grid.0);
+    let grid_step = adaptive_grid_step(base_grid, zoom);
+    // Holding Alt places notes freely
+    let snap_step = if ui.input(|i| i.modifiers.alt) {
+        0.0
+    } else {
+        grid_step
+    };
+    let now = now_secs();
+    let mut pointer_in_scene = None;
+    let mut dragged = None;
+    let mut guide_snap = None;
+    let mut over_note = false;
+    // Whether dragged notes were last lined up by guides rather than the grid
+    let guided_id = ui.id().with("guided");
+    let guided = ui.data(|d| d.get_temp::<bool>(guided_id)).unwrap_or(false);
+    let mut actions = Vec::new();
+    selection.retain_existing(board);
+    board.prune_piles();
+    let palette = board.note_palette(theme);
+    // Only notes near the visible part of the board are drawn
+    let visible = visible_scene_rect(viewport, scene_rect).expand(CULL_MARGIN);
+    let mut spatial = SpatialGrid::default();
+    for (_, note, _) in notes.iter() {
+        spatial.insert(note.id, Rect::from_min_size(note.pos, note.size));
+    }
+    let shown = spatial.query(rotate_rect_quarter_turns(
+        visible,
+        scene_center(),
+        inverse_quarter_turns(turns),
+    ));
+    let response = scene
+        .show(ui, &mut scene_rect, |ui| {
+            let visible = ui.clip_rect();
+            let background = if theme.high_contrast {
+                theme.background
+            } else {
+                board.background
+            };
+            ui.painter().rect_filled(visible, 0.0, background);
+            // The grid is symmetric under quarter turns around the center
+            let style = board.background_style;
+            if style.shows_grid() && show_grid {
+                paint_grid(
+                    ui.painter(),
+                    visible,
+                    grid_step,
+                    base_grid,
+                    zoom,
+                    theme.grid_color,
+                    style == BackgroundStyle::Dotted,
+                );
+            } else if style == BackgroundStyle::Cork {
+                paint_cork(ui.painter(), visible, zoom);
+            }
+            pointer_in_scene = ui.ctx().pointer_hover_pos().and_then(|p| {
+                ui.ctx()
+                    .layer_transform_from_global(ui.layer_id())
+                    .map(|t| t * p)
+            });
+
+            if board.kanban {
+                paint_lanes(ui.painter(), &board.lanes, visible, turns);
+            }
+
+            // Frames lie under everything else; notes are allocated later
+            // so they win their own drags
+            for frame in &mut board.frames {
+                frame_ui(
+                    ui,
+                    frame,
+                    &board.notes,
+                    turns,
+                    snap_step,
+                    &palette,
+                    &mut actions,
+                    changes,
+                );
+            }
+
+            // Shift-dragging the empty board lassos notes; notes are
+            // allocated later so they still win their own drags
+            let lasso_id = ui.id().with("lasso");
+            let lasso_start = ui.data(|d| d.get_temp::<Pos2>(lasso_id));
+            if lasso_start.is_some() || ui.input(|i| i.modifiers.shift) {
+                let lasso = ui.interact(visible, lasso_id, egui::Sense::drag());
+                if lasso.drag_started()
+                    && let Some(p) = pointer_in_scene
+                {
+                    ui.data_mut(|d| d.insert_temp(lasso_id, p));
+                }
+                if let (Some(start), Some(p)) = (lasso_start, pointer_in_scene) {
+                    let area = Rect::from_two_pos(start, p);
+                    ui.painter().rect(
+                        area,
+                        0.0,
+                        Color32::from_rgba_unmultiplied(60, 120, 220, 40),
+                        Stroke::new(1.0, Color32::from_rgb(60, 120, 220)),
+                        egui::StrokeKind::Inside,
+                    );
+                    if lasso.drag_stopped() {
+                        let on_board = rotate_rect_quarter_turns(
+                            area,
+                            scene_center(),
+                            inverse_quarter_turns(turns),
+                        );
+                        selection.extend(notes_in_rect(board, on_board));
+                    }
+                }
+                if !lasso.dragged() {
+                    ui.data_mut(|d| d.remove::<Pos2>(lasso_id));
+                }
+            }
+
+            // Ink is drawn under connectors and notes, and rotates with the view
+            let to_view = |p: Pos2| rotate_quarter_turns(p, scene_center(), turns);
+            for stroke in &board.strokes {
+                let bounds =
+                    rotate_rect_quarter_turns(stroke.bounding_rect(), scene_center(), turns);
+                if bounds.intersects(visible) {
+                    ui.painter().add(stroke.shape(to_view));
+                }
+            }
+            if pen.enabled && lasso_start.is_none() && !ui.input(|i| i.modifiers.shift) {
+                let pen_id = ui.id().with("pen");
+                let drawing = ui.interact(visible, pen_id, egui::Sense::click_and_drag());
+                let on_board = pointer_in_scene
+                    .map(|p| rotate_quarter_turns(p, scene_center(), inverse_quarter_turns(turns)));
+                if pen.erase {
+                    if (drawing.dragged() || drawing.clicked())
+                        && let Some(p) = on_board
+                        && let Some(index) = board.stroke_at(p, ERASER_RADIUS / zoom)
+                    {
+                        let stroke = board.strokes.remove(index);
+                        changes.push(Command::Erase { stroke, index });
+                    }
+                } else {
+                    let mut current = ui.data(|d| d.get_temp::<InkStroke>(pen_id));
+                    if drawing.drag_started() {
+                        current = Some(InkStroke::new(pen.color, pen.width));
+                    }
+                    if let Some(mut stroke) = current {
+                        if let Some(p) = on_board {
+                            stroke.extend_to(p);
+                        }
+                        ui.painter().add(stroke.shape(to_view));
+                        if drawing.dragged() {
+                            ui.data_mut(|d| d.insert_temp(pen_id, stroke));
+                        } else {
+                            ui.data_mut(|d| d.remove::<InkStroke>(pen_id));
+                            board.strokes.push(stroke.clone());
+                            changes.push(Command::Draw { stroke });
+                        }
+                    }
+                }
+            }
+
+            // Connectors go under the notes and follow them while dragged
+            let note_rects: HashMap<u64, Rect> = notes
+                .iter()
+                .filter(|(_, n, _)| !tag_filter.hides(n))
+                .map(|(_, n, _)| {
+                    let rect = Rect::from_min_size(n.pos, n.size);
+                    (n.id, rotate_rect_quarter_turns(rect, scene_center(), turns))
+                })
+                .collect();
+            for connector in &board.connectors {
+                if let (Some(&from), Some(&to)) = (
+                    note_rects.get(&connector.from_note),
+                    note_rects.get(&connector.to_note),
+                ) && from.union(to).intersects(visible)
+                {
+                    paint_connector(ui.painter(), from, to, connector);
+                }
+            }
+
+            // Render existing notes from ECS, bottom of the stack first so
+            // notes on top are drawn and hit last. The board's copies are
+            // looked up by index, which holds as no note is added or
+            // removed while drawing.
+            let positions = board.note_index();
+            let buried = board.buried_notes();
+            let mut stacked: Vec<_> = notes
+                .iter_mut()
+                .filter(|(_, note, ui_state)| {
+                    !tag_filter.hides(note)
+                        && !buried.contains(&note.id)
+                        && (shown.binary_search(&note.id).is_ok() || ui_state.is_editing)
+                })
+                .collect();
+            stacked.sort_by_key(|(_, note, _)| (note.z, note.id));
+            for (_, mut note, mut ui_state) in stacked {
+                over_note |= pointer_in_scene.is_some_and(|p| {
+                    let corners = view_corners(
+                        note.pos,
+                        note.size,
+                        ui_state.skew,
+                        note.rotation,
+                        Vec2::ZERO,
+                        turns,
+                        scene_center(),
+                    );
+                    note.shape.hit(p, &corners)
+                });
+                let highlight = highlight_note == Some(note.id);
+                let has_query = !query.is_empty() && query.matches(board, &note);
+                let expired = is_expired(&note, now);
+                let due = due_status(&note, now);
+                let drop_target = add_note_ui(
+                    ui,
+                    &mut note,
+                    &mut ui_state,
+                    board,
+                    &positions,
+                    snap_step,
+                    guided,
+                    turns,
+                    ev_sound,
+                    &query.text,
+                    has_query,
+                    highlight,
+                    expired,
+                    due,
+                    tag_filter.dims(&note),
+                    &palette,
+                    theme.text_color,
+                    fonts,
+                    tossing,
+                    author.as_ref().map_or(DEFAULT_VOTER, |m| m.name.as_str()),
+                    selection,
+                    connect.enabled,
+                    ballot.is_some(),
+                    &mut actions,
+                    changes,
+                );
+                if let Some(target) = drop_target {
+                    dragged = Some(target);
+                }
+            }
+
+            // Dragged notes line up with the notes around them unless Alt
+            // is held; the guides show what they line up with. Frames take
+            // their notes to the grid instead.
+            let moving: Vec<u64> = notes
+                .iter()
+                .filter(|(_, _, ui_state)| ui_state.drag_origin.is_some())
+                .map(|(_, note, _)| note.id)
+                .collect();
+            let frame_dragged = board.frames.iter().any(|frame| {
+                ui.data(|d| d.get_temp::<Frame>(frame_origin_id(ui, frame.id)))
+                    .is_some()
+            });
+            guide_snap = if moving.is_empty() || frame_dragged || ui.input(|i| i.modifiers.alt) {
+                None
+            } else {
+                board.guide_snap(&spatial, &moving, GUIDE_SNAP_DISTANCE / zoom)
+            };
+            ui.data_mut(|d| d.insert_temp(guided_id, guide_snap.is_some()));
+            for guide in guide_snap.iter().flat_map(|snap| &snap.guides) {
+                ui.painter().line_segment(
+                    [to_view(guide.from), to_view(guide.to)],
+                    Stroke::new(1.0 / zoom, GUIDE_COLOR),
+                );
+            }
+
+            // Piles show how many notes they hold; clicking the count fans
+            // them out or gathers them back
+            let mut fan = None;
+            for (index, pile) in board.piles.iter().enumerate() {
+                let anchor = if pile.fanned {
+                    pile.notes.first().copied()
+                } else {
+                    pile.top()
+                };
+                let Some(note) = anchor.and_then(|id| Some(&board.notes[*positions.get(&id)?]))
+                else {
+                    continue;
+                };
+                let corner = rotate_quarter_turns(
+                    note.pos + Vec2::new(note.size.x, 0.0),
+                    scene_center(),
+                    turns,
+                );
+                let rect = Rect::from_center_size(corner, Vec2::splat(PILE_BADGE_SIZE));
+                if !rect.intersects(visible) {
+                    continue;
+                }
+                let response = ui
+                    .interact(rect, ui.id().with(("pile", index)), egui::Sense::click())
+                    .on_hover_text(if pile.fanned {
+                        "Gather the pile"
+                    } else {
+                        "Fan the pile out"
+                    });
+                let fill = if response.hovered() {
+                    Color32::from_rgb(230, 80, 70)
+                } else {
+                    Color32::from_rgb(200, 60, 50)
+                };
+                ui.painter()
+                    .circle_filled(rect.center(), PILE_BADGE_SIZE / 2.0, fill);
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    pile.notes.len().to_string(),
+                    egui::FontId::proportional(12.0),
+                    Color32::WHITE,
+                );
+                if response.clicked() {
+                    fan = Some(index);
+                }
+            }
+            if let Some(index) = fan {
+                changes.extend(fan_pile(board, notes, index));
+            }
+        })
+        .response;
+    board.scene_rect = scene_rect;
+
+    // Dragging notes towards the edge pans the view and carries them along
+    if let Some(drop_target) = dragged {
+        let velocity = ui
+            .ctx()
+            .pointer_latest_pos()
+            .map(|p| auto_pan_velocity(p, response.rect, AUTO_PAN_MARGIN, AUTO_PAN_SPEED))
+            .unwrap_or(Vec2::ZERO);
+        if velocity != Vec2::ZERO {
+            let dt = ui.ctx().input(|i| i.stable_dt);
+            let pan = velocity * dt / zoom;
+            board.scene_rect = board.scene_rect.translate(pan);
+            let board_pan = rotate_vec_quarter_turns(pan, inverse_quarter_turns(turns));
+            let index = board.note_index();
+            for (_, mut note, _) in notes.iter_mut() {
+                if !selection.contains(note.id) {
+                    continue;
+                }
+                note.pos += board_pan;
+                if let Some(&i) = index.get(&note.id) {
+                    board.notes[i].pos = note.pos;
+                }
+            }
+            drag_pan.engaged = true;
+        }
+        if drag_pan.engaged {
+            show_drag_overview(ui.ctx(), board, drop_target);
+        }
+    } else {
+        drag_pan.engaged = false;
+    }
+
+    if response.clicked() && !over_note {
+        selection.clear();
+    }
+    let mut new_note_key = false;
+    if !ui.ctx().wants_keyboard_input() {
+        let pressed = |action| ui.ctx().input_mut(|i| keys.take(&mut i.events, action));
+        if pressed(Action::Delete) && !selection.is_empty() {
+            actions.push(NoteAction::DeleteSelected);
+        }
+        let (copy, paste, duplicate) = (
+            pressed(Action::Copy),
+            pressed(Action::Paste),
+            pressed(Action::Duplicate),
+        );
+        let nudge: Vec2 = [
+            (Action::NudgeLeft, Vec2::new(-1.0, 0.0)),
+            (Action::NudgeRight, Vec2::new(1.0, 0.0)),
+            (Action::NudgeUp, Vec2::new(0.0, -1.0)),
+            (Action::NudgeDown, Vec2::new(0.0, 1.0)),
+        ]
+        .into_iter()
+        .filter(|&(action, _)| pressed(action))
+        .fold(Vec2::ZERO, |sum, (_, direction)| sum + direction);
+        if nudge != Vec2::ZERO && !selection.is_empty() {
+            // Arrows point on screen, so rotate them back onto the board
+            let delta = rotate_vec_quarter_turns(nudge * grid_step, inverse_quarter_turns(turns));
+            actions.push(NoteAction::Nudge(delta));
+        }
+        let cycle = if pressed(Action::NextNote) {
+            Some(true)
+        } else if pressed(Action::PreviousNote) {
+            Some(false)
+        } else {
+            None
+        };
+        if let Some(forward) = cycle
+            && let Some(id) = cycle_note(board, selection.ids().last().copied(), forward)
+            && let Some(note) = board.notes.iter().find(|n| n.id == id)
+        {
+            selection.select_only(id);
+            let rect = Rect::from_min_size(note.pos, note.size);
+            let center = rotate_quarter_turns(rect.center(), scene_center(), turns);
+            let target = Rect::from_center_size(center, board.scene_rect.size());
+            fly_view(ui.ctx(), board, target);
+        }
+        if pressed(Action::RaisePriority) && !selection.is_empty() {
+            actions.push(NoteAction::StepPriority(true));
+        }
+        if pressed(Action::LowerPriority) && !selection.is_empty() {
+            actions.push(NoteAction::StepPriority(false));
+        }
+        if pressed(Action::EditNote)
+            && let Some(&id) = selection.ids().last()
+        {
+            actions.push(NoteAction::Edit(id));
+        }
+        new_note_key = pressed(Action::NewNote);
+        let selected = || {
+            let notes = notes.iter().filter(|(_, n, _)| selection.contains(n.id));
+            NoteClipboard::copy(
+                notes.map(|(_, n, _)| n.clone()).collect(),
+                &board.connectors,
+            )
+        };
+        if copy && !selection.is_empty() {
+            *clipboard = selected();
+            // Other programs get the text of the notes
+            ui.ctx().copy_text(clipboard.text());
+        }
+        // Text from other programs arrives as a paste event rather than a key
+        let pasted_text = ui.ctx().input_mut(|i| {
+            let index = i
+                .events
+                .iter()
+                .position(|e| matches!(e, egui::Event::Paste(_)))?;
+            match i.events.remove(index) {
+                egui::Event::Paste(text) => Some(text),
+                _ => None,
+            }
+        });
+        let pointer = pointer_in_scene
+            .map(|p| rotate_quarter_turns(p, scene_center(), inverse_quarter_turns(turns)));
+        // Pasting back what was copied from plop pastes the notes themselves
+        let text_paste = pasted_text.as_ref().filter(|text| {
+            !text.trim().is_empty() && (clipboard.is_empty() || text.as_str() != clipboard.text())
+        });
+        if let Some(text) = text_paste {
+            let text = sanitize_text(text, MAX_NOTE_CHARS).text;
+            let at = snap_to_grid(
+                pointer.unwrap_or_else(|| {
+                    rotate_quarter_turns(
+                        board.scene_rect.center(),
+                        scene_center(),
+                        inverse_quarter_turns(turns),
+                    )
+                }),
+                snap_step,
+            );
+            if paragraphs(&text).len() > 1 {
+                // Ask whether to split it up before adding anything
+                ui.data_mut(|d| d.insert_temp(egui::Id::new("paste_text"), (text, at)));
+            } else {
+                actions.push(NoteAction::PasteText {
+                    text,
+                    at,
+                    split: false,
+                });
+            }
+        } else if (paste || pasted_text.is_some()) && !clipboard.is_empty() {
+            let at = match (pointer, clipboard.origin()) {
+                (Some(p), _) => p + PASTE_OFFSET,
+                (None, Some(origin)) => origin + Vec2::splat(grid_step),
+                (None, None) => Pos2::ZERO,
+            };
+            actions.push(NoteAction::Paste {
+                notes: clipboard.clone(),
+                at: snap_to_grid(at, snap_step),
+            });
+        }
+        if duplicate && !selection.is_empty() {
+            let copies = selected();
+            let at = copies.origin().unwrap_or_default() + Vec2::splat(grid_step);
+            actions.push(NoteAction::Paste { notes: copies, at });
+        }
+    }
+    let paste_id = egui::Id::new("paste_text");
+    if let Some((text, at)) = ui.data(|d| d.get_temp::<(String, Pos2)>(paste_id)) {
+        let count = paragraphs(&text).len();
+        let mut choice = None;
+        egui::Window::new("Paste text")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ui.ctx(), |ui| {
+                ui.label(format!("The pasted text has {count} paragraphs."));
+                ui.horizontal(|ui| {
+                    if ui.button(format!("{count} notes")).clicked() {
+                        choice = Some(Some(true));
+                    }
+                    if ui.button("One note").clicked() {
+                        choice = Some(Some(false));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(None);
+                    }
+                });
+            });
+        if let Some(split) = choice {
+            ui.data_mut(|d| d.remove::<(String, Pos2)>(paste_id));
+            if let Some(split) = split {
+                actions.push(NoteAction::PasteText { text, at, split });
+            }
+        }
+    }
+    // Images dropped on a note are attached to it; dropped on the empty
+    // board they become new notes
+    let dropped: Vec<PathBuf> = ui.ctx().input(|i| {
+        i.raw
+            .dropped_files
+            .iter()
+            .filter_map(|f| f.path.clone())
+            .filter(|p| is_image_path(p))
+            .collect()
+    });
+    for (i, image) in dropped.into_iter().enumerate() {
+        let at = match pointer_in_scene {
+            Some(p) => rotate_quarter_turns(p, scene_center(), inverse_quarter_turns(turns)),
+            None => rotate_quarter_turns(
+                board.scene_rect.center(),
+                scene_center(),
+                inverse_quarter_turns(turns),
+            ),
+        } + Vec2::splat(grid_step * i as f32);
+        let target = board
+            .notes
+            .iter()
+            .filter(|n| !tag_filter.hides(n) && Rect::from_min_size(n.pos, n.size).contains(at))
+            .max_by_key(|n| (n.z, n.id));
+        actions.push(match target {
+            Some(note) => NoteAction::AttachImage {
+                id: note.id,
+                image: Some(image),
+            },
+            None => NoteAction::ImageNote { image, at },
+        });
+    }
+    for action in actions {
+        let change = match action {
+            NoteAction::DeleteSelected => {
+                let deleted = selection
+                    .ids()
+                    .iter()
+                    .filter(|&&id| !board.notes.iter().any(|n| n.id == id && n.locked))
+                    .filter_map(|&id| delete_note(board, notes, commands, id))
+                    .collect::<Vec<_>>();
+                if !deleted.is_empty() {
+                    ev_sound.write(PlaySound(SoundEvent::Delete));
+                }
+                selection.clear();
+                Command::batch(deleted)
+            }
+            NoteAction::ArchiveSelected => {
+                let archived: Vec<u64> = board
+                    .notes
+                    .iter()
+                    .filter(|n| selection.contains(n.id) && !n.locked)
+                    .map(|n| n.id)
+                    .collect();
+                for (entity, note, _) in notes.iter() {
+                    if archived.contains(&note.id) {
+                        commands.entity(entity).despawn();
+                    }
+                }
+                archive_notes(board, &archived);
+                selection.clear();
+                None
+            }
+            NoteAction::Duplicate(id) => {
+                let copy = duplicate_note(board, notes, commands, id, *next_note_id, grid_step);
+                if copy.is_some() {
+                    selection.select_only(*next_note_id);
+                    *next_note_id += 1;
+                }
+                copy
+            }
+            NoteAction::Paste { notes: copied, at } => {
+                let (pasted, connectors) = copied.paste(board, next_note_id, at);
+                selection.clear();
+                selection.extend(pasted.iter().map(|n| n.id));
+                let mut created = Vec::new();
+                for note in pasted {
+                    commands.spawn((note.clone(), NoteUi::default()));
+                    board.notes.push(note.clone());
+                    created.push(Command::Create { note });
+                }
+                for connector in connectors {
+                    if board.connect(connector.clone()) {
+                        created.push(Command::Connect { connector });
+                    }
+                }
+                if !created.is_empty() {
+                    ev_sound.write(PlaySound(SoundEvent::Create));
+                }
+                Command::batch(created)
+            }
+            NoteAction::Nudge(delta) => {
+                let index = board.note_index();
+                let mut nudged = Vec::new();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if !selection.contains(note.id) || note.locked {
+                        continue;
+                    }
+                    let before = note.clone();
+                    note.pos += delta;
+                    if let Some(&i) = index.get(&note.id) {
+                        board.notes[i].pos = note.pos;
+                    }
+                    nudged.push(Command::Update {
+                        before,
+                        after: note.clone(),
+                    });
+                }
+                Command::batch(nudged)
+            }
+            NoteAction::Edit(id) => {
+                if let Some((_, note, mut ui_state)) = notes.iter_mut().find(|(_, n, _)| n.id == id)
+                {
+                    start_editing(&note, &mut ui_state);
+                }
+                None
+            }
+            NoteAction::SaveTemplate(id) => {
+                if let Some((_, note, _)) = notes.iter().find(|(_, n, _)| n.id == id) {
+                    templates.push(NoteTemplate::from_note(note));
+                }
+                None
+            }
+            NoteAction::LinkTo { from, to } => {
+                notes
+                    .iter_mut()
+                    .find(|(_, n, _)| n.id == from)
+                    .map(|(_, mut note, _)| {
+                        let before = note.clone();
+                        note.text = append_note_link(&note.text, to);
+                        if let Some(n) = board.notes.iter_mut().find(|n| n.id == from) {
+                            n.text = note.text.clone();
+                        }
+                        Command::Update {
+                            before,
+                            after: note.clone(),
+                        }
+                    })
+            }
+            NoteAction::JumpTo(id) => {
+                if let Some(note) = board.notes.iter().find(|n| n.id == id) {
+                    selection.select_only(id);
+                    // Zoom in far enough for the note to be readable
+                    let rect = Rect::from_min_size(note.pos, note.size);
+                    let center = rotate_quarter_turns(rect.center(), scene_center(), turns);
+                    let size = board
+                        .scene_rect
+                        .size()
+                        .min(rect.size().max_elem() * Vec2::splat(4.0));
+                    fly_view(ui.ctx(), board, Rect::from_center_size(center, size));
+                }
+                None
+            }
+            NoteAction::AttachImage { id, image } => notes
+                .iter_mut()
+                .find(|(_, n, _)| n.id == id && n.image != image)
+                .map(|(_, mut note, _)| {
+                    let before = note.clone();
+                    note.image = image.clone();
+                    if let Some(n) = board.notes.iter_mut().find(|n| n.id == id) {
+                        n.image = image;
+                    }
+                    Command::Update {
+                        before,
+                        after: note.clone(),
+                    }
+                }),
+            NoteAction::PasteText { text, at, split } => {
+                let texts = if split {
+                    paragraphs(&text)
+                } else {
+                    vec![text.trim().to_owned()]
+                };
+                let pasted = texts_to_notes(
+                    texts,
+                    at,
+                    theme.note_size,
+                    board.new_note_color(theme),
+                    next_note_id,
+                );
+                selection.clear();
+                let mut created = Vec::new();
+                for (mut note, z) in pasted.into_iter().zip(board.top_z()..) {
+                    note.author = author.as_ref().map(|m| m.name.clone());
+                    note.z = z;
+                    note.rotation = new_note_tilt(board);
+                    selection.extend([note.id]);
+                    commands.spawn((note.clone(), NoteUi::default()));
+                    board.notes.push(note.clone());
+                    created.push(Command::Create { note });
+                }
+                ev_sound.write(PlaySound(SoundEvent::Create));
+                Command::batch(created)
+            }
+            NoteAction::ImageNote { image, at } => {
+                let note = NoteData {
+                    id: *next_note_id,
+                    pos: snap_to_grid(at - theme.note_size / 2.0, snap_step),
+                    size: theme.note_size,
+                    color: board.new_note_color(theme),
+                    author: author.as_ref().map(|m| m.name.clone()),
+                    z: board.top_z(),
+                    image: Some(image),
+                    rotation: new_note_tilt(board),
+                    ..Default::default()
+                };
+                *next_note_id += 1;
+                commands.spawn((note.clone(), NoteUi::default()));
+                board.notes.push(note.clone());
+                ev_sound.write(PlaySound(SoundEvent::Create));
+                Some(Command::Create { note })
+            }
+            NoteAction::Recolor(color) => {
+                let index = board.note_index();
+                let mut recolored = Vec::new();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if !selection.contains(note.id) || note.color == color {
+                        continue;
+                    }
+                    let before = note.clone();
+                    note.color = color;
+                    note.category = None;
+                    if let Some(&i) = index.get(&note.id) {
+                        board.notes[i].color = color;
+                        board.notes[i].category = None;
+                    }
+                    recolored.push(Command::Update {
+                        before,
+                        after: note.clone(),
+                    });
+                }
+                Command::batch(recolored)
+            }
+            NoteAction::Categorize(name) => {
+                let category = name.and_then(|name| board.category(&name).cloned());
+                let index = board.note_index();
+                let mut categorized = Vec::new();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if !selection.contains(note.id) {
+                        continue;
+                    }
+                    let before = note.clone();
+                    note.set_category(category.as_ref());
+                    if *note == before {
+                        continue;
+                    }
+                    if let Some(&i) = index.get(&note.id) {
+                        board.notes[i].set_category(category.as_ref());
+                    }
+                    categorized.push(Command::Update {
+                        before,
+                        after: note.clone(),
+                    });
+                }
+                Command::batch(categorized)
+            }
+            NoteAction::Prioritize(priority) => prioritize(board, notes, selection, |_| priority),
+            NoteAction::StepPriority(raise) => prioritize(board, notes, selection, |p| {
+                if raise { p.raised() } else { p.lowered() }
+            }),
+            NoteAction::StartMove { from } => {
+                for (_, note, mut ui_state) in notes.iter_mut() {
+                    if note.id != from && selection.contains(note.id) && !note.locked {
+                        ui_state.drag_origin = Some(note.pos);
+                    }
+                }
+                None
+            }
+            NoteAction::Move { from, delta } => {
+                let index = board.note_index();
+                for (_, mut note, _) in notes.iter_mut() {
+                    if note.id == from || !selection.contains(note.id) {
+                        continue;
+                    }
+                    note.pos += delta;
+                    if let Some(&i) = index.get(&note.id) {
+                        board.notes[i].pos = note.pos;
+                    }
+                }
+                None
+            }
+            NoteAction::Drop => {
+                // A tossed note makes its sound when it comes to rest
+                let tossed = notes.iter().any(|(_, _, ui_state)| {
+                    ui_state.drag_origin.is_some() && ui_state.toss.is_some()
+                });
+                if !tossed {
+                    ev_sound.write(PlaySound(if snap_step > 0.0 {
+                        SoundEvent::Snap
+                    } else {
+                        SoundEvent::DragStop
+                    }));
+                }
+                let offset = guide_snap.as_ref().map(|snap| snap.offset);
+                Command::batch(drop_dragged_notes(board, notes, snap_step, offset))
+            }
+            NoteAction::StartFrameMove { notes: members } => {
+                for (_, note, mut ui_state) in notes.iter_mut() {
+                    if members.contains(&note.id) && !note.locked {
+                        ui_state.drag_origin = Some(note.pos);
+                    }
+                }
+                None
+            }
+            NoteAction::MoveFrame(delta) => {
+                let index = board.note_index();
+                for (_, mut note, ui_state) in notes.iter_mut() {
+                    if ui_state.drag_origin.is_none() {
+                        continue;
+                    }
+                    note.pos += delta;
+                    if let Some(&i) = index.get(&note.id) {
+                        board.notes[i].pos = note.pos;
+                    }
+                }
+                None
+            }
+            NoteAction::DropFrame { before, after } => {
+                let mut moved = drop_dragged_notes(board, notes, snap_step, None);
+                moved.push(Command::UpdateFrame { before, after });
+                Command::batch(moved)
+            }
+            NoteAction::RemoveFrame(id) => {
+                let index = board.frames.iter().position(|f| f.id == id);
+                index.map(|index| Command::RemoveFrame {
+                    frame: board.frames.remove(index),
+                    index,
+                })
+            }
+            NoteAction::Connect { from } => {
+                let target = notes.iter().find(|(_, n, ui_state)| {
+                    n.id != from
+                        && pointer_in_scene.is_some_and(|p| {
+                            let corners = view_corners(
+                                n.pos,
+                                n.size,
+                                ui_state.skew,
+                                n.rotation,
+                                Vec2::ZERO,
+                                turns,
+                                scene_center(),
+                            );
+                            n.shape.hit(p, &corners)
+                        })
+                });
+                let connector = target.map(|(_, n, _)| Connector {
+                    from_note: from,
+                    to_note: n.id,
+                    style: connect.style,
+                    label: connect.label.clone(),
+                });
+                connector
+                    .filter(|c| board.connect(c.clone()))
+                    .map(|connector| Command::Connect { connector })
+            }
+            NoteAction::Restack {
+                id,
+                to_front,
+                record,
+            } => {
+                let z = if to_front {
+                    board.raise_z(id)
+                } else {
+                    board.lower_z(id)
+                };
+                let restacked = z.and_then(|z| {
+                    let (_, mut note, _) = notes.iter_mut().find(|(_, n, _)| n.id == id)?;
+                    let before = note.clone();
+                    note.z = z;
+                    if let Some(n) = board.notes.iter_mut().find(|n| n.id == id) {
+                        n.z = z;
+                    }
+                    Some(Command::Update {
+                        before,
+                        after: note.clone(),
+                    })
+                });
+                restacked.filter(|_| record)
+            }
+            NoteAction::Disconnect(id) => {
+                let mut removed = Vec::new();
+                for index in board.connectors_of(id).into_iter().rev() {
+                    let connector = board.connectors.remove(index);
+                    removed.push(Command::Disconnect { connector, index });
+                }
+                Command::batch(removed)
+            }
+            NoteAction::Vote { id, add } => {
+                let voter = author.as_ref().map_or(DEFAULT_VOTER, |m| m.name.as_str());
+                let counted = ballot.as_deref_mut().is_some_and(|ballot| {
+                    if add {
+                        ballot.place(voter, id)
+                    } else {
+                        ballot.take_back(voter, id)
+                    }
+                });
+                // Votes aren't undone, so dots handed out stay in step
+                if counted
+                    && let Some((_, mut note, _)) = notes.iter_mut().find(|(_, n, _)| n.id == id)
+                {
+                    note.votes = if add {
+                        note.votes + 1
+                    } else {
+                        note.votes.saturating_sub(1)
+                    };
+                    if let Some(n) = board.notes.iter_mut().find(|n| n.id == id) {
+                        n.votes = note.votes;
+                    }
+                }
+                None
+            }
+        };
+        changes.extend(change);
+    }
+
+    // Right-clicking the board offers a blank note or one from a template
+    // at that spot; the new note key adds a blank one in the middle of the
+    // view
+    let right_clicked = response.hovered()
+        && !over_note
+        && ui
+            .ctx()
+            .input(|i| i.pointer.button_released(egui::PointerButton::Secondary));
+    // Undo the view rotation to find the spot on the board
+    let to_board = |p: Pos2| rotate_quarter_turns(p, scene_center(), inverse_quarter_turns(turns));
+    let menu_pos_id = ui.id().with("new_note_at");
+    if right_clicked {
+        let pos = pointer_in_scene.map(to_board).unwrap_or(Pos2::ZERO);
+        ui.data_mut(|d| d.insert_temp(menu_pos_id, pos));
+    }
+    let mut picked = None;
+    response.context_menu(|ui| {
+        if ui.button("New note").clicked() {
+            picked = Some(None);
+            ui.close_menu();
+        }
+        if !templates.is_empty() {
+            ui.separator();
+        }
+        for template in templates.iter() {
+            if ui
+                .button(&template.name)
+                .on_hover_text(&template.text)
+                .clicked()
+            {
+                picked = Some(Some(template.clone()));
+                ui.close_menu();
+            }
+        }
+    });
+    let new_note = match picked {
+        Some(template) => {
+            let pos = ui.data(|d| d.get_temp::<Pos2>(menu_pos_id));
+            Some((pos.unwrap_or(Pos2::ZERO), template))
+        }
+        None if new_note_key => {
+            let pos = to_board(board.scene_rect.center()) - theme.note_size / 2.0;
+            Some((pos, None))
+        }
+        None => None,
+    };
+    if let Some((pos, template)) = new_note {
+        let id = *next_note_id;
+        *next_note_id += 1;
+        let mut ui_state = NoteUi::default();
+        let mut data = match &template {
+            Some(template) => template.instantiate(id, pos),
+            None => NoteData {
+                id,
+                text: "New note".into(),
+                size: theme.note_size,
+                color: match &author {
+                    Some(member) => author_color(board.new_note_color(theme), member),
+                    None => board.new_note_color(theme),
+                },
+                ..Default::default()
+            },
+        };
+        data.pos = snap_to_grid(pos, snap_step);
+        data.author = author.map(|m| m.name);
+        data.z = board.top_z();
+        data.rotation = new_note_tilt(board);
+        // Notes from a template open for typing the rest of the text, and
+        // notes made from the keyboard for typing theirs
+        if template.is_some() || new_note_key {
+            start_editing(&data, &mut ui_state);
+        }
+        commands.spawn((data.clone(), ui_state));
+        board.notes.push(data.clone());
+        changes.push(Command::Create { note: data });
+        if new_note_key {
+            selection.select_only(id);
+        }
+
+        // Send event to play sound
+        ev_sound.write(PlaySound(SoundEvent::Create));
+    }
+}
+
+/// Fan the pile at `index` out or gather it back, returning the moves for
+/// undo
+fn fan_pile(
+    board: &mut Board,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    index: usize,
+) -> Option<Command> {
+    let positions = board.toggle_fan(index);
+    let mut moved = Vec::new();
+    for (_, mut note, _) in notes.iter_mut() {
+        if let Some(&(_, pos)) = positions.iter().find(|(id, _)| *id == note.id)
+            && note.pos != pos
+        {
+            let before = note.clone();
+            note.pos = pos;
+            moved.push(Command::Update {
+                before,
+                after: note.clone(),
+            });
+        }
+    }
+    Command::batch(moved)
+}
+
+/// Snap the notes being dragged to the grid, or by `guide_offset` into line
+/// with other notes, and end their drag, returning the moves for undo; a
+/// single note dropped onto another piles up with it
+fn drop_dragged_notes(
+    board: &mut Board,
+    notes: &mut Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    grid_step: f32,
+    guide_offset: Option<Vec2>,
+) -> Vec<Command> {
+    let index = board.note_index();
+    let mut moved = Vec::new();
+    for (_, mut note, mut ui_state) in notes.iter_mut() {
+        // Tossed notes are dropped once they stop sliding
+        if ui_state.toss.is_some() {
+            continue;
+        }
+        let Some(from) = ui_state.drag_origin.take() else {
+            continue;
+        };
+        note.pos = match guide_offset {
+            Some(offset) => note.pos + offset,
+            None => snap_to_grid(note.pos, grid_step),
+        };
+        board.place_in_lane(&mut note);
+        if let Some(&i) = index.get(&note.id) {
+            board.notes[i].pos = note.pos;
+            board.notes[i].lane = note.lane.clone();
+        }
+        moved.push(Command::Update {
+            before: NoteData {
+                pos: from,
+                ..note.clone()
+            },
+            after: note.clone(),
+        });
+    }
+
+    if let [Command::Update { after, .. }] = moved.as_mut_slice() {
+        let center = after.pos + after.size / 2.0;
+        let target = board
+            .notes
+            .iter()
+            .filter(|n| n.id != after.id && !board.is_buried(n.id))
+            .filter(|n| Rect::from_min_size(n.pos, n.size).contains(center))
+            .max_by_key(|n| (n.z, n.id))
+            .map(|n| n.id);
+        if let Some(target) = target
+            && let Some(pos) = board.pile_up(after.id, target)
+        {
+            after.pos = pos;
+            for (_, mut note, _) in notes.iter_mut() {
+                if note.id == after.id {
+                    note.pos = pos;
+                }
+            }
+        }
+    }
+    // Piles dragged along whole, e.g. with their frame, stay piled
+    let dropped: HashSet<u64> = moved
+        .iter()
+        .filter_map(|c| match c {
+            Command::Update { after, .. } => Some(after.id),
+            _ => None,
+        })
+        .collect();
+    let index = board.note_index();
+    for pile in &mut board.piles {
+        if !pile.fanned
+            && pile.notes.iter().all(|id| dropped.contains(id))
+            && let Some(&i) = index.get(&pile.notes[0])
+        {
+            pile.pos = board.notes[i].pos;
+        }
+    }
+    moved
+}
+
+/// Slide tossed notes on, bouncing off the edges of the view, and snap each
+/// into place once it rests
+fn toss_system(
+    time: Res<Time>,
+    mut app: ResMut<PostItData>,
+    mut notes: Query<(&mut NoteData, &mut NoteUi)>,
+    grid: Res<GridSize>,
+    board_viewport: Res<BoardViewport>,
+    mut undo: ResMut<UndoHistory>,
+    mut ev_sound: EventWriter<PlaySound>,
+) {
+    let dt = time.delta_secs();
+    let board = app.state.board_mut();
+    let turns = board.view_quarter_turns;
+    let view = rotate_rect_quarter_turns(
+        board.scene_rect,
+        scene_center(),
+        inverse_quarter_turns(turns),
+    );
+    let index = board.note_index();
+    for (mut note, mut ui_state) in notes.iter_mut() {
+        let Some(velocity) = ui_state.toss else {
+            continue;
+        };
+        let (pos, velocity) = slide(note.pos, note.size, velocity, dt, view);
+        note.pos = pos;
+        ui_state.toss = Some(velocity);
+        if is_resting(velocity) {
+            ui_state.toss = None;
+            let step = adaptive_grid_step(
+                board.grid(grid.0),
+                view_zoom(board_viewport.0, board.scene_rect),
+            );
+            note.pos = snap_to_grid(note.pos, step);
+            board.place_in_lane(&mut note);
+            if let Some(from) = ui_state.drag_origin.take() {
+                undo.0.record(
+                    board.id,
+                    Command::Update {
+                        before: NoteData {
+                            pos: from,
+                            ..note.clone()
+                        },
+                        after: note.clone(),
+                    },
+                );
+            }
+            ev_sound.write(PlaySound(if step > 0.0 {
+                SoundEvent::Snap
+            } else {
+                SoundEvent::DragStop
+            }));
+        }
+        if let Some(&i) = index.get(&note.id) {
+            board.notes[i].pos = note.pos;
+            board.notes[i].lane = note.lane.clone();
+        }
+    }
+}
+
+/// Game controllers: the left stick pans the board and the right one
+/// zooms, the d-pad moves the selection to the next note that way, South
+/// opens the selected note or finishes editing it and East deselects
+fn gamepad_system(
+    time: Res<Time>,
+    gamepads: Query<&Gamepad>,
+    mut contexts: EguiContexts,
+    mut app: ResMut<PostItData>,
+    mut selected: ResMut<SelectedNotes>,
+    mut notes: Query<(&NoteData, &mut NoteUi)>,
+    board_viewport: Res<BoardViewport>,
+) {
+    let dt = time.delta_secs();
+    let ctx = contexts.ctx_mut();
+    let board = app.state.board_mut();
+    let turns = board.view_quarter_turns;
+    // Sticks point up, the screen counts down
+    let screen = |stick: bevy::math::Vec2| stick_input(Vec2::new(stick.x, -stick.y));
+    for gamepad in &gamepads {
+        let pan = screen(gamepad.left_stick());
+        if pan != Vec2::ZERO {
+            stop_flight(ctx);
+            board.scene_rect = pan_scene_rect(board.scene_rect, pan, dt);
+        }
+        let zoom = -screen(gamepad.right_stick()).y;
+        if zoom != 0.0 && board_viewport.0.is_positive() {
+            stop_flight(ctx);
+            board.scene_rect =
+                zoom_scene_rect(board_viewport.0, board.scene_rect, zoom_factor(zoom, dt));
+        }
+
+        let editing = notes.iter().any(|(_, ui_state)| ui_state.is_editing);
+        if gamepad.just_pressed(GamepadButton::South) {
+            if editing {
+                for (_, mut ui_state) in notes.iter_mut().filter(|(_, u)| u.is_editing) {
+                    ui_state.finish_edit = true;
+                }
+            } else if let Some(&id) = selected.0.ids().last()
+                && let Some((note, mut ui_state)) = notes.iter_mut().find(|(n, _)| n.id == id)
+            {
+                start_editing(note, &mut ui_state);
+            }
+        }
+        if editing {
+            continue;
+        }
+        if gamepad.just_pressed(GamepadButton::East) {
+            selected.0.clear();
+        }
+        let direction = [
+            (GamepadButton::DPadLeft, Vec2::new(-1.0, 0.0)),
+            (GamepadButton::DPadRight, Vec2::new(1.0, 0.0)),
+            (GamepadButton::DPadUp, Vec2::new(0.0, -1.0)),
+            (GamepadButton::DPadDown, Vec2::new(0.0, 1.0)),
+        ]
+        .into_iter()
+        .find(|&(button, _)| gamepad.just_pressed(button));
+        if let Some((_, direction)) = direction {
+            // The d-pad points on screen, so rotate it back onto the board
+            let direction = rotate_vec_quarter_turns(direction, inverse_quarter_turns(turns));
+            let next = match selected.0.ids().last() {
+                Some(&id) => note_towards(board, id, direction),
+                None => {
+                    let center = rotate_quarter_turns(
+                        board.scene_rect.center(),
+                        scene_center(),
+                        inverse_quarter_turns(turns),
+                    );
+                    nearest_note(board, center)
+                }
+            };
+            if let Some(id) = next
+                && let Some(note) = board.notes.iter().find(|n| n.id == id)
+            {
+                selected.0.select_only(id);
+                let rect = Rect::from_min_size(note.pos, note.size);
+                let center = rotate_quarter_turns(rect.center(), scene_center(), turns);
+                let target = Rect::from_center_size(center, board.scene_rect.size());
+                fly_view(ctx, board, target);
+            }
+        }
+    }
+}
+
+/// Where the frame `id` is kept as it was while it is dragged
+fn frame_origin_id(ui: &egui::Ui, id: u64) -> egui::Id {
+    ui.id().with(("frame_origin", id))
+}
+
+/// Draw a frame; its title bar drags it along with its notes and opens its
+/// menu, and its bottom-right corner resizes it
+fn frame_ui(
+    ui: &mut egui::Ui,
+    frame: &mut Frame,
+    notes: &[NoteData],
+    view_turns: u8,
+    grid_size: f32,
+    palette: &[NamedColor],
+    actions: &mut Vec<NoteAction>,
+    changes: &mut Vec<Command>,
+) {
+    let rect = rotate_rect_quarter_turns(frame.rect, scene_center(), view_turns);
+    let bar = title_bar(rect);
+    let painter = ui.painter();
+    painter.rect_filled(rect, 4.0, frame.fill());
+    painter.rect_stroke(
+        rect,
+        4.0,
+        Stroke::new(1.5, frame.color),
+        egui::StrokeKind::Inside,
+    );
+    painter.rect_filled(bar, 4.0, frame.color);
+    painter.text(
+        bar.left_center() + egui::vec2(8.0, 0.0),
+        egui::Align2::LEFT_CENTER,
+        &frame.title,
+        egui::FontId::proportional(14.0),
+        Color32::WHITE,
+    );
+
+    let origin_id = frame_origin_id(ui, frame.id);
+    let response = ui.interact(
+        bar,
+        ui.id().with(("frame", frame.id)),
+        egui::Sense::click_and_drag(),
+    );
+    if response.drag_started() {
+        ui.data_mut(|d| d.insert_temp(origin_id, frame.clone()));
+        let members = notes.iter().filter(|n| frame.contains(n)).map(|n| n.id);
+        actions.push(NoteAction::StartFrameMove {
+            notes: members.collect(),
+        });
+    }
+    if response.dragged() {
+        let delta =
+            rotate_vec_quarter_turns(response.drag_delta(), inverse_quarter_turns(view_turns));
+        frame.rect = frame.rect.translate(delta);
+        actions.push(NoteAction::MoveFrame(delta));
+    }
+    if response.drag_stopped() {
+        let snapped = snap_to_grid(frame.rect.min, grid_size);
+        frame.rect = frame.rect.translate(snapped - frame.rect.min);
+        if let Some(before) = ui.data(|d| d.get_temp::<Frame>(origin_id)) {
+            ui.data_mut(|d| d.remove::<Frame>(origin_id));
+            actions.push(NoteAction::DropFrame {
+                before,
+                after: frame.clone(),
+            });
+        }
+    }
+
+    let handle = ResizeHandle::BottomRight;
+    let handle_rect = rotate_rect_quarter_turns(
+        handle.rect(frame.rect, 2.0 * RESIZE_HANDLE_SIZE),
+        scene_center(),
+        view_turns,
+    );
+    let resize = ui
+        .interact(
+            handle_rect,
+            ui.id().with(("frame_resize", frame.id)),
+            egui::Sense::drag(),
+        )
+        .on_hover_cursor(resize_cursor(handle, view_turns));
+    if resize.drag_started() {
+        ui.data_mut(|d| d.insert_temp(origin_id, frame.clone()));
+    }
+    if resize.dragged() {
+        let delta =
+            rotate_vec_quarter_turns(resize.drag_delta(), inverse_quarter_turns(view_turns));
+        frame.rect = resize_rect(frame.rect, handle, delta, MIN_FRAME_SIZE);
+    }
+    if resize.drag_stopped()
+        && let Some(before) = ui.data(|d| d.get_temp::<Frame>(origin_id))
+    {
+        ui.data_mut(|d| d.remove::<Frame>(origin_id));
+        changes.push(Command::UpdateFrame {
+            before,
+            after: frame.clone(),
+        });
+    }
+
+    // Renaming and recoloring are recorded together when the menu closes
+    let menu_id = ui.id().with(("frame_menu", frame.id));
+    let menu = response.context_menu(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut frame.title).hint_text("title"));
+        ui.horizontal(|ui| palette_swatches(ui, &mut frame.color, palette));
+        if ui
+            .button("Remove frame")
+            .on_hover_text("The notes inside stay")
+            .clicked()
+        {
+            actions.push(NoteAction::RemoveFrame(frame.id));
+            ui.close_menu();
+        }
+    });
+    match (menu.is_some(), ui.data(|d| d.get_temp::<Frame>(menu_id))) {
+        (true, None) => ui.data_mut(|d| d.insert_temp(menu_id, frame.clone())),
+        (false, Some(before)) => {
+            ui.data_mut(|d| d.remove::<Frame>(menu_id));
+            changes.push(Command::UpdateFrame {
+                before,
+                after: frame.clone(),
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Draw a connector between notes drawn at `from` and `to` (view coordinates)
+fn paint_connector(painter: &egui::Painter, from: Rect, to: Rect, connector: &Connector) {
+    painter.extend(connector_shapes(from, to, connector.style));
+    if !connector.label.is_empty() {
+        let (start, end) = connector_ends(from, to);
+        painter.text(
+            start + (end - start) * 0.5,
+            egui::Align2::CENTER_BOTTOM,
+            &connector.label,
+            egui::FontId::proportional(12.0),
+            CONNECTOR_COLOR,
+        );
+    }
+}
+
+/// Remove the note `id` from the board along with its connectors and
+/// despawn its entity
+fn delete_note(
+    board: &mut Board,
+    notes: &Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    commands: &mut Commands,
+    id: u64,
+) -> Option<Command> {
+    let index = board.notes.iter().position(|n| n.id == id)?;
+    let mut removed = board.disconnect_note(id);
+    let note = board.notes.remove(index);
+    board.trash_note(note.clone(), now_secs());
+    if let Some((entity, ..)) = notes.iter().find(|(_, n, _)| n.id == id) {
+        commands.entity(entity).despawn();
+    }
+    removed.push(Command::Delete { note, index });
+    Command::batch(removed)
+}
+
+/// Add a copy of the note `id` as `new_id`, one grid step down and right
+fn duplicate_note(
+    board: &mut Board,
+    notes: &Query<(Entity, &mut NoteData, &mut NoteUi)>,
+    commands: &mut Commands,
+    id: u64,
+    new_id: u64,
+    grid_step: f32,
+) -> Option<Command> {
+    let (_, original, _) = notes.iter().find(|(_, n, _)| n.id == id)?;
+    let copy = NoteData {
+        id: new_id,
+        pos: original.pos + Vec2::splat(grid_step),
+        rank: None,
+        z: board.top_z(),
+        ..original.clone()
+    };
+    commands.spawn((copy.clone(), NoteUi::default()));
+    board.notes.push(copy.clone());
+    Some(Command::Create { note: copy })
+}
+
+/// Row of color swatches; returns whether one was picked
+fn palette_swatches(ui: &mut egui::Ui, color: &mut Color32, palette: &[NamedColor]) -> bool {
+    let mut picked = false;
+    for named in palette {
+        let swatch = named.color;
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::click());
+        let response = response.on_hover_text(&named.name);
+        ui.painter().rect_filled(rect, 2.0, swatch);
+        if swatch == *color {
+            ui.painter().rect_stroke(
+                rect,
+                2.0,
+                Stroke::new(1.5, Color32::BLACK),
+                egui::StrokeKind::Outside,
+            );
+        }
+        if response.clicked() {
+            *color = swatch;
+            picked = true;
+        }
+    }
+    picked
+}
+
+/// Small square of a category's color in front of its name
+fn category_swatch(ui: &mut egui::Ui, color: Color32) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::splat(12.0), egui::Sense::hover());
+    ui.painter().rect_filled(rect, 2.0, color);
+    ui.painter().rect_stroke(
+        rect,
+        2.0,
+        Stroke::new(1.0, Color32::GRAY),
+        egui::StrokeKind::Inside,
+    );
+}
+
+/// Legend in the bottom-left corner of the board saying what each category
+/// color means and how many notes are in it
+fn show_category_legend(ctx: &egui::Context, board: &Board) {
+    let counts = board.category_counts();
+    egui::Area::new(egui::Id::new("category_legend"))
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(12.0, -12.0))
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                for (category, count) in board.categories.iter().zip(counts) {
+                    ui.horizontal(|ui| {
+                        category_swatch(ui, category.color);
+                        ui.label(&category.name);
+                        ui.weak(count.to_string());
+                    });
+                }
+            });
+        });
+}
+
+/// Small inset in the corner of the board showing where the view and the
+/// dragged note's drop target are relative to all notes
+fn show_drag_overview(ctx: &egui::Context, board: &Board, drop_target: Rect) {
+    egui::Area::new(egui::Id::new("drag_overview"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(ctx, |ui| {
+            let (frame, _) = ui.allocate_exact_size(OVERVIEW_SIZE, egui::Sense::hover());
+            paint_overview(ui.painter(), frame, board, Some(drop_target));
+        });
+}
+
+/// Minimap in the corner of the board; clicking on it glides the view
+/// there, dragging on it moves the view along
+fn show_minimap(ctx: &egui::Context, board: &mut Board) {
+    egui::Area::new(egui::Id::new("minimap"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            let (frame, response) =
+                ui.allocate_exact_size(OVERVIEW_SIZE, egui::Sense::click_and_drag());
+            let transform = paint_overview(ui.painter(), frame, board, None);
+            if let Some(p) = response.interact_pointer_pos() {
+                let target =
+                    Rect::from_center_size(transform.inverse() * p, board.scene_rect.size());
+                if response.clicked() {
+                    fly_view(ctx, board, target);
+                } else if response.dragged() {
+                    stop_flight(ctx);
+                    board.scene_rect = target;
+                }
+            }
+            response.on_hover_cursor(egui::CursorIcon::PointingHand);
+        });
+}
+
+/// Paint every note of `board` scaled into `frame`, with the current view
+/// outlined and an optional highlighted `target` (in board coordinates).
+///
+/// Returns the transform from scene coordinates into `frame`.
+fn paint_overview(
+    painter: &egui::Painter,
+    frame: Rect,
+    board: &Board,
+    target: Option<Rect>,
+) -> TSTransform {
+    let turns = board.view_quarter_turns;
+    let to_view = |r: Rect| rotate_rect_quarter_turns(r, scene_center(), turns);
+    let notes: Vec<(Rect, Color32)> = board
+        .notes
+        .iter()
+        .map(|n| (to_view(Rect::from_min_size(n.pos, n.size)), n.color))
+        .collect();
+    let target = target.map(to_view);
+
+    let mut bounds = board.scene_rect;
+    for (rect, _) in &notes {
+        bounds = bounds.union(*rect);
+    }
+    if let Some(target) = target {
+        bounds = bounds.union(target);
+    }
+    let bounds = bounds.expand(bounds.size().max_elem() * 0.05);
+    let transform = fit_transform(bounds, frame.shrink(4.0));
+
+    painter.rect_filled(frame, 4.0, board.background);
+    painter.rect_stroke(
+        frame,
+        4.0,
+        Stroke::new(1.0, Color32::DARK_GRAY),
+        egui::StrokeKind::Inside,
+    );
+    for (rect, color) in notes {
+        painter.rect_filled(transform * rect, 0.0, color);
+    }
+    painter.rect_stroke(
+        transform * board.scene_rect,
+        0.0,
+        Stroke::new(1.5, Color32::DARK_BLUE),
+        egui::StrokeKind::Outside,
+    );
+    if let Some(target) = target {
+        painter.rect_stroke(
+            transform * target,
+            0.0,
+            Stroke::new(1.5, Color32::RED),
+            egui::StrokeKind::Outside,
+        );
+    }
+    transform
+}
+
+/// Paint grid lines (or dots where they cross) every `step` over `area`;
+/// lines on the base grid (or every fourth line when zoomed out) are drawn
+/// stronger
+fn paint_grid(
+    painter: &egui::Painter,
+    area: Rect,
+    step: f32,
+    base: f32,
+    zoom: f32,
+    color: Color32,
+    dots: bool,
+) {
+    if step <= 0.0 || !area.is_positive() {
+        return;
+    }
+    let major_every = if step < base { base } else { step * 4.0 };
+    let width = 1.0 / zoom.max(f32::EPSILON);
+    let minor = Stroke::new(width, color.gamma_multiply(0.45));
+    let major = Stroke::new(width, color);
+    let is_major = |v: f32| ((v / major_every).round() * major_every - v).abs() < step * 0.01;
+
+    if dots {
+        let mut y = (area.min.y / step).floor() * step;
+        while y <= area.max.y {
+            let mut x = (area.min.x / step).floor() * step;
+            while x <= area.max.x {
+                let (radius, color) = if is_major(x) && is_major(y) {
+                    (width * 2.0, major.color)
+                } else {
+                    (width * 1.5, minor.color)
+                };
+                painter.circle_filled(Pos2::new(x, y), radius, color);
+                x += step;
+            }
+            y += step;
+        }
+        return;
+    }
+
+    let mut x = (area.min.x / step).floor() * step;
+    while x <= area.max.x {
+        let stroke = if is_major(x) { major } else { minor };
+        painter.vline(x, area.y_range(), stroke);
+        x += step;
+    }
+    let mut y = (area.min.y / step).floor() * step;
+    while y <= area.max.y {
+        let stroke = if is_major(y) { major } else { minor };
+        painter.hline(area.x_range(), y, stroke);
+        y += step;
+    }
+}
+
+/// Paint cork speckles over `area`, lighter and darker than the background
+fn paint_cork(painter: &egui::Painter, area: Rect, zoom: f32) {
+    // Below a pixel across the speckles would only flicker
+    if zoom < 0.5 {
+        return;
+    }
+    for speckle in cork_speckles(area) {
+        let color = if speckle.dark {
+            Color32::from_black_alpha(50)
+        } else {
+            Color32::from_white_alpha(40)
+        };
+        painter.circle_filled(speckle.center, speckle.radius, color);
+    }
+}
+
+/// Draw the kanban lanes over the visible part `area` of a board viewed
+/// `turns` quarter turns around; their names stay in view along the top
+fn paint_lanes(painter: &egui::Painter, lanes: &[Lane], area: Rect, turns: u8) {
+    let on_board = rotate_rect_quarter_turns(area, scene_center(), inverse_quarter_turns(turns));
+    for (i, lane) in lanes.iter().enumerate() {
+        let column = Rect::from_x_y_ranges(lane_span(i), on_board.y_range());
+        if !column.intersects(on_board) {
+            continue;
+        }
+        let header = Rect::from_min_size(column.min, Vec2::new(column.width(), LANE_HEADER_HEIGHT));
+        let to_view = |r: Rect| rotate_rect_quarter_turns(r, scene_center(), turns);
+        painter.rect_filled(to_view(column), 0.0, lane.fill());
+        painter.rect_filled(to_view(header.shrink(2.0)), 4.0, lane.color);
+        painter.text(
+            to_view(header).center(),
+            egui::Align2::CENTER_CENTER,
+            &lane.name,
+            egui::FontId::proportional(15.0),
+            Color32::WHITE,
+        );
+    }
+}
+
+/// Draw the note text centered on `center`, rotated by `angle`; returns
+/// the web link under `pointer`
+fn paint_note_text(
+    ui: &egui::Ui,
+    note: &NoteData,
+    query: &str,
+    highlight_match: bool,
+    center: Pos2,
+    angle: f32,
+    fit: Vec2,
+    color: Color32,
+    font: &TextFont,
+    pointer: Option<Pos2>,
+) -> Option<String> {
+    let font_size = ui
+        .ctx()
+        .fonts(|f| fitted_font_size(f, &note.text, fit, font));
+    let mut job = markdown::layout_job(
+        &note.text,
+        &font.id(font_size),
+        font.bold,
+        highlight_match.then_some(query),
+    );
+    markdown::tint(&mut job, color);
+    ui.ctx().fonts(|f| wrap_to_fit(f, &mut job, fit));
+    let galley = ui.painter().layout_job(job);
+    // Text rotates around its top-left corner, so offset by the rotated half size
+    let half = egui::emath::Rot2::from_angle(angle) * (galley.size() * 0.5);
+    let origin = center - half;
+    let link = pointer.and_then(|p| {
+        let local = egui::emath::Rot2::from_angle(-angle) * (p - origin);
+        if !galley.rect.contains(local.to_pos2()) {
+            return None;
+        }
+        let index = galley.cursor_from_pos(local).ccursor.index;
+        let offset = galley.job.text.char_indices().nth(index)?.0;
+        markdown::link_at(&note.text, offset)
+    });
+    paint_galley(ui.painter(), origin, galley, angle, font.bold);
+    link
+}
+
+/// Draw `galley` from `pos`, turned by `angle`; bold text is drawn twice a
+/// hair apart, as the default fonts have no bold face
+fn paint_galley(
+    painter: &egui::Painter,
+    pos: Pos2,
+    galley: std::sync::Arc<egui::Galley>,
+    angle: f32,
+    bold: bool,
+) {
+    if bold {
+        let nudge = egui::emath::Rot2::from_angle(angle) * Vec2::new(0.6, 0.0);
+        painter.add(
+            egui::epaint::TextShape::new(pos + nudge, galley.clone(), Color32::BLACK)
+                .with_angle(angle),
+        );
+    }
+    painter.add(egui::epaint::TextShape::new(pos, galley, Color32::BLACK).with_angle(angle));
+}
+
+/// Texture of the image at `path`, loaded on first use; `None` if it can't
+/// be read, which is remembered so it isn't retried every frame
+fn note_texture(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    let id = egui::Id::new(("note_image", path));
+    if let Some(texture) = ctx.data(|d| d.get_temp::<Option<egui::TextureHandle>>(id)) {
+        return texture;
+    }
+    let texture = match load_image(path) {
+        Ok(image) => Some(ctx.load_texture(path.display().to_string(), image, Default::default())),
+        Err(e) => {
+            warn!("Could not load image {}: {e}", path.display());
+            None
+        }
+    };
+    ctx.data_mut(|d| d.insert_temp(id, texture.clone()));
+    texture
+}
+
+/// Draw `texture` over `rect` (board coordinates), with its corners placed
+/// on screen by `to_view`
+fn paint_note_image(
+    painter: &egui::Painter,
+    texture: &egui::TextureHandle,
+    rect: Rect,
+    to_view: impl Fn(Pos2) -> Pos2,
+) {
+    let mut mesh = egui::Mesh::with_texture(texture.id());
+    let corners = [
+        (rect.left_top(), Pos2::new(0.0, 0.0)),
+        (rect.right_top(), Pos2::new(1.0, 0.0)),
+        (rect.right_bottom(), Pos2::new(1.0, 1.0)),
+        (rect.left_bottom(), Pos2::new(0.0, 1.0)),
+    ];
+    for (pos, uv) in corners {
+        mesh.vertices.push(egui::epaint::Vertex {
+            pos: to_view(pos),
+            uv,
+            color: Color32::WHITE,
+        });
+    }
+    mesh.add_triangle(0, 1, 2);
+    mesh.add_triangle(0, 2, 3);
+    painter.add(Shape::mesh(mesh));
+}
+
+/// Space between a checklist note's border and its lines
+const CHECKLIST_PADDING: f32 = 6.0;
+
+/// Color of checked checklist items
+const CHECKED_COLOR: Color32 = Color32::from_gray(120);
+
+/// Draw a checklist note's lines from the top, with a box in front of each
+/// item that checks or unchecks it in the note's text when clicked
+fn checklist_ui(
+    ui: &mut egui::Ui,
+    note: &mut NoteData,
+    board: &mut Board,
+    query: &str,
+    highlight_match: bool,
+    view_turns: u8,
+    text_color: Color32,
+    font: &TextFont,
+    changes: &mut Vec<Command>,
+) {
+    let angle = view_turns as f32 * std::f32::consts::FRAC_PI_2 + note.rotation;
+    let (pivot, tilt) = (note.pos + note.size / 2.0, note.rotation);
+    let to_view =
+        |p: Pos2| rotate_quarter_turns(rotate_about(p, pivot, tilt), scene_center(), view_turns);
+    let font_size = ui
+        .ctx()
+        .fonts(|f| fitted_font_size(f, &note.text, note.size, font));
+    let box_size = font_size * 0.8;
+    let bottom = note.pos.y + note.size.y - CHECKLIST_PADDING;
+    let mut y = note.pos.y + CHECKLIST_PADDING;
+    let mut toggled = None;
+    for (i, line) in note.text.lines().enumerate() {
+        let item = checklist::parse_item(line);
+        let text = item.map_or(line, |(_, text)| text);
+        let mut job = markdown::layout_job(
+            text,
+            &font.id(font_size),
+            font.bold,
+            highlight_match.then_some(query),
+        );
+        markdown::tint(&mut job, text_color);
+        if let Some((true, _)) = item {
+            for section in &mut job.sections {
+                section.format.color = CHECKED_COLOR;
+                section.format.strikethrough = Stroke::new(1.0, CHECKED_COLOR);
+            }
+        }
+        // Long items wrap beside their box
+        let indent = if item.is_some() {
+            box_size + font_size * 0.4
+        } else {
+            0.0
+        };
+        job.wrap.max_width = (note.size.x - 2.0 * CHECKLIST_PADDING - indent).max(1.0);
+        let galley = ui.painter().layout_job(job);
+        let height = galley.size().y.max(font_size);
+        if y + height > bottom {
+            break;
+        }
+        let mut x = note.pos.x + CHECKLIST_PADDING;
+        if let Some((checked, _)) = item {
+            let box_rect = Rect::from_min_size(
+                Pos2::new(x, y + (height - box_size) / 2.0),
+                Vec2::splat(box_size),
+            );
+            let box_corners = [
+                box_rect.left_top(),
+                box_rect.right_top(),
+                box_rect.right_bottom(),
+                box_rect.left_bottom(),
+            ]
+            .map(to_view);
+            let view_box = quad_bounds(&box_corners);
+            let response = ui
+                .interact(
+                    view_box,
+                    ui.id().with(("check", note.id, i)),
+                    egui::Sense::click(),
+                )
+                .on_hover_cursor(egui::CursorIcon::PointingHand);
+            if response.clicked() {
+                toggled = Some(i);
+            }
+            let stroke = Stroke::new(1.5, text_color);
+            ui.painter()
+                .add(Shape::closed_line(box_corners.to_vec(), stroke));
+            if checked {
+                let mark = [(0.2, 0.55), (0.42, 0.75), (0.8, 0.25)]
+                    .map(|(u, v)| to_view(box_rect.min + box_rect.size() * Vec2::new(u, v)));
+                ui.painter().line(mark.to_vec(), stroke);
+            }
+            x += box_size + font_size * 0.4;
+        }
+        paint_galley(
+            ui.painter(),
+            to_view(Pos2::new(x, y)),
+            galley,
+            angle,
+            font.bold,
+        );
+        y += height;
+    }
+
+    // How much is done, in the top-right corner
+    let (done, total) = checklist::progress(&note.text);
+    let corner = Pos2::new(note.pos.x + note.size.x - CHECKLIST_PADDING, note.pos.y);
+    let galley = ui.painter().layout_no_wrap(
+        format!("{done}/{total}"),
+        egui::FontId::proportional(11.0),
+        CHECKED_COLOR,
+    );
+    let pos = corner - Vec2::new(galley.size().x, 0.0);
+    ui.painter()
+        .add(egui::epaint::TextShape::new(to_view(pos), galley, CHECKED_COLOR).with_angle(angle));
+
+    if let Some(line) = toggled {
+        let before = note.clone();
+        note.text = checklist::toggle_item(&note.text, line);
+        if let Some(n) = board.notes.iter_mut().find(|n| n.id == note.id) {
+            n.text = note.text.clone();
+        }
+        changes.push(Command::Update {
+            before,
+            after: note.clone(),
+        });
+    }
+}
+
+/// Show the note's `file://` links as clickable chips below `note_rect`,
+/// marking links whose target is missing; returns where further chips go
+fn paint_file_links(
+    ui: &mut egui::Ui,
+    note: &NoteData,
+    ui_state: &mut NoteUi,
+    note_rect: Rect,
+) -> Pos2 {
+    let now = ui.ctx().input(|i| i.time);
+    let stale = match &ui_state.links_checked {
+        Some((text, at)) => *text != note.text || now - at > LINK_CHECK_INTERVAL,
+        None => true,
+    };
+    if stale {
+        ui_state.file_links = file_links(&note.text)
+            .into_iter()
+            .map(|p| {
+                let exists = p.exists();
+                (p, exists)
+            })
+            .collect();
+        ui_state.links_checked = Some((note.text.clone(), now));
+    }
+
+    let mut pos = note_rect.left_bottom() + egui::vec2(0.0, 2.0);
+    for (path, exists) in &ui_state.file_links {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        let label = if !exists {
+            egui::RichText::new(format!("⚠ {name}")).color(Color32::RED)
+        } else if path.is_dir() {
+            egui::RichText::new(format!("🗀 {name}"))
+        } else {
+            egui::RichText::new(format!("🗋 {name}"))
+        };
+        let chip = egui::Button::new(label.small()).small();
+        let rect = Rect::from_min_size(pos, egui::vec2(note_rect.width(), 16.0));
+        let response = ui.put(rect, chip).on_hover_text(if *exists {
+            path.display().to_string()
+        } else {
+            format!("Missing: {}", path.display())
+        });
+        if response.clicked()
+            && *exists
+            && let Err(e) = open_path(path)
+        {
+            warn!("Failed to open {}: {e}", path.display());
+        }
+        pos.y += 18.0;
+    }
+    pos
+}
+
+/// Show the notes `note` refers to as `#<id>` as chips from `pos` down;
+/// clicking one jumps to that note
+fn paint_note_links(
+    ui: &mut egui::Ui,
+    note: &NoteData,
+    notes: &[NoteData],
+    index: &HashMap<u64, usize>,
+    mut pos: Pos2,
+    width: f32,
+    actions: &mut Vec<NoteAction>,
+) {
+    for id in note_links(&note.text) {
+        let target = index.get(&id).map(|&i| &notes[i]);
+        let label = match target {
+            Some(target) => egui::RichText::new(format!("→ {}", link_title(target))),
+            None => egui::RichText::new(format!("⚠ #{id}")).color(Color32::RED),
+        };
+        let rect = Rect::from_min_size(pos, egui::vec2(width, 16.0));
+        let response = ui
+            .put(rect, egui::Button::new(label.small()).small())
+            .on_hover_text(match target {
+                Some(_) => format!("Go to note #{id}"),
+                None => format!("There is no note #{id}"),
+            });
+        if response.clicked() && target.is_some() {
+            actions.push(NoteAction::JumpTo(id));
+        }
+        pos.y += 18.0;
+    }
+}
+
+/// Short name of a note for links: its first line, cut off if long
+fn link_title(note: &NoteData) -> String {
+    let line = note.text.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > 24 {
+        format!("{}…", line.chars().take(23).collect::<String>())
+    } else {
+        line.to_owned()
+    }
+}
+
+/// Tilt for a note added to `board`: a little random one if the board
+/// tilts new notes
+fn new_note_tilt(board: &Board) -> f32 {
+    if board.tilt_new_notes {
+        rand::thread_rng().gen_range(-MAX_NEW_NOTE_TILT..=MAX_NEW_NOTE_TILT)
+    } else {
+        0.0
+    }
+}
+
+/// Open the edit window of `note`
+fn start_editing(note: &NoteData, ui_state: &mut NoteUi) {
+    ui_state.is_editing = true;
+    ui_state.edit_origin = Some(note.clone());
+    ui_state.focus_text = true;
+    ui_state.expiry_input = note.expires_at.map(format_date).unwrap_or_default();
+    ui_state.due_input = note.due.map(format_local_datetime).unwrap_or_default();
+    ui_state.tags_input = format_tags(&note.tags);
+    ui_state.timer_input = note
+        .timer
+        .map(|timer| format_countdown(timer.duration as f64))
+        .unwrap_or_default();
+}
+
 /// Draw one note; drag-handling + wiggle
+///
+/// The note lives in board coordinates; everything drawn is rotated by the
+/// board's `view_turns` around the scene center. While the note is dragged,
+/// returns the snapped rect it would drop into.
 fn add_note_ui(
     ui: &mut egui::Ui,
     note: &mut NoteData,
     ui_state: &mut NoteUi,
     board: &mut Board,
+    index: &HashMap<u64, usize>,
     grid_size: f32,
-    ev_plop: &mut EventWriter<PlayPlopEvent>,
+    guided: bool,
+    view_turns: u8,
+    ev_sound: &mut EventWriter<PlaySound>,
     query: &str,
     highlight_match: bool,
     active: bool,
-) {
-    // Allocate interaction area based on the original note size
-    let base_rect = Rect::from_min_size(note.pos, note.size);
+    expired: bool,
+    due: Option<DueStatus>,
+    dimmed: bool,
+    palette: &[NamedColor],
+    text_color: Color32,
+    fonts: &FontDefaults,
+    tossing: bool,
+    commenter: &str,
+    selection: &mut Selection,
+    connecting: bool,
+    voting: bool,
+    actions: &mut Vec<NoteAction>,
+    changes: &mut Vec<Command>,
+) -> Option<Rect> {
+    let rect_to_view = |r: Rect| rotate_rect_quarter_turns(r, scene_center(), view_turns);
+    let text_angle = view_turns as f32 * std::f32::consts::FRAC_PI_2 + note.rotation;
+
+    // Allocate interaction area based on the original note size, tilted
+    let outline = view_corners(
+        note.pos,
+        note.size,
+        Vec2::ZERO,
+        note.rotation,
+        Vec2::ZERO,
+        view_turns,
+        scene_center(),
+    );
+    let base_rect = quad_bounds(&outline);
     let response = ui.allocate_rect(base_rect, egui::Sense::click_and_drag());
+    response.widget_info(|| {
+        let mut info = egui::WidgetInfo::labeled(egui::WidgetType::Button, true, note_label(note));
+        info.selected = Some(selection.contains(note.id));
+        info
+    });
 
-    if response.double_clicked() {
-        ui_state.is_editing = true;
+    // Only the drawn (possibly skewed) shape counts, not its bounding rect
+    let pointer = ui.ctx().pointer_interact_pos().and_then(|p| {
+        ui.ctx()
+            .layer_transform_from_global(ui.layer_id())
+            .map(|t| t * p)
+    });
+    let on_note = pointer.is_none_or(|p| {
+        let corners = view_corners(
+            note.pos,
+            note.size,
+            ui_state.skew,
+            note.rotation,
+            Vec2::ZERO,
+            view_turns,
+            scene_center(),
+        );
+        note.shape.hit(p, &corners)
+    });
+    if voting && on_note && (response.clicked() || response.secondary_clicked()) {
+        actions.push(NoteAction::Vote {
+            id: note.id,
+            add: response.clicked(),
+        });
+    }
+    if on_note && (response.clicked() || response.drag_started()) {
+        actions.push(NoteAction::Restack {
+            id: note.id,
+            to_front: true,
+            record: false,
+        });
+    }
+    let shift = ui.input(|i| i.modifiers.shift);
+    if on_note && response.clicked() {
+        if shift {
+            selection.toggle(note.id);
+        } else {
+            selection.select_only(note.id);
+        }
+    } else if on_note
+        && (response.drag_started() || response.secondary_clicked())
+        && !selection.contains(note.id)
+    {
+        if shift {
+            selection.extend([note.id]);
+        } else {
+            selection.select_only(note.id);
+        }
+    }
+    if response.double_clicked() && on_note {
+        start_editing(note, ui_state);
     }
 
     if ui_state.is_editing {
@@ -334,216 +7601,1540 @@ fn add_note_ui(
             .title_bar(false)
             .fixed_pos(note.pos)
             .show(ui.ctx(), |ui| {
-                ui.add(egui::TextEdit::multiline(&mut note.text).desired_width(note.size.x - 10.0));
+                // Ctrl+Enter finishes without reaching for the mouse; taken
+                // before the text field could type it
+                let finish = ui
+                    .input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter))
+                    || std::mem::take(&mut ui_state.finish_edit);
+                let edit = ui.add(
+                    egui::TextEdit::multiline(&mut note.text).desired_width(note.size.x - 10.0),
+                );
+                if std::mem::take(&mut ui_state.focus_text) {
+                    edit.request_focus();
+                }
+                if edit.changed() {
+                    // Huge or binary pastes would stall text layout every frame
+                    let clean = sanitize_text(&note.text, MAX_NOTE_CHARS);
+                    if clean.changed() {
+                        ui_state.text_warning = clean.warning();
+                        note.text = clean.text;
+                    }
+                }
+                if let Some(warning) = &ui_state.text_warning {
+                    ui.colored_label(Color32::RED, format!("⚠ {warning}"));
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Color:");
+                    let before = note.color;
+                    ui.color_edit_button_srgba(&mut note.color);
+                    palette_swatches(ui, &mut note.color, palette);
+                    // A color of its own takes the note out of its category
+                    if note.color != before {
+                        note.category = None;
+                    }
+                });
+                if !board.categories.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label("Category:");
+                        let mut picked = None;
+                        egui::ComboBox::from_id_salt(("category", note.id))
+                            .selected_text(note.category.as_deref().unwrap_or("None"))
+                            .show_ui(ui, |ui| {
+                                if ui
+                                    .selectable_label(note.category.is_none(), "None")
+                                    .clicked()
+                                {
+                                    picked = Some(None);
+                                }
+                                for category in &board.categories {
+                                    let current = note.category.as_ref() == Some(&category.name);
+                                    if ui.selectable_label(current, &category.name).clicked() {
+                                        picked = Some(Some(category));
+                                    }
+                                }
+                            });
+                        if let Some(category) = picked {
+                            note.set_category(category);
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Shape:");
+                    let before = note.shape;
+                    egui::ComboBox::from_id_salt(("shape", note.id))
+                        .selected_text(note.shape.name())
+                        .show_ui(ui, |ui| {
+                            for shape in NoteShape::ALL {
+                                ui.selectable_value(&mut note.shape, shape, shape.name());
+                            }
+                        });
+                    if note.shape != before && !note.locked {
+                        note.size = note.shape.fit_size(note.size);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Expires:");
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut ui_state.expiry_input)
+                            .hint_text("YYYY-MM-DD")
+                            .desired_width(80.0),
+                    );
+                    if input.changed() {
+                        note.expires_at = parse_date(&ui_state.expiry_input);
+                    }
+                    if ui
+                        .small_button("+1w")
+                        .on_hover_text("In one week")
+                        .clicked()
+                    {
+                        note.expires_at = Some(now_secs() + 7 * DAY);
+                        ui_state.expiry_input =
+                            note.expires_at.map(format_date).unwrap_or_default();
+                    }
+                    if note.expires_at.is_some()
+                        && ui.small_button("✖").on_hover_text("Never expire").clicked()
+                    {
+                        note.expires_at = None;
+                        ui_state.expiry_input.clear();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Due:");
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut ui_state.due_input)
+                            .hint_text("YYYY-MM-DD HH:MM")
+                            .desired_width(110.0),
+                    );
+                    if input.changed() {
+                        note.due = parse_local_datetime(&ui_state.due_input);
+                    }
+                    if due_picker(ui, &mut note.due) {
+                        ui_state.due_input =
+                            note.due.map(format_local_datetime).unwrap_or_default();
+                    }
+                    for (label, hover, days) in [("+1d", "Tomorrow", 1), ("+1w", "In one week", 7)]
+                    {
+                        if ui.small_button(label).on_hover_text(hover).clicked() {
+                            note.due = Some(now_secs() + days * DAY);
+                            ui_state.due_input =
+                                note.due.map(format_local_datetime).unwrap_or_default();
+                        }
+                    }
+                    if note.due.is_some()
+                        && ui.small_button("✖").on_hover_text("No due date").clicked()
+                    {
+                        note.due = None;
+                        ui_state.due_input.clear();
+                    }
+                });
                 ui.horizontal(|ui| {
-                    ui.label("Color:");
-                    ui.color_edit_button_srgba(&mut note.color);
+                    ui.label("Font:");
+                    let family = &mut note.font.family;
+                    egui::ComboBox::from_id_salt(("font", note.id))
+                        .selected_text(family.map_or("Default", FontChoice::name))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(family, None, "Default");
+                            for choice in FontChoice::ALL {
+                                ui.selectable_value(family, Some(choice), choice.name());
+                            }
+                        });
+                    let mut fixed = note.font.size.is_some();
+                    if ui
+                        .checkbox(&mut fixed, "Size")
+                        .on_hover_text("Keep one size instead of fitting the note")
+                        .changed()
+                    {
+                        note.font.size = fixed.then_some(fonts.max_size);
+                    }
+                    if let Some(size) = &mut note.font.size {
+                        ui.add(egui::DragValue::new(size).range(FONT_SIZE_RANGE));
+                    }
+                    ui.toggle_value(&mut note.font.bold, egui::RichText::new("B").strong())
+                        .on_hover_text("Bold");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Tags:");
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut ui_state.tags_input)
+                            .hint_text("work, idea")
+                            .desired_width(note.size.x - 50.0),
+                    );
+                    if input.changed() {
+                        note.tags = parse_tags(&ui_state.tags_input);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Icon:");
+                    ui.menu_button(note.icon.as_deref().unwrap_or("None"), |ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut ui_state.icon_query)
+                                .hint_text("Search: bug, idea, done…")
+                                .desired_width(200.0),
+                        )
+                        .request_focus();
+                        egui::ScrollArea::vertical()
+                            .max_height(160.0)
+                            .show(ui, |ui| {
+                                ui.set_max_width(200.0);
+                                ui.horizontal_wrapped(|ui| {
+                                    for icon in search_icons(&ui_state.icon_query) {
+                                        if ui.button(egui::RichText::new(icon).size(18.0)).clicked()
+                                        {
+                                            note.icon = Some(icon.to_owned());
+                                            ui_state.icon_query.clear();
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            });
+                    });
+                    if note.icon.is_some()
+                        && ui.small_button("✖").on_hover_text("No icon").clicked()
+                    {
+                        note.icon = None;
+                    }
+                    ui.label("Priority:");
+                    egui::ComboBox::from_id_salt(("priority", note.id))
+                        .selected_text(note.priority.name())
+                        .show_ui(ui, |ui| {
+                            for priority in Priority::ALL {
+                                ui.selectable_value(&mut note.priority, priority, priority.name());
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Timer:");
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut ui_state.timer_input)
+                            .hint_text("25m, 1h 30m, 0:90")
+                            .desired_width(80.0),
+                    );
+                    let submitted =
+                        input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (ui.small_button("Set").clicked() || submitted)
+                        && let Some(duration) = parse_duration(&ui_state.timer_input)
+                    {
+                        note.timer = Some(NoteTimer::new(duration));
+                    }
+                    if ui
+                        .small_button("🍅")
+                        .on_hover_text("A 25-minute pomodoro")
+                        .clicked()
+                    {
+                        note.timer = Some(NoteTimer::new(POMODORO));
+                        ui_state.timer_input = format_countdown(POMODORO as f64);
+                    }
+                    if note.timer.is_some()
+                        && ui
+                            .small_button("✖")
+                            .on_hover_text("Remove the timer")
+                            .clicked()
+                    {
+                        note.timer = None;
+                    }
                 });
-                if ui.button("Done").clicked() {
+                ui.horizontal(|ui| {
+                    ui.label("Attach:");
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut ui_state.attachment_input)
+                            .hint_text("https://… or a file path")
+                            .desired_width(note.size.x - 100.0),
+                    );
+                    let submitted =
+                        input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (ui.small_button("+").on_hover_text("Attach").clicked() || submitted)
+                        && let Some(attachment) = Attachment::parse(&ui_state.attachment_input)
+                    {
+                        note.attachments.push(attachment);
+                        ui_state.attachment_input.clear();
+                    }
+                    if ui
+                        .small_button("🗀")
+                        .on_hover_text("Attach a file…")
+                        .clicked()
+                        && let Some(path) = rfd::FileDialog::new().pick_file()
+                    {
+                        note.attachments.push(Attachment::File(path));
+                    }
+                });
+                let mut detached = None;
+                for (i, attachment) in note.attachments.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("✖").on_hover_text("Detach").clicked() {
+                            detached = Some(i);
+                        }
+                        ui.label(format!("📎 {}", attachment.label()))
+                            .on_hover_text(attachment.target());
+                    });
+                }
+                if let Some(i) = detached {
+                    note.attachments.remove(i);
+                }
+                egui::CollapsingHeader::new(format!("Comments ({})", note.comments.len()))
+                    .id_salt(("comments", note.id))
+                    .show(ui, |ui| {
+                        let mut removed = None;
+                        egui::ScrollArea::vertical()
+                            .max_height(160.0)
+                            .show(ui, |ui| {
+                                for (i, comment) in note.comments.iter().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        ui.strong(&comment.author);
+                                        ui.label(
+                                            egui::RichText::new(format_local_datetime(comment.at))
+                                                .weak(),
+                                        );
+                                        if ui.small_button("✖").on_hover_text("Delete").clicked()
+                                        {
+                                            removed = Some(i);
+                                        }
+                                    });
+                                    ui.label(&comment.text);
+                                }
+                            });
+                        if let Some(i) = removed {
+                            note.comments.remove(i);
+                        }
+                        ui.horizontal(|ui| {
+                            let input = ui.add(
+                                egui::TextEdit::singleline(&mut ui_state.comment_input)
+                                    .hint_text(format!("Comment as {commenter}"))
+                                    .desired_width(note.size.x - 60.0),
+                            );
+                            let submitted =
+                                input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            if (ui.small_button("Post").clicked() || submitted)
+                                && note.add_comment(commenter, &ui_state.comment_input, now_secs())
+                            {
+                                ui_state.comment_input.clear();
+                            }
+                        });
+                    });
+                if !note.revisions.is_empty() {
+                    egui::CollapsingHeader::new("History")
+                        .id_salt(("history", note.id))
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical()
+                                .max_height(160.0)
+                                .show(ui, |ui| {
+                                    let mut restore = None;
+                                    for revision in note.revisions.iter().rev() {
+                                        ui.horizontal(|ui| {
+                                            if ui
+                                                .small_button("Restore")
+                                                .on_hover_text(&revision.text)
+                                                .clicked()
+                                            {
+                                                restore = Some(revision.text.clone());
+                                            }
+                                            ui.label(format_local_datetime(revision.at));
+                                            let first = revision.text.lines().next();
+                                            ui.add(
+                                                egui::Label::new(
+                                                    egui::RichText::new(first.unwrap_or_default())
+                                                        .weak(),
+                                                )
+                                                .truncate(),
+                                            );
+                                        });
+                                    }
+                                    if let Some(text) = restore {
+                                        note.text = text;
+                                    }
+                                });
+                        });
+                }
+                if ui.button("Done").on_hover_text("Ctrl+Enter").clicked() || finish {
                     ui_state.is_editing = false;
+                    ui_state.text_warning = None;
+                    if let Some(before) = ui_state.edit_origin.take() {
+                        note.record_revision(&before.text, now_secs());
+                        changes.push(Command::Update {
+                            before,
+                            after: note.clone(),
+                        });
+                    }
                 }
             });
-        if let Some(n) = board.notes.iter_mut().find(|n| n.id == note.id) {
+        if let Some(n) = index.get(&note.id).map(|&i| &mut board.notes[i]) {
             n.text = note.text.clone();
             n.color = note.color;
+            n.expires_at = note.expires_at;
+            n.due = note.due;
+            n.tags = note.tags.clone();
+            n.revisions = note.revisions.clone();
+            n.font = note.font;
+            n.shape = note.shape;
+            n.size = note.size;
+            n.attachments = note.attachments.clone();
+            n.category = note.category.clone();
+            n.timer = note.timer;
+            n.comments = note.comments.clone();
+            n.icon = note.icon.clone();
+            n.priority = note.priority;
         }
-        return;
+        return None;
     }
 
-    if response.dragged() {
-        // Wiggle offset combined with stretchy scaling for a satisfying drag
-        let t = ui.ctx().input(|i| i.time as f32);
-        let wiggle_amp = 3.0;
-        let wiggle_off = wiggle_amp * (t * 15.0).sin();
-
-        let delta = response.drag_delta();
-        note.pos.x += delta.x;
-        note.pos.y += delta.y;
-        if let Some(n) = board.notes.iter_mut().find(|n| n.id == note.id) {
+    // While voting, right-clicking takes a dot back instead
+    if !voting {
+        response.context_menu(|ui| {
+            let count = selection.ids().len();
+            let label = if count > 1 {
+                format!("Delete {count} notes")
+            } else {
+                "Delete".to_owned()
+            };
+            let deletable = !note.locked || count > 1;
+            if ui
+                .add_enabled(deletable, egui::Button::new(label))
+                .clicked()
+            {
+                actions.push(NoteAction::DeleteSelected);
+                ui.close_menu();
+            }
+            if ui
+                .add_enabled(deletable, egui::Button::new("Archive"))
+                .on_hover_text("Put away, to restore from the 🗑 panel")
+                .clicked()
+            {
+                actions.push(NoteAction::ArchiveSelected);
+                ui.close_menu();
+            }
+            if ui.button("Duplicate").clicked() {
+                actions.push(NoteAction::Duplicate(note.id));
+                ui.close_menu();
+            }
+            let lock = if note.locked {
+                "🔓 Unlock"
+            } else {
+                "🔒 Lock"
+            };
+            if ui
+                .button(lock)
+                .on_hover_text("Locked notes can't be moved, resized or deleted")
+                .clicked()
+            {
+                let before = note.clone();
+                note.locked = !note.locked;
+                if let Some(n) = index.get(&note.id).map(|&i| &mut board.notes[i]) {
+                    n.locked = note.locked;
+                }
+                changes.push(Command::Update {
+                    before,
+                    after: note.clone(),
+                });
+                ui.close_menu();
+            }
+            if note.rotation != 0.0 && !note.locked && ui.button("Straighten").clicked() {
+                let before = note.clone();
+                note.rotation = 0.0;
+                if let Some(n) = index.get(&note.id).map(|&i| &mut board.notes[i]) {
+                    n.rotation = 0.0;
+                }
+                changes.push(Command::Update {
+                    before,
+                    after: note.clone(),
+                });
+                ui.close_menu();
+            }
+            if ui
+                .button("Save as template")
+                .on_hover_text("Offer notes like this one when right-clicking the board")
+                .clicked()
+            {
+                actions.push(NoteAction::SaveTemplate(note.id));
+                ui.close_menu();
+            }
+            ui.menu_button("Link to note", |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for other in board.notes.iter().filter(|n| n.id != note.id) {
+                            if ui
+                                .button(format!("#{} {}", other.id, link_title(other)))
+                                .clicked()
+                            {
+                                actions.push(NoteAction::LinkTo {
+                                    from: note.id,
+                                    to: other.id,
+                                });
+                                ui.close_menu();
+                            }
+                        }
+                    });
+            });
+            if ui.button("Attach image…").clicked() {
+                ui.close_menu();
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Images", &IMAGE_EXTENSIONS)
+                    .pick_file()
+                {
+                    actions.push(NoteAction::AttachImage {
+                        id: note.id,
+                        image: Some(path),
+                    });
+                }
+            }
+            if note.image.is_some() && ui.button("Remove image").clicked() {
+                actions.push(NoteAction::AttachImage {
+                    id: note.id,
+                    image: None,
+                });
+                ui.close_menu();
+            }
+            for (label, to_front) in [("Bring to front", true), ("Send to back", false)] {
+                if ui.button(label).clicked() {
+                    actions.push(NoteAction::Restack {
+                        id: note.id,
+                        to_front,
+                        record: true,
+                    });
+                    ui.close_menu();
+                }
+            }
+            if !board.connectors_of(note.id).is_empty() && ui.button("Remove connectors").clicked()
+            {
+                actions.push(NoteAction::Disconnect(note.id));
+                ui.close_menu();
+            }
+            ui.menu_button("Change color", |ui| {
+                let mut color = note.color;
+                let picked = ui
+                    .horizontal(|ui| palette_swatches(ui, &mut color, palette))
+                    .inner;
+                if picked {
+                    actions.push(NoteAction::Recolor(color));
+                    ui.close_menu();
+                }
+            });
+            if !board.categories.is_empty() {
+                ui.menu_button("Category", |ui| {
+                    for category in &board.categories {
+                        let current = note.category.as_ref() == Some(&category.name);
+                        let clicked = ui
+                            .horizontal(|ui| {
+                                category_swatch(ui, category.color);
+                                ui.selectable_label(current, &category.name).clicked()
+                            })
+                            .inner;
+                        if clicked {
+                            actions.push(NoteAction::Categorize(Some(category.name.clone())));
+                            ui.close_menu();
+                        }
+                    }
+                    if note.category.is_some() && ui.button("None").clicked() {
+                        actions.push(NoteAction::Categorize(None));
+                        ui.close_menu();
+                    }
+                });
+            }
+            ui.menu_button("Priority", |ui| {
+                for priority in Priority::ALL {
+                    if ui
+                        .selectable_label(note.priority == priority, priority.name())
+                        .clicked()
+                    {
+                        actions.push(NoteAction::Prioritize(priority));
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+    }
+
+    // Handles are allocated after the note so they win its drags
+    let note_rect = Rect::from_min_size(note.pos, note.size);
+    let handles = if connecting || note.locked {
+        &[][..]
+    } else {
+        &ResizeHandle::ALL[..]
+    };
+    for &handle in handles {
+        let handle_rect = rect_to_view(handle.rect(note_rect, RESIZE_HANDLE_SIZE));
+        let handle_response = ui
+            .interact(
+                handle_rect,
+                ui.id().with(("resize", note.id, handle)),
+                egui::Sense::drag(),
+            )
+            .on_hover_cursor(resize_cursor(handle, view_turns));
+        if handle_response.drag_started() {
+            ui_state.resize_origin = Some(note.clone());
+        }
+        if handle_response.dragged() {
+            let delta = rotate_vec_quarter_turns(
+                handle_response.drag_delta(),
+                inverse_quarter_turns(view_turns),
+            );
+            let resized = resize_rect(
+                Rect::from_min_size(note.pos, note.size),
+                handle,
+                delta,
+                MIN_NOTE_SIZE,
+            );
+            note.pos = resized.min;
+            note.size = note.shape.fit_size(resized.size());
+        }
+        if handle_response.drag_stopped() {
+            note.pos = snap_to_grid(note.pos, grid_size);
+            note.size = note.shape.fit_size(snap_size_to_grid(note.size, grid_size));
+            if let Some(before) = ui_state.resize_origin.take() {
+                changes.push(Command::Update {
+                    before,
+                    after: note.clone(),
+                });
+            }
+        }
+        if (handle_response.dragged() || handle_response.drag_stopped())
+            && let Some(n) = index.get(&note.id).map(|&i| &mut board.notes[i])
+        {
             n.pos = note.pos;
+            n.size = note.size;
         }
+    }
 
-        // Update temporary skew based on drag speed
-        let skew_factor = 0.02;
-        let target_skew_x = delta.x * skew_factor;
-        let target_skew_y = delta.y * skew_factor;
-        ui_state.skew.x += (target_skew_x - ui_state.skew.x) * 0.5;
-        ui_state.skew.y += (target_skew_y - ui_state.skew.y) * 0.5;
-
-        let w = note.size.x;
-        let h = note.size.y;
-        let sx = ui_state.skew.x;
-        let sy = ui_state.skew.y;
-        let off = egui::vec2(wiggle_off, 0.0);
-
-        let p1 = note.pos + off;
-        let p2 = Pos2 {
-            x: note.pos.x + w + off.x,
-            y: note.pos.y + w * sy + off.y,
-        };
-        let p3 = Pos2 {
-            x: note.pos.x + w + h * sx + off.x,
-            y: note.pos.y + h + w * sy + off.y,
-        };
-        let p4 = Pos2 {
-            x: note.pos.x + h * sx + off.x,
-            y: note.pos.y + h + off.y,
+    // The rotate handle sits above a note selected on its own
+    if !connecting && !note.locked && selection.ids() == [note.id] {
+        let center = note.pos + note.size / 2.0;
+        let top = Pos2::new(center.x, note.pos.y);
+        let to_view = |p: Pos2| {
+            let tilted = rotate_about(p, center, note.rotation);
+            rotate_quarter_turns(tilted, scene_center(), view_turns)
         };
+        let knob = to_view(top - Vec2::new(0.0, ROTATE_HANDLE_DISTANCE));
+        let stroke = Stroke::new(1.5, Color32::from_rgb(60, 120, 220));
+        ui.painter().line_segment([to_view(top), knob], stroke);
+        ui.painter().circle(knob, 4.0, Color32::WHITE, stroke);
+        let handle_response = ui
+            .interact(
+                Rect::from_center_size(knob, Vec2::splat(RESIZE_HANDLE_SIZE * 1.5)),
+                ui.id().with(("rotate", note.id)),
+                egui::Sense::drag(),
+            )
+            .on_hover_cursor(egui::CursorIcon::Grab)
+            .on_hover_text("Drag to turn the note; Shift snaps to 15°");
+        if handle_response.drag_started() {
+            ui_state.rotate_origin = Some(note.clone());
+        }
+        if handle_response.dragged()
+            && let Some(p) = pointer
+        {
+            let on_board =
+                rotate_quarter_turns(p, scene_center(), inverse_quarter_turns(view_turns));
+            let snap = if ui.input(|i| i.modifiers.shift) {
+                ROTATION_SNAP
+            } else {
+                0.0
+            };
+            note.rotation = rotation_towards(center, on_board, snap);
+            if let Some(n) = index.get(&note.id).map(|&i| &mut board.notes[i]) {
+                n.rotation = note.rotation;
+            }
+        }
+        if handle_response.drag_stopped()
+            && let Some(before) = ui_state.rotate_origin.take()
+            && before.rotation != note.rotation
+        {
+            changes.push(Command::Update {
+                before,
+                after: note.clone(),
+            });
+        }
+    }
 
-        let center = Pos2::new(
-            (p1.x + p2.x + p3.x + p4.x) / 4.0,
-            (p1.y + p2.y + p3.y + p4.y) / 4.0,
-        );
+    let mut drop_target = None;
+    // In connection mode dragging draws a connector instead of moving the
+    // note, and locked notes stay put
+    let moving = !connecting && !note.locked;
+    if response.drag_started() && moving {
+        // Catching a sliding note stops it, and undoing the drop that
+        // follows puts it back where its toss started
+        if ui_state.toss.take().is_none() {
+            ui_state.drag_origin = Some(note.pos);
+        }
+        actions.push(NoteAction::StartMove { from: note.id });
+    }
+    let offset = if response.dragged() && moving {
+        // The drag happens on screen, so rotate it back onto the board
+        let delta =
+            rotate_vec_quarter_turns(response.drag_delta(), inverse_quarter_turns(view_turns));
+        note.pos += delta;
+        actions.push(NoteAction::Move {
+            from: note.id,
+            delta,
+        });
+        if let Some(n) = index.get(&note.id).map(|&i| &mut board.notes[i]) {
+            n.pos = note.pos;
+        }
+        // Lean into the drag and wiggle for a satisfying feel
+        ui_state.skew = ease_skew(ui_state.skew, drag_skew_target(delta), SKEW_EASE_DRAGGING);
+        wiggle_offset(ui.ctx().input(|i| i.time as f32))
+    } else {
+        // Gradually return to no skew when not dragging
+        ui_state.skew = ease_skew(ui_state.skew, Vec2::ZERO, SKEW_EASE_RELEASED);
+        Vec2::ZERO
+    };
 
+    let corners = view_corners(
+        note.pos,
+        note.size,
+        ui_state.skew,
+        note.rotation,
+        offset,
+        view_turns,
+        scene_center(),
+    );
+    for piece in note.shape.pieces() {
         ui.painter().add(Shape::convex_polygon(
-            vec![p1, p2, p3, p4],
+            piece.iter().map(|&p| unit_to_quad(p, &corners)).collect(),
             note.color,
             Stroke::NONE,
         ));
-        let font_size = fitted_font_size(ui.ctx(), &note.text, note.size, 16.0);
-        if highlight_match {
-            let job = highlighted_layout(&note.text, query, font_size);
-            let galley = ui.painter().layout_job(job);
-            ui.painter()
-                .galley(center - galley.size() * 0.5, galley, Color32::BLACK);
-        } else {
-            ui.painter().text(
-                center,
-                egui::Align2::CENTER_CENTER,
-                &note.text,
-                egui::FontId::proportional(font_size),
-                Color32::BLACK,
+    }
+    let texture = note
+        .image
+        .as_deref()
+        .and_then(|path| note_texture(ui.ctx(), path));
+    let font = note.font.resolve(fonts);
+    let hover = pointer.filter(|_| response.hovered());
+    let mut link = None;
+    if let Some(texture) = texture {
+        // The image sits in the top part with the text in the strip below
+        let note_rect = Rect::from_min_size(note.pos, note.size).translate(offset);
+        let has_text = !note.text.trim().is_empty();
+        let area = image_rect(note_rect, texture.size_vec2(), has_text);
+        let to_view = |p: Pos2| {
+            let tilted = rotate_about(p, note_rect.center(), note.rotation);
+            rotate_quarter_turns(tilted, scene_center(), view_turns)
+        };
+        paint_note_image(ui.painter(), &texture, area, to_view);
+        if has_text {
+            link = paint_note_text(
+                ui,
+                note,
+                query,
+                highlight_match,
+                to_view(text_center_below_image(note_rect)),
+                text_angle,
+                Vec2::new(note.size.x, note.size.y * IMAGE_TEXT_SHARE),
+                text_color,
+                &font,
+                hover,
             );
         }
-
-        // Draw preview of snapped position
-        let snapped = snap_to_grid(note.pos, grid_size);
-        let preview = Rect::from_min_size(snapped, note.size);
-        ui.painter().rect_stroke(
-            preview,
-            0.0,
-            Stroke::new(1.0, Color32::WHITE),
-            egui::StrokeKind::Inside,
+    } else if checklist::is_checklist(&note.text) {
+        checklist_ui(
+            ui,
+            note,
+            board,
+            query,
+            highlight_match,
+            view_turns,
+            text_color,
+            &font,
+            changes,
         );
     } else {
-        // Gradually return to no skew when not dragging
-        ui_state.skew.x += (0.0 - ui_state.skew.x) * 0.2;
-        ui_state.skew.y += (0.0 - ui_state.skew.y) * 0.2;
-
-        let w = note.size.x;
-        let h = note.size.y;
-        let sx = ui_state.skew.x;
-        let sy = ui_state.skew.y;
-
-        let p1 = note.pos;
-        let p2 = Pos2 {
-            x: note.pos.x + w,
-            y: note.pos.y + w * sy,
-        };
-        let p3 = Pos2 {
-            x: note.pos.x + w + h * sx,
-            y: note.pos.y + h + w * sy,
-        };
-        let p4 = Pos2 {
-            x: note.pos.x + h * sx,
-            y: note.pos.y + h,
-        };
-
-        let center = Pos2::new(
-            (p1.x + p2.x + p3.x + p4.x) / 4.0,
-            (p1.y + p2.y + p3.y + p4.y) / 4.0,
+        let area = note.shape.text_area();
+        link = paint_note_text(
+            ui,
+            note,
+            query,
+            highlight_match,
+            unit_to_quad(area.center(), &corners),
+            text_angle,
+            note.size * area.size(),
+            text_color,
+            &font,
+            hover,
         );
+    }
+    // Links in the text open in the browser
+    if let Some(url) = link {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        if response.clicked() {
+            ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+        }
+    }
 
+    if response.dragged()
+        && connecting
+        && let Some(p) = pointer
+    {
+        let start = border_point(base_rect, p);
+        ui.painter()
+            .line_segment([start, p], Stroke::new(2.0, CONNECTOR_COLOR));
         ui.painter().add(Shape::convex_polygon(
-            vec![p1, p2, p3, p4],
-            note.color,
+            [p].into_iter()
+                .chain(arrow_head(start, p, ARROW_SIZE))
+                .collect(),
+            CONNECTOR_COLOR,
             Stroke::NONE,
         ));
-        let font_size = fitted_font_size(ui.ctx(), &note.text, note.size, 16.0);
-        if highlight_match {
-            let job = highlighted_layout(&note.text, query, font_size);
-            let galley = ui.painter().layout_job(job);
-            ui.painter()
-                .galley(center - galley.size() * 0.5, galley, Color32::BLACK);
+    }
+
+    if response.dragged() && moving {
+        // Draw preview of snapped position, unless guides line it up instead
+        let snapped = Rect::from_min_size(snap_to_grid(note.pos, grid_size), note.size);
+        drop_target = Some(snapped);
+        if !guided {
+            ui.painter().rect_stroke(
+                rect_to_view(snapped),
+                0.0,
+                Stroke::new(1.0, Color32::WHITE),
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+
+    let view_rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+    let chips_end = paint_file_links(ui, note, ui_state, view_rect);
+    paint_note_links(
+        ui,
+        note,
+        &board.notes,
+        index,
+        chips_end,
+        view_rect.width(),
+        actions,
+    );
+
+    // Outlines follow the note's tilt and shape
+    let outline_of = |grow: f32| {
+        let corners = view_corners(
+            note.pos - Vec2::splat(grow),
+            note.size + Vec2::splat(grow * 2.0),
+            Vec2::ZERO,
+            note.rotation,
+            Vec2::ZERO,
+            view_turns,
+            scene_center(),
+        );
+        note.shape
+            .outline()
+            .into_iter()
+            .map(|p| unit_to_quad(p, &corners))
+            .collect::<Vec<_>>()
+    };
+
+    let priority = note.priority;
+    if priority.border_width() > 0.0 {
+        let width = priority.border_width();
+        ui.painter().add(Shape::closed_line(
+            outline_of(-width / 2.0),
+            Stroke::new(width, priority.color()),
+        ));
+    }
+
+    if let Some(status) = due {
+        let rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+        let color = due_color(status);
+        ui.painter().add(Shape::closed_line(
+            outline_of(-1.5),
+            Stroke::new(3.0, color),
+        ));
+        let when = note.due.map(format_local_datetime).unwrap_or_default();
+        ui.painter().text(
+            rect.left_bottom() + egui::vec2(4.0, -4.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("⏰ {when}"),
+            egui::FontId::proportional(11.0),
+            color,
+        );
+    }
+
+    if note.locked {
+        let rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+        ui.painter().text(
+            rect.left_top() + egui::vec2(4.0, 4.0),
+            egui::Align2::LEFT_TOP,
+            "🔒",
+            egui::FontId::proportional(12.0),
+            Color32::from_gray(90),
+        );
+    }
+
+    if expired {
+        let rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+        ui.painter().add(Shape::closed_line(
+            outline_of(-1.0),
+            Stroke::new(2.0, Color32::DARK_RED),
+        ));
+        ui.painter().text(
+            rect.right_top() + egui::vec2(-4.0, 4.0),
+            egui::Align2::RIGHT_TOP,
+            "⌛",
+            egui::FontId::proportional(14.0),
+            Color32::DARK_RED,
+        );
+    }
+
+    if let Some(author) = &note.author {
+        ui.painter().text(
+            rect_to_view(Rect::from_min_size(note.pos, note.size)).right_bottom()
+                - egui::vec2(4.0, 4.0),
+            egui::Align2::RIGHT_BOTTOM,
+            initials(author),
+            egui::FontId::proportional(11.0),
+            Color32::from_black_alpha(160),
+        );
+    }
+
+    // The priority badge and the rank follow the icon along the top edge
+    let mut corner =
+        rect_to_view(Rect::from_min_size(note.pos, note.size)).left_top() + egui::vec2(4.0, 4.0);
+    if let Some(icon) = &note.icon {
+        let drawn = ui.painter().text(
+            corner,
+            egui::Align2::LEFT_TOP,
+            icon,
+            egui::FontId::proportional(16.0),
+            Color32::WHITE,
+        );
+        corner.x = drawn.right() + 4.0;
+    }
+
+    if let Some(badge) = priority.badge() {
+        let drawn = ui.painter().text(
+            corner,
+            egui::Align2::LEFT_TOP,
+            badge,
+            egui::FontId::proportional(14.0),
+            priority.color(),
+        );
+        corner.x = drawn.right() + 4.0;
+    }
+
+    if let Some(rank) = note.rank {
+        ui.painter().text(
+            corner,
+            egui::Align2::LEFT_TOP,
+            format!("#{rank}"),
+            egui::FontId::proportional(12.0),
+            Color32::from_black_alpha(160),
+        );
+    }
+
+    if let Some(last) = note.comments.last() {
+        let anchor = rect_to_view(Rect::from_min_size(note.pos, note.size)).left_bottom()
+            + egui::vec2(4.0, -4.0);
+        let badge = ui.painter().text(
+            anchor,
+            egui::Align2::LEFT_BOTTOM,
+            format!("💬{}", note.comments.len()),
+            egui::FontId::proportional(11.0),
+            Color32::from_black_alpha(160),
+        );
+        ui.interact(
+            badge,
+            ui.id().with(("comments", note.id)),
+            egui::Sense::hover(),
+        )
+        .on_hover_text(format!("{}: {}", last.author, last.text));
+    }
+
+    if !note.attachments.is_empty() {
+        let rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+        let chip = Rect::from_min_size(
+            rect.right_top() + egui::vec2(-62.0, 2.0),
+            egui::vec2(40.0, 16.0),
+        );
+        let label = egui::RichText::new(format!("📎{}", note.attachments.len())).small();
+        let hover = note
+            .attachments
+            .iter()
+            .map(Attachment::target)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut opened = None;
+        if let [only] = &note.attachments[..] {
+            if ui
+                .put(chip, egui::Button::new(label).small())
+                .on_hover_text(hover)
+                .clicked()
+            {
+                opened = Some(only);
+            }
         } else {
+            ui.allocate_new_ui(egui::UiBuilder::new().max_rect(chip), |ui| {
+                ui.menu_button(label, |ui| {
+                    for attachment in &note.attachments {
+                        if ui
+                            .button(attachment.label())
+                            .on_hover_text(attachment.target())
+                            .clicked()
+                        {
+                            opened = Some(attachment);
+                            ui.close_menu();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text(hover);
+            });
+        }
+        match opened {
+            Some(Attachment::File(path)) => {
+                if let Err(e) = open_path(path) {
+                    warn!("Failed to open {}: {e}", path.display());
+                }
+            }
+            Some(Attachment::Url(url)) => ui.ctx().open_url(egui::OpenUrl::new_tab(url)),
+            None => {}
+        }
+    }
+
+    if note.votes > 0 {
+        // A row of dots along the top edge, with the count once they run out
+        let rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+        let radius = 4.0;
+        let shown = note.votes.min(MAX_VOTE_DOTS);
+        let start = rect.center_top() + egui::vec2(-(shown as f32 - 1.0) * radius * 1.25, 8.0);
+        for i in 0..shown {
+            let center = start + egui::vec2(i as f32 * radius * 2.5, 0.0);
+            ui.painter()
+                .circle(center, radius, VOTE_COLOR, Stroke::new(1.0, Color32::WHITE));
+        }
+        if note.votes > shown {
             ui.painter().text(
-                center,
-                egui::Align2::CENTER_CENTER,
-                &note.text,
-                egui::FontId::proportional(font_size),
-                Color32::BLACK,
+                start + egui::vec2(shown as f32 * radius * 2.5, 0.0),
+                egui::Align2::LEFT_CENTER,
+                format!("+{}", note.votes - shown),
+                egui::FontId::proportional(11.0),
+                VOTE_COLOR,
+            );
+        }
+    }
+
+    if let Some(timer) = &mut note.timer {
+        let now = timers::now();
+        if timer.ringing {
+            // Flash until acknowledged
+            let pulse = (ui.input(|i| i.time) * std::f64::consts::TAU).sin() * 0.5 + 0.5;
+            let corners = view_corners(
+                note.pos,
+                note.size,
+                Vec2::ZERO,
+                note.rotation,
+                Vec2::ZERO,
+                view_turns,
+                scene_center(),
             );
+            for piece in note.shape.pieces() {
+                ui.painter().add(Shape::convex_polygon(
+                    piece.iter().map(|&p| unit_to_quad(p, &corners)).collect(),
+                    TIMER_COLOR.gamma_multiply(0.5 * pulse as f32),
+                    Stroke::NONE,
+                ));
+            }
+            ui.ctx().request_repaint();
+        } else if timer.is_running() {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        // A ring filling up as time goes by, around the time left
+        let rect = rect_to_view(Rect::from_min_size(note.pos, note.size));
+        let radius = 16.0;
+        let center = rect.right_top() + egui::vec2(-radius - 6.0, radius + 22.0);
+        ui.painter().circle(
+            center,
+            radius,
+            Color32::from_white_alpha(200),
+            Stroke::new(3.0, Color32::from_black_alpha(40)),
+        );
+        let progress = timer.progress(now);
+        if progress > 0.0 {
+            let steps = (progress * 48.0).ceil() as usize;
+            let arc = (0..=steps)
+                .map(|i| {
+                    let turned = std::f32::consts::TAU * progress * i as f32 / steps as f32;
+                    center + radius * Vec2::angled(turned - std::f32::consts::FRAC_PI_2)
+                })
+                .collect();
+            ui.painter()
+                .add(Shape::line(arc, Stroke::new(3.0, TIMER_COLOR)));
+        }
+        ui.painter().text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            format_countdown(timer.remaining(now)),
+            egui::FontId::proportional(9.0),
+            Color32::from_black_alpha(200),
+        );
+        let ring = ui
+            .interact(
+                Rect::from_center_size(center, Vec2::splat(radius * 2.0)),
+                ui.id().with(("timer", note.id)),
+                egui::Sense::click(),
+            )
+            .on_hover_cursor(egui::CursorIcon::PointingHand)
+            .on_hover_text(if timer.ringing {
+                "Stop ringing"
+            } else if timer.is_running() {
+                "Pause"
+            } else {
+                "Start"
+            });
+        // Clicking anywhere on a ringing note stops it
+        let acknowledged = timer.ringing && (ring.clicked() || (on_note && response.clicked()));
+        if acknowledged {
+            timer.acknowledge();
+        } else if ring.clicked() && timer.is_running() {
+            timer.pause(now);
+        } else if ring.clicked() {
+            timer.start(now);
+        }
+        if (acknowledged || ring.clicked())
+            && let Some(&i) = index.get(&note.id)
+        {
+            board.notes[i].timer = note.timer;
+        }
+    }
+
+    if !note.tags.is_empty() {
+        let tags: Vec<String> = note.tags.iter().map(|t| format!("#{t}")).collect();
+        ui.painter().text(
+            rect_to_view(Rect::from_min_size(note.pos, note.size)).left_bottom()
+                + egui::vec2(4.0, -4.0),
+            egui::Align2::LEFT_BOTTOM,
+            tags.join(" "),
+            egui::FontId::proportional(11.0),
+            Color32::from_black_alpha(140),
+        );
+    }
+
+    if dimmed {
+        // Filled piece by piece, as a speech bubble's outline isn't convex
+        let corners = view_corners(
+            note.pos,
+            note.size,
+            Vec2::ZERO,
+            note.rotation,
+            Vec2::ZERO,
+            view_turns,
+            scene_center(),
+        );
+        for piece in note.shape.pieces() {
+            ui.painter().add(Shape::convex_polygon(
+                piece.iter().map(|&p| unit_to_quad(p, &corners)).collect(),
+                board.background.gamma_multiply(0.7),
+                Stroke::NONE,
+            ));
         }
     }
 
+    if selection.contains(note.id) {
+        ui.painter().add(Shape::closed_line(
+            outline_of(3.0),
+            Stroke::new(2.0, Color32::from_rgb(60, 120, 220)),
+        ));
+    }
+
     if highlight_match {
         let stroke = if active {
             Stroke::new(3.0, Color32::RED)
         } else {
             Stroke::new(2.0, Color32::LIGHT_RED)
         };
-        ui.painter().rect_stroke(
-            Rect::from_min_size(note.pos, note.size),
-            0.0,
-            stroke,
-            egui::StrokeKind::Inside,
-        );
+        ui.painter()
+            .add(Shape::closed_line(outline_of(-stroke.width / 2.0), stroke));
     }
 
     if response.drag_stopped() {
-        note.pos = snap_to_grid(note.pos, grid_size);
-        if let Some(n) = board.notes.iter_mut().find(|n| n.id == note.id) {
-            n.pos = note.pos;
+        if connecting {
+            actions.push(NoteAction::Connect { from: note.id });
+            ev_sound.write(PlaySound(SoundEvent::DragStop));
+        } else {
+            // A single note let go while moving slides on and snaps once it
+            // rests
+            if tossing && moving && selection.ids().len() <= 1 {
+                let scale = ui
+                    .ctx()
+                    .layer_transform_to_global(ui.layer_id())
+                    .map_or(1.0, |t| t.scaling);
+                let velocity = rotate_vec_quarter_turns(
+                    ui.input(|i| i.pointer.velocity()) / scale,
+                    inverse_quarter_turns(view_turns),
+                );
+                if velocity.length() >= MIN_TOSS_SPEED {
+                    ui_state.toss = Some(velocity);
+                }
+            }
+            // Snapping happens for the whole selection at once, and so
+            // does its sound
+            actions.push(NoteAction::Drop);
         }
-        // Play sound when dragging stops
-        ev_plop.write_default();
     }
+    drop_target
 }
 
-// System to load audio assets at startup
-fn setup_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(AudioAssets {
-        plop: asset_server.load("plop.wav"),
-    });
+/// Mouse cursor for a resize handle, following the view rotation
+fn resize_cursor(handle: ResizeHandle, view_turns: u8) -> egui::CursorIcon {
+    let (sx, sy) = handle.sides();
+    let (sx, sy) = if view_turns % 2 == 1 {
+        (-sy, sx)
+    } else {
+        (sx, sy)
+    };
+    match (sx, sy) {
+        (0, _) => egui::CursorIcon::ResizeVertical,
+        (_, 0) => egui::CursorIcon::ResizeHorizontal,
+        _ if sx == sy => egui::CursorIcon::ResizeNwSe,
+        _ => egui::CursorIcon::ResizeNeSw,
+    }
 }
 
-// Spawn note entities from the loaded application state
-fn spawn_existing_notes(mut commands: Commands, app: Res<PostItData>) {
-    for note in &app.state.board.notes {
-        commands.spawn((note.clone(), NoteUi::default()));
+/// Ctrl+Shift+C copies exactly what the board viewport shows to the clipboard
+fn screenshot_viewport_shortcut(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    board_viewport: Res<BoardViewport>,
+    mut contexts: EguiContexts,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !(ctrl && shift && keys.just_pressed(KeyCode::KeyC)) {
+        return;
+    }
+    let viewport = board_viewport.0;
+    let pixels_per_point = contexts.ctx_mut().pixels_per_point();
+    commands.spawn(Screenshot::primary_window()).observe(
+        move |trigger: Trigger<ScreenshotCaptured>,
+              mut clipboard: ResMut<ClipboardHolder>,
+              mut status: ResMut<StatusMessage>| {
+            status.0 = match copy_screenshot(
+                &trigger.event().0,
+                viewport,
+                pixels_per_point,
+                &mut clipboard,
+            ) {
+                Ok(()) => "Viewport copied to clipboard".into(),
+                Err(e) => format!("Screenshot failed: {e}"),
+            };
+        },
+    );
+}
+
+/// Crop a window capture to the board viewport and put it on the clipboard
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_screenshot(
+    capture: &Image,
+    viewport: Rect,
+    pixels_per_point: f32,
+    clipboard: &mut ClipboardHolder,
+) -> Result<(), String> {
+    let full = capture
+        .clone()
+        .try_into_dynamic()
+        .map_err(|e| e.to_string())?;
+    // The alpha channel may hold HDR brightness, so make the image opaque
+    let full = image::DynamicImage::ImageRgb8(full.to_rgb8()).to_rgba8();
+    let cropped = crop_to_rect(&full, viewport, pixels_per_point).ok_or("board is not visible")?;
+    let image = arboard::ImageData {
+        width: cropped.width() as usize,
+        height: cropped.height() as usize,
+        bytes: cropped.into_raw().into(),
+    };
+    if clipboard.0.is_none() {
+        clipboard.0 = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+    }
+    let target = clipboard.0.as_mut().expect("clipboard was just created");
+    target.set_image(image).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn copy_screenshot(
+    _capture: &Image,
+    _viewport: Rect,
+    _pixels_per_point: f32,
+    _clipboard: &mut ClipboardHolder,
+) -> Result<(), String> {
+    Err("the browser doesn't let pages copy images".into())
+}
+
+// System to load audio assets at startup. Browsers keep a page silent until
+// it is interacted with, so there the sound waits for the first plop.
+fn setup_audio(mut commands: Commands, asset_server: Res<AssetServer>, themes: Res<Themes>) {
+    if cfg!(target_arch = "wasm32") {
+        return;
+    }
+    let pack = themes.settings.sounds.pack.as_deref();
+    commands.insert_resource(SoundPack::load(&asset_server, pack));
+}
+
+/// Show the board, views and search the user left off with
+fn restore_session(
+    mut app: ResMut<PostItData>,
+    last: Res<LastSession>,
+    mut search: ResMut<SearchState>,
+) {
+    let app = &mut *app;
+    if last.session.restore(&app.save_path, &mut app.state) {
+        search.query = last.session.search.clone();
+        update_search(app, &mut search);
+    }
+}
+
+// Spawn note entities from the loaded application state, reopening the
+// edit windows left open
+fn spawn_existing_notes(mut commands: Commands, app: Res<PostItData>, last: Res<LastSession>) {
+    let restored = last.session.file == app.save_path;
+    for note in &app.state.board().notes {
+        let mut ui_state = NoteUi::default();
+        if restored && last.session.editing.contains(&note.id) {
+            start_editing(note, &mut ui_state);
+        }
+        commands.spawn((note.clone(), ui_state));
+    }
+}
+/// Bring note entities in line with the board and log every repair
+fn check_note_integrity(
+    mut commands: Commands,
+    mut app: ResMut<PostItData>,
+    notes: Query<(Entity, &NoteData)>,
+) {
+    let entities: Vec<(Entity, u64)> = notes.iter().map(|(e, n)| (e, n.id)).collect();
+    let repair = reconcile(&mut app.state, &entities);
+    for entity in repair.despawn {
+        commands.entity(entity).despawn();
+    }
+    for note in repair.spawn {
+        commands.spawn((note, NoteUi::default()));
+    }
+    for fix in &repair.fixes {
+        warn!("Integrity check: {fix}");
+    }
+}
+
+/// Keep `saved`, just written to the save file at `path`, as the version
+/// later outside changes to it are merged against
+fn remember_merge_base(path: &Path, saved: &str) {
+    if let Err(e) = write_base(path, saved) {
+        warn!("Could not keep the merge base of {}: {e}", path.display());
+    }
+}
+
+/// Write the save file in the form the current password gives it right
+/// away, along with its backups and merge base, so no copy stays in the form
+/// `old_password` gave them; returns the JSON saved
+fn reseal_save_files(app: &PostItData, old_password: Option<&str>) -> std::io::Result<String> {
+    let path = &app.save_path;
+    let password = app.password.as_deref();
+    let json = app.state.to_json().map_err(std::io::Error::other)?;
+    let saved = app.state.to_saved(password)?;
+    // Without a backup, which would keep the old form
+    write_with_backups(path, &saved, 0)?;
+    write_base(path, &saved)?;
+    rewrite_backups(path, SAVE_BACKUPS, |contents| {
+        reseal(contents, old_password, password)
+    })?;
+    Ok(json)
+}
+
+/// Save every few seconds if anything changed since the last save
+fn autosave_system(
+    mut app: ResMut<PostItData>,
+    mut autosave: ResMut<Autosave>,
+    mut save_watch: ResMut<SaveWatch>,
+    mut status: ResMut<StatusMessage>,
+    notes: Query<(Entity, &NoteData)>,
+    time: Res<Time>,
+) {
+    if !autosave.0.due(time.elapsed_secs_f64()) {
+        return;
+    }
+    if app.is_locked(&app.save_path) || save_watch.pending {
+        return;
+    }
+    sync_board_from_entities(app.state.board_mut(), &notes);
+    let Ok(json) = app.state.to_json() else {
+        return;
+    };
+    if !autosave.0.is_dirty(&json) {
+        return;
+    }
+    let saved = app.state.to_saved(app.password.as_deref());
+    let written = saved.and_then(|saved| {
+        write_with_backups(&app.save_path, &saved, SAVE_BACKUPS)?;
+        remember_merge_base(&app.save_path, &saved);
+        Ok(())
+    });
+    match written {
+        Ok(()) => {
+            autosave.0.mark_saved(&json);
+            save_watch.watch.acknowledge();
+            status.0 = format!("Autosaved at {}", format_local_datetime(now_secs()));
+        }
+        Err(e) => status.0 = format!("Autosave failed: {e}"),
     }
 }
-// Auto save when the app exits
+
+// Auto save when the app exits, along with where the user left off
 fn autosave_on_exit(
     mut exit_events: EventReader<AppExit>,
     mut app: ResMut<PostItData>,
-    notes: Query<&NoteData>,
+    notes: Query<(&NoteData, &NoteUi)>,
+    search: Res<SearchState>,
+    last: Res<LastSession>,
+    save_watch: Res<SaveWatch>,
 ) {
     if exit_events.read().next().is_some() {
-        for note in notes.iter() {
-            if let Some(n) = app.state.board.notes.iter_mut().find(|n| n.id == note.id) {
+        for (note, _) in notes.iter() {
+            if let Some(n) = app
+                .state
+                .board_mut()
+                .notes
+                .iter_mut()
+                .find(|n| n.id == note.id)
+            {
                 *n = note.clone();
             }
         }
-        app.state.save_to_file(&app.save_path);
+        // A file changed outside plop is left alone, with the boards here
+        // kept next to it
+        let path = if save_watch.pending {
+            own_copy_path(&app.save_path)
+        } else {
+            app.save_path.clone()
+        };
+        if app.is_locked(&app.save_path) {
+            // Leave the sealed file as it is
+        } else {
+            let saved = app.state.to_saved(app.password.as_deref());
+            match saved
+                .and_then(|saved| write_with_backups(&path, &saved, SAVE_BACKUPS).map(|()| saved))
+            {
+                Ok(saved) if !save_watch.pending => remember_merge_base(&path, &saved),
+                Ok(_) => {}
+                Err(e) => error!("Could not save {}: {e}", path.display()),
+            }
+        }
+        let editing = notes
+            .iter()
+            .filter(|(_, ui_state)| ui_state.is_editing)
+            .map(|(note, _)| note.id)
+            .collect();
+        let session = Session::capture(&app.save_path, &app.state, &search.query, editing);
+        if let Err(e) = save_json(&session, &last.path) {
+            error!("Could not save the session: {e}");
+        }
     }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match plop::cli::parse(&args) {
+        Ok(None) => {}
+        Ok(Some(command)) => {
+            match plop::cli::execute(command) {
+                Ok(report) => println!("{report}"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{}", plop::cli::USAGE);
+            std::process::exit(2);
+        }
+    }
+
     App::new()
         .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.1)))
         .init_resource::<PostItData>()
-        .init_resource::<GridSize>()
         .init_resource::<SearchState>()
-        .add_event::<PlayPlopEvent>()
+        .init_resource::<DragPan>()
+        .init_resource::<StatusMessage>()
+        .init_resource::<BoardViewport>()
+        .init_resource::<ClipboardHolder>()
+        .init_resource::<ExpiryCleanup>()
+        .init_resource::<SaveWatch>()
+        .init_resource::<RemoteSync>()
+        .init_resource::<Themes>()
+        .init_resource::<GridSize>()
+        .init_resource::<HotSeat>()
+        .init_resource::<TimeTravel>()
+        .init_resource::<UndoHistory>()
+        .init_resource::<SelectedNotes>()
+        .init_resource::<ConnectTool>()
+        .init_resource::<PenTool>()
+        .init_resource::<LanSync>()
+        .init_resource::<CopiedNotes>()
+        .init_resource::<Minimap>()
+        .init_resource::<ShowGrid>()
+        .init_resource::<DueList>()
+        .init_resource::<StatsPanel>()
+        .init_resource::<NoteList>()
+        .init_resource::<TrashPanel>()
+        .init_resource::<VotePanel>()
+        .init_resource::<Presentation>()
+        .init_resource::<Recent>()
+        .init_resource::<BoardTemplates>()
+        .init_resource::<SplitPane>()
+        .init_resource::<QuickCapture>()
+        .insert_non_send_resource(GlobalShortcut::default())
+        .init_resource::<LastSession>()
+        .init_resource::<Keys>()
+        .init_resource::<Autosave>()
+        .add_event::<PlaySound>()
+        .add_event::<CheckIntegrity>()
         .add_plugins(EntropyPlugin::<WyRand>::default())
-        .add_plugins(DefaultPlugins)
+        // Closing the window may only hide it, see `close_window_system`
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            close_when_requested: false,
+            ..default()
+        }))
         .add_plugins(bevy_egui::EguiPlugin {
             // Default configuration
             enable_multipass_for_primary_context: false,
         })
-        .add_systems(Startup, (setup_audio, spawn_existing_notes))
-        .add_systems(Update, (ui_system, play_plop_sound))
+        .add_systems(
+            Startup,
+            (
+                setup_audio,
+                restore_session,
+                spawn_existing_notes.after(restore_session),
+            ),
+        )
+        .add_systems(PostStartup, check_note_integrity)
+        .add_systems(
+            Update,
+            (
+                board_tabs_system
+                    .before(ui_system)
+                    .run_if(|presentation: Res<Presentation>| !presentation.active),
+                split_pane_system
+                    .after(board_tabs_system)
+                    .before(ui_system)
+                    .run_if(|presentation: Res<Presentation>| !presentation.active),
+                ui_system,
+                global_shortcut_system,
+                quick_capture_system.after(ui_system),
+                close_window_system,
+                expiry_cleanup_system,
+                note_timer_system.after(ui_system),
+                toss_system.after(ui_system),
+                gamepad_system
+                    .before(ui_system)
+                    .run_if(|presentation: Res<Presentation>| !presentation.active),
+                time_travel_system,
+                autosave_system.after(ui_system),
+                save_watch_system.after(autosave_system),
+                remote_sync_system.after(autosave_system),
+                lan_sync_system.after(ui_system),
+                presentation_window_system.after(ui_system),
+                play_sounds,
+                screenshot_viewport_shortcut,
+                check_note_integrity
+                    .after(ui_system)
+                    .after(time_travel_system)
+                    .run_if(on_event::<CheckIntegrity>),
+            ),
+        )
         .add_systems(Last, autosave_on_exit)
         .run();
 }
@@ -0,0 +1,384 @@
+//! Lightweight Markdown for note text: headings, bullets, bold and italics,
+//! plus web links and `TODO:` lines picked out of plain text
+//!
+//! Only the rendering is affected; the note text itself stays raw Markdown.
+
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Stroke};
+use std::ops::Range;
+
+/// Color of plain text
+pub const TEXT_COLOR: Color32 = Color32::from_gray(30);
+
+/// Color of bold text and headings. The default fonts have no bold face, so
+/// like egui's own "strong" text, bold is drawn in a stronger color.
+pub const STRONG_COLOR: Color32 = Color32::BLACK;
+
+/// Background of the parts matching a search
+pub const HIGHLIGHT_COLOR: Color32 = Color32::LIGHT_RED;
+
+/// Color of web links, which are underlined too
+pub const LINK_COLOR: Color32 = Color32::from_rgb(20, 80, 200);
+
+/// Color of `TODO:` lines
+pub const TODO_COLOR: Color32 = Color32::from_rgb(190, 80, 0);
+
+/// Schemes a web link starts with
+const LINK_SCHEMES: [&str; 2] = ["https://", "http://"];
+
+/// How a run of text is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    /// Heading level, 0 for body text
+    pub heading: u8,
+    /// A web link, drawn as is and clickable
+    pub link: bool,
+    /// Part of a `TODO:` line
+    pub todo: bool,
+}
+
+impl Style {
+    /// Font size relative to body text
+    pub fn scale(self) -> f32 {
+        match self.heading {
+            0 => 1.0,
+            1 => 1.5,
+            2 => 1.3,
+            _ => 1.15,
+        }
+    }
+}
+
+/// A run of rendered text in one style
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Split Markdown text into styled runs, with the markup removed and list
+/// markers drawn as bullets
+pub fn parse(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            push(&mut spans, "\n", Style::default());
+        }
+        let (content, style) = block(line);
+        inline(&content, style, &mut spans);
+    }
+    spans
+}
+
+/// Line content and style after handling a heading or list marker
+fn block(line: &str) -> (String, Style) {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+        let style = Style {
+            bold: true,
+            heading: hashes as u8,
+            ..Default::default()
+        };
+        return (line[hashes + 1..].to_owned(), style);
+    }
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    if trimmed.starts_with("TODO:") {
+        let style = Style {
+            todo: true,
+            ..Default::default()
+        };
+        return (line.to_owned(), style);
+    }
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(marker) {
+            return (format!("{indent}• {item}"), Style::default());
+        }
+    }
+    (line.to_owned(), Style::default())
+}
+
+/// Length in chars of the web link starting `chars`, without trailing
+/// punctuation; 0 if none starts there
+fn link_len(chars: &[char]) -> usize {
+    let Some(scheme) = LINK_SCHEMES.iter().find(|scheme| {
+        let scheme: Vec<char> = scheme.chars().collect();
+        chars.starts_with(&scheme)
+    }) else {
+        return 0;
+    };
+    let word = chars.iter().take_while(|c| !c.is_whitespace()).count();
+    let trailing = chars[..word]
+        .iter()
+        .rev()
+        .take_while(|c| {
+            matches!(
+                c,
+                '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '>' | '"' | '\''
+            )
+        })
+        .count();
+    let len = word - trailing;
+    if len > scheme.len() { len } else { 0 }
+}
+
+/// Handle `**bold**`, `*italic*` and `_italic_`; markers without a closing
+/// counterpart stay as they are, and web links are kept whole
+fn inline(line: &str, base: Style, spans: &mut Vec<Span>) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut style = base;
+    let mut bold = false;
+    let mut italic_marker = None;
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i + 1..];
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let at_word_start = prev.is_none_or(|p| p.is_whitespace() || "([<\"'".contains(p));
+        let link = if at_word_start {
+            link_len(&chars[i..])
+        } else {
+            0
+        };
+        if link > 0 {
+            push(spans, &text, style);
+            text.clear();
+            let url: String = chars[i..i + link].iter().collect();
+            let link_style = Style {
+                link: true,
+                ..style
+            };
+            push(spans, &url, link_style);
+            i += link;
+            continue;
+        }
+        if c == '*' && rest.first() == Some(&'*') {
+            let closed = rest[1..].windows(2).any(|w| w == ['*', '*']);
+            if bold || closed {
+                push(spans, &text, style);
+                text.clear();
+                bold = !bold;
+                style.bold = base.bold || bold;
+                i += 2;
+                continue;
+            }
+        } else if c == '*' || c == '_' {
+            let closes = italic_marker == Some(c);
+            let opens = italic_marker.is_none()
+                && rest.first().is_some_and(|n| !n.is_whitespace())
+                && !(c == '_' && prev.is_some_and(char::is_alphanumeric))
+                && rest.contains(&c);
+            if closes || opens {
+                push(spans, &text, style);
+                text.clear();
+                style.italic = opens;
+                italic_marker = opens.then_some(c);
+                i += 1;
+                continue;
+            }
+        }
+        text.push(c);
+        i += 1;
+    }
+    push(spans, &text, style);
+}
+
+/// Append text, merging it with the last span if the style matches
+fn push(spans: &mut Vec<Span>, text: &str, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.to_owned(),
+            style,
+        }),
+    }
+}
+
+/// The web link at byte `offset` of the rendered text of `text`
+pub fn link_at(text: &str, offset: usize) -> Option<String> {
+    let mut start = 0;
+    for span in parse(text) {
+        let end = start + span.text.len();
+        if span.style.link && (start..end).contains(&offset) {
+            return Some(span.text);
+        }
+        start = end;
+    }
+    None
+}
+
+/// Rendered text without any styling
+pub fn plain_text(text: &str) -> String {
+    parse(text).into_iter().map(|s| s.text).collect()
+}
+
+/// Byte ranges of `haystack` matching `needle`, ignoring case
+fn matches(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let lower = haystack.to_lowercase();
+    let needle = needle.to_lowercase();
+    // Lowercasing can change byte lengths; then ranges wouldn't line up
+    if needle.is_empty() || lower.len() != haystack.len() {
+        return Vec::new();
+    }
+    lower
+        .match_indices(&needle)
+        .map(|(start, m)| start..start + m.len())
+        .collect()
+}
+
+/// Layout job drawing `text` as Markdown with body text in `font`, all of
+/// it bold if `bold`, marking the parts that match `highlight`
+pub fn layout_job(text: &str, font: &FontId, bold: bool, highlight: Option<&str>) -> LayoutJob {
+    let spans = parse(text);
+    let plain: String = spans.iter().map(|s| s.text.as_str()).collect();
+    let found = highlight.map(|q| matches(&plain, q)).unwrap_or_default();
+
+    let mut job = LayoutJob::default();
+    let mut offset = 0;
+    for span in &spans {
+        let range = offset..offset + span.text.len();
+        offset = range.end;
+        // Split the span where matches start and end
+        let mut cuts = vec![range.start, range.end];
+        for m in &found {
+            cuts.extend([m.start, m.end].into_iter().filter(|c| range.contains(c)));
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        for cut in cuts.windows(2) {
+            let (start, end) = (cut[0], cut[1]);
+            let mut format = TextFormat {
+                font_id: FontId::new(font.size * span.style.scale(), font.family.clone()),
+                color: if span.style.link {
+                    LINK_COLOR
+                } else if span.style.todo {
+                    TODO_COLOR
+                } else if span.style.bold || bold {
+                    STRONG_COLOR
+                } else {
+                    TEXT_COLOR
+                },
+                italics: span.style.italic,
+                ..Default::default()
+            };
+            if span.style.link {
+                format.underline = Stroke::new(1.0, LINK_COLOR);
+            }
+            if found.iter().any(|m| m.start <= start && end <= m.end) {
+                format.background = HIGHLIGHT_COLOR;
+            }
+            job.append(&plain[start..end], 0.0, format);
+        }
+    }
+    job
+}
+
+/// Draw every part of `job` in `color` instead of the usual text colors,
+/// for themes with text of their own color; links and TODOs keep theirs
+pub fn tint(job: &mut LayoutJob, color: Color32) {
+    if color == TEXT_COLOR {
+        return;
+    }
+    for section in &mut job.sections {
+        if ![LINK_COLOR, TODO_COLOR].contains(&section.format.color) {
+            section.format.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles(text: &str) -> Vec<(String, bool, bool, u8)> {
+        parse(text)
+            .into_iter()
+            .map(|s| (s.text, s.style.bold, s.style.italic, s.style.heading))
+            .collect()
+    }
+
+    #[test]
+    fn inline_markup_is_removed() {
+        assert_eq!(
+            styles("a **b** *c* _d_"),
+            [
+                ("a ".into(), false, false, 0),
+                ("b".into(), true, false, 0),
+                (" ".into(), false, false, 0),
+                ("c".into(), false, true, 0),
+                (" ".into(), false, false, 0),
+                ("d".into(), false, true, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_markers_stay() {
+        assert_eq!(plain_text("2 * 3 and **x"), "2 * 3 and **x");
+        assert_eq!(plain_text("snake_case_name"), "snake_case_name");
+    }
+
+    #[test]
+    fn headings_and_bullets() {
+        assert_eq!(
+            plain_text("## Plan\n- one\n  * two\n#tag"),
+            "Plan\n• one\n  • two\n#tag"
+        );
+        assert_eq!(styles("# Big")[0], ("Big".into(), true, false, 1));
+    }
+
+    #[test]
+    fn highlight_follows_rendered_text() {
+        let font = FontId::proportional(10.0);
+        let job = layout_job("**milk** and more milk", &font, false, Some("MILK"));
+        let highlighted: Vec<&str> = job
+            .sections
+            .iter()
+            .filter(|s| s.format.background == HIGHLIGHT_COLOR)
+            .map(|s| &job.text[s.byte_range.clone()])
+            .collect();
+        assert_eq!(highlighted, ["milk", "milk"]);
+        assert_eq!(job.text, "milk and more milk");
+    }
+
+    #[test]
+    fn links_and_todos_stand_out() {
+        let text = "TODO: call\nsee https://example.com/a_b_c, or (http://x.org)";
+        let spans = parse(text);
+        assert!(spans[0].style.todo);
+        let links: Vec<&str> = spans
+            .iter()
+            .filter(|s| s.style.link)
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(links, ["https://example.com/a_b_c", "http://x.org"]);
+        let offset = plain_text(text).find("example").unwrap();
+        assert_eq!(
+            link_at(text, offset).as_deref(),
+            Some("https://example.com/a_b_c")
+        );
+        assert_eq!(link_at(text, 0), None);
+        // A scheme alone or inside a word is no link
+        assert!(parse("https:// xhttps://a").iter().all(|s| !s.style.link));
+    }
+
+    #[test]
+    fn tinting_recolors_all_text() {
+        let mut job = layout_job("**milk** and eggs", &FontId::monospace(10.0), false, None);
+        let before = job.clone();
+        tint(&mut job, TEXT_COLOR);
+        assert_eq!(job, before);
+        tint(&mut job, Color32::WHITE);
+        assert!(
+            job.sections
+                .iter()
+                .all(|s| s.format.color == Color32::WHITE)
+        );
+    }
+}
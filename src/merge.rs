@@ -0,0 +1,389 @@
+//! Merging two copies of a save file that were changed apart, e.g. on two
+//! computers sharing a folder
+//!
+//! Notes are matched by id. With the common version both copies started
+//! from, a note changed on one side only takes that change and a note
+//! deleted on one side stays deleted; without it every note of either copy
+//! is kept. A note changed differently on both sides is kept twice, so no
+//! edit is ever lost, and one deleted there goes to the trash here.
+//! Connectors, ink and frames follow the same rules, keeping our version
+//! where both sides changed one.
+//!
+//! What plop last wrote to a save file is kept beside it as that common
+//! version, see [`base_path`].
+
+use crate::expiry::now_secs;
+use crate::{AppState, Board, NoteData};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What merging found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// Notes taken from the other copy
+    pub added: usize,
+    /// Notes changed in the other copy only, whose change was taken
+    pub updated: usize,
+    /// Notes deleted in the other copy
+    pub removed: usize,
+    /// Notes changed on both sides, kept as an extra copy
+    pub conflicts: usize,
+}
+
+impl MergeReport {
+    /// One-line summary for status messages
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} updated, {} removed, {} conflicting",
+            self.added, self.updated, self.removed, self.conflicts
+        )
+    }
+}
+
+/// File beside the save file at `save_path` keeping what plop last wrote
+/// to it, sealed the same way
+pub fn base_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".base");
+    save_path.with_file_name(name)
+}
+
+/// Keep `saved`, just written to or read from the save file at `save_path`,
+/// as the version later changes to it are merged against
+pub fn write_base(save_path: &Path, saved: &str) -> io::Result<()> {
+    crate::backups::write_with_backups(&base_path(save_path), saved, 0)
+}
+
+/// The version last written to the save file at `save_path`, opened with
+/// `password`; `None` if none was kept or it can't be read
+pub fn read_base(save_path: &Path, password: Option<&str>) -> Option<AppState> {
+    AppState::try_load_sealed_file(&base_path(save_path), password).ok()
+}
+
+/// Merge `theirs` into `ours`, given the version `base` both started from
+/// if it is known
+pub fn merge_states(
+    base: Option<&AppState>,
+    ours: &mut AppState,
+    theirs: &AppState,
+) -> MergeReport {
+    let mut report = MergeReport::default();
+    ours.next_note_id = ours.next_note_id.max(theirs.next_note_id);
+    for their_board in &theirs.boards {
+        let base_board = base.and_then(|base| base.boards.iter().find(|b| b.id == their_board.id));
+        let our_board = ours.boards.iter().position(|b| b.id == their_board.id);
+        // Boards both sides added under the same id are different boards
+        let added_on_both = base.is_some() && base_board.is_none();
+        match our_board.filter(|_| !added_on_both) {
+            Some(index) => {
+                let mut used = note_ids(ours);
+                let next_id = &mut ours.next_note_id;
+                merge_board(
+                    base_board,
+                    &mut ours.boards[index],
+                    their_board,
+                    &mut used,
+                    next_id,
+                    &mut report,
+                );
+            }
+            // A board removed here but changed there comes back
+            None if our_board.is_some() || base_board.is_none_or(|b| b != their_board) => {
+                report.added += their_board.notes.len();
+                ours.add_board_copy(their_board.clone());
+            }
+            None => {}
+        }
+    }
+    report
+}
+
+/// Ids of every note in `state`
+fn note_ids(state: &AppState) -> HashSet<u64> {
+    state
+        .boards
+        .iter()
+        .flat_map(Board::stored_notes)
+        .map(|n| n.id)
+        .collect()
+}
+
+/// Merge the notes, connectors, ink and frames of `theirs` into `ours`
+fn merge_board(
+    base: Option<&Board>,
+    ours: &mut Board,
+    theirs: &Board,
+    used: &mut HashSet<u64>,
+    next_id: &mut u64,
+    report: &mut MergeReport,
+) {
+    let base_note = |id: u64| base.and_then(|b| b.notes.iter().find(|n| n.id == id));
+    let mut fresh_id = |used: &mut HashSet<u64>| {
+        while used.contains(&*next_id) {
+            *next_id += 1;
+        }
+        used.insert(*next_id);
+        *next_id += 1;
+        *next_id - 1
+    };
+
+    for their_note in &theirs.notes {
+        let base_note = base_note(their_note.id);
+        match ours.notes.iter().position(|n| n.id == their_note.id) {
+            Some(i) if ours.notes[i] == *their_note => {}
+            Some(i) if base_note == Some(&ours.notes[i]) => {
+                ours.notes[i] = their_note.clone();
+                report.updated += 1;
+            }
+            // Only our side changed it
+            Some(_) if base_note == Some(their_note) => {}
+            // Both sides added a note under the same id
+            Some(_) if base.is_some() && base_note.is_none() => {
+                ours.notes.push(NoteData {
+                    id: fresh_id(used),
+                    ..their_note.clone()
+                });
+                report.added += 1;
+            }
+            Some(_) => {
+                ours.notes.push(NoteData {
+                    id: fresh_id(used),
+                    ..their_note.clone()
+                });
+                report.conflicts += 1;
+            }
+            // Deleted here and unchanged there
+            None if base_note == Some(their_note) => {}
+            None => {
+                let id = if used.contains(&their_note.id) {
+                    fresh_id(used)
+                } else {
+                    used.insert(their_note.id);
+                    their_note.id
+                };
+                ours.notes.push(NoteData {
+                    id,
+                    ..their_note.clone()
+                });
+                report.added += 1;
+            }
+        }
+    }
+
+    // Notes deleted there go to the trash, unless changed here since
+    if let Some(base) = base {
+        let (kept, deleted): (Vec<_>, Vec<_>) = std::mem::take(&mut ours.notes)
+            .into_iter()
+            .partition(|note| {
+                theirs.notes.iter().any(|n| n.id == note.id) || !base.notes.contains(note)
+            });
+        ours.notes = kept;
+        report.removed += deleted.len();
+        let now = now_secs();
+        for note in deleted {
+            let trashed = theirs.trash.iter().find(|t| t.note.id == note.id);
+            ours.trash_note(note, trashed.map_or(now, |t| t.deleted_at));
+        }
+    }
+
+    merge_items(
+        base.map(|b| b.connectors.as_slice()),
+        &mut ours.connectors,
+        &theirs.connectors,
+        |a, b| (a.from_note, a.to_note) == (b.from_note, b.to_note),
+    );
+    merge_items(
+        base.map(|b| b.strokes.as_slice()),
+        &mut ours.strokes,
+        &theirs.strokes,
+        PartialEq::eq,
+    );
+    merge_items(
+        base.map(|b| b.frames.as_slice()),
+        &mut ours.frames,
+        &theirs.frames,
+        |a, b| a.id == b.id,
+    );
+}
+
+/// Merge `theirs` into `ours`, items told apart by `same`: a change or
+/// deletion on one side is taken where the other side left the item as in
+/// `base`, and ours is kept where both changed it
+fn merge_items<T: Clone + PartialEq>(
+    base: Option<&[T]>,
+    ours: &mut Vec<T>,
+    theirs: &[T],
+    same: impl Fn(&T, &T) -> bool,
+) {
+    for their in theirs {
+        let base_item = base.and_then(|base| base.iter().find(|item| same(item, their)));
+        match ours.iter().position(|item| same(item, their)) {
+            Some(i) if base_item == Some(&ours[i]) => ours[i] = their.clone(),
+            Some(_) => {}
+            // Deleted here and unchanged there
+            None if base_item == Some(their) => {}
+            None => ours.push(their.clone()),
+        }
+    }
+    if let Some(base) = base {
+        ours.retain(|item| theirs.iter().any(|t| same(t, item)) || !base.contains(item));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoardOps;
+    use crate::connectors::{Connector, ConnectorStyle};
+    use crate::frames::{DEFAULT_FRAME_COLOR, Frame};
+    use crate::ink::InkStroke;
+    use egui::{Color32, Pos2, Rect};
+
+    fn text(state: &AppState) -> Vec<&str> {
+        let mut texts: Vec<&str> = state.boards[0]
+            .notes
+            .iter()
+            .map(|n| n.text.as_str())
+            .collect();
+        texts.sort();
+        texts
+    }
+
+    /// The same starting state every time, as AppState can't be cloned
+    fn start(texts: &[&str]) -> AppState {
+        let mut state = AppState::default();
+        for text in texts {
+            state.add_note(text, Pos2::ZERO);
+        }
+        state
+    }
+
+    #[test]
+    fn changes_from_both_sides_are_kept() {
+        let texts = ["kept", "edited", "deleted", "both"];
+        let base = start(&texts);
+        let [kept, edited, deleted, both] = [1, 2, 3, 4];
+        let mut ours = start(&texts);
+        let mut theirs = start(&texts);
+        ours.add_note("ours", Pos2::ZERO);
+        theirs.add_note("theirs", Pos2::ZERO);
+        fn note(state: &mut AppState, id: u64) -> &mut String {
+            let board = state.board_mut();
+            let index = board.notes.iter().position(|n| n.id == id).unwrap();
+            &mut board.notes[index].text
+        }
+        *note(&mut theirs, edited) = "edited there".into();
+        theirs.board_mut().notes.retain(|n| n.id != deleted);
+        *note(&mut ours, both) = "both here".into();
+        *note(&mut theirs, both) = "both there".into();
+
+        let report = merge_states(Some(&base), &mut ours, &theirs);
+        assert_eq!(
+            text(&ours),
+            [
+                "both here",
+                "both there",
+                "edited there",
+                "kept",
+                "ours",
+                "theirs"
+            ]
+        );
+        assert_eq!(
+            report,
+            MergeReport {
+                added: 1,
+                updated: 1,
+                removed: 1,
+                conflicts: 1,
+            }
+        );
+        // The note deleted there can still be restored here
+        let trash = &ours.boards[0].trash;
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].note.id, deleted);
+        // The notes both sides added under the same id were told apart
+        let ids = note_ids(&ours);
+        assert_eq!(ids.len(), 7);
+        assert!(ids.contains(&kept));
+        assert!(ours.next_note_id > *ids.iter().max().unwrap());
+    }
+
+    #[test]
+    fn connectors_ink_and_frames_merge_like_notes() {
+        let connector = |from_note, to_note| Connector {
+            from_note,
+            to_note,
+            style: ConnectorStyle::default(),
+            label: String::new(),
+        };
+        let stroke = |width| InkStroke::new(Color32::BLACK, width);
+        let frame = |id, title: &str| Frame {
+            id,
+            title: title.into(),
+            rect: Rect::from_min_size(Pos2::ZERO, egui::vec2(100.0, 100.0)),
+            color: DEFAULT_FRAME_COLOR,
+        };
+        let sample = || {
+            let mut state = start(&["a", "b", "c"]);
+            let board = state.board_mut();
+            board.connectors = vec![connector(1, 2), connector(2, 3)];
+            board.strokes = vec![stroke(1.0), stroke(2.0)];
+            board.frames = vec![frame(1, "Todo"), frame(2, "Done")];
+            state
+        };
+        let base = sample();
+        let mut ours = sample();
+        let mut theirs = sample();
+        let there = theirs.board_mut();
+        there.connectors.remove(1);
+        there.connectors[0].label = "next".into();
+        there.strokes[0] = stroke(3.0);
+        there.frames[0].title = "Doing".into();
+        let here = ours.board_mut();
+        here.strokes.push(stroke(4.0));
+        here.frames.remove(1);
+
+        merge_states(Some(&base), &mut ours, &theirs);
+        let board = ours.board();
+        assert_eq!(board.connectors.len(), 1);
+        assert_eq!(board.connectors[0].label, "next");
+        let widths: Vec<f32> = board.strokes.iter().map(|s| s.width).collect();
+        assert_eq!(widths, [2.0, 4.0, 3.0]);
+        assert_eq!(board.frames, [frame(1, "Doing")]);
+    }
+
+    #[test]
+    fn without_a_base_no_note_is_lost() {
+        let mut ours = start(&["gone there"]);
+        let mut theirs = start(&["gone there"]);
+        theirs.board_mut().notes.clear();
+        theirs.add_note("new there", Pos2::ZERO);
+        let report = merge_states(None, &mut ours, &theirs);
+        assert_eq!(text(&ours), ["gone there", "new there"]);
+        assert_eq!(report.removed, 0);
+    }
+
+    #[test]
+    fn the_kept_base_lets_deletions_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let save = dir.path().join("boards.json");
+        assert!(read_base(&save, None).is_none());
+
+        let written = start(&["gone there", "kept"]);
+        let saved = written.to_saved(Some("secret")).unwrap();
+        write_base(&save, &saved).unwrap();
+        let base_file = std::fs::read_to_string(base_path(&save)).unwrap();
+        assert!(!base_file.contains("gone there"));
+        assert!(read_base(&save, None).is_none());
+
+        let base = read_base(&save, Some("secret")).unwrap();
+        let mut ours = start(&["gone there", "kept"]);
+        let mut theirs = start(&["gone there", "kept"]);
+        theirs.board_mut().notes.retain(|n| n.text != "gone there");
+        let report = merge_states(Some(&base), &mut ours, &theirs);
+        assert_eq!(text(&ours), ["kept"]);
+        assert_eq!(report.removed, 1);
+    }
+}
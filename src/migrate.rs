@@ -0,0 +1,127 @@
+//! Upgrading save files written by older versions of plop
+//!
+//! Files are upgraded as JSON values before they are deserialized, so a file
+//! missing fields that are now required still loads instead of being dropped.
+
+use crate::{Board, DEFAULT_NOTE_COLOR, DEFAULT_NOTE_SIZE, NoteData};
+use serde_json::{Map, Value};
+
+/// Format version of the save files this build writes.
+///
+/// Version 0 has no `version` field; it covers single-board files as well as
+/// the first multi-board files.
+pub const SAVE_VERSION: u64 = 1;
+
+/// Upgrade a parsed save file to the current format
+pub fn migrate(mut value: Value) -> Result<Value, String> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SAVE_VERSION {
+        return Err(format!(
+            "save file has format version {version}, but this plop only reads up to {SAVE_VERSION}"
+        ));
+    }
+    let Value::Object(root) = &mut value else {
+        return Err("save file is not a JSON object".into());
+    };
+    if version < 1 {
+        from_v0(root);
+    }
+    fill_defaults(root);
+    root.insert("version".into(), SAVE_VERSION.into());
+    Ok(value)
+}
+
+/// Add the current version to a serialized state
+pub fn with_version(mut value: Value) -> Value {
+    if let Value::Object(root) = &mut value {
+        root.insert("version".into(), SAVE_VERSION.into());
+    }
+    value
+}
+
+/// Single-board files become a list with one board
+fn from_v0(root: &mut Map<String, Value>) {
+    if let Some(board) = root.remove("board") {
+        root.insert("boards".into(), Value::Array(vec![board]));
+    }
+}
+
+/// Fill fields the current format requires but the file lacks
+fn fill_defaults(root: &mut Map<String, Value>) {
+    let boards = root
+        .entry("boards")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let note_defaults = serde_json::to_value(NoteData {
+        size: DEFAULT_NOTE_SIZE,
+        color: DEFAULT_NOTE_COLOR,
+        ..Default::default()
+    })
+    .expect("notes serialize");
+    let mut max_note_id = 0;
+    for (index, board) in boards.as_array_mut().into_iter().flatten().enumerate() {
+        let board_defaults = serde_json::to_value(Board {
+            id: index as u64 + 1,
+            ..Default::default()
+        })
+        .expect("boards serialize");
+        fill_missing(board, &board_defaults);
+        for key in ["notes", "archived"] {
+            for note in board[key].as_array_mut().into_iter().flatten() {
+                fill_missing(note, &note_defaults);
+                max_note_id = max_note_id.max(note["id"].as_u64().unwrap_or(0));
+            }
+        }
+    }
+    root.entry("next_note_id")
+        .or_insert_with(|| (max_note_id + 1).into());
+}
+
+fn fill_missing(target: &mut Value, defaults: &Value) {
+    if let (Value::Object(target), Value::Object(defaults)) = (target, defaults) {
+        for (key, value) in defaults {
+            target.entry(key).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+    use serde_json::json;
+
+    #[test]
+    fn old_single_board_without_sizes_is_upgraded() {
+        let old = json!({
+            "board": {
+                "name": "Old",
+                "notes": [{"id": 4, "text": "hi", "pos": {"x": 1.0, "y": 2.0}}]
+            }
+        });
+        let value = migrate(old).unwrap();
+        assert_eq!(value["version"], SAVE_VERSION);
+        let state: AppState = serde_json::from_value(value).unwrap();
+        assert_eq!(state.board().name, "Old");
+        assert_eq!(state.board().notes[0].size, DEFAULT_NOTE_SIZE);
+        assert_eq!(state.board().notes[0].text, "hi");
+        assert_eq!(state.next_note_id, 5);
+    }
+
+    #[test]
+    fn current_files_roundtrip() {
+        let mut state = AppState::default();
+        state.add_board("Second");
+        let json = state.to_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], SAVE_VERSION);
+        let loaded: AppState = serde_json::from_value(migrate(value).unwrap()).unwrap();
+        assert_eq!(loaded, state);
+    }
+
+    #[test]
+    fn newer_files_are_refused() {
+        let newer = json!({"version": SAVE_VERSION + 1, "boards": []});
+        assert!(migrate(newer).unwrap_err().contains("version"));
+        assert!(migrate(json!([1, 2])).is_err());
+    }
+}
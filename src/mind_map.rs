@@ -0,0 +1,262 @@
+//! Importing OPML outlines and FreeMind (`.mm`) mind maps as notes laid out
+//! in rings around the root, joined to their parents by connectors
+
+use crate::NoteData;
+use crate::connectors::{Connector, ConnectorStyle};
+use egui::{Color32, Pos2, Vec2};
+
+/// File extensions of the mind maps that can be imported
+pub const MIND_MAP_EXTENSIONS: [&str; 3] = ["opml", "mm", "xml"];
+
+/// Space kept between notes on neighbouring rings
+const RING_GAP: f32 = 60.0;
+
+/// Space kept between neighbouring notes on the outer ring
+const LEAF_GAP: f32 = 20.0;
+
+/// A topic and the topics under it
+#[derive(Debug, Clone, PartialEq)]
+struct Topic {
+    text: String,
+    children: Vec<Topic>,
+}
+
+impl Topic {
+    /// Topics with no children under this one, itself if it has none
+    fn leaves(&self) -> usize {
+        self.children
+            .iter()
+            .map(Topic::leaves)
+            .sum::<usize>()
+            .max(1)
+    }
+
+    /// Rings below this topic
+    fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .map(|c| c.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Topics of the `<outline>` elements under `node`, with their text from
+/// the `text` attribute (or `title`, which some tools write instead)
+fn opml_topics(node: roxmltree::Node) -> Vec<Topic> {
+    node.children()
+        .filter(|n| n.has_tag_name("outline"))
+        .map(|n| Topic {
+            text: n
+                .attribute("text")
+                .or_else(|| n.attribute("title"))
+                .unwrap_or_default()
+                .trim()
+                .to_owned(),
+            children: opml_topics(n),
+        })
+        .collect()
+}
+
+/// Topics of the `<node>` elements under `node`, with their text from the
+/// `TEXT` attribute
+fn freemind_topics(node: roxmltree::Node) -> Vec<Topic> {
+    node.children()
+        .filter(|n| n.has_tag_name("node"))
+        .map(|n| Topic {
+            text: n.attribute("TEXT").unwrap_or_default().trim().to_owned(),
+            children: freemind_topics(n),
+        })
+        .collect()
+}
+
+/// The first child of `parent` tagged `tag`
+fn child<'a, 'input>(
+    parent: roxmltree::Node<'a, 'input>,
+    tag: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    parent.children().find(|n| n.has_tag_name(tag))
+}
+
+/// The root topic of an OPML or FreeMind document; several top-level topics
+/// hang under one named after the document
+fn parse_topics(xml: &str) -> Result<Topic, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    let (title, topics) = match root.tag_name().name() {
+        "opml" => {
+            let title = child(root, "head")
+                .and_then(|head| child(head, "title"))
+                .and_then(|title| title.text())
+                .map(|t| t.trim().to_owned());
+            let body = child(root, "body").ok_or("the outline has no body")?;
+            (title, opml_topics(body))
+        }
+        "map" => (None, freemind_topics(root)),
+        other => return Err(format!("not an OPML or FreeMind file (<{other}>)")),
+    };
+    match <[Topic; 1]>::try_from(topics) {
+        Ok([topic]) => Ok(topic),
+        Err(topics) if topics.is_empty() => Err("the mind map is empty".into()),
+        Err(children) => Ok(Topic {
+            text: title
+                .filter(|t| !t.is_empty())
+                .unwrap_or_else(|| "Mind map".into()),
+            children,
+        }),
+    }
+}
+
+/// Lays topics out around the board origin: each gets a slice of the circle as wide as its share of
+/// the leaves, its children splitting that slice one ring further out
+struct Layout<'a> {
+    size: Vec2,
+    color: Color32,
+    ring: f32,
+    next_note_id: &'a mut u64,
+    notes: Vec<NoteData>,
+    connectors: Vec<Connector>,
+}
+
+impl Layout<'_> {
+    /// Place `topic` at `depth` in the slice from angle `start` to `end`,
+    /// returning its note's id
+    fn place(&mut self, topic: &Topic, depth: usize, start: f32, end: f32) -> u64 {
+        let middle = (start + end) / 2.0;
+        let radius = depth as f32 * self.ring;
+        let center = Pos2::ZERO + radius * Vec2::angled(middle);
+        let id = *self.next_note_id;
+        *self.next_note_id += 1;
+        self.notes.push(NoteData {
+            id,
+            text: topic.text.clone(),
+            pos: center - self.size / 2.0,
+            size: self.size,
+            color: self.color,
+            ..Default::default()
+        });
+        let share = (end - start) / topic.leaves() as f32;
+        let mut from = start;
+        for child in &topic.children {
+            let to = from + share * child.leaves() as f32;
+            let child_id = self.place(child, depth + 1, from, to);
+            self.connectors.push(Connector {
+                from_note: id,
+                to_note: child_id,
+                style: ConnectorStyle::Line,
+                label: String::new(),
+            });
+            from = to;
+        }
+        id
+    }
+}
+
+/// Notes for the topics of an OPML or FreeMind document, the root in the
+/// middle and each level on a ring further out, the whole map starting at
+/// `origin` at the top left. Note ids come from `next_note_id`; connectors
+/// run from each topic to the ones under it.
+pub fn mind_map_to_notes(
+    xml: &str,
+    origin: Pos2,
+    size: Vec2,
+    color: Color32,
+    next_note_id: &mut u64,
+) -> Result<(Vec<NoteData>, Vec<Connector>), String> {
+    let root = parse_topics(xml)?;
+    let extent = size.max_elem();
+    // Far enough apart for the rings not to touch, and for the leaves to
+    // fit around the outer ring
+    let outer = root.leaves() as f32 * (extent + LEAF_GAP) / std::f32::consts::TAU;
+    let ring = (extent + RING_GAP).max(outer / root.depth().max(1) as f32);
+    let mut layout = Layout {
+        size,
+        color,
+        ring,
+        next_note_id,
+        notes: Vec::new(),
+        connectors: Vec::new(),
+    };
+    layout.place(&root, 0, 0.0, std::f32::consts::TAU);
+    let mut notes = layout.notes;
+    let top_left = notes.iter().map(|n| n.pos).fold(Pos2::ZERO, Pos2::min);
+    for note in &mut notes {
+        note.pos += origin - top_left;
+    }
+    Ok((notes, layout.connectors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outlines_become_rings_of_connected_notes() {
+        let opml = r#"<?xml version="1.0"?>
+            <opml version="2.0">
+              <head><title>Trip</title></head>
+              <body>
+                <outline text="Packing">
+                  <outline text="Tent"/>
+                  <outline text="Stove"/>
+                </outline>
+                <outline title="Route"/>
+              </body>
+            </opml>"#;
+        let mut next_note_id = 10;
+        let size = Vec2::new(100.0, 60.0);
+        let origin = Pos2::new(40.0, 20.0);
+        let (notes, connectors) =
+            mind_map_to_notes(opml, origin, size, Color32::YELLOW, &mut next_note_id).unwrap();
+        let texts: Vec<&str> = notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(texts, ["Trip", "Packing", "Tent", "Stove", "Route"]);
+        assert_eq!(next_note_id, 15);
+        // The title in the middle, the rest further out the deeper they are
+        let top_left = notes.iter().map(|n| n.pos).reduce(Pos2::min).unwrap();
+        assert_eq!(top_left, origin);
+        let distance = |i: usize| (notes[i].pos - notes[0].pos).length();
+        assert!(distance(1) > 0.0 && distance(2) > distance(1));
+        assert!((distance(1) - distance(4)).abs() < 0.01);
+        let links: Vec<(u64, u64)> = connectors
+            .iter()
+            .map(|c| (c.from_note, c.to_note))
+            .collect();
+        assert_eq!(links, [(10, 11), (11, 12), (11, 13), (10, 14)]);
+    }
+
+    #[test]
+    fn freemind_maps_keep_their_root() {
+        let mm = r#"<map version="1.0.1">
+              <node TEXT="Project">
+                <node TEXT="Design"/>
+                <node TEXT="Build"><node TEXT="Test"/></node>
+              </node>
+            </map>"#;
+        let mut next_note_id = 1;
+        let (notes, connectors) = mind_map_to_notes(
+            mm,
+            Pos2::new(500.0, 500.0),
+            Vec2::splat(80.0),
+            Color32::YELLOW,
+            &mut next_note_id,
+        )
+        .unwrap();
+        assert_eq!(notes[0].text, "Project");
+        assert_eq!(notes.len(), 4);
+        assert_eq!(connectors.len(), 3);
+
+        let mut next_note_id = 1;
+        let mut import = |xml: &str| {
+            mind_map_to_notes(
+                xml,
+                Pos2::ZERO,
+                Vec2::splat(80.0),
+                Color32::YELLOW,
+                &mut next_note_id,
+            )
+        };
+        assert!(import("<html/>").is_err());
+        assert!(import("<map></map>").is_err());
+        assert!(import("<opml>").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Fonts of note text: a family, an optional fixed size and boldness per
+//! note, falling back to app-wide defaults
+
+use egui::{FontFamily, FontId};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Font sizes that can be picked, for notes and the defaults
+pub const FONT_SIZE_RANGE: RangeInclusive<f32> = 6.0..=72.0;
+
+/// Family note text can be set in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontChoice {
+    #[default]
+    Proportional,
+    Monospace,
+}
+
+impl FontChoice {
+    pub const ALL: [Self; 2] = [Self::Proportional, Self::Monospace];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Proportional => "Proportional",
+            Self::Monospace => "Monospace",
+        }
+    }
+
+    pub fn family(self) -> FontFamily {
+        match self {
+            Self::Proportional => FontFamily::Proportional,
+            Self::Monospace => FontFamily::Monospace,
+        }
+    }
+
+    /// Generic CSS family, for SVG export
+    pub fn css_family(self) -> &'static str {
+        match self {
+            Self::Proportional => "sans-serif",
+            Self::Monospace => "monospace",
+        }
+    }
+}
+
+/// A note's own font choices; what is left unset follows the defaults
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct NoteFont {
+    #[serde(default)]
+    pub family: Option<FontChoice>,
+    /// Fixed text size instead of the largest that fits
+    #[serde(default)]
+    pub size: Option<f32>,
+    #[serde(default)]
+    pub bold: bool,
+}
+
+/// App-wide font of notes without choices of their own
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct FontDefaults {
+    #[serde(default)]
+    pub family: FontChoice,
+    /// Smallest size text is shrunk to so it fits its note
+    #[serde(default = "default_min_size")]
+    pub min_size: f32,
+    /// Size of text with plenty of room
+    #[serde(default = "default_max_size")]
+    pub max_size: f32,
+    /// Size no note text is drawn below, even a note's own fixed size, so
+    /// it stays readable
+    #[serde(default)]
+    pub min_readable: Option<f32>,
+}
+
+fn default_min_size() -> f32 {
+    6.0
+}
+
+fn default_max_size() -> f32 {
+    16.0
+}
+
+impl Default for FontDefaults {
+    fn default() -> Self {
+        Self {
+            family: FontChoice::default(),
+            min_size: default_min_size(),
+            max_size: default_max_size(),
+            min_readable: None,
+        }
+    }
+}
+
+/// The font a note's text is drawn in, all choices made
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextFont {
+    pub family: FontChoice,
+    pub bold: bool,
+    /// Sizes the text is fitted between; the same for a fixed size
+    pub min_size: f32,
+    pub max_size: f32,
+}
+
+impl TextFont {
+    /// Body text of `size` in this font's family
+    pub fn id(&self, size: f32) -> FontId {
+        FontId::new(size, self.family.family())
+    }
+}
+
+impl Default for TextFont {
+    fn default() -> Self {
+        NoteFont::default().resolve(&FontDefaults::default())
+    }
+}
+
+impl NoteFont {
+    /// The note's font with `defaults` filled in
+    pub fn resolve(&self, defaults: &FontDefaults) -> TextFont {
+        let clamp = |size: f32| size.clamp(*FONT_SIZE_RANGE.start(), *FONT_SIZE_RANGE.end());
+        let (min_size, max_size) = match self.size {
+            Some(size) => (clamp(size), clamp(size)),
+            None => {
+                let min = clamp(defaults.min_size);
+                (min, clamp(defaults.max_size).max(min))
+            }
+        };
+        let floor = defaults.min_readable.unwrap_or(0.0);
+        TextFont {
+            family: self.family.unwrap_or(defaults.family),
+            bold: self.bold,
+            min_size: min_size.max(floor),
+            max_size: max_size.max(floor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_choices_override_the_defaults() {
+        let defaults = FontDefaults {
+            family: FontChoice::Monospace,
+            min_size: 10.0,
+            max_size: 4.0,
+            min_readable: None,
+        };
+        let font = NoteFont::default().resolve(&defaults);
+        assert_eq!(font.family, FontChoice::Monospace);
+        // A maximum below the minimum is raised to it
+        assert_eq!((font.min_size, font.max_size), (10.0, 10.0));
+
+        let own = NoteFont {
+            family: Some(FontChoice::Proportional),
+            size: Some(200.0),
+            bold: true,
+        };
+        let font = own.resolve(&defaults);
+        assert_eq!(font.family, FontChoice::Proportional);
+        assert!(font.bold);
+        assert_eq!((font.min_size, font.max_size), (72.0, 72.0));
+
+        // No text drops below the readable size, not even a fixed one
+        let readable = FontDefaults {
+            min_readable: Some(14.0),
+            ..defaults
+        };
+        let small = NoteFont {
+            size: Some(8.0),
+            ..Default::default()
+        };
+        let font = small.resolve(&readable);
+        assert_eq!((font.min_size, font.max_size), (14.0, 14.0));
+    }
+}
@@ -0,0 +1,88 @@
+//! The board's notes as a list, newest, alphabetically or most pressing
+//! first, for finding a note without panning around
+
+use crate::{Board, NoteData};
+use std::cmp::Reverse;
+
+/// Order the note list is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteOrder {
+    /// Last changed first
+    #[default]
+    Modified,
+    /// Last made first
+    Created,
+    /// By text, ignoring case and Markdown
+    Alphabetical,
+    /// Most pressing first, then last changed first
+    Priority,
+}
+
+impl NoteOrder {
+    pub const ALL: [Self; 4] = [
+        Self::Modified,
+        Self::Created,
+        Self::Alphabetical,
+        Self::Priority,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Modified => "Recently changed",
+            Self::Created => "Recently made",
+            Self::Alphabetical => "A–Z",
+            Self::Priority => "Priority",
+        }
+    }
+}
+
+/// Key the alphabetical order goes by
+fn sort_text(note: &NoteData) -> String {
+    crate::markdown::plain_text(&note.text)
+        .trim_start()
+        .to_lowercase()
+}
+
+impl Board {
+    /// All notes in `order`; notes without a time come last, newest ids
+    /// first
+    pub fn notes_in_order(&self, order: NoteOrder) -> Vec<&NoteData> {
+        let mut notes: Vec<&NoteData> = self.notes.iter().collect();
+        match order {
+            NoteOrder::Modified => notes.sort_by_key(|n| Reverse((n.modified_at, n.id))),
+            NoteOrder::Created => notes.sort_by_key(|n| Reverse((n.created_at, n.id))),
+            NoteOrder::Alphabetical => notes.sort_by_cached_key(|n| (sort_text(n), n.id)),
+            NoteOrder::Priority => {
+                notes.sort_by_key(|n| Reverse((n.priority, n.modified_at, n.id)))
+            }
+        }
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+
+    #[test]
+    fn notes_list_newest_or_alphabetically_first() {
+        let mut state = AppState::default();
+        let old = state.add_note("**banana**", Pos2::ZERO);
+        let new = state.add_note("Apple", Pos2::ZERO);
+        let undated = state.add_note("cherry", Pos2::ZERO);
+        let board = state.board_mut();
+        board.notes[0].created_at = Some(10);
+        board.notes[0].modified_at = Some(30);
+        board.notes[1].created_at = Some(20);
+        board.notes[1].modified_at = Some(20);
+        board.notes[2].priority = crate::priority::Priority::High;
+        let ids =
+            |order| -> Vec<u64> { board.notes_in_order(order).iter().map(|n| n.id).collect() };
+        assert_eq!(ids(NoteOrder::Modified), [old, new, undated]);
+        assert_eq!(ids(NoteOrder::Created), [new, old, undated]);
+        assert_eq!(ids(NoteOrder::Alphabetical), [new, old, undated]);
+        assert_eq!(ids(NoteOrder::Priority), [undated, old, new]);
+    }
+}
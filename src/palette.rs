@@ -0,0 +1,94 @@
+//! Named sticky-note colors a board offers for quick picking
+
+use crate::Board;
+use crate::theme::Theme;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// A palette entry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NamedColor {
+    pub name: String,
+    pub color: Color32,
+}
+
+impl NamedColor {
+    pub fn new(name: &str, color: Color32) -> Self {
+        Self {
+            name: name.into(),
+            color,
+        }
+    }
+}
+
+/// Classic sticky-note colors, used to name colors that have no name
+pub fn sticky_colors() -> Vec<NamedColor> {
+    vec![
+        NamedColor::new("Yellow", Color32::YELLOW),
+        NamedColor::new("Pink", Color32::from_rgb(255, 170, 200)),
+        NamedColor::new("Green", Color32::from_rgb(170, 230, 170)),
+        NamedColor::new("Blue", Color32::from_rgb(170, 200, 255)),
+        NamedColor::new("Orange", Color32::from_rgb(255, 200, 130)),
+        NamedColor::new("Purple", Color32::from_rgb(210, 180, 240)),
+    ]
+}
+
+/// Name for `color`: its sticky-note name if it has one, else its hex code
+pub fn color_name(color: Color32) -> String {
+    match sticky_colors().into_iter().find(|c| c.color == color) {
+        Some(named) => named.name,
+        None => {
+            let [r, g, b, _] = color.to_array();
+            format!("#{r:02x}{g:02x}{b:02x}")
+        }
+    }
+}
+
+impl Board {
+    /// Colors offered for notes: the board's own palette, or the theme's
+    /// while the board has none
+    pub fn note_palette(&self, theme: &Theme) -> Vec<NamedColor> {
+        if !self.palette.is_empty() {
+            return self.palette.clone();
+        }
+        theme
+            .palette
+            .iter()
+            .map(|&color| NamedColor {
+                name: color_name(color),
+                color,
+            })
+            .collect()
+    }
+
+    /// Color given to new notes on this board
+    pub fn new_note_color(&self, theme: &Theme) -> Color32 {
+        self.default_note_color.unwrap_or(theme.note_color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boards_fall_back_to_the_theme() {
+        let theme = Theme::default();
+        let mut board = Board::default();
+        let palette = board.note_palette(&theme);
+        assert_eq!(palette.len(), theme.palette.len());
+        assert_eq!(palette[0], NamedColor::new("Yellow", Color32::YELLOW));
+        assert_eq!(board.new_note_color(&theme), theme.note_color);
+
+        board.palette = vec![NamedColor::new("Urgent", Color32::RED)];
+        board.default_note_color = Some(Color32::RED);
+        assert_eq!(board.note_palette(&theme), board.palette);
+        assert_eq!(board.new_note_color(&theme), Color32::RED);
+    }
+
+    #[test]
+    fn unnamed_colors_are_named_by_hex() {
+        assert_eq!(color_name(Color32::from_rgb(170, 230, 170)), "Green");
+        assert_eq!(color_name(Color32::from_rgb(1, 2, 255)), "#0102ff");
+    }
+}
@@ -0,0 +1,190 @@
+//! Piles: notes dropped onto each other, stacked at one place so only the
+//! top note shows, and fanned out in a row for browsing
+
+use crate::Board;
+use egui::Pos2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Space between the notes of a fanned-out pile
+pub const PILE_FAN_GAP: f32 = 16.0;
+
+/// Notes stacked at one place, bottom first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Pile {
+    pub notes: Vec<u64>,
+    /// Top-left corner every note of the gathered pile sits at
+    pub pos: Pos2,
+    /// Whether the notes are spread out in a row instead
+    #[serde(default)]
+    pub fanned: bool,
+}
+
+impl Pile {
+    /// The note shown on top of the gathered pile
+    pub fn top(&self) -> Option<u64> {
+        self.notes.last().copied()
+    }
+}
+
+impl Board {
+    /// Index of the pile holding the note `id`
+    pub fn pile_of(&self, id: u64) -> Option<usize> {
+        self.piles.iter().position(|p| p.notes.contains(&id))
+    }
+
+    /// Whether the note `id` is hidden under the top of a gathered pile
+    pub fn is_buried(&self, id: u64) -> bool {
+        self.pile_of(id)
+            .is_some_and(|i| !self.piles[i].fanned && self.piles[i].top() != Some(id))
+    }
+
+    /// Every note hidden under the top of a gathered pile, to look up many
+    /// notes at once
+    pub fn buried_notes(&self) -> HashSet<u64> {
+        self.piles
+            .iter()
+            .filter(|p| !p.fanned)
+            .flat_map(|p| p.notes.iter().rev().skip(1).copied())
+            .collect()
+    }
+
+    /// Put the note `id` on the pile of `target`, starting one if there is
+    /// none, and return where the note now sits
+    pub fn pile_up(&mut self, id: u64, target: u64) -> Option<Pos2> {
+        if id == target {
+            return None;
+        }
+        self.leave_pile(id);
+        let index = match self.pile_of(target) {
+            Some(index) => index,
+            None => {
+                let pos = self.notes.iter().find(|n| n.id == target)?.pos;
+                self.piles.push(Pile {
+                    notes: vec![target],
+                    pos,
+                    fanned: false,
+                });
+                self.piles.len() - 1
+            }
+        };
+        self.piles[index].notes.push(id);
+        let pos = if self.piles[index].fanned {
+            self.fan_positions(index).last()?.1
+        } else {
+            self.piles[index].pos
+        };
+        if let Some(note) = self.notes.iter_mut().find(|n| n.id == id) {
+            note.pos = pos;
+        }
+        Some(pos)
+    }
+
+    /// Take the note `id` off its pile; a pile of one note is no pile
+    pub fn leave_pile(&mut self, id: u64) {
+        for pile in &mut self.piles {
+            pile.notes.retain(|&n| n != id);
+        }
+        self.piles.retain(|p| p.notes.len() > 1);
+    }
+
+    /// Where the notes of the pile at `index` sit when fanned out: in a row
+    /// to the right of the pile's place, bottom note first
+    pub fn fan_positions(&self, index: usize) -> Vec<(u64, Pos2)> {
+        let Some(pile) = self.piles.get(index) else {
+            return Vec::new();
+        };
+        let mut x = pile.pos.x;
+        let mut positions = Vec::new();
+        for &id in &pile.notes {
+            if let Some(note) = self.notes.iter().find(|n| n.id == id) {
+                positions.push((id, Pos2::new(x, pile.pos.y)));
+                x += note.size.x + PILE_FAN_GAP;
+            }
+        }
+        positions
+    }
+
+    /// Fan the pile at `index` out or gather it back, returning the new
+    /// places of its notes
+    pub fn toggle_fan(&mut self, index: usize) -> Vec<(u64, Pos2)> {
+        let Some(pile) = self.piles.get_mut(index) else {
+            return Vec::new();
+        };
+        pile.fanned = !pile.fanned;
+        let positions = if pile.fanned {
+            self.fan_positions(index)
+        } else {
+            let pos = pile.pos;
+            pile.notes.iter().map(|&id| (id, pos)).collect()
+        };
+        for note in &mut self.notes {
+            if let Some((_, pos)) = positions.iter().find(|(id, _)| *id == note.id) {
+                note.pos = *pos;
+            }
+        }
+        positions
+    }
+
+    /// Drop notes that left the board, or were moved away from their
+    /// gathered pile, from piles; piles left with one note are dissolved
+    pub fn prune_piles(&mut self) {
+        let notes = &self.notes;
+        for pile in &mut self.piles {
+            pile.notes.retain(|id| {
+                notes
+                    .iter()
+                    .any(|n| n.id == *id && (pile.fanned || n.pos == pile.pos))
+            });
+        }
+        self.piles.retain(|p| p.notes.len() > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+    use std::collections::HashSet;
+
+    #[test]
+    fn notes_dropped_on_each_other_pile_up() {
+        let mut state = AppState::default();
+        let a = state.add_note("a", Pos2::new(0.0, 0.0));
+        let b = state.add_note("b", Pos2::new(300.0, 0.0));
+        let c = state.add_note("c", Pos2::new(600.0, 0.0));
+        let board = state.board_mut();
+        assert_eq!(board.pile_up(b, a), Some(Pos2::ZERO));
+        assert_eq!(board.pile_up(c, b), Some(Pos2::ZERO));
+        assert_eq!(board.piles.len(), 1);
+        assert_eq!(board.piles[0].notes, [a, b, c]);
+        assert!(board.is_buried(a) && board.is_buried(b) && !board.is_buried(c));
+        assert_eq!(board.buried_notes(), HashSet::from([a, b]));
+
+        // Taking the top note off leaves the rest piled
+        board.notes.iter_mut().find(|n| n.id == c).unwrap().pos = Pos2::new(50.0, 0.0);
+        board.prune_piles();
+        assert_eq!(board.piles[0].notes, [a, b]);
+        board.leave_pile(b);
+        assert!(board.piles.is_empty());
+    }
+
+    #[test]
+    fn fanning_spreads_a_pile_in_a_row() {
+        let mut state = AppState::default();
+        let a = state.add_note("a", Pos2::new(10.0, 20.0));
+        let b = state.add_note("b", Pos2::new(300.0, 0.0));
+        let board = state.board_mut();
+        board.pile_up(b, a);
+        let width = board.notes[0].size.x;
+        let fanned = board.toggle_fan(0);
+        let second = Pos2::new(10.0 + width + super::PILE_FAN_GAP, 20.0);
+        assert_eq!(fanned, [(a, Pos2::new(10.0, 20.0)), (b, second)]);
+        assert!(!board.is_buried(a));
+        // Fanned notes stay piled where they were spread to
+        board.prune_piles();
+        assert_eq!(board.piles.len(), 1);
+        board.toggle_fan(0);
+        assert_eq!(board.notes[1].pos, Pos2::new(10.0, 20.0));
+    }
+}
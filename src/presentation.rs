@@ -0,0 +1,111 @@
+//! Presenting a board by flying the view from note to note
+
+use crate::Board;
+use egui::Rect;
+
+/// Room left around a presented note, as a share of its size on each side
+pub const SLIDE_MARGIN: f32 = 0.25;
+
+/// How long the view takes to fly to the next note, in seconds
+pub const FLIGHT_SECONDS: f64 = 0.6;
+
+/// How long the view takes to glide to a note picked elsewhere, e.g. a
+/// search match, in seconds
+pub const FOCUS_SECONDS: f64 = 0.3;
+
+impl Board {
+    /// Ids of the notes in the order they are presented: the board's own
+    /// sequence if it has one, else reading order, row by row
+    pub fn presentation_order(&self) -> Vec<u64> {
+        if !self.presentation.is_empty() {
+            return self
+                .presentation
+                .iter()
+                .copied()
+                .filter(|id| self.notes.iter().any(|n| n.id == *id))
+                .collect();
+        }
+        let mut notes: Vec<(Rect, u64)> = self
+            .notes
+            .iter()
+            .map(|n| (Rect::from_min_size(n.pos, n.size), n.id))
+            .collect();
+        notes.sort_by(|a, b| a.0.center().y.total_cmp(&b.0.center().y));
+        // A note starts a new row once its center is below the first note
+        // of the current row
+        let mut rows: Vec<Vec<(Rect, u64)>> = Vec::new();
+        for note in notes {
+            match rows.last_mut() {
+                Some(row) if note.0.center().y <= row[0].0.max.y => row.push(note),
+                _ => rows.push(vec![note]),
+            }
+        }
+        rows.into_iter()
+            .flat_map(|mut row| {
+                row.sort_by(|a, b| a.0.center().x.total_cmp(&b.0.center().x));
+                row.into_iter().map(|(_, id)| id)
+            })
+            .collect()
+    }
+}
+
+/// Part of the board shown while presenting a note occupying `note`
+pub fn slide_rect(note: Rect) -> Rect {
+    note.expand2(note.size() * SLIDE_MARGIN)
+}
+
+/// View `t` of the way (0 to 1) from `from` to `to`, easing in and out
+pub fn fly(from: Rect, to: Rect, t: f32) -> Rect {
+    let t = t.clamp(0.0, 1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+    Rect::from_min_max(from.min.lerp(to.min, eased), from.max.lerp(to.max, eased))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn notes_are_presented_in_reading_order_or_the_boards_own() {
+        let note = |id: u64, x: f32, y: f32| NoteData {
+            id,
+            pos: Pos2::new(x, y),
+            size: Vec2::new(100.0, 100.0),
+            ..Default::default()
+        };
+        let mut board = Board {
+            notes: vec![
+                note(1, 300.0, 10.0),
+                note(2, 0.0, 200.0),
+                note(3, 0.0, 0.0),
+                note(4, 150.0, 40.0),
+            ],
+            ..Default::default()
+        };
+        // 4 sits a little lower than 3 but still on the first row
+        assert_eq!(board.presentation_order(), [3, 4, 1, 2]);
+        board.presentation = vec![2, 9, 1];
+        assert_eq!(board.presentation_order(), [2, 1]);
+    }
+
+    #[test]
+    fn flights_ease_between_views() {
+        let from = Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0));
+        let to = slide_rect(Rect::from_min_size(
+            Pos2::new(200.0, 0.0),
+            Vec2::splat(100.0),
+        ));
+        assert_eq!(
+            to,
+            Rect::from_min_max(Pos2::new(175.0, -25.0), Pos2::new(325.0, 125.0))
+        );
+        assert_eq!(fly(from, to, 0.0), from);
+        assert_eq!(fly(from, to, 2.0), to);
+        let middle = fly(from, to, 0.5);
+        assert_eq!(middle.min, Pos2::new(87.5, -12.5));
+        // Easing starts slower than moving at a constant speed
+        assert!(fly(from, to, 0.1).min.x < 17.5);
+    }
+}
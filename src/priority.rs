@@ -0,0 +1,100 @@
+//! How pressing a note is, shown by the weight of its border and a badge in
+//! its corner
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// Priority of a note; most notes are `Normal` and look as they always did
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub const ALL: [Self; 4] = [Self::Low, Self::Normal, Self::High, Self::Urgent];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::Normal => "Normal",
+            Self::High => "High",
+            Self::Urgent => "Urgent",
+        }
+    }
+
+    /// The priority called `name`, ignoring case, as in `prio:high`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+
+    /// One level up, staying at `Urgent`
+    pub fn raised(self) -> Self {
+        Self::ALL[(self as usize + 1).min(Self::ALL.len() - 1)]
+    }
+
+    /// One level down, staying at `Low`
+    pub fn lowered(self) -> Self {
+        Self::ALL[(self as usize).saturating_sub(1)]
+    }
+
+    /// Width of the border drawn around the note, none for `Normal`
+    pub fn border_width(self) -> f32 {
+        match self {
+            Self::Low => 1.0,
+            Self::Normal => 0.0,
+            Self::High => 2.5,
+            Self::Urgent => 4.0,
+        }
+    }
+
+    /// Color of the border and the badge
+    pub fn color(self) -> Color32 {
+        match self {
+            Self::Low => Color32::from_gray(140),
+            Self::Normal => Color32::TRANSPARENT,
+            Self::High => Color32::from_rgb(230, 130, 0),
+            Self::Urgent => Color32::from_rgb(210, 30, 30),
+        }
+    }
+
+    /// Badge in the note's corner, none for `Normal`
+    pub fn badge(self) -> Option<&'static str> {
+        match self {
+            Self::Low => Some("↓"),
+            Self::Normal => None,
+            Self::High => Some("!"),
+            Self::Urgent => Some("‼"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+
+    #[test]
+    fn priorities_step_and_parse() {
+        assert_eq!(Priority::Normal.raised(), Priority::High);
+        assert_eq!(Priority::Urgent.raised(), Priority::Urgent);
+        assert_eq!(Priority::Normal.lowered(), Priority::Low);
+        assert_eq!(Priority::Low.lowered(), Priority::Low);
+        assert_eq!(Priority::from_name("URGENT"), Some(Priority::Urgent));
+        assert_eq!(Priority::from_name("soon"), None);
+        assert!(Priority::Urgent > Priority::High);
+
+        // Notes saved before priorities are normal ones
+        let mut json = serde_json::to_value(NoteData::default()).unwrap();
+        json.as_object_mut().unwrap().remove("priority");
+        let old: NoteData = serde_json::from_value(json).unwrap();
+        assert_eq!(old.priority, Priority::Normal);
+    }
+}
@@ -0,0 +1,48 @@
+//! Save files opened or saved recently, most recent first
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Most files remembered
+pub const MAX_RECENT: usize = 8;
+
+/// Recently used save files, most recent first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RecentFiles {
+    pub paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Put `path` at the top of the list
+    pub fn add(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+        self.paths.insert(0, path.to_owned());
+        self.paths.truncate(MAX_RECENT);
+    }
+
+    /// Forget `path`, e.g. after it could not be opened
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_recent_comes_first_without_duplicates() {
+        let mut recent = RecentFiles::default();
+        for i in 0..MAX_RECENT + 2 {
+            recent.add(Path::new(&format!("{i}.json")));
+        }
+        assert_eq!(recent.paths.len(), MAX_RECENT);
+        assert_eq!(recent.paths[0], Path::new("9.json"));
+
+        recent.add(Path::new("5.json"));
+        assert_eq!(recent.paths[0], Path::new("5.json"));
+        assert_eq!(recent.paths.len(), MAX_RECENT);
+        recent.remove(Path::new("5.json"));
+        assert!(!recent.paths.iter().any(|p| p == Path::new("5.json")));
+    }
+}
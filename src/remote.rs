@@ -0,0 +1,377 @@
+//! Keeping a copy of the save file on a WebDAV server or in S3-compatible
+//! storage, so the boards follow you from one computer to the next
+//!
+//! Every push names the ETag of the copy last pulled or pushed, so a copy
+//! changed elsewhere in the meantime is reported as a conflict instead of
+//! being written over.
+//!
+//! The web build has no HTTP client, so there syncing fails as unsupported.
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+use crate::expiry::{DAY, format_date, now_secs};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Seconds between checks for changes on either side
+pub const SYNC_INTERVAL: f64 = 60.0;
+
+/// Where the synced copy is kept
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RemoteKind {
+    #[default]
+    WebDav,
+    S3,
+}
+
+impl RemoteKind {
+    pub const ALL: [Self; 2] = [Self::WebDav, Self::S3];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::WebDav => "WebDAV",
+            Self::S3 => "S3",
+        }
+    }
+}
+
+/// The synced copy and how to reach it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RemoteConfig {
+    pub kind: RemoteKind,
+    /// Address of the file: on the WebDAV server, or
+    /// `https://endpoint/bucket/key` for S3
+    pub url: String,
+    /// S3 region, e.g. `us-east-1`
+    #[serde(default)]
+    pub region: String,
+    /// WebDAV user name or S3 access key id
+    #[serde(default)]
+    pub user: String,
+    /// WebDAV password or S3 secret key
+    #[serde(default)]
+    pub secret: String,
+    /// ETag of the copy as last pulled or pushed, `None` before the first
+    /// sync
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+/// What pulling found
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pulled {
+    /// The copy is the one last pulled or pushed
+    Unchanged,
+    /// The copy changed since, or was never pulled
+    Changed { body: Vec<u8>, etag: Option<String> },
+    /// There is no copy yet
+    Missing,
+}
+
+/// What pushing did
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pushed {
+    Written {
+        etag: Option<String>,
+    },
+    /// The copy changed since it was last pulled or pushed, and was left
+    /// alone
+    Conflict,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl RemoteConfig {
+    pub fn pull(&self) -> io::Result<Pulled> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn push(&self, _body: &[u8]) -> io::Result<Pushed> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RemoteConfig {
+    /// Fetch the copy unless it is still the one with [`RemoteConfig::etag`]
+    pub fn pull(&self) -> io::Result<Pulled> {
+        let mut request = self.request("GET", &[])?;
+        if let Some(etag) = &self.etag {
+            request = request.set("If-None-Match", etag);
+        }
+        match request.call() {
+            Ok(response) => {
+                let etag = response.header("ETag").map(str::to_owned);
+                let mut body = Vec::new();
+                response.into_reader().read_to_end(&mut body)?;
+                Ok(Pulled::Changed { body, etag })
+            }
+            Err(ureq::Error::Status(304, _)) => Ok(Pulled::Unchanged),
+            Err(ureq::Error::Status(404, _)) => Ok(Pulled::Missing),
+            Err(e) => Err(http_error(e)),
+        }
+    }
+
+    /// Write `body` over the copy if it is still the one with
+    /// [`RemoteConfig::etag`], or if there is none yet before the first sync
+    pub fn push(&self, body: &[u8]) -> io::Result<Pushed> {
+        let request = self.request("PUT", body)?;
+        let request = match &self.etag {
+            Some(etag) => request.set("If-Match", etag),
+            None => request.set("If-None-Match", "*"),
+        };
+        match request.send_bytes(body) {
+            Ok(response) => {
+                let etag = match response.header("ETag") {
+                    Some(etag) => Some(etag.to_owned()),
+                    // Some WebDAV servers only tell when asked
+                    None => self.head_etag()?,
+                };
+                Ok(Pushed::Written { etag })
+            }
+            Err(ureq::Error::Status(409 | 412, _)) => Ok(Pushed::Conflict),
+            Err(e) => Err(http_error(e)),
+        }
+    }
+
+    fn head_etag(&self) -> io::Result<Option<String>> {
+        let response = self.request("HEAD", &[])?.call().map_err(http_error)?;
+        Ok(response.header("ETag").map(str::to_owned))
+    }
+
+    /// A request for the copy, signed for S3 or with the WebDAV login
+    fn request(&self, method: &str, body: &[u8]) -> io::Result<ureq::Request> {
+        let (origin, host, path) = split_url(&self.url).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the sync address is not a URL")
+        })?;
+        let path = encode_path(path);
+        let request = ureq::request(method, &format!("{origin}{path}"));
+        Ok(match self.kind {
+            RemoteKind::WebDav if self.user.is_empty() => request,
+            RemoteKind::WebDav => {
+                request.set("Authorization", &basic_auth(&self.user, &self.secret))
+            }
+            RemoteKind::S3 => {
+                let headers = self.s3_headers(method, host, &path, body, now_secs());
+                headers
+                    .iter()
+                    .fold(request, |request, (name, value)| request.set(name, value))
+            }
+        })
+    }
+}
+
+impl RemoteConfig {
+    /// Headers signing an S3 request with AWS Signature Version 4, made at
+    /// `secs` since the Unix epoch
+    fn s3_headers(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        body: &[u8],
+        secs: u64,
+    ) -> Vec<(&'static str, String)> {
+        let date = format_date(secs).replace('-', "");
+        let time = secs % DAY;
+        let stamp = format!(
+            "{date}T{:02}{:02}{:02}Z",
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        );
+        let payload = hex(&Sha256::digest(body));
+        let signed = "host;x-amz-content-sha256;x-amz-date";
+        let canonical = format!(
+            "{method}\n{path}\n\nhost:{host}\nx-amz-content-sha256:{payload}\nx-amz-date:{stamp}\n\n{signed}\n{payload}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{stamp}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let key = [self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            hmac(format!("AWS4{}", self.secret).as_bytes(), date.as_bytes()),
+            |key, part| hmac(&key, part.as_bytes()),
+        );
+        let signature = hex(&hmac(&key, to_sign.as_bytes()));
+        vec![
+            ("x-amz-date", stamp),
+            ("x-amz-content-sha256", payload),
+            (
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed}, Signature={signature}",
+                    self.user
+                ),
+            ),
+        ]
+    }
+}
+
+/// What a [`RemoteJob`] came back with
+pub enum RemoteReply {
+    Pulled(io::Result<Pulled>),
+    Pushed(io::Result<Pushed>),
+}
+
+/// A pull or push running in the background, so a slow server doesn't hold
+/// up the app
+pub struct RemoteJob(Mutex<Receiver<RemoteReply>>);
+
+impl RemoteJob {
+    pub fn pull(config: RemoteConfig) -> Self {
+        Self::spawn(move || RemoteReply::Pulled(config.pull()))
+    }
+
+    pub fn push(config: RemoteConfig, body: Vec<u8>) -> Self {
+        Self::spawn(move || RemoteReply::Pushed(config.push(&body)))
+    }
+
+    fn spawn(work: impl FnOnce() -> RemoteReply + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(work());
+        });
+        Self(Mutex::new(receiver))
+    }
+
+    /// The reply, once it came
+    pub fn poll(&self) -> Option<RemoteReply> {
+        self.0.lock().ok()?.try_recv().ok()
+    }
+}
+
+/// `url` split into its scheme and host, the host alone and the path
+fn split_url(url: &str) -> Option<(&str, &str, &str)> {
+    let url = url.trim();
+    let (scheme, rest) = url.split_once("://")?;
+    if !matches!(scheme, "http" | "https") {
+        return None;
+    }
+    let host_end = rest.find('/').unwrap_or(rest.len());
+    let (host, path) = rest.split_at(host_end);
+    if host.is_empty() {
+        return None;
+    }
+    let origin = &url[..scheme.len() + 3 + host.len()];
+    Some((origin, host, if path.is_empty() { "/" } else { path }))
+}
+
+/// `path` with everything but unreserved characters and slashes
+/// percent-encoded, as S3 signs it
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn basic_auth(user: &str, password: &str) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let input = format!("{user}:{password}");
+    let mut encoded = String::new();
+    for chunk in input.as_bytes().chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            encoded.push(if i <= chunk.len() {
+                ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char
+            } else {
+                '='
+            });
+        }
+    }
+    format!("Basic {encoded}")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn http_error(error: ureq::Error) -> io::Error {
+    match error {
+        ureq::Error::Status(401 | 403, _) => io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the server refused the login",
+        ),
+        ureq::Error::Status(code, response) => io::Error::other(format!(
+            "the server answered {code} {}",
+            response.status_text()
+        )),
+        ureq::Error::Transport(transport) => io::Error::other(transport.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_split_into_host_and_path() {
+        assert_eq!(
+            split_url("https://dav.example.com/plop/my board.json"),
+            Some((
+                "https://dav.example.com",
+                "dav.example.com",
+                "/plop/my board.json"
+            ))
+        );
+        assert_eq!(
+            split_url("http://localhost:9000"),
+            Some(("http://localhost:9000", "localhost:9000", "/"))
+        );
+        assert_eq!(split_url("ftp://example.com/x"), None);
+        assert_eq!(split_url("example.com/x"), None);
+        assert_eq!(encode_path("/plop/my board.json"), "/plop/my%20board.json");
+        assert_eq!(basic_auth("ann", "secret"), "Basic YW5uOnNlY3JldA==");
+        assert_eq!(basic_auth("a", "b"), "Basic YTpi");
+    }
+
+    #[test]
+    fn s3_requests_are_signed() {
+        let config = RemoteConfig {
+            kind: RemoteKind::S3,
+            url: "https://s3.eu-west-1.amazonaws.com/boards/plop.json".into(),
+            region: "eu-west-1".into(),
+            user: "AKIDEXAMPLE".into(),
+            secret: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            etag: None,
+        };
+        // 2024-03-05 06:07:08 UTC
+        let headers = config.s3_headers(
+            "PUT",
+            "s3.eu-west-1.amazonaws.com",
+            "/boards/plop.json",
+            b"{}",
+            1_709_618_828,
+        );
+        assert_eq!(headers[0], ("x-amz-date", "20240305T060708Z".into()));
+        assert_eq!(
+            headers[1].1,
+            "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+        assert_eq!(
+            headers[2].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240305/eu-west-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=21f7f8e7a87812bd45e7aa51041ad959e384d3192d95f1dadc195c1cac48720b"
+        );
+    }
+}
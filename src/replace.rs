@@ -0,0 +1,105 @@
+//! Find and replace: rewriting what a search looks for in the text of the
+//! notes it matches
+
+use crate::history::Command;
+use crate::sanitize::{MAX_NOTE_CHARS, sanitize_text};
+use crate::search::SearchQuery;
+use crate::{Board, NoteData};
+use regex::{NoExpand, RegexBuilder};
+
+impl SearchQuery {
+    /// `text` with the words of the query, ignoring case, and then every
+    /// match of its patterns replaced by `replacement`; in place of pattern
+    /// matches `$1` and the like stand for their groups
+    pub fn replace(&self, text: &str, replacement: &str) -> String {
+        let mut text = text.to_owned();
+        if !self.text.is_empty()
+            && let Ok(words) = RegexBuilder::new(&regex::escape(&self.text))
+                .case_insensitive(true)
+                .build()
+        {
+            text = words.replace_all(&text, NoExpand(replacement)).into_owned();
+        }
+        for pattern in &self.patterns {
+            text = pattern.replace_all(&text, replacement).into_owned();
+        }
+        text
+    }
+}
+
+/// The notes among `ids` on `board` whose text the replacement changes,
+/// each as it would be after it
+pub fn replace_preview(
+    board: &Board,
+    ids: &[u64],
+    query: &SearchQuery,
+    replacement: &str,
+) -> Vec<NoteData> {
+    board
+        .notes
+        .iter()
+        .filter(|n| ids.contains(&n.id))
+        .filter_map(|note| {
+            let text = query.replace(&note.text, replacement);
+            let text = sanitize_text(&text, MAX_NOTE_CHARS).text;
+            (text != note.text).then(|| NoteData {
+                text,
+                ..note.clone()
+            })
+        })
+        .collect()
+}
+
+impl Board {
+    /// Replace what `query` looks for by `replacement` in the notes `ids`,
+    /// returning the change for undo, or `None` if no note changed
+    pub fn replace_text(
+        &mut self,
+        ids: &[u64],
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Option<Command> {
+        let index = self.note_index();
+        let changes = replace_preview(self, ids, query, replacement)
+            .into_iter()
+            .filter_map(|after| {
+                let note = &mut self.notes[*index.get(&after.id)?];
+                let before = std::mem::replace(note, after.clone());
+                Some(Command::Update { before, after })
+            })
+            .collect();
+        Command::batch(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+    use egui::Pos2;
+
+    #[test]
+    fn replacing_is_one_undo_step() {
+        let mut state = AppState::default();
+        let a = state.add_note("Fix bug 12, then fix docs", Pos2::ZERO);
+        let b = state.add_note("FIX later", Pos2::ZERO);
+        let c = state.add_note("Nothing here", Pos2::ZERO);
+        let board = state.board_mut();
+
+        let query = SearchQuery::parse("fix").unwrap();
+        let preview = replace_preview(board, &[a, b, c], &query, "$mend");
+        assert_eq!(preview.len(), 2);
+        assert_eq!(preview[0].text, "$mend bug 12, then $mend docs");
+        let Some(Command::Batch(changes)) = board.replace_text(&[a, b, c], &query, "mend") else {
+            panic!("expected one batch");
+        };
+        assert_eq!(changes.len(), 2);
+        assert_eq!(board.notes[1].text, "mend later");
+        assert!(board.replace_text(&[c], &query, "mend").is_none());
+
+        // Patterns can reuse what they matched
+        let query = SearchQuery::parse(r"/bug (\d+)/").unwrap();
+        board.replace_text(&[a], &query, "issue #$1");
+        assert_eq!(board.notes[0].text, "mend issue #12, then mend docs");
+    }
+}
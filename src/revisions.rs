@@ -0,0 +1,61 @@
+//! Earlier texts of a note, kept so an edit can be taken back later
+
+use crate::NoteData;
+use serde::{Deserialize, Serialize};
+
+/// Most earlier texts kept per note; the oldest are dropped first
+pub const MAX_REVISIONS: usize = 20;
+
+/// A text the note had until an edit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Revision {
+    /// When the text was replaced, in seconds since the Unix epoch
+    pub at: u64,
+    pub text: String,
+}
+
+impl NoteData {
+    /// Remember `previous` as the text replaced at `now`, unless the text is
+    /// still the same
+    pub fn record_revision(&mut self, previous: &str, now: u64) {
+        if previous == self.text {
+            return;
+        }
+        self.revisions.push(Revision {
+            at: now,
+            text: previous.into(),
+        });
+        if self.revisions.len() > MAX_REVISIONS {
+            let excess = self.revisions.len() - MAX_REVISIONS;
+            self.revisions.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_latest_changed_texts_are_kept() {
+        let mut note = NoteData {
+            text: "v0".into(),
+            ..Default::default()
+        };
+        note.record_revision("v0", 5);
+        assert!(note.revisions.is_empty());
+        for version in 1..=MAX_REVISIONS as u64 + 2 {
+            let previous = std::mem::replace(&mut note.text, format!("v{version}"));
+            note.record_revision(&previous, version);
+        }
+        assert_eq!(note.revisions.len(), MAX_REVISIONS);
+        assert_eq!(
+            note.revisions[0],
+            Revision {
+                at: 3,
+                text: "v2".into()
+            }
+        );
+        assert_eq!(note.revisions.last().unwrap().text, "v21");
+    }
+}
@@ -0,0 +1,162 @@
+//! Cleaning up pasted or imported note text
+
+use crate::{AppState, Board};
+
+/// Longest note text kept, in characters
+pub const MAX_NOTE_CHARS: usize = 10_000;
+
+/// Result of cleaning a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sanitized {
+    pub text: String,
+    /// Number of control characters that were dropped
+    pub removed_controls: usize,
+    /// Whether the text was cut to the length limit
+    pub truncated: bool,
+    /// Whether `\r\n` or lone `\r` line endings became `\n`
+    pub normalized_newlines: bool,
+}
+
+impl Sanitized {
+    /// Whether the text had to be changed
+    pub fn changed(&self) -> bool {
+        self.removed_controls > 0 || self.truncated || self.normalized_newlines
+    }
+
+    /// Warning describing what was changed, if anything
+    pub fn warning(&self) -> Option<String> {
+        match (self.removed_controls, self.truncated) {
+            (0, false) => None,
+            (0, true) => Some(format!("Text cut to {MAX_NOTE_CHARS} characters")),
+            (n, false) => Some(format!("Removed {n} control character(s)")),
+            (n, true) => Some(format!(
+                "Removed {n} control character(s) and cut text to {MAX_NOTE_CHARS} characters"
+            )),
+        }
+    }
+}
+
+/// Drop control characters (keeping newlines and tabs), normalize line
+/// endings and cut the text to at most `max_chars` characters
+pub fn sanitize_text(text: &str, max_chars: usize) -> Sanitized {
+    let mut out = String::with_capacity(text.len().min(max_chars * 4));
+    let mut removed_controls = 0;
+    let mut kept = 0;
+    let mut truncated = false;
+    let mut normalized_newlines = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\r' => {
+                normalized_newlines = true;
+                if chars.peek() == Some(&'\n') {
+                    continue;
+                }
+                '\n'
+            }
+            '\n' | '\t' => c,
+            c if c.is_control() => {
+                removed_controls += 1;
+                continue;
+            }
+            c => c,
+        };
+        if kept == max_chars {
+            truncated = true;
+            break;
+        }
+        out.push(c);
+        kept += 1;
+    }
+    Sanitized {
+        text: out,
+        removed_controls,
+        truncated,
+        normalized_newlines,
+    }
+}
+
+/// Decode bytes as UTF-8, replacing invalid sequences, and sanitize the result
+pub fn sanitize_bytes(bytes: &[u8], max_chars: usize) -> Sanitized {
+    sanitize_text(&String::from_utf8_lossy(bytes), max_chars)
+}
+
+/// Sanitize every note on the board, returning a warning per note that
+/// lost characters; line endings are normalized silently
+pub fn sanitize_board(board: &mut Board) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for note in board.stored_notes_mut() {
+        let clean = sanitize_text(&note.text, MAX_NOTE_CHARS);
+        if clean.changed() {
+            if let Some(warning) = clean.warning() {
+                warnings.push(format!("note {}: {warning}", note.id));
+            }
+            note.text = clean.text;
+        }
+    }
+    warnings
+}
+
+/// Sanitize every note on every board of `state`, returning a warning per
+/// note that lost characters
+pub fn sanitize_state(state: &mut AppState) -> Vec<String> {
+    state
+        .boards
+        .iter_mut()
+        .flat_map(|board| {
+            let name = board.name.clone();
+            sanitize_board(board)
+                .into_iter()
+                .map(move |warning| format!("{name}, {warning}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_controls_and_normalizes_newlines() {
+        let clean = sanitize_text("a\u{0}b\r\nc\rd\te\u{1b}[0m", 100);
+        assert_eq!(clean.text, "ab\nc\nd\te[0m");
+        assert_eq!(clean.removed_controls, 2);
+        assert!(!clean.truncated);
+
+        // Pasted CRLF text is changed even with nothing to warn about
+        let crlf = sanitize_text("one\r\ntwo", 100);
+        assert!(crlf.changed());
+        assert_eq!(crlf.warning(), None);
+        assert!(!sanitize_text("one\ntwo", 100).changed());
+    }
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        let clean = sanitize_text("żółw", 2);
+        assert_eq!(clean.text, "żó");
+        assert!(clean.truncated);
+        assert!(clean.warning().is_some());
+        assert!(!sanitize_text("ok", 2).changed());
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let clean = sanitize_bytes(b"hi \xff there", 100);
+        assert_eq!(clean.text, "hi \u{fffd} there");
+    }
+
+    #[test]
+    fn every_board_is_sanitized() {
+        let mut state = AppState::default();
+        let other = state.add_board("Other");
+        state.boards[other].notes.push(crate::NoteData {
+            id: 7,
+            text: "bell\u{7}".into(),
+            ..Default::default()
+        });
+        let warnings = sanitize_state(&mut state);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Other, note 7"));
+        assert_eq!(state.boards[other].notes[0].text, "bell");
+    }
+}
@@ -0,0 +1,290 @@
+//! Rhai scripts automating the board, e.g. coloring every note about a bug
+//! red, run from the Scripts menu
+//!
+//! Scripts see notes by id and change them through functions:
+//!
+//! ```text
+//! for id in search("bug") {
+//!     set_color(id, "#ff0000");
+//! }
+//! ```
+//!
+//! `notes()` and `search(query)` list ids; `text`, `color`, `tags`, `x` and
+//! `y` read a note; `set_text`, `set_color`, `add_tag`, `remove_tag`,
+//! `move_to`, `move_by` and `delete` change it; `create(text, x, y)` adds a
+//! note and returns its id. `print` goes to the status bar.
+
+use crate::palette::{color_name, sticky_colors};
+use crate::search::SearchQuery;
+use crate::{Board, DEFAULT_NOTE_COLOR, DEFAULT_NOTE_SIZE, NoteData};
+use egui::{Color32, Pos2, Vec2};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, INT};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Folder next to the save file holding the user's scripts
+pub const SCRIPTS_DIR: &str = "plop-scripts";
+
+/// File extension of scripts
+pub const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Most operations a script may take, so a runaway loop can't hang plop
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Scripts in `dir`, sorted by name
+pub fn list_scripts(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// What a script left behind
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptRun {
+    /// The board's notes after the script, new ones at the end
+    pub notes: Vec<NoteData>,
+    pub next_note_id: u64,
+    /// What the script printed
+    pub output: Vec<String>,
+}
+
+/// The board a script works on
+struct Scripted {
+    board: Board,
+    next_note_id: u64,
+}
+
+impl Scripted {
+    fn note(&self, id: INT) -> ScriptResult<&NoteData> {
+        self.board
+            .notes
+            .iter()
+            .find(|n| n.id as INT == id)
+            .ok_or_else(|| format!("there is no note {id}").into())
+    }
+
+    fn note_mut(&mut self, id: INT) -> ScriptResult<&mut NoteData> {
+        self.board
+            .notes
+            .iter_mut()
+            .find(|n| n.id as INT == id)
+            .ok_or_else(|| format!("there is no note {id}").into())
+    }
+
+    /// The note `id`, unless it is locked against moving and deleting
+    fn unlocked_mut(&mut self, id: INT) -> ScriptResult<&mut NoteData> {
+        let note = self.note_mut(id)?;
+        if note.locked {
+            return Err(format!("note {id} is locked").into());
+        }
+        Ok(note)
+    }
+}
+
+fn ids(ids: impl IntoIterator<Item = u64>) -> Array {
+    ids.into_iter().map(|id| Dynamic::from(id as INT)).collect()
+}
+
+/// A whole or fractional number passed by a script
+fn number(value: Dynamic) -> ScriptResult<f32> {
+    value
+        .as_float()
+        .map(|f| f as f32)
+        .or_else(|_| value.as_int().map(|i| i as f32))
+        .map_err(|kind| format!("expected a number, got {kind}").into())
+}
+
+/// A color as a sticky-note name like `pink` or a hex code like `#ff0000`
+fn parse_color(name: &str) -> ScriptResult<Color32> {
+    if let Some(named) = sticky_colors()
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+    {
+        return Ok(named.color);
+    }
+    Color32::from_hex(name).map_err(|_| format!("unknown color {name:?}").into())
+}
+
+/// Run the script `source` on a copy of `board`, making new notes from id
+/// `next_note_id` on; the board itself is left alone
+pub fn run_script(source: &str, board: &Board, next_note_id: u64) -> Result<ScriptRun, String> {
+    let scripted = Rc::new(RefCell::new(Scripted {
+        board: board.clone(),
+        next_note_id,
+    }));
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let printed = output.clone();
+    engine.on_print(move |line| printed.borrow_mut().push(line.to_owned()));
+
+    let s = scripted.clone();
+    engine.register_fn("notes", move || {
+        ids(s.borrow().board.notes.iter().map(|n| n.id))
+    });
+    let s = scripted.clone();
+    engine.register_fn("search", move |query: &str| -> ScriptResult<Array> {
+        let query = SearchQuery::parse(query)?;
+        Ok(ids(query.search(&s.borrow().board)))
+    });
+    let s = scripted.clone();
+    engine.register_fn("text", move |id: INT| -> ScriptResult<String> {
+        Ok(s.borrow().note(id)?.text.clone())
+    });
+    let s = scripted.clone();
+    engine.register_fn("color", move |id: INT| -> ScriptResult<String> {
+        Ok(color_name(s.borrow().note(id)?.color))
+    });
+    let s = scripted.clone();
+    engine.register_fn("tags", move |id: INT| -> ScriptResult<Array> {
+        let tags = s.borrow().note(id)?.tags.clone();
+        Ok(tags.into_iter().map(Dynamic::from).collect())
+    });
+    let s = scripted.clone();
+    engine.register_fn("x", move |id: INT| -> ScriptResult<f64> {
+        Ok(s.borrow().note(id)?.pos.x as f64)
+    });
+    let s = scripted.clone();
+    engine.register_fn("y", move |id: INT| -> ScriptResult<f64> {
+        Ok(s.borrow().note(id)?.pos.y as f64)
+    });
+    let s = scripted.clone();
+    engine.register_fn("set_text", move |id: INT, text: &str| -> ScriptResult<()> {
+        s.borrow_mut().note_mut(id)?.text = text.to_owned();
+        Ok(())
+    });
+    let s = scripted.clone();
+    engine.register_fn(
+        "set_color",
+        move |id: INT, color: &str| -> ScriptResult<()> {
+            s.borrow_mut().note_mut(id)?.color = parse_color(color)?;
+            Ok(())
+        },
+    );
+    let s = scripted.clone();
+    engine.register_fn("add_tag", move |id: INT, tag: &str| -> ScriptResult<()> {
+        let mut s = s.borrow_mut();
+        let note = s.note_mut(id)?;
+        for tag in crate::tags::parse_tags(tag) {
+            if !note.tags.contains(&tag) {
+                note.tags.push(tag);
+            }
+        }
+        Ok(())
+    });
+    let s = scripted.clone();
+    engine.register_fn(
+        "remove_tag",
+        move |id: INT, tag: &str| -> ScriptResult<()> {
+            let removed = crate::tags::parse_tags(tag);
+            s.borrow_mut()
+                .note_mut(id)?
+                .tags
+                .retain(|t| !removed.contains(t));
+            Ok(())
+        },
+    );
+    let s = scripted.clone();
+    engine.register_fn(
+        "move_to",
+        move |id: INT, x: Dynamic, y: Dynamic| -> ScriptResult<()> {
+            s.borrow_mut().unlocked_mut(id)?.pos = Pos2::new(number(x)?, number(y)?);
+            Ok(())
+        },
+    );
+    let s = scripted.clone();
+    engine.register_fn(
+        "move_by",
+        move |id: INT, dx: Dynamic, dy: Dynamic| -> ScriptResult<()> {
+            s.borrow_mut().unlocked_mut(id)?.pos += Vec2::new(number(dx)?, number(dy)?);
+            Ok(())
+        },
+    );
+    let s = scripted.clone();
+    engine.register_fn("delete", move |id: INT| -> ScriptResult<()> {
+        let mut s = s.borrow_mut();
+        s.unlocked_mut(id)?;
+        s.board.notes.retain(|n| n.id as INT != id);
+        Ok(())
+    });
+    let s = scripted.clone();
+    engine.register_fn(
+        "create",
+        move |text: &str, x: Dynamic, y: Dynamic| -> ScriptResult<INT> {
+            let mut s = s.borrow_mut();
+            let note = NoteData {
+                id: s.next_note_id,
+                text: text.to_owned(),
+                pos: Pos2::new(number(x)?, number(y)?),
+                size: DEFAULT_NOTE_SIZE,
+                color: DEFAULT_NOTE_COLOR,
+                z: s.board.top_z(),
+                ..Default::default()
+            };
+            s.next_note_id += 1;
+            s.board.notes.push(note);
+            Ok(s.next_note_id as INT - 1)
+        },
+    );
+
+    engine.run(source).map_err(|e| e.to_string())?;
+    drop(engine);
+    let Scripted {
+        board,
+        next_note_id,
+    } = Rc::into_inner(scripted)
+        .expect("the engine holding the board is gone")
+        .into_inner();
+    Ok(ScriptRun {
+        notes: board.notes,
+        next_note_id,
+        output: output.take(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+
+    #[test]
+    fn scripts_change_a_copy_of_the_board() {
+        let mut state = AppState::default();
+        let bug = state.add_note("Fix the bug", Pos2::ZERO);
+        state.add_note("Write docs", Pos2::ZERO);
+        let next_id = state.next_note_id;
+        let script = r##"
+            for id in search("bug") {
+                set_color(id, "#ff0000");
+                move_by(id, 10, 2.5);
+            }
+            let new = create("Found " + search("bug").len() + " bug(s)", 0, 100);
+            add_tag(new, "#Report");
+            print(color(new));
+        "##;
+        let run = run_script(script, state.board(), next_id).unwrap();
+        assert_eq!(state.board().notes.len(), 2);
+        assert_eq!(run.notes.len(), 3);
+        assert_eq!(run.notes[0].id, bug);
+        assert_eq!(run.notes[0].color, Color32::from_rgb(255, 0, 0));
+        assert_eq!(run.notes[0].pos, Pos2::new(10.0, 2.5));
+        assert_eq!(run.notes[2].text, "Found 1 bug(s)");
+        assert_eq!(run.notes[2].tags, ["report"]);
+        assert_eq!(run.next_note_id, next_id + 1);
+        assert_eq!(run.output, ["Yellow"]);
+
+        let error = run_script("delete(12345)", state.board(), next_id).unwrap_err();
+        assert!(error.contains("there is no note 12345"));
+        assert!(run_script("loop {}", state.board(), next_id).is_err());
+    }
+}
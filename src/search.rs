@@ -0,0 +1,227 @@
+//! Search queries: words to find plus `color:`, `tag:`, `prio:` and
+//! `/regex/` terms
+
+use crate::palette::{color_name, sticky_colors};
+use crate::priority::Priority;
+use crate::{AppState, Board, NoteData};
+use egui::Color32;
+use regex::{Regex, RegexBuilder};
+
+/// A parsed search like `color:yellow tag:urgent /due \d+/ report`
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Lowercase text the note has to contain, the plain words of the query
+    pub text: String,
+    /// Color names or hex codes; the note needs one of them
+    pub colors: Vec<String>,
+    /// Tags the note needs all of
+    pub tags: Vec<String>,
+    /// Priorities; the note needs one of them
+    pub priorities: Vec<Priority>,
+    /// Patterns the text has to match, ignoring case
+    pub patterns: Vec<Regex>,
+}
+
+impl SearchQuery {
+    /// Parse `input`, failing on an invalid regular expression or an
+    /// unknown priority
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut query = Self::default();
+        let mut words = Vec::new();
+        let mut rest = input.trim_start();
+        while !rest.is_empty() {
+            // A pattern runs up to the next slash and may contain spaces
+            if let Some(body) = rest.strip_prefix('/')
+                && let Some(end) = body.find('/')
+            {
+                let pattern = RegexBuilder::new(&body[..end])
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                query.patterns.push(pattern);
+                rest = body[end + 1..].trim_start();
+                continue;
+            }
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let token = &rest[..end];
+            rest = rest[end..].trim_start();
+            if let Some(color) = token.strip_prefix("color:") {
+                if !color.is_empty() {
+                    query.colors.push(color.to_lowercase());
+                }
+            } else if let Some(tag) = token.strip_prefix("tag:") {
+                let tag = tag.trim_start_matches('#').to_lowercase();
+                if !tag.is_empty() {
+                    query.tags.push(tag);
+                }
+            } else if let Some(name) = token.strip_prefix("prio:") {
+                if !name.is_empty() {
+                    let priority = Priority::from_name(name)
+                        .ok_or_else(|| format!("unknown priority \"{name}\""))?;
+                    query.priorities.push(priority);
+                }
+            } else {
+                words.push(token);
+            }
+        }
+        query.text = words.join(" ").to_lowercase();
+        Ok(query)
+    }
+
+    /// Whether the query has nothing to look for
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.colors.is_empty()
+            && self.tags.is_empty()
+            && self.priorities.is_empty()
+            && self.patterns.is_empty()
+    }
+
+    /// Whether `note` on `board` matches every part of the query
+    pub fn matches(&self, board: &Board, note: &NoteData) -> bool {
+        note.text.to_lowercase().contains(&self.text)
+            && self.patterns.iter().all(|p| p.is_match(&note.text))
+            && self.tags.iter().all(|t| note.tags.contains(t))
+            && (self.priorities.is_empty() || self.priorities.contains(&note.priority))
+            && (self.colors.is_empty()
+                || self
+                    .colors
+                    .iter()
+                    .any(|name| is_named(board, note.color, name)))
+    }
+
+    /// Ids of the notes on `board` matching the query, none if it is empty
+    pub fn search(&self, board: &Board) -> Vec<u64> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        board
+            .notes
+            .iter()
+            .filter(|n| self.matches(board, n))
+            .map(|n| n.id)
+            .collect()
+    }
+
+    /// Ids of the matching notes of every board of `state` that has any,
+    /// by board index
+    pub fn search_boards(&self, state: &AppState) -> Vec<(usize, Vec<u64>)> {
+        state
+            .boards
+            .iter()
+            .enumerate()
+            .map(|(index, board)| (index, self.search(board)))
+            .filter(|(_, ids)| !ids.is_empty())
+            .collect()
+    }
+}
+
+/// Index of the board after `active` among the boards with `results`, or
+/// before it going backwards, wrapping around; `None` if only `active` has
+/// any
+pub fn next_board_with_matches(
+    results: &[(usize, Vec<u64>)],
+    active: usize,
+    forward: bool,
+) -> Option<usize> {
+    let others = results.iter().map(|(i, _)| *i).filter(|&i| i != active);
+    if forward {
+        let (after, before): (Vec<usize>, Vec<usize>) = others.partition(|&i| i > active);
+        after.first().or(before.first()).copied()
+    } else {
+        let (before, after): (Vec<usize>, Vec<usize>) = others.partition(|&i| i < active);
+        before.last().or(after.last()).copied()
+    }
+}
+
+/// Whether `color` goes by the lowercase `name` on `board`: in its palette,
+/// as a sticky-note color, or as its hex code with or without `#`
+fn is_named(board: &Board, color: Color32, name: &str) -> bool {
+    let hex = name.trim_start_matches('#');
+    board
+        .palette
+        .iter()
+        .chain(&sticky_colors())
+        .any(|c| c.color == color && c.name.to_lowercase() == name)
+        || color_name(color).trim_start_matches('#') == hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::NamedColor;
+
+    fn note(id: u64, text: &str, color: Color32, tags: &[&str]) -> NoteData {
+        NoteData {
+            id,
+            text: text.into(),
+            color,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn terms_narrow_the_matches() {
+        let board = Board {
+            notes: vec![
+                note(1, "Fix bug 12", Color32::YELLOW, &["urgent"]),
+                note(2, "Fix docs", Color32::YELLOW, &[]),
+                note(3, "Release 1.0", Color32::from_rgb(1, 2, 3), &["urgent"]),
+            ],
+            palette: vec![NamedColor::new("Blocked", Color32::from_rgb(1, 2, 3))],
+            ..Default::default()
+        };
+        let search = |q: &str| SearchQuery::parse(q).unwrap().search(&board);
+        assert_eq!(search("fix"), [1, 2]);
+        assert_eq!(search("color:yellow"), [1, 2]);
+        assert_eq!(search("tag:#Urgent fix"), [1]);
+        assert_eq!(search("color:blocked"), [3]);
+        assert_eq!(search("color:#010203 color:Yellow tag:urgent"), [1, 3]);
+        assert_eq!(search(r"/bug \d+/"), [1]);
+        assert_eq!(search(r"/^(fix|release)/ /\d/"), [1, 3]);
+        assert!(search("").is_empty());
+    }
+
+    #[test]
+    fn priorities_filter_the_matches() {
+        let mut notes: Vec<NoteData> = (1..=3)
+            .map(|id| note(id, "task", Color32::YELLOW, &[]))
+            .collect();
+        notes[0].priority = Priority::High;
+        notes[2].priority = Priority::Urgent;
+        let board = Board {
+            notes,
+            ..Default::default()
+        };
+        let search = |q: &str| SearchQuery::parse(q).unwrap().search(&board);
+        assert_eq!(search("prio:high"), [1]);
+        assert_eq!(search("prio:High prio:URGENT task"), [1, 3]);
+        assert_eq!(search("prio:normal"), [2]);
+        assert!(SearchQuery::parse("prio:someday").is_err());
+    }
+
+    #[test]
+    fn matches_are_found_on_every_board() {
+        let mut state = AppState::default();
+        for (name, text) in [("Work", "bug"), ("Home", "milk"), ("Side", "bug too")] {
+            let index = state.add_board(name);
+            state.boards[index].notes = vec![note(index as u64 + 10, text, Color32::YELLOW, &[])];
+        }
+        let results = SearchQuery::parse("bug").unwrap().search_boards(&state);
+        assert_eq!(results, [(1, vec![11]), (3, vec![13])]);
+        assert_eq!(next_board_with_matches(&results, 1, true), Some(3));
+        assert_eq!(next_board_with_matches(&results, 3, true), Some(1));
+        assert_eq!(next_board_with_matches(&results, 0, false), Some(3));
+        assert_eq!(next_board_with_matches(&results[..1], 1, true), None);
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported() {
+        assert!(SearchQuery::parse("/(unclosed/").is_err());
+        // A lone slash is just text
+        let query = SearchQuery::parse("a/b").unwrap();
+        assert_eq!(query.text, "a/b");
+        assert!(query.patterns.is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+//! Selecting several notes to move, delete or recolor them together
+
+use crate::Board;
+use egui::Rect;
+
+/// Ids of the selected notes, in the order they were selected
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    ids: Vec<u64>,
+}
+
+impl Selection {
+    /// The selected ids
+    pub fn ids(&self) -> &[u64] {
+        &self.ids
+    }
+
+    /// Whether the note `id` is selected
+    pub fn contains(&self, id: u64) -> bool {
+        self.ids.contains(&id)
+    }
+
+    /// Whether nothing is selected
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Deselect everything
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+
+    /// Select the note `id` and nothing else
+    pub fn select_only(&mut self, id: u64) {
+        self.ids.clear();
+        self.ids.push(id);
+    }
+
+    /// Select `id` if it isn't selected, deselect it otherwise
+    pub fn toggle(&mut self, id: u64) {
+        match self.ids.iter().position(|&i| i == id) {
+            Some(index) => {
+                self.ids.remove(index);
+            }
+            None => self.ids.push(id),
+        }
+    }
+
+    /// Add the notes `ids` to the selection
+    pub fn extend(&mut self, ids: impl IntoIterator<Item = u64>) {
+        for id in ids {
+            if !self.contains(id) {
+                self.ids.push(id);
+            }
+        }
+    }
+
+    /// Forget notes that are no longer on `board`
+    pub fn retain_existing(&mut self, board: &Board) {
+        self.ids
+            .retain(|id| board.notes.iter().any(|n| n.id == *id));
+    }
+}
+
+/// The note after `current` in reading order (top to bottom, then left to
+/// right), or before it when going backwards; wraps around at the ends
+pub fn cycle_note(board: &Board, current: Option<u64>, forward: bool) -> Option<u64> {
+    let mut notes: Vec<_> = board.notes.iter().collect();
+    notes.sort_by(|a, b| {
+        a.pos
+            .y
+            .total_cmp(&b.pos.y)
+            .then(a.pos.x.total_cmp(&b.pos.x))
+    });
+    let len = notes.len();
+    let index = match current.and_then(|id| notes.iter().position(|n| n.id == id)) {
+        Some(i) if forward => (i + 1) % len,
+        Some(i) => (i + len - 1) % len,
+        None if forward => 0,
+        None => len.checked_sub(1)?,
+    };
+    notes.get(index).map(|n| n.id)
+}
+
+/// Ids of the notes of `board` overlapping `area` (board coordinates)
+pub fn notes_in_rect(board: &Board, area: Rect) -> Vec<u64> {
+    board
+        .notes
+        .iter()
+        .filter(|n| Rect::from_min_size(n.pos, n.size).intersects(area))
+        .map(|n| n.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn toggle_and_extend_keep_ids_unique() {
+        let mut selection = Selection::default();
+        selection.select_only(1);
+        selection.extend([2, 1, 3]);
+        assert_eq!(selection.ids(), [1, 2, 3]);
+        selection.toggle(2);
+        selection.toggle(4);
+        assert_eq!(selection.ids(), [1, 3, 4]);
+        selection.select_only(3);
+        assert_eq!(selection.ids(), [3]);
+    }
+
+    #[test]
+    fn lasso_picks_overlapping_notes() {
+        let note = |id: u64, x: f32| NoteData {
+            id,
+            pos: Pos2::new(x, 0.0),
+            size: Vec2::new(50.0, 50.0),
+            ..Default::default()
+        };
+        let mut board = Board {
+            notes: vec![note(1, 0.0), note(2, 100.0), note(3, 200.0)],
+            ..Default::default()
+        };
+        let lasso = Rect::from_min_max(Pos2::new(40.0, 10.0), Pos2::new(120.0, 20.0));
+        assert_eq!(notes_in_rect(&board, lasso), [1, 2]);
+
+        let mut selection = Selection::default();
+        selection.extend([1, 2]);
+        board.notes.remove(0);
+        selection.retain_existing(&board);
+        assert_eq!(selection.ids(), [2]);
+    }
+
+    #[test]
+    fn cycling_follows_reading_order_and_wraps() {
+        let note = |id: u64, x: f32, y: f32| NoteData {
+            id,
+            pos: Pos2::new(x, y),
+            ..Default::default()
+        };
+        let board = Board {
+            notes: vec![note(1, 0.0, 100.0), note(2, 100.0, 0.0), note(3, 0.0, 0.0)],
+            ..Default::default()
+        };
+        assert_eq!(cycle_note(&board, None, true), Some(3));
+        assert_eq!(cycle_note(&board, Some(3), true), Some(2));
+        assert_eq!(cycle_note(&board, Some(1), true), Some(3));
+        assert_eq!(cycle_note(&board, Some(3), false), Some(1));
+        assert_eq!(cycle_note(&Board::default(), None, false), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Where the user left off: the view of each board, the board shown, the
+//! search and the open edit windows, kept apart from the boards
+
+use crate::AppState;
+use egui::Rect;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// UI state restored on the next launch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Session {
+    /// Save file the session was spent on; other files start afresh
+    #[serde(default)]
+    pub file: PathBuf,
+    /// Id of the board shown
+    #[serde(default)]
+    pub board: Option<u64>,
+    /// Visible part of each board, by board id
+    #[serde(default)]
+    pub views: BTreeMap<u64, Rect>,
+    #[serde(default)]
+    pub search: String,
+    /// Ids of the notes whose edit window was open
+    #[serde(default)]
+    pub editing: Vec<u64>,
+}
+
+impl Session {
+    /// The session of `state`, loaded from `file`
+    pub fn capture(file: &Path, state: &AppState, search: &str, editing: Vec<u64>) -> Self {
+        Self {
+            file: file.to_owned(),
+            board: Some(state.board().id),
+            views: state.boards.iter().map(|b| (b.id, b.scene_rect)).collect(),
+            search: search.to_owned(),
+            editing,
+        }
+    }
+
+    /// Show the board and views of the session on `state`, if it was spent
+    /// on `file`. Returns whether it was.
+    pub fn restore(&self, file: &Path, state: &mut AppState) -> bool {
+        if self.file != file {
+            return false;
+        }
+        for board in &mut state.boards {
+            if let Some(view) = self.views.get(&board.id) {
+                board.scene_rect = *view;
+            }
+        }
+        if let Some(index) = state.boards.iter().position(|b| Some(b.id) == self.board) {
+            state.active = index;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SESSION_FILE, load_json, save_json};
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn sessions_restore_only_their_own_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("boards.json");
+        let mut state = AppState::default();
+        let second = state.add_board("Second");
+        state.active = second;
+        let view = Rect::from_min_size(Pos2::new(-300.0, 40.0), Vec2::new(800.0, 600.0));
+        state.boards[second].scene_rect = view;
+
+        let path = dir.path().join(SESSION_FILE);
+        save_json(
+            &Session::capture(&file, &state, "tag:urgent", vec![7]),
+            &path,
+        )
+        .unwrap();
+        let session: Session = load_json(&path, &path).unwrap();
+        assert_eq!(session.search, "tag:urgent");
+        assert_eq!(session.editing, [7]);
+
+        state.active = 0;
+        state.boards[second].scene_rect = Rect::ZERO;
+        assert!(!session.restore(&dir.path().join("other.json"), &mut state));
+        assert_eq!(state.active, 0);
+        assert!(session.restore(&file, &mut state));
+        assert_eq!(state.active, second);
+        assert_eq!(state.boards[second].scene_rect, view);
+    }
+}
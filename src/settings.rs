@@ -0,0 +1,144 @@
+//! App-wide preferences, kept apart from the boards in the platform's config
+//! folder
+
+use crate::autosave::DEFAULT_INTERVAL;
+use crate::note_font::FontDefaults;
+use crate::remote::RemoteConfig;
+use crate::sounds::SoundSettings;
+use crate::theme::{Theme, high_contrast_theme};
+use crate::trash::DEFAULT_TRASH_DAYS;
+use egui::{Color32, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Preferences that apply whichever save file is open
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// Theme of boards that don't pick one, and of the panels around them
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// Font of notes that don't pick their own
+    #[serde(default)]
+    pub fonts: FontDefaults,
+    /// Sound pack, volume and muting
+    #[serde(default)]
+    pub sounds: SoundSettings,
+    /// Days deleted notes stay in the trash before they are gone for good
+    #[serde(default = "default_trash_days")]
+    pub trash_days: u64,
+    /// Grid spacing notes snap to on boards without their own
+    #[serde(default = "default_grid_size")]
+    pub grid_size: f32,
+    /// Seconds between autosaves, `None` when autosave is off
+    #[serde(default = "default_autosave_interval")]
+    pub autosave_interval: Option<f64>,
+    /// Size of new notes instead of the theme's
+    #[serde(default)]
+    pub note_size: Option<Vec2>,
+    /// Color of new notes instead of the theme's, on boards without their own
+    #[serde(default)]
+    pub note_color: Option<Color32>,
+    /// Copy of the save file kept in sync on a server, if any
+    #[serde(default)]
+    pub remote: Option<RemoteConfig>,
+    /// Show every board in the high-contrast theme, whatever theme it picks
+    #[serde(default)]
+    pub high_contrast: bool,
+    /// Let notes let go while moving slide on before they snap into place
+    #[serde(default)]
+    pub tossing: bool,
+    /// System-wide shortcut popping up quick capture, like `ctrl+shift+N`;
+    /// `None` when off
+    #[serde(default)]
+    pub quick_capture_shortcut: Option<String>,
+    /// Hide the window instead of quitting when it is closed, so the quick
+    /// capture shortcut keeps working
+    #[serde(default)]
+    pub run_in_background: bool,
+}
+
+/// Quick capture shortcut offered when it is turned on
+pub const DEFAULT_QUICK_CAPTURE_SHORTCUT: &str = "ctrl+shift+N";
+
+fn default_trash_days() -> u64 {
+    DEFAULT_TRASH_DAYS
+}
+
+fn default_grid_size() -> f32 {
+    50.0
+}
+
+fn default_autosave_interval() -> Option<f64> {
+    Some(DEFAULT_INTERVAL)
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            fonts: FontDefaults::default(),
+            sounds: SoundSettings::default(),
+            trash_days: default_trash_days(),
+            grid_size: default_grid_size(),
+            autosave_interval: default_autosave_interval(),
+            note_size: None,
+            note_color: None,
+            remote: None,
+            high_contrast: false,
+            tossing: false,
+            quick_capture_shortcut: None,
+            run_in_background: false,
+        }
+    }
+}
+
+impl Settings {
+    /// `theme`, or the high-contrast theme if that is on, with the default
+    /// note size and color set here
+    pub fn apply_to(&self, mut theme: Theme) -> Theme {
+        if self.high_contrast {
+            theme = high_contrast_theme();
+        }
+        if let Some(size) = self.note_size {
+            theme.note_size = size;
+        }
+        if let Some(color) = self.note_color {
+            theme.note_color = color;
+        }
+        theme
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SETTINGS_FILE, load_json, save_json};
+
+    #[test]
+    fn settings_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        let settings = Settings {
+            theme: Some("Dark".into()),
+            ..Default::default()
+        };
+        save_json(&settings, &path).unwrap();
+        assert_eq!(load_json::<Settings>(&path, &path).unwrap(), settings);
+
+        let moved = Settings {
+            note_size: Some(Vec2::splat(120.0)),
+            ..settings
+        };
+        assert_eq!(
+            moved.apply_to(Theme::default()).note_size,
+            Vec2::splat(120.0)
+        );
+        // High contrast wins over the board's theme but keeps the note size
+        let contrast = Settings {
+            high_contrast: true,
+            ..moved
+        };
+        let theme = contrast.apply_to(Theme::default());
+        assert!(theme.high_contrast);
+        assert_eq!(theme.note_size, Vec2::splat(120.0));
+    }
+}
@@ -0,0 +1,212 @@
+//! Note shapes: the plain rectangle, a square sticky, a circle, a diamond and
+//! a speech bubble, all drawn inside the note's (skewed, tilted) box
+//!
+//! Shapes are laid out in unit coordinates, `0..1` across and down the box,
+//! and mapped onto the note's corners when drawn or hit-tested.
+
+use egui::{Pos2, Rect, Vec2, pos2};
+use serde::{Deserialize, Serialize};
+
+/// Points around the circle's outline
+const CIRCLE_POINTS: usize = 32;
+
+/// Where the speech bubble's body ends and its tail starts, down the box
+const BUBBLE_BODY: f32 = 0.8;
+
+/// The outline a note is drawn with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteShape {
+    #[default]
+    Rectangle,
+    /// A classic square sticky; kept square when resized
+    Square,
+    /// Kept round when resized
+    Circle,
+    Diamond,
+    /// A box with a tail at the bottom left
+    SpeechBubble,
+}
+
+impl NoteShape {
+    pub const ALL: [Self; 5] = [
+        Self::Rectangle,
+        Self::Square,
+        Self::Circle,
+        Self::Diamond,
+        Self::SpeechBubble,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rectangle => "Rectangle",
+            Self::Square => "Square",
+            Self::Circle => "Circle",
+            Self::Diamond => "Diamond",
+            Self::SpeechBubble => "Speech bubble",
+        }
+    }
+
+    /// Convex pieces the shape is filled from, in unit coordinates, as
+    /// only convex polygons can be filled
+    pub fn pieces(self) -> Vec<Vec<Pos2>> {
+        match self {
+            Self::Rectangle | Self::Square => vec![unit_rect(1.0)],
+            Self::Circle => vec![circle()],
+            Self::Diamond => vec![vec![
+                pos2(0.5, 0.0),
+                pos2(1.0, 0.5),
+                pos2(0.5, 1.0),
+                pos2(0.0, 0.5),
+            ]],
+            Self::SpeechBubble => vec![unit_rect(BUBBLE_BODY), tail().to_vec()],
+        }
+    }
+
+    /// The whole outline as one closed line, in unit coordinates
+    pub fn outline(self) -> Vec<Pos2> {
+        match self {
+            Self::SpeechBubble => {
+                let [start, end, tip] = tail();
+                vec![
+                    pos2(0.0, 0.0),
+                    pos2(1.0, 0.0),
+                    pos2(1.0, BUBBLE_BODY),
+                    end,
+                    tip,
+                    start,
+                    pos2(0.0, BUBBLE_BODY),
+                ]
+            }
+            _ => self.pieces().swap_remove(0),
+        }
+    }
+
+    /// Whether the point `unit`, in unit coordinates, is inside the shape
+    pub fn contains(self, unit: Pos2) -> bool {
+        self.pieces().iter().any(|piece| in_convex(unit, piece))
+    }
+
+    /// Whether `p` hits the shape drawn in the box with `corners`
+    pub fn hit(self, p: Pos2, corners: &[Pos2; 4]) -> bool {
+        quad_to_unit(p, corners).is_some_and(|unit| self.contains(unit))
+    }
+
+    /// Part of the box the text is fitted into, in unit coordinates
+    pub fn text_area(self) -> Rect {
+        match self {
+            Self::Rectangle | Self::Square => Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0)),
+            // The largest square inside the circle
+            Self::Circle => Rect::from_center_size(pos2(0.5, 0.5), Vec2::splat(0.5_f32.sqrt())),
+            Self::Diamond => Rect::from_center_size(pos2(0.5, 0.5), Vec2::splat(0.5)),
+            Self::SpeechBubble => Rect::from_min_max(Pos2::ZERO, pos2(1.0, BUBBLE_BODY)),
+        }
+    }
+
+    /// The size a note of this shape takes when given `size`
+    pub fn fit_size(self, size: Vec2) -> Vec2 {
+        match self {
+            Self::Square | Self::Circle => Vec2::splat(size.max_elem()),
+            _ => size,
+        }
+    }
+}
+
+fn unit_rect(height: f32) -> Vec<Pos2> {
+    vec![
+        pos2(0.0, 0.0),
+        pos2(1.0, 0.0),
+        pos2(1.0, height),
+        pos2(0.0, height),
+    ]
+}
+
+fn circle() -> Vec<Pos2> {
+    (0..CIRCLE_POINTS)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_POINTS as f32 * std::f32::consts::TAU;
+            pos2(0.5 + 0.5 * angle.cos(), 0.5 + 0.5 * angle.sin())
+        })
+        .collect()
+}
+
+/// The speech bubble's tail: where it leaves the body, left then right, and
+/// its tip
+fn tail() -> [Pos2; 3] {
+    [
+        pos2(0.2, BUBBLE_BODY),
+        pos2(0.4, BUBBLE_BODY),
+        pos2(0.12, 1.0),
+    ]
+}
+
+/// Whether `p` is inside or on the convex polygon `points`, whichever way
+/// it winds
+fn in_convex(p: Pos2, points: &[Pos2]) -> bool {
+    let mut sign = 0.0_f32;
+    for (i, &a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let cross = (b - a).x * (p - a).y - (b - a).y * (p - a).x;
+        if cross == 0.0 {
+            continue;
+        }
+        if sign == 0.0 {
+            sign = cross.signum();
+        } else if cross.signum() != sign {
+            return false;
+        }
+    }
+    true
+}
+
+/// The point `unit` of the box with `corners` (top left, top right, bottom
+/// right, bottom left)
+pub fn unit_to_quad(unit: Pos2, corners: &[Pos2; 4]) -> Pos2 {
+    corners[0] + (corners[1] - corners[0]) * unit.x + (corners[3] - corners[0]) * unit.y
+}
+
+/// Where `p` is in the box with `corners`, in unit coordinates; `None` if
+/// the box is squashed flat
+pub fn quad_to_unit(p: Pos2, corners: &[Pos2; 4]) -> Option<Pos2> {
+    let across = corners[1] - corners[0];
+    let down = corners[3] - corners[0];
+    let det = across.x * down.y - across.y * down.x;
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let d = p - corners[0];
+    Some(pos2(
+        (d.x * down.y - d.y * down.x) / det,
+        (across.x * d.y - across.y * d.x) / det,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::note_corners;
+
+    #[test]
+    fn hits_follow_the_shape() {
+        let corners = note_corners(Pos2::ZERO, Vec2::splat(100.0), Vec2::ZERO, 0.3, Vec2::ZERO);
+        let at = |x: f32, y: f32| unit_to_quad(pos2(x, y), &corners);
+        // Corners of the box are outside the round and pointy shapes
+        for shape in [NoteShape::Circle, NoteShape::Diamond] {
+            assert!(shape.hit(at(0.5, 0.5), &corners));
+            assert!(!shape.hit(at(0.05, 0.05), &corners));
+        }
+        assert!(NoteShape::Rectangle.hit(at(0.05, 0.05), &corners));
+        assert!(!NoteShape::Rectangle.hit(at(1.1, 0.5), &corners));
+        // The bubble's tail counts, the gap beside it doesn't
+        assert!(NoteShape::SpeechBubble.hit(at(0.2, 0.85), &corners));
+        assert!(!NoteShape::SpeechBubble.hit(at(0.8, 0.9), &corners));
+
+        assert_eq!(
+            NoteShape::Circle.fit_size(Vec2::new(120.0, 80.0)),
+            Vec2::splat(120.0)
+        );
+        assert_eq!(
+            NoteShape::Diamond.fit_size(Vec2::new(120.0, 80.0)),
+            Vec2::new(120.0, 80.0)
+        );
+    }
+}
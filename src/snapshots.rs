@@ -0,0 +1,188 @@
+//! Periodic snapshots of a board for browsing and restoring past states
+
+use crate::{Board, encryption};
+use egui::Rect;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Most snapshots kept; the oldest are dropped first
+pub const MAX_SNAPSHOTS: usize = 200;
+
+/// The board as it was at one point in time
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch
+    pub taken_at: u64,
+    pub board: Board,
+}
+
+/// Snapshots of the boards, oldest first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Timeline {
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// Whether two boards hold the same content, ignoring where the view is
+fn same_content(a: &Board, b: &Board) -> bool {
+    let view = |board: &Board| Board {
+        scene_rect: Rect::NOTHING,
+        view_quarter_turns: 0,
+        ..board.clone()
+    };
+    view(a) == view(b)
+}
+
+impl Timeline {
+    /// File the history of the board saved at `save_path` is kept in
+    pub fn path_for(save_path: &Path) -> PathBuf {
+        let mut name = save_path.file_stem().unwrap_or_default().to_os_string();
+        name.push(".history");
+        save_path.with_file_name(name)
+    }
+
+    /// Take a snapshot of `board` unless it matches the latest one of the
+    /// same board.
+    ///
+    /// Returns whether a snapshot was added.
+    pub fn record(&mut self, board: &Board, now: u64) -> bool {
+        if self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|s| s.board.id == board.id)
+            .is_some_and(|last| same_content(&last.board, board))
+        {
+            return false;
+        }
+        self.snapshots.push(Snapshot {
+            taken_at: now,
+            board: board.clone(),
+        });
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            let excess = self.snapshots.len() - MAX_SNAPSHOTS;
+            self.snapshots.drain(..excess);
+        }
+        true
+    }
+
+    /// Indices of the snapshots of the board with id `board_id`
+    pub fn snapshots_of(&self, board_id: u64) -> Vec<usize> {
+        (0..self.snapshots.len())
+            .filter(|&i| self.snapshots[i].board.id == board_id)
+            .collect()
+    }
+
+    /// Save to JSON file, sealed with `password` like the save file if
+    /// there is one
+    pub fn save_to_file(&self, path: &Path, password: Option<&str>) -> io::Result<()> {
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        std::fs::write(path, encryption::seal_with(json, password)?)
+    }
+
+    /// Load from JSON file, opening it with `password` if it is sealed;
+    /// starts empty if there is none or it can't be parsed.
+    ///
+    /// `PermissionDenied` if it is sealed and no password is given.
+    pub fn load_from_file(path: &Path, password: Option<&str>) -> io::Result<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let data = if encryption::is_sealed(&bytes) {
+            let Some(password) = password else {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "the history is locked with a password",
+                ));
+            };
+            encryption::open(&bytes, password)?
+        } else {
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+        Ok(serde_json::from_str(&data).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn record_skips_unchanged_content() {
+        let mut history = Timeline::default();
+        let mut board = Board::default();
+        assert!(history.record(&board, 1));
+        // Panning the view is not a change
+        board.scene_rect = Rect::from_min_size(Pos2::new(5.0, 5.0), Vec2::splat(100.0));
+        assert!(!history.record(&board, 2));
+        board.notes.push(NoteData::default());
+        assert!(history.record(&board, 3));
+        let times: Vec<u64> = history.snapshots.iter().map(|s| s.taken_at).collect();
+        assert_eq!(times, [1, 3]);
+
+        // Another board is tracked separately
+        let other = Board {
+            id: 2,
+            ..Default::default()
+        };
+        assert!(history.record(&other, 4));
+        assert!(!history.record(&board, 5));
+        assert_eq!(history.snapshots_of(1), [0, 1]);
+        assert_eq!(history.snapshots_of(2), [2]);
+    }
+
+    #[test]
+    fn oldest_snapshots_are_dropped() {
+        let mut history = Timeline::default();
+        for i in 0..MAX_SNAPSHOTS as u64 + 5 {
+            let board = Board {
+                name: i.to_string(),
+                ..Default::default()
+            };
+            history.record(&board, i);
+        }
+        assert_eq!(history.snapshots.len(), MAX_SNAPSHOTS);
+        assert_eq!(history.snapshots[0].taken_at, 5);
+    }
+
+    #[test]
+    fn history_roundtrips_and_lives_next_to_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let save = dir.path().join("board.json");
+        let path = Timeline::path_for(&save);
+        assert_eq!(path, dir.path().join("board.history"));
+
+        let mut history = Timeline::default();
+        history.record(&Board::default(), 42);
+        history.save_to_file(&path, None).unwrap();
+        assert_eq!(Timeline::load_from_file(&path, None).unwrap(), history);
+    }
+
+    #[test]
+    fn history_is_sealed_with_the_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.history");
+        let board = Board {
+            notes: vec![NoteData {
+                text: "Salary talk".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut history = Timeline::default();
+        history.record(&board, 42);
+        history.save_to_file(&path, Some("secret")).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("Salary talk"));
+        let error = Timeline::load_from_file(&path, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(Timeline::load_from_file(&path, Some("wrong")).is_err());
+        let opened = Timeline::load_from_file(&path, Some("secret")).unwrap();
+        assert_eq!(opened, history);
+    }
+}
@@ -0,0 +1,146 @@
+//! Sound packs: a sound per board event, from the built-in plop or from a
+//! folder of sounds the user picks, played at a chosen volume
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Folder inside the assets folder holding one subfolder per sound pack
+pub const SOUND_PACKS_DIR: &str = "sounds";
+
+/// Sound every event makes without a pack, and when a pack lacks one
+pub const DEFAULT_SOUND: &str = "plop.wav";
+
+/// File types a pack's sounds are looked for in, preferred first
+const SOUND_EXTENSIONS: [&str; 2] = ["ogg", "wav"];
+
+/// Something happening on the board that makes a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    Create,
+    Delete,
+    /// A note was let go after dragging
+    DragStop,
+    /// A dragged note was let go and snapped to the grid
+    Snap,
+    /// A timer note ran out
+    TimerDone,
+}
+
+impl SoundEvent {
+    pub const ALL: [Self; 5] = [
+        Self::Create,
+        Self::Delete,
+        Self::DragStop,
+        Self::Snap,
+        Self::TimerDone,
+    ];
+
+    /// Name of the event's sound file in a pack, without extension
+    pub fn file_stem(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Delete => "delete",
+            Self::DragStop => "drag_stop",
+            Self::Snap => "snap",
+            Self::TimerDone => "timer_done",
+        }
+    }
+}
+
+/// How sounds are played, kept with the app settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SoundSettings {
+    /// Folder name of the chosen pack, `None` for the built-in plop
+    #[serde(default)]
+    pub pack: Option<String>,
+    /// Master volume, from 0 to 1
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            pack: None,
+            volume: default_volume(),
+            muted: false,
+        }
+    }
+}
+
+impl SoundSettings {
+    /// Factor every sound's volume is multiplied by
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume.clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Names of the sound packs in `assets_dir`, sorted
+pub fn list_packs(assets_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(assets_dir.join(SOUND_PACKS_DIR)) else {
+        return Vec::new();
+    };
+    let mut packs: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_str().map(str::to_owned))
+        .collect();
+    packs.sort();
+    packs
+}
+
+/// Asset path of the sound `event` makes in `pack` under `assets_dir`,
+/// falling back to the built-in plop
+pub fn sound_asset(assets_dir: &Path, pack: Option<&str>, event: SoundEvent) -> String {
+    pack.and_then(|pack| {
+        SOUND_EXTENSIONS.iter().find_map(|ext| {
+            let asset = format!("{SOUND_PACKS_DIR}/{pack}/{}.{ext}", event.file_stem());
+            assets_dir.join(&asset).is_file().then_some(asset)
+        })
+    })
+    .unwrap_or_else(|| DEFAULT_SOUND.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_fall_back_to_the_plop() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack = dir.path().join(SOUND_PACKS_DIR).join("clicky");
+        std::fs::create_dir_all(&pack).unwrap();
+        std::fs::write(pack.join("create.wav"), b"").unwrap();
+        std::fs::write(pack.join("create.ogg"), b"").unwrap();
+        assert_eq!(list_packs(dir.path()), ["clicky"]);
+        assert_eq!(
+            sound_asset(dir.path(), Some("clicky"), SoundEvent::Create),
+            "sounds/clicky/create.ogg"
+        );
+        assert_eq!(
+            sound_asset(dir.path(), Some("clicky"), SoundEvent::Delete),
+            DEFAULT_SOUND
+        );
+        assert_eq!(
+            sound_asset(dir.path(), None, SoundEvent::Create),
+            DEFAULT_SOUND
+        );
+
+        let muted = SoundSettings {
+            muted: true,
+            ..Default::default()
+        };
+        assert_eq!(muted.gain(), 0.0);
+        assert_eq!(SoundSettings::default().gain(), 1.0);
+    }
+}
@@ -0,0 +1,139 @@
+//! Finding notes by id or by area without scanning the whole board
+
+use crate::Board;
+use egui::Rect;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Side of a grid cell, in board coordinates
+pub const CELL_SIZE: f32 = 256.0;
+
+/// Notes bucketed into the square grid cells they overlap
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell: f32,
+    cells: HashMap<(i32, i32), Vec<u64>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell: f32) -> Self {
+        Self {
+            cell,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Range of cells `rect` overlaps on each axis
+    fn cell_range(&self, rect: Rect) -> (RangeInclusive<i32>, RangeInclusive<i32>) {
+        let cell = |v: f32| (v / self.cell).floor() as i32;
+        (
+            cell(rect.min.x)..=cell(rect.max.x),
+            cell(rect.min.y)..=cell(rect.max.y),
+        )
+    }
+
+    /// Add the note `id` occupying `rect`
+    pub fn insert(&mut self, id: u64, rect: Rect) {
+        let (xs, ys) = self.cell_range(rect);
+        for x in xs {
+            for y in ys.clone() {
+                self.cells.entry((x, y)).or_default().push(id);
+            }
+        }
+    }
+
+    /// Ids of the notes in cells overlapping `area`, sorted and without
+    /// duplicates. Notes near the edge of `area` may be included even if
+    /// they don't overlap it.
+    pub fn query(&self, area: Rect) -> Vec<u64> {
+        if !area.is_positive() {
+            return Vec::new();
+        }
+        let (xs, ys) = self.cell_range(area);
+        let mut ids = Vec::new();
+        // A huge area has more cells than there are filled ones
+        let cells = (*xs.end() as i64 - *xs.start() as i64 + 1)
+            * (*ys.end() as i64 - *ys.start() as i64 + 1);
+        if cells > self.cells.len() as i64 {
+            for (&(x, y), bucket) in &self.cells {
+                if xs.contains(&x) && ys.contains(&y) {
+                    ids.extend(bucket);
+                }
+            }
+        } else {
+            for x in xs {
+                for y in ys.clone() {
+                    ids.extend(self.cells.get(&(x, y)).into_iter().flatten());
+                }
+            }
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+impl Board {
+    /// Position of each note in `notes`, by id
+    pub fn note_index(&self) -> HashMap<u64, usize> {
+        self.notes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id, i))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+    use egui::{Pos2, Vec2};
+
+    fn board() -> Board {
+        let note = |id: u64, x: f32, y: f32| NoteData {
+            id,
+            pos: Pos2::new(x, y),
+            size: Vec2::new(100.0, 80.0),
+            ..Default::default()
+        };
+        Board {
+            notes: vec![
+                note(1, 0.0, 0.0),
+                note(2, 200.0, 0.0),
+                note(3, 5000.0, 5000.0),
+                note(4, -900.0, -300.0),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn query_finds_notes_in_nearby_cells_only() {
+        let mut grid = SpatialGrid::default();
+        for note in board().notes {
+            grid.insert(note.id, Rect::from_min_size(note.pos, note.size));
+        }
+        let view = Rect::from_min_size(Pos2::new(-50.0, -50.0), Vec2::new(400.0, 300.0));
+        // Note 2 straddles two cells but is reported once
+        assert_eq!(grid.query(view), [1, 2]);
+        let far = Rect::from_min_size(Pos2::new(4900.0, 4900.0), Vec2::splat(200.0));
+        assert_eq!(grid.query(far), [3]);
+        let everything = Rect::from_min_max(Pos2::splat(-1e6), Pos2::splat(1e6));
+        assert_eq!(grid.query(everything), [1, 2, 3, 4]);
+        assert!(grid.query(Rect::NOTHING).is_empty());
+    }
+
+    #[test]
+    fn index_maps_ids_to_positions() {
+        let index = board().note_index();
+        assert_eq!(index[&3], 2);
+        assert_eq!(index.get(&9), None);
+    }
+}
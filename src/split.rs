@@ -0,0 +1,93 @@
+//! Split view: a second view beside the board, of it or of another board,
+//! that notes can be dragged across to
+
+use crate::AppState;
+use crate::history::Command;
+use egui::Vec2;
+
+impl AppState {
+    /// Move the notes `ids` of the board at `from` onto the board at `to`,
+    /// shifted by `offset`, above its notes. Returns the changes for undo:
+    /// their removal from `from` and their arrival on `to`; `None` if no
+    /// note moved. Recording the arrival first means undoing halfway leaves
+    /// the notes on both boards rather than on neither.
+    pub fn move_notes_to_board(
+        &mut self,
+        from: usize,
+        ids: &[u64],
+        to: usize,
+        offset: Vec2,
+    ) -> Option<(Command, Command)> {
+        if from == to || from >= self.boards.len() || to >= self.boards.len() {
+            return None;
+        }
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        let z = self.boards[to].top_z();
+        for &id in ids {
+            let source = &mut self.boards[from];
+            let Some(index) = source.notes.iter().position(|n| n.id == id) else {
+                continue;
+            };
+            let note = source.notes.remove(index);
+            let mut moved = note.clone();
+            moved.pos += offset;
+            moved.z = z + added.len() as i32;
+            moved.lane = None;
+            let target = &mut self.boards[to];
+            target.place_in_lane(&mut moved);
+            target.notes.push(moved.clone());
+            removed.push(Command::Delete { note, index });
+            added.push(Command::Create { note: moved });
+        }
+        Some((Command::batch(removed)?, Command::batch(added)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::UndoStack;
+    use egui::Pos2;
+
+    #[test]
+    fn notes_move_between_boards() {
+        let mut state = AppState::default();
+        let a = state.add_note("travel", Pos2::new(10.0, 10.0));
+        let b = state.add_note("stays", Pos2::ZERO);
+        let other = state.add_board("Later");
+        state.boards[other].notes.push(crate::NoteData {
+            id: 99,
+            z: 4,
+            ..Default::default()
+        });
+
+        let (removed, added) = state
+            .move_notes_to_board(0, &[a], other, Vec2::new(5.0, 0.0))
+            .unwrap();
+        assert_eq!(state.boards[0].notes.len(), 1);
+        assert_eq!(state.boards[0].notes[0].id, b);
+        let moved = state.boards[other].notes.last().unwrap();
+        assert_eq!(
+            (moved.id, moved.pos, moved.z),
+            (a, Pos2::new(15.0, 10.0), 5)
+        );
+        assert!(
+            state
+                .move_notes_to_board(0, &[a], other, Vec2::ZERO)
+                .is_none()
+        );
+        assert!(state.move_notes_to_board(0, &[b], 0, Vec2::ZERO).is_none());
+
+        // Each board undoes its side of the move
+        let mut undo = UndoStack::default();
+        let (source, target) = (state.boards[0].id, state.boards[other].id);
+        undo.record(target, added);
+        undo.record(source, removed);
+        undo.undo(&mut state);
+        assert_eq!(state.boards[0].notes.len(), 2);
+        assert!(state.boards[0].trash.is_empty());
+        undo.undo(&mut state);
+        assert_eq!(state.boards[other].notes.len(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+//! CSV files of notes, a row per note, for working on boards in
+//! spreadsheets or making them with other tools
+
+use crate::import::IMPORT_COLUMNS;
+use crate::palette::sticky_colors;
+use crate::tags::{format_tags, parse_tags};
+use crate::{Board, NoteData};
+use egui::{Color32, Pos2, Rect, Vec2};
+
+/// Columns of an exported file, in order
+pub const CSV_COLUMNS: [&str; 8] = [
+    "id",
+    "text",
+    "x",
+    "y",
+    "color",
+    "tags",
+    "created_at",
+    "modified_at",
+];
+
+/// Space between imported notes laid out in a grid
+const IMPORT_GAP: f32 = 20.0;
+
+/// The notes of `board` as CSV, a row per note in stacking order; times are
+/// in seconds since the Unix epoch
+pub fn board_to_csv(board: &Board) -> String {
+    let mut out = Vec::new();
+    let mut writer = csv::Writer::from_writer(&mut out);
+    let rows = std::iter::once(CSV_COLUMNS.map(String::from)).chain(board.notes.iter().map(|n| {
+        let [r, g, b, _] = n.color.to_array();
+        let time = |t: Option<u64>| t.map(|t| t.to_string()).unwrap_or_default();
+        [
+            n.id.to_string(),
+            n.text.clone(),
+            n.pos.x.to_string(),
+            n.pos.y.to_string(),
+            format!("#{r:02x}{g:02x}{b:02x}"),
+            format_tags(&n.tags),
+            time(n.created_at),
+            time(n.modified_at),
+        ]
+    }));
+    for row in rows {
+        writer.write_record(&row).expect("writing to memory");
+    }
+    writer.flush().expect("writing to memory");
+    drop(writer);
+    String::from_utf8(out).expect("written as UTF-8")
+}
+
+/// Color written as a sticky-note color name or a hex code
+fn parse_color(value: &str) -> Option<Color32> {
+    sticky_colors()
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(value))
+        .map(|c| c.color)
+        .or_else(|| Color32::from_hex(&format!("#{}", value.trim_start_matches('#'))).ok())
+}
+
+/// Notes for the rows of the CSV `data`, found by the names in its header
+/// row, in any order and case. Only `text` is needed; the `id` column is
+/// ignored and ids come from `next_note_id`. Rows with `x` and `y` keep
+/// their layout, moved so it starts at `origin`; the rest are laid out in a
+/// grid below them. Notes without a color get `color`.
+pub fn csv_to_notes(
+    data: &str,
+    origin: Pos2,
+    size: Vec2,
+    color: Color32,
+    next_note_id: &mut u64,
+) -> Result<Vec<NoteData>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::Headers)
+        .from_reader(data.as_bytes());
+    let header = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let text = column("text").ok_or("the file has no \"text\" column")?;
+    let [x, y, color_column, tags, created_at, modified_at] =
+        ["x", "y", "color", "tags", "created_at", "modified_at"].map(column);
+
+    let mut notes = Vec::new();
+    let mut placed = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| e.to_string())?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let number = |column| {
+            field(column)
+                .and_then(|v| v.parse::<f32>().ok())
+                .filter(|v| v.is_finite())
+        };
+        let time = |column| field(column).and_then(|v| v.parse::<u64>().ok());
+        let color = match field(color_column) {
+            Some(value) => parse_color(value)
+                .ok_or_else(|| format!("row {}: unknown color {value:?}", line + 2))?,
+            None => color,
+        };
+        let id = *next_note_id;
+        *next_note_id += 1;
+        let pos = number(x).zip(number(y)).map(|(x, y)| Pos2::new(x, y));
+        placed.push(pos.is_some());
+        notes.push(NoteData {
+            id,
+            text: record.get(text).unwrap_or_default().to_owned(),
+            pos: pos.unwrap_or_default(),
+            size,
+            color,
+            tags: field(tags).map(parse_tags).unwrap_or_default(),
+            created_at: time(created_at),
+            modified_at: time(modified_at),
+            ..Default::default()
+        });
+    }
+
+    let layout = notes
+        .iter()
+        .zip(&placed)
+        .filter(|(_, placed)| **placed)
+        .map(|(n, _)| Rect::from_min_size(n.pos, n.size))
+        .reduce(Rect::union);
+    let offset = layout.map_or(Vec2::ZERO, |r| origin - r.min);
+    let top = layout.map_or(origin.y, |r| origin.y + r.height() + IMPORT_GAP);
+    let step = size + Vec2::splat(IMPORT_GAP);
+    let mut unplaced = 0;
+    for (note, placed) in notes.iter_mut().zip(placed) {
+        if placed {
+            note.pos += offset;
+        } else {
+            let (row, column) = (unplaced / IMPORT_COLUMNS, unplaced % IMPORT_COLUMNS);
+            note.pos = Pos2::new(origin.x, top) + Vec2::new(column as f32, row as f32) * step;
+            unplaced += 1;
+        }
+    }
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_import_back() {
+        let board = Board {
+            notes: vec![
+                NoteData {
+                    id: 4,
+                    text: "Buy milk, \"fresh\"\nand bread".into(),
+                    pos: Pos2::new(100.0, 50.0),
+                    size: Vec2::splat(80.0),
+                    color: Color32::from_rgb(170, 200, 255),
+                    tags: vec!["home".into(), "errand".into()],
+                    created_at: Some(1_700_000_000),
+                    ..Default::default()
+                },
+                NoteData {
+                    id: 9,
+                    text: "Call back".into(),
+                    pos: Pos2::new(300.0, 150.0),
+                    size: Vec2::splat(80.0),
+                    color: Color32::YELLOW,
+                    modified_at: Some(1_700_000_500),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let csv = board_to_csv(&board);
+        assert!(csv.starts_with("id,text,x,y,color,tags,created_at,modified_at\n"));
+
+        let mut next_note_id = 20;
+        let size = Vec2::splat(80.0);
+        let notes =
+            csv_to_notes(&csv, Pos2::ZERO, size, Color32::WHITE, &mut next_note_id).unwrap();
+        assert_eq!(next_note_id, 22);
+        for (imported, original) in notes.iter().zip(&board.notes) {
+            assert_eq!(
+                NoteData {
+                    id: original.id,
+                    pos: imported.pos + Vec2::new(100.0, 50.0),
+                    ..imported.clone()
+                },
+                *original
+            );
+        }
+    }
+
+    #[test]
+    fn generated_files_need_only_text() {
+        let csv = "Tags, Text ,Color\nwork,Plan sprint,pink\n,Retro,\n";
+        let mut next_note_id = 1;
+        let size = Vec2::splat(10.0);
+        let notes = csv_to_notes(
+            csv,
+            Pos2::new(5.0, 5.0),
+            size,
+            Color32::WHITE,
+            &mut next_note_id,
+        )
+        .unwrap();
+        assert_eq!(notes[0].text, "Plan sprint");
+        assert_eq!(notes[0].tags, ["work"]);
+        assert_eq!(notes[0].color, Color32::from_rgb(255, 170, 200));
+        assert_eq!(notes[1].color, Color32::WHITE);
+        assert_eq!(notes[1].pos, Pos2::new(35.0, 5.0));
+
+        let broken = format!("{csv}x,Broken,mauve\n");
+        let result = csv_to_notes(&broken, Pos2::ZERO, size, Color32::WHITE, &mut next_note_id);
+        assert_eq!(result.unwrap_err(), "row 4: unknown color \"mauve\"");
+        assert!(csv_to_notes("title\nx\n", Pos2::ZERO, size, Color32::WHITE, &mut 1).is_err());
+
+        // Coordinates that aren't numbers leave the note to be laid out
+        let odd = "text,x,y\nNowhere,NaN,inf\n";
+        let notes = csv_to_notes(odd, Pos2::new(5.0, 5.0), size, Color32::WHITE, &mut 1).unwrap();
+        assert_eq!(notes[0].pos, Pos2::new(5.0, 5.0));
+    }
+}
@@ -0,0 +1,146 @@
+//! Board statistics: how many notes of which color and tag, how much text,
+//! how far checklists are done and when notes were made
+
+use crate::checklist;
+use crate::expiry::DAY;
+use crate::history::Command;
+use crate::{Board, NoteData};
+use egui::Color32;
+
+/// Figures about the notes of a board
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BoardStats {
+    pub notes: usize,
+    pub words: usize,
+    /// Notes per color, the most common first
+    pub colors: Vec<(Color32, usize)>,
+    /// Notes per tag, the most common first
+    pub tags: Vec<(String, usize)>,
+    /// Checked and all checklist items
+    pub checklist: (usize, usize),
+    /// Notes made per day, as the start of the day, oldest first
+    pub timeline: Vec<(u64, usize)>,
+    /// Notes made before creation times were kept
+    pub undated: usize,
+}
+
+impl BoardStats {
+    pub fn of(board: &Board) -> Self {
+        let mut stats = Self {
+            notes: board.notes.len(),
+            ..Default::default()
+        };
+        for note in &board.notes {
+            // Checkboxes and lone punctuation are no words
+            stats.words += note
+                .text
+                .lines()
+                .map(|line| checklist::parse_item(line).map_or(line, |(_, text)| text))
+                .flat_map(str::split_whitespace)
+                .filter(|word| word.chars().any(char::is_alphanumeric))
+                .count();
+            count(&mut stats.colors, note.color);
+            for tag in &note.tags {
+                count(&mut stats.tags, tag.clone());
+            }
+            let (checked, total) = checklist::progress(&note.text);
+            stats.checklist.0 += checked;
+            stats.checklist.1 += total;
+            match note.created_at {
+                Some(at) => count(&mut stats.timeline, at / DAY * DAY),
+                None => stats.undated += 1,
+            }
+        }
+        // Colors tied keep the order they first turn up in, tags go by name
+        stats.colors.sort_by(|a, b| b.1.cmp(&a.1));
+        stats
+            .tags
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        stats.timeline.sort_unstable();
+        stats
+    }
+
+    /// Share of checklist items that are checked, in percent
+    pub fn checklist_percent(&self) -> Option<f32> {
+        let (checked, total) = self.checklist;
+        (total > 0).then(|| checked as f32 * 100.0 / total as f32)
+    }
+}
+
+fn count<K: PartialEq>(counts: &mut Vec<(K, usize)>, key: K) {
+    match counts.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, n)) => *n += 1,
+        None => counts.push((key, 1)),
+    }
+}
+
+/// Stamp the notes `command` makes or changes with the time `now`,
+/// returning them as stamped
+pub fn stamp(command: &mut Command, now: u64) -> Vec<NoteData> {
+    match command {
+        Command::Create { note } => {
+            note.created_at = Some(now);
+            note.modified_at = Some(now);
+            vec![note.clone()]
+        }
+        Command::Update { before, after } if before != after => {
+            after.modified_at = Some(now);
+            vec![after.clone()]
+        }
+        Command::Batch(commands) => commands.iter_mut().flat_map(|c| stamp(c, now)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+
+    #[test]
+    fn stats_count_notes_words_and_checklists() {
+        let mut state = AppState::default();
+        state.add_note("[x] milk\n[ ] eggs", Pos2::ZERO);
+        state.add_note("three whole words", Pos2::ZERO);
+        let board = state.board_mut();
+        board.notes[0].tags = vec!["shop".into()];
+        board.notes[1].color = Color32::RED;
+        board.notes[1].created_at = Some(3 * DAY + 5);
+        let stats = BoardStats::of(board);
+        assert_eq!(stats.notes, 2);
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.colors[0].1, 1);
+        assert_eq!(stats.tags, [("shop".to_string(), 1)]);
+        assert_eq!(stats.checklist_percent(), Some(50.0));
+        assert_eq!(stats.timeline, [(3 * DAY, 1)]);
+        assert_eq!(stats.undated, 1);
+    }
+
+    #[test]
+    fn only_real_changes_are_stamped() {
+        let note = NoteData::default();
+        let mut create = Command::Create { note: note.clone() };
+        let mut unchanged = Command::Update {
+            before: note.clone(),
+            after: note.clone(),
+        };
+        assert!(stamp(&mut unchanged, 7).is_empty());
+        assert_eq!(stamp(&mut create, 7)[0].created_at, Some(7));
+
+        let edited = NoteData {
+            text: "new".into(),
+            created_at: Some(1),
+            ..note.clone()
+        };
+        let mut batch = Command::Batch(vec![Command::Update {
+            before: note,
+            after: edited,
+        }]);
+        let stamped = stamp(&mut batch, 9);
+        assert_eq!(
+            (stamped[0].created_at, stamped[0].modified_at),
+            (Some(1), Some(9))
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Where save files are kept: files on disk natively, the browser's local
+//! storage on the web, keyed by the same paths
+
+use std::io;
+use std::path::Path;
+
+/// A place save files can be read from and written to
+pub trait Storage {
+    /// What is stored under `path`; `NotFound` if nothing is
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Replace what is stored under `path` with `contents`
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// Store a copy of what is under `from` under `to`
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+}
+
+/// Files on disk, replaced in one step with earlier versions kept
+#[derive(Debug, Clone, Copy)]
+pub struct FileStorage {
+    /// Number of earlier versions kept next to each file
+    pub backups: usize,
+}
+
+impl Storage for FileStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        crate::backups::write_with_backups(path, contents, self.backups)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// The browser's local storage, which survives reloads of the page
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy)]
+pub struct BrowserStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl BrowserStorage {
+    fn local() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no local storage"))
+    }
+
+    /// Key of the item holding `path`
+    fn key(path: &Path) -> String {
+        format!("plop:{}", path.display())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for BrowserStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        Self::local()?
+            .get_item(&Self::key(path))
+            .map_err(|e| io::Error::other(format!("{e:?}")))?
+            .map(String::into_bytes)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        Self::local()?
+            .set_item(&Self::key(path), contents)
+            .map_err(|e| io::Error::other(format!("{e:?}")))
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let contents = self.read(from)?;
+        self.write(to, &String::from_utf8_lossy(&contents))
+    }
+}
+
+/// Storage of the platform the app runs on
+#[cfg(not(target_arch = "wasm32"))]
+pub fn platform_storage() -> FileStorage {
+    FileStorage {
+        backups: crate::backups::SAVE_BACKUPS,
+    }
+}
+
+/// Storage of the platform the app runs on
+#[cfg(target_arch = "wasm32")]
+pub fn platform_storage() -> BrowserStorage {
+    BrowserStorage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    /// Storage in memory, standing in for one the tests can't reach
+    #[derive(Default)]
+    struct MemoryStorage(RefCell<HashMap<PathBuf, String>>);
+
+    impl Storage for MemoryStorage {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.0
+                .borrow()
+                .get(path)
+                .map(|s| s.clone().into_bytes())
+                .ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+
+        fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+            self.0.borrow_mut().insert(path.into(), contents.into());
+            Ok(())
+        }
+
+        fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let contents = self.read(from)?;
+            self.write(to, &String::from_utf8_lossy(&contents))
+        }
+    }
+
+    #[test]
+    fn boards_round_trip_through_any_storage() {
+        let storage = MemoryStorage::default();
+        let path = Path::new("boards.json");
+        let error = AppState::try_load_from(&storage, path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        let mut state = AppState::default();
+        state.add_note("Stored", Pos2::ZERO);
+        state.save_to(&storage, path).unwrap();
+        let loaded = AppState::try_load_from(&storage, path).unwrap();
+        assert_eq!(loaded, state);
+
+        // A sealed save needs its password, and isn't set aside without it
+        let sealed = state.to_saved(Some("secret")).unwrap();
+        storage.write(path, &sealed).unwrap();
+        let error = AppState::try_load_from(&storage, path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(AppState::load_from(&storage, path), AppState::default());
+        assert!(storage.read(&crate::backup_path(path)).is_err());
+        let opened = AppState::try_load_sealed_from(&storage, path, Some("secret")).unwrap();
+        assert_eq!(opened, state);
+
+        storage.write(path, "not json").unwrap();
+        assert_eq!(AppState::load_from(&storage, path), AppState::default());
+        // The unreadable file is kept aside
+        assert_eq!(
+            storage.read(&crate::backup_path(path)).unwrap(),
+            b"not json"
+        );
+    }
+}
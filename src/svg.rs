@@ -0,0 +1,273 @@
+//! Exporting boards as SVG drawings that vector tools can edit
+
+use crate::Board;
+use crate::connectors::{ARROW_SIZE, CONNECTOR_COLOR, ConnectorStyle, arrow_head, connector_ends};
+use crate::export::board_extent;
+use crate::frames::title_bar;
+use crate::markdown::TEXT_COLOR;
+use crate::note_font::{FontDefaults, TextFont};
+use egui::{Color32, Rect};
+use std::fmt::Write;
+
+/// Corner radius of exported notes
+const NOTE_ROUNDING: f32 = 4.0;
+
+/// Space between a note's border and its text
+const TEXT_PADDING: f32 = 6.0;
+
+/// The whole board as an SVG document in board units: frames, ink,
+/// connectors and notes with their text, in drawing order
+pub fn board_to_svg(board: &Board) -> String {
+    let extent = board_extent(board);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+        x = extent.min.x,
+        y = extent.min.y,
+        w = extent.width(),
+        h = extent.height(),
+    );
+    let _ = writeln!(out, "  <title>{}</title>", escape(&board.name));
+    let _ = writeln!(out, "  {}", rect(extent, 0.0, board.background));
+
+    for frame in &board.frames {
+        let bar = title_bar(frame.rect);
+        let _ = writeln!(out, "  {}", rect(frame.rect, 4.0, frame.fill()));
+        let _ = writeln!(out, "  {}", rect(bar, 4.0, frame.color));
+        let _ = writeln!(
+            out,
+            r#"  <text x="{}" y="{}" font-family="sans-serif" font-size="14" dominant-baseline="central"{}>{}</text>"#,
+            bar.min.x + 8.0,
+            bar.center().y,
+            fill(Color32::WHITE),
+            escape(&frame.title)
+        );
+    }
+
+    for stroke in &board.strokes {
+        let points: Vec<String> = stroke
+            .points
+            .iter()
+            .map(|p| format!("{},{}", p.x, p.y))
+            .collect();
+        let _ = writeln!(
+            out,
+            r#"  <polyline points="{}" fill="none"{} stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            points.join(" "),
+            paint("stroke", stroke.color),
+            stroke.width
+        );
+    }
+
+    let rect_of = |id: u64| {
+        board
+            .notes
+            .iter()
+            .find(|n| n.id == id)
+            .map(|n| Rect::from_min_size(n.pos, n.size))
+    };
+    for connector in &board.connectors {
+        let (Some(from), Some(to)) = (rect_of(connector.from_note), rect_of(connector.to_note))
+        else {
+            continue;
+        };
+        let (start, end) = connector_ends(from, to);
+        let dashes = if connector.style == ConnectorStyle::Dashed {
+            r#" stroke-dasharray="8 5""#
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            r#"  <line x1="{}" y1="{}" x2="{}" y2="{}"{} stroke-width="2"{dashes}/>"#,
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            paint("stroke", CONNECTOR_COLOR)
+        );
+        if connector.style.has_head() && start != end {
+            let [a, b] = arrow_head(start, end, ARROW_SIZE);
+            let _ = writeln!(
+                out,
+                r#"  <polygon points="{},{} {},{} {},{}"{}/>"#,
+                end.x,
+                end.y,
+                a.x,
+                a.y,
+                b.x,
+                b.y,
+                fill(CONNECTOR_COLOR)
+            );
+        }
+        if !connector.label.is_empty() {
+            let middle = start + (end - start) * 0.5;
+            let _ = writeln!(
+                out,
+                r#"  <text x="{}" y="{}" font-family="sans-serif" font-size="12" text-anchor="middle"{}>{}</text>"#,
+                middle.x,
+                middle.y - 4.0,
+                fill(CONNECTOR_COLOR),
+                escape(&connector.label)
+            );
+        }
+    }
+
+    for note in board.notes_by_z() {
+        let area = Rect::from_min_size(note.pos, note.size);
+        let center = area.center();
+        if note.rotation == 0.0 {
+            let _ = writeln!(out, "  <g>");
+        } else {
+            let _ = writeln!(
+                out,
+                r#"  <g transform="rotate({} {} {})">"#,
+                note.rotation.to_degrees(),
+                center.x,
+                center.y
+            );
+        }
+        let _ = writeln!(out, "    {}", rect(area, NOTE_ROUNDING, note.color));
+        let lines: Vec<&str> = note.text.lines().collect();
+        if !lines.is_empty() {
+            let font = note.font.resolve(&FontDefaults::default());
+            let size = font_size(&lines, area, &font);
+            let top = center.y - size * 1.2 * (lines.len() - 1) as f32 / 2.0;
+            let weight = if font.bold {
+                r#" font-weight="bold""#
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                out,
+                r#"    <text x="{}" y="{top}" font-family="{}" font-size="{size}"{weight} text-anchor="middle" dominant-baseline="central"{}>"#,
+                center.x,
+                font.family.css_family(),
+                fill(TEXT_COLOR)
+            );
+            for (i, line) in lines.iter().enumerate() {
+                let dy = if i == 0 { 0.0 } else { size * 1.2 };
+                let _ = writeln!(
+                    out,
+                    r#"      <tspan x="{}" dy="{dy}">{}</tspan>"#,
+                    center.x,
+                    escape(line)
+                );
+            }
+            let _ = writeln!(out, "    </text>");
+        }
+        let _ = writeln!(out, "  </g>");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Font size at which `lines` roughly fit inside `area`, from an average
+/// glyph width of half the font size, within the sizes `font` allows
+fn font_size(lines: &[&str], area: Rect, font: &TextFont) -> f32 {
+    let longest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let width = (area.width() - 2.0 * TEXT_PADDING).max(1.0);
+    let height = (area.height() - 2.0 * TEXT_PADDING).max(1.0);
+    let by_width = width / (longest.max(1) as f32 * 0.5);
+    let by_height = height / (lines.len() as f32 * 1.2);
+    by_width.min(by_height).clamp(font.min_size, font.max_size)
+}
+
+/// A filled `<rect>` over `area`
+fn rect(area: Rect, rounding: f32, color: Color32) -> String {
+    format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{rounding}"{}/>"#,
+        area.min.x,
+        area.min.y,
+        area.width(),
+        area.height(),
+        fill(color)
+    )
+}
+
+fn fill(color: Color32) -> String {
+    paint("fill", color)
+}
+
+/// The `attribute` (fill or stroke) painting in `color`, with its opacity
+/// if it is translucent
+fn paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut out = format!(r##" {attribute}="#{r:02x}{g:02x}{b:02x}""##);
+    if a < 255 {
+        let _ = write!(out, r#" {attribute}-opacity="{:.3}""#, a as f32 / 255.0);
+    }
+    out
+}
+
+/// `text` with the characters XML gives a meaning escaped
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+    use crate::connectors::Connector;
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn notes_become_rounded_rects_with_their_text() {
+        let note = |id: u64, text: &str, x: f32| NoteData {
+            id,
+            text: text.into(),
+            pos: Pos2::new(x, 0.0),
+            size: Vec2::new(100.0, 80.0),
+            color: Color32::from_rgb(0xff, 0xee, 0x88),
+            ..Default::default()
+        };
+        let mut tilted = note(2, "b", 200.0);
+        tilted.rotation = std::f32::consts::FRAC_PI_2;
+        let board = Board {
+            name: "Plans & <ideas>".into(),
+            notes: vec![note(1, "Buy milk\nand <eggs>", 0.0), tilted],
+            connectors: vec![Connector {
+                from_note: 1,
+                to_note: 2,
+                style: ConnectorStyle::Dashed,
+                label: String::new(),
+            }],
+            ..Default::default()
+        };
+        let svg = board_to_svg(&board);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains("<title>Plans &amp; &lt;ideas&gt;</title>"));
+        assert!(
+            svg.contains(r##"<rect x="0" y="0" width="100" height="80" rx="4" fill="#ffee88"/>"##)
+        );
+        assert!(svg.contains(">Buy milk</tspan>"));
+        assert!(svg.contains(">and &lt;eggs&gt;</tspan>"));
+        // Turned around its center, 250 40
+        assert!(svg.contains(r#"<g transform="rotate("#) && svg.contains(r#" 250 40)">"#));
+        assert!(svg.contains(r#"stroke-dasharray="8 5""#));
+        assert!(svg.contains("<polygon"));
+    }
+
+    #[test]
+    fn translucent_colors_keep_their_opacity() {
+        assert_eq!(fill(Color32::RED), r##" fill="#ff0000""##);
+        assert_eq!(
+            paint("stroke", Color32::from_black_alpha(128)),
+            r##" stroke="#000000" stroke-opacity="0.502""##
+        );
+    }
+}
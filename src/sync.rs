@@ -0,0 +1,651 @@
+//! Sharing a board between instances on the local network
+//!
+//! One instance hosts, others join over TCP; messages are JSON lines. Every
+//! note change carries a [`Stamp`] and the latest stamp wins, so when two
+//! people edit the same note at once the later edit is kept everywhere. The
+//! host relays what each peer sends to the others.
+//!
+//! Host and peer first prove to each other that they know the session key,
+//! so nobody else on the network can join; the messages themselves are not
+//! encrypted.
+
+use crate::NoteData;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Port used when the address doesn't name one
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// How often the host checks for new peers
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+/// How long proving the session key may take
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a write to a peer may stall before the peer is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest message accepted; a peer sending a longer one is dropped
+const MAX_LINE_BYTES: usize = 8 << 20;
+
+/// Current time for stamping changes, in milliseconds since the Unix epoch
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// When a change was made and by whom; later stamps win
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stamp {
+    /// Milliseconds since the Unix epoch, never going backwards per peer
+    pub millis: u64,
+    /// Breaks ties between changes made in the same millisecond
+    pub peer: u64,
+}
+
+/// What peers send each other
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SyncMessage {
+    /// Every note of the host's board, sent to a peer that just joined
+    Snapshot { notes: Vec<NoteData> },
+    /// A note was created or changed
+    Upsert { note: NoteData, stamp: Stamp },
+    /// A note was deleted
+    Remove { id: u64, stamp: Stamp },
+}
+
+/// A change from another peer to make to the local board
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteChange {
+    Upsert(NoteData),
+    Remove(u64),
+}
+
+impl RemoteChange {
+    /// Make the change to `notes`
+    pub fn apply(&self, notes: &mut Vec<NoteData>) {
+        match self {
+            RemoteChange::Upsert(note) => match notes.iter_mut().find(|n| n.id == note.id) {
+                Some(n) => *n = note.clone(),
+                None => notes.push(note.clone()),
+            },
+            RemoteChange::Remove(id) => notes.retain(|n| n.id != *id),
+        }
+    }
+}
+
+/// What this instance last knew about each note, to find local changes
+/// worth sending and to drop remote changes that lost to newer ones
+#[derive(Debug, Clone)]
+pub struct SyncState {
+    pub peer: u64,
+    known: HashMap<u64, NoteData>,
+    /// Latest stamp per note id, kept after deletion so a late edit doesn't
+    /// bring the note back
+    stamps: HashMap<u64, Stamp>,
+    clock: u64,
+}
+
+impl SyncState {
+    /// Start tracking `notes`, which peers are assumed to have already
+    pub fn new(peer: u64, notes: &[NoteData]) -> Self {
+        Self {
+            peer,
+            known: notes.iter().map(|n| (n.id, n.clone())).collect(),
+            stamps: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// First note id this peer uses after joining, so notes created on
+    /// different machines at the same time don't share ids
+    pub fn first_note_id(&self) -> u64 {
+        ((self.peer & 0xffff) + 1) << 32
+    }
+
+    fn stamp(&mut self, now_millis: u64) -> Stamp {
+        self.clock = now_millis.max(self.clock + 1);
+        Stamp {
+            millis: self.clock,
+            peer: self.peer,
+        }
+    }
+
+    /// Messages for whatever changed in `notes` since the last call
+    pub fn local_changes(&mut self, notes: &[NoteData], now_millis: u64) -> Vec<SyncMessage> {
+        let mut messages = Vec::new();
+        for note in notes {
+            if self.known.get(&note.id) == Some(note) {
+                continue;
+            }
+            let stamp = self.stamp(now_millis);
+            self.stamps.insert(note.id, stamp);
+            self.known.insert(note.id, note.clone());
+            messages.push(SyncMessage::Upsert {
+                note: note.clone(),
+                stamp,
+            });
+        }
+        let present: HashSet<u64> = notes.iter().map(|n| n.id).collect();
+        let mut removed: Vec<u64> = self
+            .known
+            .keys()
+            .filter(|id| !present.contains(id))
+            .copied()
+            .collect();
+        removed.sort_unstable();
+        for id in removed {
+            let stamp = self.stamp(now_millis);
+            self.stamps.insert(id, stamp);
+            self.known.remove(&id);
+            messages.push(SyncMessage::Remove { id, stamp });
+        }
+        messages
+    }
+
+    /// Changes to make for a message from another peer; nothing if the
+    /// note has since been changed more recently
+    pub fn receive(&mut self, message: SyncMessage) -> Vec<RemoteChange> {
+        match message {
+            SyncMessage::Snapshot { notes } => {
+                let present: HashSet<u64> = notes.iter().map(|n| n.id).collect();
+                let mut changes: Vec<RemoteChange> = self
+                    .known
+                    .keys()
+                    .filter(|id| !present.contains(id))
+                    .map(|&id| RemoteChange::Remove(id))
+                    .collect();
+                self.known = notes.iter().map(|n| (n.id, n.clone())).collect();
+                self.stamps.clear();
+                changes.extend(notes.into_iter().map(RemoteChange::Upsert));
+                changes
+            }
+            SyncMessage::Upsert { note, stamp } => {
+                if !self.accept(note.id, stamp) {
+                    return Vec::new();
+                }
+                self.known.insert(note.id, note.clone());
+                vec![RemoteChange::Upsert(note)]
+            }
+            SyncMessage::Remove { id, stamp } => {
+                if !self.accept(id, stamp) {
+                    return Vec::new();
+                }
+                self.known.remove(&id);
+                vec![RemoteChange::Remove(id)]
+            }
+        }
+    }
+
+    /// Whether a change to note `id` stamped `stamp` is the newest yet
+    fn accept(&mut self, id: u64, stamp: Stamp) -> bool {
+        self.clock = self.clock.max(stamp.millis);
+        if self.stamps.get(&id).is_some_and(|&known| known >= stamp) {
+            return false;
+        }
+        self.stamps.insert(id, stamp);
+        true
+    }
+}
+
+/// Something that happened on the connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncEvent {
+    /// A peer joined the host; its id can be passed to
+    /// [`SyncConnection::send_to`]
+    Joined(u64),
+    /// A peer left, or the host went away
+    Left(u64),
+    Message(SyncMessage),
+}
+
+/// A connected instance
+struct Peer {
+    id: u64,
+    /// Kept to cut the connection
+    stream: TcpStream,
+    /// Lines for the thread writing to the peer
+    lines: Sender<Arc<[u8]>>,
+}
+
+type Peers = Arc<Mutex<Vec<Peer>>>;
+
+/// An open connection to the other instances, as host or as a peer
+pub struct SyncConnection {
+    peers: Peers,
+    events: Mutex<Receiver<SyncEvent>>,
+    stop: Arc<AtomicBool>,
+    hosting: bool,
+    local_addr: SocketAddr,
+}
+
+/// `address` with [`DEFAULT_PORT`] added if it has no port
+fn with_port(address: &str) -> String {
+    if address.contains(':') {
+        address.to_owned()
+    } else {
+        format!("{address}:{DEFAULT_PORT}")
+    }
+}
+
+/// Serialize a message as one line
+fn encode(message: &SyncMessage) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(message).map_err(io::Error::other)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// A random challenge for the other end to prove the key on, as hex
+fn challenge() -> String {
+    hex(&rand::random::<[u8; 16]>())
+}
+
+/// Proof that the `side` ("host" or "peer") knows `key`, for `challenge`
+fn proof(key: &str, side: &str, challenge: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(side.as_bytes());
+    mac.update(challenge.as_bytes());
+    hex(&mac.finalize().into_bytes())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Whether `a` and `b` are equal, taking as long wherever they differ
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |d, (x, y)| d | (x ^ y)) == 0
+}
+
+fn wrong_key() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "the session key doesn't match, or nobody is sharing there",
+    )
+}
+
+/// The next line from `reader`, without its newline; `None` once the
+/// connection ends, fails or sends a line over [`MAX_LINE_BYTES`]
+fn read_line(reader: &mut impl BufRead) -> Option<String> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE_BYTES as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .ok()?;
+    if line.pop() != Some(b'\n') {
+        return None;
+    }
+    String::from_utf8(line).ok()
+}
+
+/// Check as the host that the peer on `stream` knows `key`, and prove it
+/// back, returning the reader for its messages
+fn admit(stream: &TcpStream, key: &str) -> io::Result<BufReader<TcpStream>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let ours = challenge();
+    (&*stream).write_all(format!("{ours}\n").as_bytes())?;
+    let reply = read_line(&mut reader).ok_or_else(wrong_key)?;
+    let (their_proof, theirs) = reply.split_once(' ').ok_or_else(wrong_key)?;
+    if !same(their_proof, &proof(key, "peer", &ours)) {
+        return Err(wrong_key());
+    }
+    (&*stream).write_all(format!("{}\n", proof(key, "host", theirs)).as_bytes())?;
+    stream.set_read_timeout(None)?;
+    Ok(reader)
+}
+
+/// Prove as a peer that we know `key` to the host on `stream`, and check it
+/// does too, returning the reader for its messages
+fn greet(stream: &TcpStream, key: &str) -> io::Result<BufReader<TcpStream>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let theirs = read_line(&mut reader).ok_or_else(wrong_key)?;
+    let ours = challenge();
+    let reply = format!("{} {ours}\n", proof(key, "peer", &theirs));
+    (&*stream).write_all(reply.as_bytes())?;
+    let their_proof = read_line(&mut reader).ok_or_else(wrong_key)?;
+    if !same(&their_proof, &proof(key, "host", &ours)) {
+        return Err(wrong_key());
+    }
+    stream.set_read_timeout(None)?;
+    Ok(reader)
+}
+
+/// Add the peer on `stream` as `id`, written to from a thread of its own so
+/// a stalled peer holds up nobody; it is cut off once a write fails or
+/// stalls for [`WRITE_TIMEOUT`]
+fn add_peer(peers: &Peers, id: u64, stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let (lines, queue) = mpsc::channel::<Arc<[u8]>>();
+    thread::spawn(move || {
+        for line in queue {
+            if writer.write_all(&line).is_err() {
+                let _ = writer.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+    });
+    let peer = Peer { id, stream, lines };
+    peers.lock().expect("peer list poisoned").push(peer);
+    Ok(())
+}
+
+/// Queue `line` for every peer other than `except`, forgetting the ones
+/// that are gone
+fn broadcast(peers: &Peers, line: Arc<[u8]>, except: Option<u64>) {
+    let mut peers = peers.lock().expect("peer list poisoned");
+    peers.retain(|peer| Some(peer.id) == except || peer.lines.send(line.clone()).is_ok());
+}
+
+/// Read messages from `reader` until the connection closes, relaying them
+/// to the other peers when hosting
+fn read_peer(
+    mut reader: BufReader<TcpStream>,
+    id: u64,
+    peers: Peers,
+    events: Sender<SyncEvent>,
+    relay: bool,
+) {
+    while let Some(line) = read_line(&mut reader) {
+        let Ok(message) = serde_json::from_str::<SyncMessage>(&line) else {
+            continue;
+        };
+        if relay {
+            broadcast(&peers, format!("{line}\n").into_bytes().into(), Some(id));
+        }
+        if events.send(SyncEvent::Message(message)).is_err() {
+            break;
+        }
+    }
+    peers
+        .lock()
+        .expect("peer list poisoned")
+        .retain(|peer| peer.id != id);
+    let _ = events.send(SyncEvent::Left(id));
+}
+
+fn no_key() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "a session key is needed")
+}
+
+impl SyncConnection {
+    /// Wait for peers knowing `key` on `address`, like `0.0.0.0:7878`
+    pub fn host(address: &str, key: &str) -> io::Result<Self> {
+        if key.is_empty() {
+            return Err(no_key());
+        }
+        let key: Arc<str> = key.into();
+        let listener = TcpListener::bind(with_port(address))?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let peers: Peers = Arc::default();
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let next_id = AtomicU64::new(1);
+        {
+            let peers = peers.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let Ok((stream, _)) = listener.accept() else {
+                        thread::sleep(ACCEPT_POLL);
+                        continue;
+                    };
+                    let id = next_id.fetch_add(1, Ordering::Relaxed);
+                    let (peers, sender, key) = (peers.clone(), sender.clone(), key.clone());
+                    // Checked apart, so a slow peer doesn't hold up the others
+                    thread::spawn(move || {
+                        let admitted = stream
+                            .set_nonblocking(false)
+                            .and_then(|()| admit(&stream, &key))
+                            .and_then(|reader| {
+                                add_peer(&peers, id, stream.try_clone()?).map(|()| reader)
+                            });
+                        let Ok(reader) = admitted else {
+                            let _ = stream.shutdown(Shutdown::Both);
+                            return;
+                        };
+                        if sender.send(SyncEvent::Joined(id)).is_ok() {
+                            read_peer(reader, id, peers, sender, true);
+                        }
+                    });
+                }
+            });
+        }
+        Ok(Self {
+            peers,
+            events: Mutex::new(events),
+            stop,
+            hosting: true,
+            local_addr,
+        })
+    }
+
+    /// Connect to the host at `address`, like `192.168.1.20:7878`, proving
+    /// it and us to know `key`
+    pub fn join(address: &str, key: &str) -> io::Result<Self> {
+        if key.is_empty() {
+            return Err(no_key());
+        }
+        let address = with_port(address)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such host"))?;
+        let stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+        let local_addr = stream.local_addr()?;
+        let reader = greet(&stream, key)?;
+        let peers: Peers = Arc::default();
+        add_peer(&peers, 0, stream)?;
+        let (sender, events) = mpsc::channel();
+        {
+            let peers = peers.clone();
+            thread::spawn(move || read_peer(reader, 0, peers, sender, false));
+        }
+        Ok(Self {
+            peers,
+            events: Mutex::new(events),
+            stop: Arc::new(AtomicBool::new(false)),
+            hosting: false,
+            local_addr,
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.hosting
+    }
+
+    /// Address this end listens on or connected from
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Number of instances connected to this one
+    pub fn peer_count(&self) -> usize {
+        self.peers.lock().expect("peer list poisoned").len()
+    }
+
+    /// Send `message` to every peer
+    pub fn send(&self, message: &SyncMessage) -> io::Result<()> {
+        broadcast(&self.peers, encode(message)?.into(), None);
+        Ok(())
+    }
+
+    /// Send `message` to the peer `id` only
+    pub fn send_to(&self, id: u64, message: &SyncMessage) -> io::Result<()> {
+        let line = encode(message)?;
+        let peers = self.peers.lock().expect("peer list poisoned");
+        let left = || io::Error::new(io::ErrorKind::NotConnected, "peer left");
+        let peer = peers.iter().find(|peer| peer.id == id).ok_or_else(left)?;
+        peer.lines.send(line.into()).map_err(|_| left())
+    }
+
+    /// Everything that happened since the last call
+    pub fn poll(&self) -> Vec<SyncEvent> {
+        self.events
+            .lock()
+            .expect("event queue poisoned")
+            .try_iter()
+            .collect()
+    }
+}
+
+impl Drop for SyncConnection {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for peer in self.peers.lock().expect("peer list poisoned").drain(..) {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn note(id: u64, text: &str) -> NoteData {
+        NoteData {
+            id,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn latest_change_wins() {
+        let mut alice = SyncState::new(1, &[note(1, "a")]);
+        let mut bob = SyncState::new(2, &[note(1, "a")]);
+        assert!(alice.local_changes(&[note(1, "a")], 100).is_empty());
+
+        let from_alice = alice.local_changes(&[note(1, "alice")], 100);
+        let from_bob = bob.local_changes(&[note(1, "bob")], 200);
+        // Bob's later edit replaces Alice's, and Alice's is dropped by Bob
+        assert_eq!(
+            alice.receive(from_bob[0].clone()),
+            [RemoteChange::Upsert(note(1, "bob"))]
+        );
+        assert!(bob.receive(from_alice[0].clone()).is_empty());
+
+        // A deletion isn't undone by an older edit arriving late
+        let stale = bob.local_changes(&[note(1, "bob again")], 250);
+        let removed = alice.local_changes(&[], 300);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(bob.receive(removed[0].clone()), [RemoteChange::Remove(1)]);
+        assert!(alice.receive(stale[0].clone()).is_empty());
+    }
+
+    #[test]
+    fn snapshots_replace_the_board() {
+        let mut peer = SyncState::new(3, &[note(1, "mine"), note(2, "shared")]);
+        let changes = peer.receive(SyncMessage::Snapshot {
+            notes: vec![note(2, "theirs")],
+        });
+        assert_eq!(
+            changes,
+            [
+                RemoteChange::Remove(1),
+                RemoteChange::Upsert(note(2, "theirs"))
+            ]
+        );
+        assert!(peer.local_changes(&[note(2, "theirs")], 1).is_empty());
+        assert_eq!(peer.first_note_id(), 4 << 32);
+    }
+
+    #[test]
+    fn received_changes_are_not_sent_back() {
+        let mut notes = vec![note(1, "kept"), note(2, "removed")];
+        let mut peer = SyncState::new(2, &notes);
+        let upsert = SyncMessage::Upsert {
+            note: note(3, "new"),
+            stamp: Stamp { millis: 5, peer: 1 },
+        };
+        let remove = SyncMessage::Remove {
+            id: 2,
+            stamp: Stamp { millis: 5, peer: 1 },
+        };
+        for change in [upsert, remove].into_iter().flat_map(|m| peer.receive(m)) {
+            change.apply(&mut notes);
+        }
+        assert_eq!(notes, [note(1, "kept"), note(3, "new")]);
+        assert!(peer.local_changes(&notes, 10).is_empty());
+    }
+
+    #[test]
+    fn messages_travel_between_host_and_peers() {
+        let host = SyncConnection::host("127.0.0.1:0", "secret").unwrap();
+        let address = format!("127.0.0.1:{}", host.local_addr().port());
+        let first = SyncConnection::join(&address, "secret").unwrap();
+        let second = SyncConnection::join(&address, "secret").unwrap();
+        let wait_for = |connection: &SyncConnection, done: &dyn Fn(&SyncEvent) -> bool| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if connection.poll().iter().any(done) {
+                    return true;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            false
+        };
+        let joined = Mutex::new(0);
+        let both_joined = |e: &SyncEvent| {
+            let mut joined = joined.lock().unwrap();
+            *joined += matches!(e, SyncEvent::Joined(_)) as usize;
+            *joined == 2
+        };
+        assert!(wait_for(&host, &both_joined));
+        assert_eq!(host.peer_count(), 2);
+
+        let message = SyncMessage::Remove {
+            id: 7,
+            stamp: Stamp { millis: 1, peer: 9 },
+        };
+        first.send(&message).unwrap();
+        let expected = SyncEvent::Message(message);
+        assert!(wait_for(&host, &|e| *e == expected));
+        // The host passes it on to the other peer
+        assert!(wait_for(&second, &|e| *e == expected));
+
+        drop(host);
+        assert!(wait_for(&first, &|e| matches!(e, SyncEvent::Left(_))));
+    }
+
+    #[test]
+    fn peers_need_the_session_key() {
+        let host = SyncConnection::host("127.0.0.1:0", "secret").unwrap();
+        let address = format!("127.0.0.1:{}", host.local_addr().port());
+        let error = SyncConnection::join(&address, "guess").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(SyncConnection::join(&address, "").is_err());
+
+        // Someone not speaking the protocol is cut off without joining
+        let mut stranger = TcpStream::connect(&address).unwrap();
+        stranger.write_all(&[b'x'; 64]).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(host.peer_count(), 0);
+        assert!(host.poll().is_empty());
+    }
+
+    #[test]
+    fn overlong_lines_end_the_connection() {
+        let mut reader = io::Cursor::new(vec![b'x'; MAX_LINE_BYTES + 2]);
+        assert_eq!(read_line(&mut reader), None);
+        let mut reader = io::Cursor::new(b"short\nrest".to_vec());
+        assert_eq!(read_line(&mut reader).as_deref(), Some("short"));
+        assert_eq!(read_line(&mut reader), None);
+    }
+}
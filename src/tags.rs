@@ -0,0 +1,107 @@
+//! Tagging notes and filtering the board by tag
+
+use crate::{Board, NoteData};
+
+/// Tags typed as a list separated by commas or spaces, without a leading
+/// `#`, lowercased and without duplicates
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Tags as they are typed in the editor
+pub fn format_tags(tags: &[String]) -> String {
+    tags.join(", ")
+}
+
+/// What happens to notes without the filtered tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    /// Drawn faded out
+    #[default]
+    Dim,
+    /// Not drawn at all
+    Hide,
+}
+
+/// Filtering the board down to the notes carrying a tag
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagFilter {
+    /// The tag notes need, `None` to show every note
+    pub tag: Option<String>,
+    pub mode: FilterMode,
+}
+
+impl TagFilter {
+    /// Whether `note` passes the filter
+    pub fn matches(&self, note: &NoteData) -> bool {
+        self.tag.as_ref().is_none_or(|tag| note.tags.contains(tag))
+    }
+
+    /// Whether `note` is drawn faded out
+    pub fn dims(&self, note: &NoteData) -> bool {
+        self.mode == FilterMode::Dim && !self.matches(note)
+    }
+
+    /// Whether `note` is left out of the board
+    pub fn hides(&self, note: &NoteData) -> bool {
+        self.mode == FilterMode::Hide && !self.matches(note)
+    }
+}
+
+impl Board {
+    /// Every tag used on the board, sorted
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .notes
+            .iter()
+            .flat_map(|n| n.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_normalized() {
+        assert_eq!(
+            parse_tags("#Work, urgent  work,,idea"),
+            ["work", "urgent", "idea"]
+        );
+        assert_eq!(format_tags(&parse_tags("a b")), "a, b");
+        assert!(parse_tags(" , #").is_empty());
+    }
+
+    #[test]
+    fn filter_dims_or_hides_untagged_notes() {
+        let tagged = |id: u64, tags: &str| NoteData {
+            id,
+            tags: parse_tags(tags),
+            ..Default::default()
+        };
+        let board = Board {
+            notes: vec![tagged(1, "work idea"), tagged(2, ""), tagged(3, "idea")],
+            ..Default::default()
+        };
+        assert_eq!(board.tags(), ["idea", "work"]);
+
+        let mut filter = TagFilter::default();
+        assert!(board.notes.iter().all(|n| filter.matches(n)));
+        filter.tag = Some("work".into());
+        assert!(filter.dims(&board.notes[1]) && !filter.hides(&board.notes[1]));
+        assert!(!filter.dims(&board.notes[0]));
+        filter.mode = FilterMode::Hide;
+        assert!(filter.hides(&board.notes[2]) && !filter.dims(&board.notes[2]));
+    }
+}
@@ -0,0 +1,102 @@
+//! Presets for recurring kinds of notes
+
+use crate::{DEFAULT_NOTE_SIZE, NoteData};
+use egui::{Color32, Pos2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Size, color, text and tags new notes can start with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NoteTemplate {
+    /// Shown in the new note menu
+    pub name: String,
+    pub text: String,
+    pub size: Vec2,
+    pub color: Color32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl NoteTemplate {
+    /// Template making notes like `note`, named after its first line
+    pub fn from_note(note: &NoteData) -> Self {
+        let first_line = note.text.lines().next().unwrap_or_default().trim();
+        Self {
+            name: if first_line.is_empty() {
+                "Template".into()
+            } else {
+                first_line.chars().take(30).collect()
+            },
+            text: note.text.clone(),
+            size: note.size,
+            color: note.color,
+            tags: note.tags.clone(),
+        }
+    }
+
+    /// A note made from the template with `id`, its top-left corner at `pos`
+    pub fn instantiate(&self, id: u64, pos: Pos2) -> NoteData {
+        NoteData {
+            id,
+            text: self.text.clone(),
+            pos,
+            size: self.size,
+            color: self.color,
+            tags: self.tags.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Templates offered until the user makes their own
+pub fn default_templates() -> Vec<NoteTemplate> {
+    let template = |name: &str, text: &str, color: Color32| NoteTemplate {
+        name: name.into(),
+        text: text.into(),
+        size: DEFAULT_NOTE_SIZE,
+        color,
+        tags: Vec::new(),
+    };
+    vec![
+        template("To do", "TODO: ", Color32::YELLOW),
+        template("Idea", "Idea: ", Color32::from_rgb(170, 230, 170)),
+        template("Question", "Q: ", Color32::from_rgb(170, 200, 255)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+
+    #[test]
+    fn templates_make_notes_like_the_original() {
+        let note = NoteData {
+            id: 3,
+            text: "Bug: \nsteps to reproduce".into(),
+            size: Vec2::new(200.0, 150.0),
+            color: Color32::RED,
+            tags: vec!["bug".into()],
+            ..Default::default()
+        };
+        let template = NoteTemplate::from_note(&note);
+        assert_eq!(template.name, "Bug:");
+        let made = template.instantiate(9, Pos2::new(5.0, 6.0));
+        assert_eq!(
+            made,
+            NoteData {
+                id: 9,
+                pos: Pos2::new(5.0, 6.0),
+                ..note
+            }
+        );
+    }
+
+    #[test]
+    fn saves_without_templates_get_the_defaults() {
+        let json = r#"{"boards": [], "next_note_id": 1}"#;
+        let state: AppState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.templates, default_templates());
+        let roundtrip: AppState = serde_json::from_str(&state.to_json().unwrap()).unwrap();
+        assert_eq!(roundtrip, state);
+    }
+}
@@ -0,0 +1,304 @@
+//! Named color themes boards can refer to
+
+use crate::markdown::TEXT_COLOR;
+use crate::{Board, DEFAULT_NOTE_COLOR, DEFAULT_NOTE_SIZE};
+use egui::{Color32, Vec2};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// Look of a board: background, grid, note palette and default note style,
+/// and of the panels around it while it is shown
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color32,
+    pub grid_color: Color32,
+    /// Quick-pick colors offered when editing a note
+    pub palette: Vec<Color32>,
+    /// Color of newly created notes
+    pub note_color: Color32,
+    /// Size of newly created notes
+    pub note_size: Vec2,
+    /// Color of note text
+    #[serde(default = "default_text_color")]
+    pub text_color: Color32,
+    /// Draw the panels light on dark
+    #[serde(default)]
+    pub dark_ui: bool,
+    /// Show the theme's background on every board, and panels in strong
+    /// contrast
+    #[serde(default)]
+    pub high_contrast: bool,
+}
+
+fn default_text_color() -> Color32 {
+    TEXT_COLOR
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "Classic".into(),
+            background: Color32::LIGHT_BLUE,
+            grid_color: Color32::from_black_alpha(40),
+            palette: vec![
+                DEFAULT_NOTE_COLOR,
+                Color32::from_rgb(255, 170, 200),
+                Color32::from_rgb(170, 230, 170),
+                Color32::from_rgb(170, 200, 255),
+                Color32::from_rgb(255, 200, 130),
+            ],
+            note_color: DEFAULT_NOTE_COLOR,
+            note_size: DEFAULT_NOTE_SIZE,
+            text_color: TEXT_COLOR,
+            dark_ui: false,
+            high_contrast: false,
+        }
+    }
+}
+
+/// Black board, bright notes with black text and panels in white on black
+pub fn high_contrast_theme() -> Theme {
+    Theme {
+        name: "High contrast".into(),
+        background: Color32::BLACK,
+        grid_color: Color32::from_white_alpha(90),
+        palette: vec![
+            Color32::YELLOW,
+            Color32::WHITE,
+            Color32::from_rgb(120, 220, 255),
+            Color32::from_rgb(140, 255, 140),
+            Color32::from_rgb(255, 170, 60),
+        ],
+        note_color: Color32::YELLOW,
+        note_size: DEFAULT_NOTE_SIZE,
+        text_color: Color32::BLACK,
+        dark_ui: true,
+        high_contrast: true,
+    }
+}
+
+/// Themes shipped with plop
+pub fn builtin_themes() -> Vec<Theme> {
+    vec![
+        Theme::default(),
+        Theme {
+            name: "Cork".into(),
+            background: Color32::from_rgb(196, 154, 108),
+            grid_color: Color32::from_black_alpha(30),
+            note_color: Color32::from_rgb(255, 250, 205),
+            ..Theme::default()
+        },
+        Theme {
+            name: "Midnight".into(),
+            background: Color32::from_rgb(30, 34, 46),
+            grid_color: Color32::from_white_alpha(30),
+            palette: vec![
+                Color32::from_rgb(250, 220, 90),
+                Color32::from_rgb(240, 130, 160),
+                Color32::from_rgb(120, 210, 160),
+                Color32::from_rgb(120, 170, 250),
+            ],
+            note_color: Color32::from_rgb(250, 220, 90),
+            dark_ui: true,
+            ..Theme::default()
+        },
+        Theme {
+            name: "Dark".into(),
+            background: Color32::from_gray(24),
+            grid_color: Color32::from_white_alpha(20),
+            palette: vec![
+                Color32::from_rgb(70, 66, 40),
+                Color32::from_rgb(80, 45, 60),
+                Color32::from_rgb(40, 70, 50),
+                Color32::from_rgb(40, 55, 85),
+            ],
+            note_color: Color32::from_rgb(70, 66, 40),
+            text_color: Color32::from_gray(225),
+            dark_ui: true,
+            ..Theme::default()
+        },
+        Theme {
+            name: "Pastel".into(),
+            background: Color32::from_rgb(250, 245, 240),
+            grid_color: Color32::from_rgba_unmultiplied(180, 160, 200, 60),
+            palette: vec![
+                Color32::from_rgb(255, 223, 211),
+                Color32::from_rgb(224, 187, 228),
+                Color32::from_rgb(210, 145, 188),
+                Color32::from_rgb(254, 200, 216),
+                Color32::from_rgb(200, 230, 220),
+            ],
+            note_color: Color32::from_rgb(255, 223, 211),
+            note_size: Vec2::new(140.0, 100.0),
+            ..Theme::default()
+        },
+        high_contrast_theme(),
+    ]
+}
+
+/// Read a theme file
+pub fn read_theme(path: &Path) -> io::Result<Theme> {
+    let data = std::fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write `theme` to a theme file
+pub fn write_theme(theme: &Theme, path: &Path) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(theme).map_err(io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+/// The built-in themes plus the user's themes stored in a folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeLibrary {
+    pub themes: Vec<Theme>,
+    /// Theme of boards that don't pick one, `None` for the default theme
+    pub app_theme: Option<String>,
+}
+
+impl Default for ThemeLibrary {
+    fn default() -> Self {
+        Self {
+            themes: builtin_themes(),
+            app_theme: None,
+        }
+    }
+}
+
+impl ThemeLibrary {
+    /// Built-in themes plus every theme file in `dir`; user themes replace
+    /// built-in ones of the same name. Unreadable files are skipped.
+    pub fn load(dir: &Path) -> Self {
+        let mut library = Self::default();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return library;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            if let Ok(theme) = read_theme(&path) {
+                library.insert(theme);
+            }
+        }
+        library
+    }
+
+    /// Theme called `name`
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.iter().find(|t| t.name == name)
+    }
+
+    /// Theme used by `board`, falling back to the app theme and then the
+    /// default theme
+    pub fn for_board(&self, board: &Board) -> Theme {
+        board
+            .theme
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.app_theme.as_deref().and_then(|name| self.get(name)))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Add `theme`, replacing a theme with the same name
+    pub fn insert(&mut self, theme: Theme) {
+        match self.themes.iter_mut().find(|t| t.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => self.themes.push(theme),
+        }
+    }
+
+    /// Import the theme file at `path` and keep a copy in the library folder
+    /// `dir`. Returns the name of the imported theme.
+    pub fn import(&mut self, path: &Path, dir: &Path) -> io::Result<String> {
+        let theme = read_theme(path)?;
+        std::fs::create_dir_all(dir)?;
+        write_theme(&theme, &dir.join(theme_file_name(&theme.name)))?;
+        let name = theme.name.clone();
+        self.insert(theme);
+        Ok(name)
+    }
+}
+
+/// File name a theme is stored under in the library folder
+pub fn theme_file_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{stem}.json")
+}
+
+impl Board {
+    /// Switch the board to `theme`
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        self.theme = Some(theme.name.clone());
+        self.background = theme.background;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applying_a_theme_sets_background() {
+        let library = ThemeLibrary::default();
+        let mut board = Board::default();
+        assert_eq!(library.for_board(&board), Theme::default());
+        let midnight = library.get("Midnight").unwrap();
+        board.apply_theme(midnight);
+        assert_eq!(board.background, midnight.background);
+        assert_eq!(&library.for_board(&board), midnight);
+    }
+
+    #[test]
+    fn boards_without_a_theme_follow_the_app_theme() {
+        let mut library = ThemeLibrary {
+            app_theme: Some("Dark".into()),
+            ..Default::default()
+        };
+        let board = Board::default();
+        let dark = library.for_board(&board);
+        assert!(dark.dark_ui);
+        assert_eq!(dark.name, "Dark");
+        library.app_theme = Some("Missing".into());
+        assert_eq!(library.for_board(&board), Theme::default());
+
+        // Theme files from before text colors get the usual one
+        let mut json = serde_json::to_value(library.get("Dark").unwrap()).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("text_color");
+        fields.remove("dark_ui");
+        let old: Theme = serde_json::from_value(json).unwrap();
+        assert_eq!(old.text_color, TEXT_COLOR);
+        assert!(!old.dark_ui);
+    }
+
+    #[test]
+    fn imported_theme_is_kept_in_library_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("mine.json");
+        let theme = Theme {
+            name: "Cork".into(),
+            background: Color32::RED,
+            ..Theme::default()
+        };
+        write_theme(&theme, &file).unwrap();
+
+        let library_dir = dir.path().join("themes");
+        let mut library = ThemeLibrary::default();
+        assert_eq!(library.import(&file, &library_dir).unwrap(), "Cork");
+        assert_eq!(library.get("Cork"), Some(&theme));
+
+        // A fresh library picks it up and it replaces the built-in theme
+        let reloaded = ThemeLibrary::load(&library_dir);
+        assert_eq!(reloaded.themes.len(), builtin_themes().len());
+        assert_eq!(reloaded.get("Cork"), Some(&theme));
+    }
+}
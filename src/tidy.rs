@@ -0,0 +1,157 @@
+//! Tidying notes up into a compact grid, optionally clustered by color or tag
+
+use crate::{NoteData, snap_to_grid};
+use egui::{Pos2, Vec2};
+use std::collections::HashMap;
+
+/// Space between tidied notes
+pub const TIDY_GAP: f32 = 20.0;
+
+/// How long notes take to glide to their tidy places, in seconds
+pub const TIDY_SECONDS: f64 = 0.4;
+
+/// How tidied notes are grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TidyGrouping {
+    /// One grid in reading order
+    Grid,
+    /// A grid per note color, side by side
+    Color,
+    /// A grid per first tag, untagged notes last
+    Tag,
+}
+
+impl TidyGrouping {
+    pub const ALL: [Self; 3] = [Self::Grid, Self::Color, Self::Tag];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Grid => "Grid",
+            Self::Color => "By color",
+            Self::Tag => "By tag",
+        }
+    }
+}
+
+/// New top-left corners of `notes`, packed into grids from the top-left of
+/// their current bounds, on grid points `grid` apart
+pub fn tidy_positions(notes: &[&NoteData], grouping: TidyGrouping, grid: f32) -> Vec<(u64, Pos2)> {
+    let Some(origin) = notes.iter().map(|n| n.pos).reduce(|a, b| a.min(b)) else {
+        return Vec::new();
+    };
+    let mut sorted = notes.to_vec();
+    sorted.sort_by(|a, b| {
+        a.pos
+            .y
+            .total_cmp(&b.pos.y)
+            .then(a.pos.x.total_cmp(&b.pos.x))
+    });
+
+    // Groups in the order their first note comes up
+    let mut groups: Vec<Vec<&NoteData>> = Vec::new();
+    let mut group_of = HashMap::new();
+    for note in sorted {
+        let key = match grouping {
+            TidyGrouping::Grid => (None, None),
+            TidyGrouping::Color => (Some(note.color), None),
+            TidyGrouping::Tag => (None, note.tags.first()),
+        };
+        let index = *group_of.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(note);
+    }
+    if grouping == TidyGrouping::Tag {
+        // Untagged notes come after every tag
+        groups.sort_by_key(|g| (g[0].tags.is_empty(), g[0].tags.first()));
+    }
+
+    // Every cell fits the largest note, rounded up to whole grid steps
+    let largest = notes.iter().map(|n| n.size).fold(Vec2::ZERO, Vec2::max);
+    let round_up = |x: f32| {
+        if grid > 0.0 {
+            (x / grid).ceil() * grid
+        } else {
+            x
+        }
+    };
+    let cell = Vec2::new(
+        round_up(largest.x + TIDY_GAP),
+        round_up(largest.y + TIDY_GAP),
+    );
+    let origin = snap_to_grid(origin, grid);
+
+    let mut positions = Vec::new();
+    let mut left = origin.x;
+    for group in groups {
+        let columns = (group.len() as f32).sqrt().ceil().max(1.0) as usize;
+        for (i, note) in group.iter().enumerate() {
+            let (row, column) = (i / columns, i % columns);
+            let pos = Pos2::new(
+                left + column as f32 * cell.x,
+                origin.y + row as f32 * cell.y,
+            );
+            positions.push((note.id, pos));
+        }
+        // Clusters are a cell apart
+        left += (columns + 1) as f32 * cell.x;
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Color32;
+
+    fn note(id: u64, x: f32, y: f32, color: Color32) -> NoteData {
+        NoteData {
+            id,
+            pos: Pos2::new(x, y),
+            size: Vec2::new(100.0, 80.0),
+            color,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn notes_pack_into_a_grid_in_reading_order() {
+        let notes = [
+            note(1, 500.0, 300.0, Color32::RED),
+            note(2, 30.0, 10.0, Color32::RED),
+            note(3, 400.0, 20.0, Color32::YELLOW),
+            note(4, 10.0, 700.0, Color32::YELLOW),
+        ];
+        let refs: Vec<&NoteData> = notes.iter().collect();
+        // Cells of 120 by 100, from the top-left corner 10,10 snapped
+        assert_eq!(
+            tidy_positions(&refs, TidyGrouping::Grid, 20.0),
+            [
+                (2, Pos2::new(20.0, 20.0)),
+                (3, Pos2::new(140.0, 20.0)),
+                (1, Pos2::new(20.0, 120.0)),
+                (4, Pos2::new(140.0, 120.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn clusters_sit_side_by_side() {
+        let mut notes = [
+            note(1, 0.0, 0.0, Color32::RED),
+            note(2, 0.0, 100.0, Color32::YELLOW),
+            note(3, 0.0, 200.0, Color32::RED),
+        ];
+        notes[1].tags = vec!["b".into()];
+        notes[2].tags = vec!["a".into()];
+        let refs: Vec<&NoteData> = notes.iter().collect();
+        let by_color = tidy_positions(&refs, TidyGrouping::Color, 0.0);
+        // Two red notes make a 2-column cluster, then a gap cell
+        assert_eq!(by_color[1], (3, Pos2::new(120.0, 0.0)));
+        assert_eq!(by_color[2], (2, Pos2::new(360.0, 0.0)));
+        let by_tag = tidy_positions(&refs, TidyGrouping::Tag, 0.0);
+        let ids: Vec<u64> = by_tag.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [3, 2, 1]);
+    }
+}
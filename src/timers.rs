@@ -0,0 +1,189 @@
+//! Timer notes: a countdown, e.g. a 25-minute pomodoro, that rings when it
+//! runs out and keeps ringing until acknowledged
+//!
+//! A running timer keeps its deadline rather than the time left, so it
+//! carries on through a restart.
+
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Length of a pomodoro, in seconds
+pub const POMODORO: u64 = 25 * 60;
+
+/// Current time for timers, in seconds since the Unix epoch
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// A countdown on a note
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct NoteTimer {
+    /// Seconds the timer counts down from
+    pub duration: u64,
+    /// When it runs out, in seconds since the Unix epoch, while running
+    deadline: Option<f64>,
+    /// Seconds left while paused or stopped
+    left: f64,
+    /// Ran out and not yet acknowledged
+    pub ringing: bool,
+}
+
+impl NoteTimer {
+    /// A stopped timer of `duration` seconds
+    pub fn new(duration: u64) -> Self {
+        Self {
+            duration,
+            deadline: None,
+            left: duration as f64,
+            ringing: false,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Seconds left at `now`
+    pub fn remaining(&self, now: f64) -> f64 {
+        match self.deadline {
+            Some(deadline) => (deadline - now).max(0.0),
+            None => self.left,
+        }
+    }
+
+    /// How much of the time has gone by at `now`, from 0 to 1
+    pub fn progress(&self, now: f64) -> f32 {
+        if self.duration == 0 {
+            return 1.0;
+        }
+        (1.0 - self.remaining(now) / self.duration as f64).clamp(0.0, 1.0) as f32
+    }
+
+    /// Count down from `now`, from the top if the timer ran out
+    pub fn start(&mut self, now: f64) {
+        if self.left <= 0.0 {
+            self.left = self.duration as f64;
+        }
+        self.ringing = false;
+        self.deadline = Some(now + self.left);
+    }
+
+    pub fn pause(&mut self, now: f64) {
+        self.left = self.remaining(now);
+        self.deadline = None;
+    }
+
+    /// Whether the timer ran out by `now`; it then stops and starts ringing
+    pub fn tick(&mut self, now: f64) -> bool {
+        if self.deadline.is_some_and(|deadline| now >= deadline) {
+            self.deadline = None;
+            self.left = 0.0;
+            self.ringing = true;
+            return true;
+        }
+        false
+    }
+
+    /// Stop ringing, ready to count down again
+    pub fn acknowledge(&mut self) {
+        self.ringing = false;
+        self.left = self.duration as f64;
+    }
+}
+
+/// Seconds in a duration typed like `25`, `25m`, `90s`, `1h 30m` or
+/// `1:30:00`; a bare number is minutes
+pub fn parse_duration(input: &str) -> Option<u64> {
+    let input = input.trim();
+    if input.contains(':') {
+        let parts: Vec<u64> = input
+            .split(':')
+            .map(|part| part.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        if parts.len() > 3 {
+            return None;
+        }
+        return Some(parts.iter().fold(0, |total, part| total * 60 + part));
+    }
+    if let Ok(minutes) = input.parse::<u64>() {
+        return Some(minutes * 60);
+    }
+    let mut total = 0;
+    let mut number = String::new();
+    for c in input.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total += number.parse::<u64>().ok()? * unit;
+        number.clear();
+    }
+    (number.is_empty() && total > 0).then_some(total)
+}
+
+/// `secs` as `M:SS`, or `H:MM:SS` from an hour up
+pub fn format_countdown(secs: f64) -> String {
+    // Round up, so the clock shows 0:00 only once the time is up
+    let secs = secs.max(0.0).ceil() as u64;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_count_down_and_ring() {
+        let mut timer = NoteTimer::new(60);
+        timer.start(1000.0);
+        assert_eq!(timer.remaining(1015.0), 45.0);
+        timer.pause(1015.0);
+        // Paused time doesn't count
+        assert_eq!(timer.remaining(2000.0), 45.0);
+        timer.start(2000.0);
+        assert!(!timer.tick(2044.0));
+        assert!(timer.tick(2045.0));
+        assert!(timer.ringing && !timer.is_running());
+        assert_eq!(timer.progress(2050.0), 1.0);
+        // It only rings once
+        assert!(!timer.tick(2050.0));
+        timer.acknowledge();
+        assert_eq!(timer.remaining(3000.0), 60.0);
+
+        // The deadline is kept, so a restart doesn't reset a running timer
+        let mut running = NoteTimer::new(POMODORO);
+        running.start(0.0);
+        let json = serde_json::to_string(&running).unwrap();
+        let restored: NoteTimer = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.remaining(600.0), 900.0);
+    }
+
+    #[test]
+    fn durations_parse_and_format() {
+        assert_eq!(parse_duration("25"), Some(1500));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("1h 30m"), Some(5400));
+        assert_eq!(parse_duration("1:30"), Some(90));
+        assert_eq!(parse_duration("1:00:00"), Some(3600));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(format_countdown(1500.0), "25:00");
+        assert_eq!(format_countdown(59.2), "1:00");
+        assert_eq!(format_countdown(3725.0), "1:02:05");
+    }
+}
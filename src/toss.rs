@@ -0,0 +1,68 @@
+//! Tossing notes: a note let go while moving slides on, slowing down and
+//! bouncing off the edges of the view, before it snaps into place
+
+use egui::{Pos2, Rect, Vec2};
+
+/// Slowest release, in board units per second, that sets a note sliding
+pub const MIN_TOSS_SPEED: f32 = 400.0;
+/// Speed below which a sliding note comes to rest
+pub const REST_SPEED: f32 = 20.0;
+/// How fast sliding notes slow down; the share of speed lost per second is
+/// `1 - e^-FRICTION`
+pub const FRICTION: f32 = 4.0;
+/// Share of its speed a note keeps when it bounces off an edge
+pub const BOUNCE: f32 = 0.5;
+
+/// Where a note of `size` at `pos` moving at `velocity` is after `dt`
+/// seconds, and how fast it moves then, bouncing back into `bounds`
+pub fn slide(pos: Pos2, size: Vec2, velocity: Vec2, dt: f32, bounds: Rect) -> (Pos2, Vec2) {
+    let mut pos = pos + velocity * dt;
+    let mut velocity = velocity * (-FRICTION * dt).exp();
+    let max = bounds.max - size;
+    for axis in 0..2 {
+        // A note wider than the view can't bounce between its edges
+        if max[axis] < bounds.min[axis] {
+            continue;
+        }
+        if pos[axis] < bounds.min[axis] {
+            pos[axis] = bounds.min[axis];
+            velocity[axis] = velocity[axis].abs() * BOUNCE;
+        } else if pos[axis] > max[axis] {
+            pos[axis] = max[axis];
+            velocity[axis] = -velocity[axis].abs() * BOUNCE;
+        }
+    }
+    (pos, velocity)
+}
+
+/// Whether a note moving at `velocity` has come to rest
+pub fn is_resting(velocity: Vec2) -> bool {
+    velocity.length() < REST_SPEED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tossed_notes_slow_down_and_bounce() {
+        let bounds = Rect::from_min_size(Pos2::ZERO, Vec2::new(1000.0, 500.0));
+        let size = Vec2::new(100.0, 100.0);
+        let (pos, velocity) = slide(Pos2::new(100.0, 100.0), size, Vec2::X * 1000.0, 0.1, bounds);
+        assert_eq!(pos, Pos2::new(200.0, 100.0));
+        assert!(velocity.x < 1000.0 && velocity.x > 600.0);
+
+        // Hitting the right edge sends it back, slower
+        let (pos, velocity) = slide(Pos2::new(880.0, 100.0), size, Vec2::X * 500.0, 0.1, bounds);
+        assert_eq!(pos.x, 900.0);
+        assert!(velocity.x < 0.0 && velocity.x > -250.0);
+
+        // Left alone it comes to rest
+        let (mut pos, mut velocity) = (Pos2::new(400.0, 200.0), Vec2::new(800.0, -300.0));
+        for _ in 0..300 {
+            (pos, velocity) = slide(pos, size, velocity, 1.0 / 60.0, bounds);
+        }
+        assert!(is_resting(velocity));
+        assert!(bounds.contains_rect(Rect::from_min_size(pos, size)));
+    }
+}
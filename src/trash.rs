@@ -0,0 +1,106 @@
+//! The trash deleted notes go to, and the archive notes are put away in,
+//! both per board and both restorable
+
+use crate::expiry::DAY;
+use crate::{Board, NoteData};
+use serde::{Deserialize, Serialize};
+
+/// Days deleted notes stay in the trash unless set otherwise
+pub const DEFAULT_TRASH_DAYS: u64 = 30;
+
+/// A deleted note and when it was deleted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trashed {
+    pub note: NoteData,
+    pub deleted_at: u64,
+}
+
+impl Board {
+    /// Every note kept with the board: on it, archived or in the trash
+    pub fn stored_notes(&self) -> impl Iterator<Item = &NoteData> {
+        self.notes
+            .iter()
+            .chain(&self.archived)
+            .chain(self.trash.iter().map(|t| &t.note))
+    }
+
+    /// Every note kept with the board, to change
+    pub fn stored_notes_mut(&mut self) -> impl Iterator<Item = &mut NoteData> {
+        self.notes
+            .iter_mut()
+            .chain(&mut self.archived)
+            .chain(self.trash.iter_mut().map(|t| &mut t.note))
+    }
+
+    /// Put the deleted `note` in the trash at time `now`
+    pub fn trash_note(&mut self, note: NoteData, now: u64) {
+        self.trash.retain(|t| t.note.id != note.id);
+        self.trash.push(Trashed {
+            note,
+            deleted_at: now,
+        });
+    }
+
+    /// Take the note `id` out of the trash, without putting it back
+    pub fn untrash(&mut self, id: u64) -> Option<NoteData> {
+        let index = self.trash.iter().position(|t| t.note.id == id)?;
+        Some(self.trash.remove(index).note)
+    }
+
+    /// Put the note `id` from the trash, or from the archive if `archived`,
+    /// back on top of the board, returning it as restored
+    pub fn restore_note(&mut self, id: u64, archived: bool) -> Option<NoteData> {
+        let mut note = if archived {
+            let index = self.archived.iter().position(|n| n.id == id)?;
+            self.archived.remove(index)
+        } else {
+            self.untrash(id)?
+        };
+        note.z = self.top_z();
+        self.notes.push(note.clone());
+        Some(note)
+    }
+
+    /// Delete notes that have been in the trash for `days` days or more
+    /// for good, returning how many
+    pub fn purge_trash(&mut self, now: u64, days: u64) -> usize {
+        let before = self.trash.len();
+        self.trash
+            .retain(|t| now.saturating_sub(t.deleted_at) < days * DAY);
+        before - self.trash.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Command;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+
+    #[test]
+    fn deleted_notes_wait_in_the_trash() {
+        let mut state = AppState::default();
+        let id = state.add_note("oops", Pos2::ZERO);
+        let note = state.board().notes[0].clone();
+        let delete = Command::Delete { note, index: 0 };
+        let board = state.board_mut();
+        delete.apply(board);
+        assert!(board.notes.is_empty());
+        assert_eq!(board.trash[0].note.id, id);
+        delete.revert(board);
+        assert!(board.trash.is_empty());
+
+        delete.apply(board);
+        let restored = board.restore_note(id, false).unwrap();
+        assert_eq!(board.notes, [restored]);
+        // Undoing the delete of a restored note doesn't add it twice
+        delete.revert(board);
+        assert_eq!(board.notes.len(), 1);
+
+        board.trash_note(board.notes[0].clone(), 10 * DAY);
+        assert_eq!(board.purge_trash(39 * DAY, 30), 0);
+        assert_eq!(board.purge_trash(40 * DAY, 30), 1);
+        assert!(board.trash.is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+//! Ordering search results into a ranked list of notes
+
+use crate::Board;
+
+/// Rank of the note `id`, if it has one
+fn rank_of(board: &Board, id: u64) -> Option<u32> {
+    board.notes.iter().find(|n| n.id == id).and_then(|n| n.rank)
+}
+
+/// Sort note `ids` by rank; unranked notes follow in their current order
+pub fn sort_by_rank(board: &Board, ids: &mut [u64]) {
+    ids.sort_by_key(|&id| rank_of(board, id).unwrap_or(u32::MAX));
+}
+
+/// Move the item at `from` so it ends up at index `to`
+pub fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) {
+    if from >= items.len() {
+        return;
+    }
+    let item = items.remove(from);
+    items.insert(to.min(items.len()), item);
+}
+
+/// Number the notes in `order` 1, 2, 3, … by setting their rank
+pub fn assign_ranks(board: &mut Board, order: &[u64]) {
+    for (rank, id) in (1..).zip(order) {
+        if let Some(note) = board.notes.iter_mut().find(|n| n.id == *id) {
+            note.rank = Some(rank);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NoteData;
+
+    #[test]
+    fn reordering_assigns_ranks() {
+        let mut board = Board {
+            notes: (1..=3)
+                .map(|id| NoteData {
+                    id,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut order = vec![1, 2, 3];
+        move_item(&mut order, 2, 0);
+        assert_eq!(order, [3, 1, 2]);
+        assign_ranks(&mut board, &order[..2]);
+
+        let mut ids = vec![1, 2, 3];
+        sort_by_rank(&board, &mut ids);
+        assert_eq!(ids, [3, 1, 2]);
+        assert_eq!(board.notes[1].rank, None);
+    }
+}
@@ -0,0 +1,399 @@
+//! A folder of Markdown files, one per note, that Obsidian or any text editor
+//! can work on; syncing brings their edits back onto the board
+//!
+//! Each file starts with YAML frontmatter holding what the board needs to
+//! place the note (id, position, size, color, tags) and the board it was
+//! written for, followed by its text. Only files written for the board are
+//! ever deleted.
+
+use crate::geometry::MIN_NOTE_SIZE;
+use crate::sanitize::{MAX_NOTE_CHARS, sanitize_text};
+use crate::tags::parse_tags;
+use crate::{Board, DEFAULT_NOTE_COLOR, DEFAULT_NOTE_SIZE, NoteData};
+use egui::{Color32, Pos2, Vec2};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Room left between notes made from new files
+const NEW_NOTE_GAP: f32 = 20.0;
+
+/// A note as read from its file; what the frontmatter leaves out is `None`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VaultNote {
+    /// Id of the note the file was written for; none for a file made outside
+    /// plop
+    pub id: Option<u64>,
+    /// Id of the board the file was written for
+    pub board: Option<u64>,
+    pub pos: Option<Pos2>,
+    pub size: Option<Vec2>,
+    pub color: Option<Color32>,
+    pub tags: Vec<String>,
+    pub text: String,
+}
+
+/// The file for `note` of the board with id `board_id`: frontmatter, then
+/// the text
+pub fn note_to_markdown(note: &NoteData, board_id: u64) -> String {
+    let mut out = String::from("---\n");
+    out += &format!("id: {}\n", note.id);
+    out += &format!("board: {board_id}\n");
+    out += &format!("pos: [{}, {}]\n", note.pos.x, note.pos.y);
+    out += &format!("size: [{}, {}]\n", note.size.x, note.size.y);
+    out += &format!("color: \"{}\"\n", note.color.to_hex());
+    if !note.tags.is_empty() {
+        out += &format!("tags: [{}]\n", note.tags.join(", "));
+    }
+    out += "---\n";
+    out += &note.text;
+    if !note.text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Read a note file; a file without frontmatter is all text
+pub fn parse_note_file(content: &str) -> VaultNote {
+    let content = content.replace("\r\n", "\n");
+    let Some((frontmatter, text)) = content.strip_prefix("---\n").and_then(|rest| {
+        rest.split_once("\n---\n")
+            .or_else(|| rest.split_once("\n---"))
+    }) else {
+        return VaultNote {
+            text: content.trim_end().to_owned(),
+            ..Default::default()
+        };
+    };
+    let mut note = VaultNote {
+        text: text.trim_end().to_owned(),
+        ..Default::default()
+    };
+    let mut lines = frontmatter.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "id" => note.id = value.parse().ok(),
+            "board" => note.board = value.parse().ok(),
+            "pos" => note.pos = pair(value).map(|(x, y)| Pos2::new(x, y)),
+            "size" => note.size = pair(value).map(|(x, y)| Vec2::new(x, y)),
+            "color" => note.color = Color32::from_hex(value.trim_matches(['"', '\''])).ok(),
+            "tags" if value.is_empty() => {
+                // A block list, one `- tag` per line
+                let mut listed = String::new();
+                while let Some(item) = lines.next_if(|l| l.trim_start().starts_with('-')) {
+                    listed += item.trim_start().trim_start_matches('-');
+                    listed.push(',');
+                }
+                note.tags = parse_tags(&listed);
+            }
+            "tags" => note.tags = parse_tags(value.trim_matches(['[', ']'])),
+            _ => {}
+        }
+    }
+    note
+}
+
+/// Two finite numbers written as `[a, b]`
+fn pair(value: &str) -> Option<(f32, f32)> {
+    let (a, b) = value.trim_matches(['[', ']']).split_once(',')?;
+    let (a, b): (f32, f32) = (a.trim().parse().ok()?, b.trim().parse().ok()?);
+    (a.is_finite() && b.is_finite()).then_some((a, b))
+}
+
+/// File name for `note`: its id, then the start of its first line
+pub fn note_file_name(note: &NoteData) -> String {
+    let title: String = note
+        .text
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+        .take(40)
+        .collect();
+    let title = title.trim();
+    if title.is_empty() {
+        format!("{}.md", note.id)
+    } else {
+        format!("{} {title}.md", note.id)
+    }
+}
+
+/// Every Markdown file in `dir` and what it holds
+pub fn read_vault(dir: &Path) -> io::Result<Vec<(PathBuf, VaultNote)>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let note = parse_note_file(&std::fs::read_to_string(&path)?);
+            Ok((path, note))
+        })
+        .collect()
+}
+
+/// Whether `file` was written for `board`: for it, or before files named
+/// their board, for one of its notes
+fn written_for(file: &VaultNote, board: &Board) -> bool {
+    match file.board {
+        Some(id) => id == board.id,
+        None => file
+            .id
+            .is_some_and(|id| board.notes.iter().any(|n| n.id == id)),
+    }
+}
+
+/// Write every note of `board` into `dir`, then remove the files written for
+/// it before that weren't just written again, e.g. of deleted notes; other
+/// files are left alone
+pub fn export_vault(board: &Board, dir: &Path) -> io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let mut written = HashSet::new();
+    for note in &board.notes {
+        let path = dir.join(note_file_name(note));
+        std::fs::write(&path, note_to_markdown(note, board.id))?;
+        written.insert(path);
+    }
+    for (path, file) in read_vault(dir)? {
+        if written_for(&file, board) && !written.contains(&path) {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(board.notes.len())
+}
+
+/// What a sync changed on the board
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VaultSync {
+    /// Notes edited in their files, as they are now
+    pub updated: Vec<NoteData>,
+    /// Notes made from files new to the folder
+    pub created: Vec<NoteData>,
+    /// Warnings for texts that had to be cleaned up
+    pub cleaned: Vec<String>,
+}
+
+/// Bring the edits to the files in `dir` onto the notes of `board`, then
+/// write the board back out. Files made outside plop become notes with ids
+/// from `next_note_id`, in a row from `origin`, and are then rewritten as
+/// theirs; each is only removed once the board is written. Files written
+/// for other boards are left alone.
+pub fn sync_vault(
+    board: &Board,
+    dir: &Path,
+    next_note_id: &mut u64,
+    origin: Pos2,
+) -> io::Result<VaultSync> {
+    let files = if dir.exists() {
+        read_vault(dir)?
+    } else {
+        Vec::new()
+    };
+    let mut synced = board.clone();
+    let mut changes = VaultSync::default();
+    let index = board.note_index();
+    let mut x = origin.x;
+    let mut replaced = Vec::new();
+    for (path, file) in files {
+        if !written_for(&file, board) && file.id.is_some() {
+            continue;
+        }
+        let size = file.size.map(|size| size.max(MIN_NOTE_SIZE));
+        if let Some(&i) = file.id.and_then(|id| index.get(&id)) {
+            let note = &mut synced.notes[i];
+            let before = note.clone();
+            let clean = sanitize_text(&file.text, MAX_NOTE_CHARS);
+            changes.cleaned.extend(clean.warning());
+            note.text = clean.text;
+            // Locked notes stay where they are
+            if !note.locked {
+                note.pos = file.pos.unwrap_or(note.pos);
+                note.size = size.unwrap_or(note.size);
+            }
+            note.color = file.color.unwrap_or(note.color);
+            note.tags = file.tags;
+            if *note != before {
+                changes.updated.push(note.clone());
+            }
+        } else if file.id.is_none() {
+            let size = size.unwrap_or(DEFAULT_NOTE_SIZE);
+            let clean = sanitize_text(&file.text, MAX_NOTE_CHARS);
+            changes.cleaned.extend(clean.warning());
+            let note = NoteData {
+                id: *next_note_id,
+                text: clean.text,
+                pos: file.pos.unwrap_or(Pos2::new(x, origin.y)),
+                size,
+                color: file.color.unwrap_or(DEFAULT_NOTE_COLOR),
+                tags: file.tags,
+                z: synced.top_z(),
+                ..Default::default()
+            };
+            *next_note_id += 1;
+            x += size.x + NEW_NOTE_GAP;
+            if path != dir.join(note_file_name(&note)) {
+                replaced.push(path);
+            }
+            synced.notes.push(note.clone());
+            changes.created.push(note);
+        }
+        // Files of notes deleted from the board go with the next export
+    }
+    export_vault(&synced, dir)?;
+    for path in replaced {
+        std::fs::remove_file(path)?;
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_note() -> NoteData {
+        NoteData {
+            id: 7,
+            text: "Groceries\n- milk".into(),
+            pos: Pos2::new(10.0, -20.5),
+            size: Vec2::new(120.0, 80.0),
+            color: Color32::from_rgb(255, 170, 200),
+            tags: vec!["home".into(), "todo".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn note_files_round_trip() {
+        let note = sample_note();
+        let markdown = note_to_markdown(&note, 3);
+        assert!(markdown.starts_with("---\nid: 7\nboard: 3\npos: [10, -20.5]\n"));
+        assert_eq!(note_file_name(&note), "7 Groceries.md");
+        let read = parse_note_file(&markdown);
+        assert_eq!((read.id, read.board), (Some(7), Some(3)));
+        assert_eq!(read.pos, Some(note.pos));
+        assert_eq!(read.color, Some(note.color));
+        assert_eq!(read.tags, note.tags);
+        assert_eq!(read.text, note.text);
+
+        // Obsidian writes tags as a block list, and plain files have no
+        // frontmatter at all
+        let edited = "---\nid: 7\ntags:\n  - work\n  - Urgent\n---\nCall back\n";
+        assert_eq!(parse_note_file(edited).tags, ["work", "urgent"]);
+        let plain = parse_note_file("Just text\n");
+        assert_eq!((plain.id, plain.text.as_str()), (None, "Just text"));
+    }
+
+    #[test]
+    fn edits_in_the_folder_flow_back() {
+        let board = Board {
+            notes: vec![sample_note()],
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        export_vault(&board, dir.path()).unwrap();
+        let file = dir.path().join("7 Groceries.md");
+        let edited = std::fs::read_to_string(&file)
+            .unwrap()
+            .replace("- milk", "- milk\n- eggs");
+        std::fs::write(&file, edited).unwrap();
+        std::fs::write(dir.path().join("idea.md"), "New idea\n").unwrap();
+
+        let mut next_note_id = 20;
+        let sync = sync_vault(&board, dir.path(), &mut next_note_id, Pos2::ZERO).unwrap();
+        assert_eq!(sync.updated.len(), 1);
+        assert_eq!(sync.updated[0].text, "Groceries\n- milk\n- eggs");
+        assert_eq!(sync.created.len(), 1);
+        assert_eq!((sync.created[0].id, next_note_id), (20, 21));
+        // The new file is rewritten as the note's, so it isn't imported twice
+        let names: Vec<String> = read_vault(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(p, _)| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["20 New idea.md", "7 Groceries.md"]);
+    }
+
+    #[test]
+    fn odd_files_are_kept_in_bounds() {
+        let board = Board {
+            notes: vec![NoteData {
+                locked: true,
+                ..sample_note()
+            }],
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let moved = "---\nid: 7\npos: [500, 500]\nsize: [300, 300]\n---\nRing\u{7}\n";
+        std::fs::write(dir.path().join("7 Groceries.md"), moved).unwrap();
+        let odd = "---\npos: [NaN, 5]\nsize: [1, 1]\n---\nTiny\n";
+        std::fs::write(dir.path().join("odd.md"), odd).unwrap();
+
+        let mut next_note_id = 20;
+        let sync = sync_vault(&board, dir.path(), &mut next_note_id, Pos2::ZERO).unwrap();
+        let locked = &sync.updated[0];
+        assert_eq!(locked.text, "Ring");
+        assert_eq!(
+            (locked.pos, locked.size),
+            (board.notes[0].pos, board.notes[0].size)
+        );
+        assert_eq!(sync.cleaned.len(), 1);
+        assert_eq!(sync.created[0].pos, Pos2::ZERO);
+        assert_eq!(sync.created[0].size, MIN_NOTE_SIZE);
+    }
+
+    #[test]
+    fn files_of_other_boards_are_left_alone() {
+        let board = Board {
+            notes: vec![sample_note()],
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let other = Board {
+            id: board.id + 1,
+            notes: vec![NoteData {
+                id: 8,
+                ..sample_note()
+            }],
+            ..Default::default()
+        };
+        export_vault(&other, dir.path()).unwrap();
+        let old = dir.path().join("3 Old.md");
+        std::fs::write(&old, "---\nid: 3\n---\nOld\n").unwrap();
+
+        let mut next_note_id = 20;
+        let sync = sync_vault(&board, dir.path(), &mut next_note_id, Pos2::ZERO).unwrap();
+        assert_eq!(sync, VaultSync::default());
+        assert!(old.exists());
+        assert!(dir.path().join("8 Groceries.md").exists());
+        assert!(dir.path().join("7 Groceries.md").exists());
+
+        // A note deleted from the board takes its file along
+        export_vault(&Board::default(), dir.path()).unwrap();
+        assert!(!dir.path().join("7 Groceries.md").exists());
+        assert!(dir.path().join("8 Groceries.md").exists());
+    }
+
+    #[test]
+    fn new_files_survive_a_failed_sync() {
+        let board = Board {
+            notes: vec![sample_note()],
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let idea = dir.path().join("idea.md");
+        std::fs::write(&idea, "New idea\n").unwrap();
+        // Where the note's file would go can't be written
+        std::fs::create_dir(dir.path().join("20 New idea.md")).unwrap();
+
+        let mut next_note_id = 20;
+        assert!(sync_vault(&board, dir.path(), &mut next_note_id, Pos2::ZERO).is_err());
+        assert_eq!(std::fs::read_to_string(&idea).unwrap(), "New idea\n");
+    }
+}
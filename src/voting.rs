@@ -0,0 +1,108 @@
+//! Dot voting for retrospectives: everyone gets a few dots to put on the
+//! notes that matter most to them, and the notes are ranked by their dots
+
+use crate::{Board, NoteData};
+use std::collections::HashMap;
+
+/// Dots each person gets unless set otherwise
+pub const DEFAULT_DOTS: u32 = 5;
+
+/// Who voted on what in the current round; only the dot counts on the notes
+/// are kept with the board
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ballot {
+    /// Dots each person may place
+    pub dots: u32,
+    /// Notes each person put a dot on, once per dot
+    placed: HashMap<String, Vec<u64>>,
+}
+
+impl Default for Ballot {
+    fn default() -> Self {
+        Self {
+            dots: DEFAULT_DOTS,
+            placed: HashMap::new(),
+        }
+    }
+}
+
+impl Ballot {
+    /// Dots `voter` has left to place
+    pub fn remaining(&self, voter: &str) -> u32 {
+        let used = self.placed.get(voter).map_or(0, Vec::len) as u32;
+        self.dots.saturating_sub(used)
+    }
+
+    /// Put one of `voter`'s dots on the note `id`; false if they have none
+    /// left
+    pub fn place(&mut self, voter: &str, id: u64) -> bool {
+        if self.remaining(voter) == 0 {
+            return false;
+        }
+        self.placed.entry(voter.to_owned()).or_default().push(id);
+        true
+    }
+
+    /// Take one of `voter`'s dots back off the note `id`; false if they put
+    /// none there
+    pub fn take_back(&mut self, voter: &str, id: u64) -> bool {
+        let Some(placed) = self.placed.get_mut(voter) else {
+            return false;
+        };
+        let Some(index) = placed.iter().rposition(|&p| p == id) else {
+            return false;
+        };
+        placed.remove(index);
+        true
+    }
+
+    /// Start a new round with every dot back in hand
+    pub fn reset(&mut self) {
+        self.placed.clear();
+    }
+}
+
+impl Board {
+    /// Notes with at least one vote, most votes first
+    pub fn ranked_by_votes(&self) -> Vec<&NoteData> {
+        let mut ranked: Vec<&NoteData> = self.notes.iter().filter(|n| n.votes > 0).collect();
+        ranked.sort_by(|a, b| b.votes.cmp(&a.votes).then(a.id.cmp(&b.id)));
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppState, BoardOps};
+    use egui::Pos2;
+
+    #[test]
+    fn dots_are_bounded_and_rank_notes() {
+        let mut ballot = Ballot {
+            dots: 2,
+            ..Default::default()
+        };
+        assert!(ballot.place("ann", 1));
+        assert!(ballot.place("ann", 1));
+        assert!(!ballot.place("ann", 2));
+        assert!(ballot.place("bob", 2));
+        assert_eq!(ballot.remaining("bob"), 1);
+        // Only dots someone placed can be taken back
+        assert!(!ballot.take_back("bob", 1));
+        assert!(ballot.take_back("ann", 1));
+        assert_eq!(ballot.remaining("ann"), 1);
+        ballot.reset();
+        assert_eq!(ballot.remaining("ann"), 2);
+
+        let mut state = AppState::default();
+        let a = state.add_note("a", Pos2::ZERO);
+        let b = state.add_note("b", Pos2::ZERO);
+        state.add_note("c", Pos2::ZERO);
+        let board = state.board_mut();
+        board.notes[0].votes = 1;
+        board.notes[1].votes = 3;
+        let ranked: Vec<u64> = board.ranked_by_votes().iter().map(|n| n.id).collect();
+        assert_eq!(ranked, [b, a]);
+    }
+}
@@ -0,0 +1,83 @@
+//! Noticing when the save file is changed by someone else, like another
+//! plop or a sync service, so it isn't silently overwritten
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Where plop's own version goes when the save file changed outside plop
+/// and the app closes before anyone decided: `<file name>.mine` next to it
+pub fn own_copy_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".mine");
+    path.with_file_name(name)
+}
+
+/// What a file looked like when last seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(Stamp {
+        modified: metadata.modified().ok(),
+        len: metadata.len(),
+    })
+}
+
+/// A file and what it looked like when plop last read or wrote it
+#[derive(Debug, Clone)]
+pub struct FileWatch {
+    path: PathBuf,
+    known: Option<Stamp>,
+}
+
+impl FileWatch {
+    /// Watch `path`, taking it as it is now as known
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            known: stamp(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Take the file as it is now as known, e.g. right after writing it
+    pub fn acknowledge(&mut self) {
+        self.known = stamp(&self.path);
+    }
+
+    /// Whether the file was written since it was last known; a file that
+    /// went away doesn't count, as there is nothing to reload
+    pub fn changed(&self) -> bool {
+        stamp(&self.path).is_some_and(|now| Some(now) != self.known)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_writes_by_others_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("boards.json");
+        let mut watch = FileWatch::new(&path);
+        assert!(!watch.changed());
+
+        std::fs::write(&path, "ours").unwrap();
+        watch.acknowledge();
+        assert!(!watch.changed());
+
+        std::fs::write(&path, "theirs, longer").unwrap();
+        assert!(watch.changed());
+        watch.acknowledge();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!watch.changed());
+    }
+}